@@ -0,0 +1,145 @@
+use bevy::{
+    app::{App, Plugin, Update},
+    ecs::{
+        component::Component,
+        entity::Entity,
+        query::Without,
+        reflect::ReflectComponent,
+        system::{Commands, Query, Res},
+    },
+    math::{Vec2, Vec3Swizzles},
+    reflect::Reflect,
+    render::{color::Color, view::Visibility},
+    text::{Text, Text2dBundle, TextStyle},
+    transform::components::Transform,
+    utils::default,
+};
+
+use crate::{ai::platformer_ai::PlatformerAI, GizmosVisible, Physics};
+
+/// How far above the top of its agent a label sits
+const AGENT_LABEL_OFFSET: f32 = 20.0;
+
+pub struct AgentLabelsPlugin;
+
+impl Plugin for AgentLabelsPlugin {
+    fn build(&self, app: &mut App) {
+        app.register_type::<AgentLabel>()
+            .add_systems(Update, s_update_agent_labels);
+    }
+}
+
+/// Text floating above an agent with what it's doing, shown along with the gizmos
+#[derive(Component, Reflect)]
+#[reflect(Component)]
+pub struct AgentLabel {
+    pub agent: Entity,
+}
+
+fn label_position(agent_transform: &Transform, physics: &Physics) -> Vec2 {
+    agent_transform.translation.xy() + Vec2::Y * (physics.radius + AGENT_LABEL_OFFSET)
+}
+
+fn agent_label_text(platformer_ai: &PlatformerAI) -> String {
+    // The path is trimmed as the agent walks it, so the target's index is how far along the
+    // rest of it the agent is
+    let target_index = platformer_ai.current_target_node.and_then(|target_node| {
+        platformer_ai
+            .path
+            .iter()
+            .position(|path_node| path_node.id == target_node)
+    });
+
+    let target = match (platformer_ai.current_target_node, target_index) {
+        (Some(target_node), Some(target_index)) => {
+            format!(
+                "node {} ({}/{})",
+                target_node,
+                target_index,
+                platformer_ai.path.len()
+            )
+        }
+        (Some(target_node), None) => format!("node {}", target_node),
+        (None, _) => "none".to_string(),
+    };
+
+    format!(
+        "{:?}\npath: {} nodes\ntarget: {}",
+        platformer_ai.state,
+        platformer_ai.path.len(),
+        target
+    )
+}
+
+pub fn s_update_agent_labels(
+    mut commands: Commands,
+    gizmos_visible: Res<GizmosVisible>,
+    agent_query: Query<(Entity, &Transform, &Physics, &PlatformerAI)>,
+    mut label_query: Query<
+        (
+            Entity,
+            &AgentLabel,
+            &mut Text,
+            &mut Transform,
+            &mut Visibility,
+        ),
+        Without<PlatformerAI>,
+    >,
+) {
+    let visibility = if gizmos_visible.visible {
+        Visibility::Inherited
+    } else {
+        Visibility::Hidden
+    };
+
+    let mut labelled_agents = Vec::new();
+
+    for (label_entity, agent_label, mut text, mut transform, mut label_visibility) in
+        label_query.iter_mut()
+    {
+        // The agent's gone, so its label goes too
+        let Ok((_, agent_transform, physics, platformer_ai)) = agent_query.get(agent_label.agent)
+        else {
+            commands.entity(label_entity).despawn();
+            continue;
+        };
+        labelled_agents.push(agent_label.agent);
+
+        *label_visibility = visibility;
+        if !gizmos_visible.visible {
+            continue;
+        }
+
+        text.sections[0].value = agent_label_text(platformer_ai);
+
+        transform.translation = label_position(agent_transform, physics).extend(1.0);
+    }
+
+    // Agents spawned since last frame
+    for (agent_entity, agent_transform, physics, platformer_ai) in agent_query.iter() {
+        if labelled_agents.contains(&agent_entity) {
+            continue;
+        }
+
+        commands.spawn((
+            Text2dBundle {
+                text: Text::from_section(
+                    agent_label_text(platformer_ai),
+                    TextStyle {
+                        font_size: 12.0,
+                        color: Color::WHITE,
+                        ..default()
+                    },
+                ),
+                transform: Transform::from_translation(
+                    label_position(agent_transform, physics).extend(1.0),
+                ),
+                visibility,
+                ..default()
+            },
+            AgentLabel {
+                agent: agent_entity,
+            },
+        ));
+    }
+}
@@ -1,8 +1,10 @@
-use std::{cmp::Ordering, collections::BinaryHeap};
+use std::{cmp::Ordering, collections::BinaryHeap, time::Duration};
 
-use bevy::math::Vec2;
+use bevy::{ecs::entity::Entity, math::Vec2, reflect::Reflect, utils::Instant};
 
-use super::pathfinding::{Pathfinding, PathfindingGraphConnection, PathfindingGraphNode};
+use super::pathfinding::{
+    Pathfinding, PathfindingGraphConnection, PathfindingGraphConnectionType, PathfindingGraphNode,
+};
 
 pub fn find_path(pathfinding: &Pathfinding, start_position: Vec2) -> Option<Vec<PathNode>> {
     if pathfinding.goal_graph_node.is_none() {
@@ -11,11 +13,144 @@ pub fn find_path(pathfinding: &Pathfinding, start_position: Vec2) -> Option<Vec<
 
     let goal_node = pathfinding.goal_graph_node.as_ref().unwrap();
 
-    let mut open_list: BinaryHeap<AStarNode> = BinaryHeap::new();
-    let mut closed_list: Vec<AStarNode> = vec![];
+    find_path_to(
+        pathfinding,
+        start_position,
+        goal_node.id,
+        pathfinding.goal_position,
+    )
+}
 
+/// Finds a path to any node in the graph, not just the one closest to the goal point
+pub fn find_path_to(
+    pathfinding: &Pathfinding,
+    start_position: Vec2,
+    goal_node_id: usize,
+    goal_position: Vec2,
+) -> Option<Vec<PathNode>> {
     // Get the start node
-    let start_node = get_start_node(pathfinding, start_position);
+    let start_node = get_start_node(pathfinding, start_position, goal_position);
+
+    search(
+        pathfinding,
+        start_node,
+        goal_node_id,
+        goal_position,
+        None,
+        None,
+        None,
+    )
+}
+
+/// Finds a path from a specific start node, for callers that pick the start node themselves
+///
+/// How much work the search took is written to the stats, if there are any, and the danger
+/// left by the searcher, if it's an agent, is left out
+pub fn find_path_from(
+    pathfinding: &Pathfinding,
+    start_node_id: usize,
+    goal_node_id: usize,
+    goal_position: Vec2,
+    mut stats: Option<&mut SearchStats>,
+    searcher: Option<Entity>,
+) -> Option<Vec<PathNode>> {
+    let start_time = Instant::now();
+
+    let mut start_node = AStarNode::new(&pathfinding.nodes[start_node_id]);
+
+    // Set the h-cost to the distance to the goal
+    start_node.h_cost =
+        (goal_position - start_node.position).length() * pathfinding.heuristic_weight;
+
+    let path = search(
+        pathfinding,
+        start_node,
+        goal_node_id,
+        goal_position,
+        None,
+        stats.as_deref_mut(),
+        searcher,
+    );
+
+    if let Some(stats) = stats {
+        stats.duration = start_time.elapsed();
+    }
+
+    path
+}
+
+/// Finds a path to the goal like `find_path`, writing down every step of the search on the way
+pub fn find_path_traced(
+    pathfinding: &Pathfinding,
+    start_position: Vec2,
+) -> (Option<Vec<PathNode>>, SearchTrace) {
+    let mut trace = SearchTrace::default();
+
+    let Some(goal_node) = pathfinding.goal_graph_node.as_ref() else {
+        return (None, trace);
+    };
+
+    let start_node = get_start_node(pathfinding, start_position, pathfinding.goal_position);
+
+    let path = search(
+        pathfinding,
+        start_node,
+        goal_node.id,
+        pathfinding.goal_position,
+        Some(&mut trace),
+        None,
+        None,
+    );
+
+    (path, trace)
+}
+
+/// How much work a single search took, and what the path it found costs
+#[derive(Debug, Clone, Copy, Default, Reflect)]
+pub struct SearchStats {
+    /// How many nodes were taken off the open list and had their connections looked at
+    pub nodes_expanded: usize,
+    pub duration: Duration,
+    /// The g-cost of the path, counting the connection costs and danger the search weighs up
+    /// rather than just its length
+    pub path_cost: f32,
+}
+
+/// A node as the search saw it, when it was expanded or added to the open list
+#[derive(Debug, Clone, Copy)]
+pub struct SearchTraceNode {
+    pub id: usize,
+    pub g_cost: f32,
+    pub h_cost: f32,
+}
+
+/// One expansion of the search: the node it took off the open list and the ones it added
+#[derive(Debug, Clone)]
+pub struct SearchStep {
+    pub current: SearchTraceNode,
+    pub opened: Vec<SearchTraceNode>,
+}
+
+/// Every expansion of a search in order, for replaying it
+///
+/// The open and closed lists at any step are rebuilt from the steps up to it rather than
+/// copied at each one
+#[derive(Debug, Clone, Default)]
+pub struct SearchTrace {
+    pub steps: Vec<SearchStep>,
+}
+
+fn search(
+    pathfinding: &Pathfinding,
+    start_node: AStarNode,
+    goal_node_id: usize,
+    goal_position: Vec2,
+    mut trace: Option<&mut SearchTrace>,
+    mut stats: Option<&mut SearchStats>,
+    searcher: Option<Entity>,
+) -> Option<Vec<PathNode>> {
+    let mut open_list: BinaryHeap<AStarNode> = BinaryHeap::new();
+    let mut closed_list: Vec<AStarNode> = vec![];
 
     // Add the start node to the open list
     open_list.push(start_node);
@@ -29,12 +164,46 @@ pub fn find_path(pathfinding: &Pathfinding, start_position: Vec2) -> Option<Vec<
         // Get the node with the lowest f-cost
         let current_node = open_list.pop().unwrap();
 
+        let already_closed = closed_list.iter().any(|n| n.id == current_node.id);
+
+        if let Some(trace) = trace.as_deref_mut() {
+            if !already_closed {
+                trace.steps.push(SearchStep {
+                    current: SearchTraceNode {
+                        id: current_node.id,
+                        g_cost: current_node.g_cost,
+                        h_cost: current_node.h_cost,
+                    },
+                    opened: Vec::new(),
+                });
+            }
+        }
+
         // If the current node is the goal, reconstruct the path
-        if current_node.id == goal_node.id {
+        if current_node.id == goal_node_id {
+            // The goal isn't given a g-cost, so the path costs what the node before it did
+            if let Some(stats) = stats.as_deref_mut() {
+                stats.path_cost = current_node.parent.map_or(0.0, |parent_id| {
+                    closed_list
+                        .iter()
+                        .find(|n| n.id == parent_id)
+                        .unwrap()
+                        .g_cost
+                });
+            }
+
             let mut path: Vec<PathNode> = vec![];
 
             let mut current_node = current_node;
             while let Some(parent_id) = current_node.parent {
+                // Put back the nodes of any walkable run skipped between the parent and here
+                for &skipped_node_id in current_node.skipped_node_ids.iter().rev() {
+                    path.push(PathNode::new(
+                        skipped_node_id,
+                        pathfinding.nodes[skipped_node_id].position,
+                    ));
+                }
+
                 let parent_node = closed_list.iter().find(|n| n.id == parent_id).unwrap();
                 path.push(PathNode::new(parent_id, parent_node.position));
                 current_node = parent_node.clone();
@@ -46,51 +215,172 @@ pub fn find_path(pathfinding: &Pathfinding, start_position: Vec2) -> Option<Vec<
         }
 
         // If the node is in the closed list, skip it
-        if closed_list.iter().any(|n| n.id == current_node.id) {
+        if already_closed {
             continue;
         }
 
         // Add the current node to the closed list
         closed_list.push(current_node.clone());
 
+        if let Some(stats) = stats.as_deref_mut() {
+            stats.nodes_expanded += 1;
+        }
+
         // For each connection of the current node
         for connection in current_node.connections.iter() {
-            let connected_graph_node = &pathfinding.nodes[connection.node_id];
+            // Skip connections that have been disabled at runtime
+            if !pathfinding.is_connection_enabled(current_node.id, connection.node_id) {
+                continue;
+            }
+
+            let mut g_cost =
+                step_cost(pathfinding, current_node.id, connection, searcher) + current_node.g_cost;
+
+            // Walkable runs get skipped along in one go, other connections are a single step
+            let mut connected_node_id = connection.node_id;
+            let mut skipped_node_ids = Vec::new();
+            if pathfinding.skip_walkable_runs
+                && connection.connection_type == PathfindingGraphConnectionType::Walkable
+            {
+                (connected_node_id, g_cost, skipped_node_ids) = skip_walkable_run(
+                    pathfinding,
+                    current_node.id,
+                    connection.node_id,
+                    g_cost,
+                    goal_node_id,
+                    searcher,
+                );
+            }
+
+            let connected_graph_node = &pathfinding.nodes[connected_node_id];
             let mut new_node = AStarNode::new(connected_graph_node);
+            new_node.skipped_node_ids = skipped_node_ids;
 
             // If the new node is the goal, set the is_goal flag
-            if new_node.id != goal_node.id {
+            if new_node.id != goal_node_id {
                 // Set the g-cost to the distance to the start node
-                new_node.g_cost = connection.dist + current_node.g_cost;
+                new_node.g_cost = g_cost;
 
                 // Set the h-cost to the distance to the goal
-                new_node.h_cost = (pathfinding.goal_position - new_node.position).length();
+                new_node.h_cost =
+                    (goal_position - new_node.position).length() * pathfinding.heuristic_weight;
             }
 
             // Set the parent of the new node
             new_node.parent = Some(current_node.id);
 
+            if let Some(step) = trace
+                .as_deref_mut()
+                .and_then(|trace| trace.steps.last_mut())
+            {
+                step.opened.push(SearchTraceNode {
+                    id: new_node.id,
+                    g_cost: new_node.g_cost,
+                    h_cost: new_node.h_cost,
+                });
+            }
+
             open_list.push(new_node);
         }
     }
 }
 
-fn get_start_node(pathfinding: &Pathfinding, start_position: Vec2) -> AStarNode {
+/// The cost of a single connection, including the danger of the node it leads to
+fn step_cost(
+    pathfinding: &Pathfinding,
+    from_node_id: usize,
+    connection: &PathfindingGraphConnection,
+    searcher: Option<Entity>,
+) -> f32 {
+    pathfinding.connection_cost(from_node_id, connection)
+        + pathfinding.influence.cost(connection.node_id, searcher)
+}
+
+/// Jump Point Search style skipping along a walkable run, from the node the search just
+/// stepped onto
+///
+/// Nodes in the middle of a surface only lead on to the next node along it, so rather than
+/// expanding them one at a time the search carries on until it reaches a node that offers a
+/// choice: a corner, a node with any other kind of connection or a disabled connection. It
+/// stops short of the goal, so the goal is still reached from a node with its own g-cost
+///
+/// Returns the node the run stops at, its g-cost and the nodes skipped on the way, in order
+fn skip_walkable_run(
+    pathfinding: &Pathfinding,
+    from_node_id: usize,
+    node_id: usize,
+    g_cost: f32,
+    goal_node_id: usize,
+    searcher: Option<Entity>,
+) -> (usize, f32, Vec<usize>) {
+    let mut prev_node_id = from_node_id;
+    let mut current_node_id = node_id;
+    let mut g_cost = g_cost;
+    let mut skipped_node_ids = Vec::new();
+
+    // Guard against runs that loop back on themselves
+    while skipped_node_ids.len() < pathfinding.nodes.len() {
+        let current_node = &pathfinding.nodes[current_node_id];
+
+        let is_plain_run_node = current_node_id != goal_node_id
+            && !current_node.is_corner
+            && current_node.connections.len() == 2
+            && current_node.connections.iter().all(|connection| {
+                connection.connection_type == PathfindingGraphConnectionType::Walkable
+            });
+        if !is_plain_run_node {
+            break;
+        }
+
+        // The run carries on through whichever connection doesn't lead back
+        let Some(next_connection) = current_node
+            .connections
+            .iter()
+            .find(|connection| connection.node_id != prev_node_id)
+        else {
+            break;
+        };
+
+        if next_connection.node_id == goal_node_id
+            || !pathfinding.is_connection_enabled(current_node_id, next_connection.node_id)
+        {
+            break;
+        }
+
+        g_cost += step_cost(pathfinding, current_node_id, next_connection, searcher);
+        skipped_node_ids.push(current_node_id);
+
+        prev_node_id = current_node_id;
+        current_node_id = next_connection.node_id;
+    }
+
+    (current_node_id, g_cost, skipped_node_ids)
+}
+
+/// How many of the closest nodes get considered when picking the start node
+const START_NODE_CANDIDATES: usize = 4;
+
+fn get_start_node(
+    pathfinding: &Pathfinding,
+    start_position: Vec2,
+    goal_position: Vec2,
+) -> AStarNode {
     let mut start_graph_node: PathfindingGraphNode = PathfindingGraphNode {
         id: 0,
         position: Vec2::ZERO,
         polygon_index: 0,
         line_indicies: vec![],
-        walkable_connections: vec![],
-        jumpable_connections: vec![],
-        droppable_connections: vec![],
+        connections: vec![],
         normal: Vec2::ZERO,
         is_corner: false,
         is_external_corner: None,
     };
     let mut start_graph_node_distance = f32::MAX;
 
-    for node in pathfinding.nodes.iter() {
+    // Only the closest few nodes can win, ties included
+    for node_id in pathfinding.k_nearest_node_ids(start_position, START_NODE_CANDIDATES) {
+        let node = &pathfinding.nodes[node_id];
+
         let distance = (start_position - node.position).length_squared();
 
         if distance > start_graph_node_distance {
@@ -98,8 +388,8 @@ fn get_start_node(pathfinding: &Pathfinding, start_position: Vec2) -> AStarNode
         }
 
         if distance == start_graph_node_distance {
-            let start_node_to_goal = (pathfinding.goal_position - start_position).length_squared();
-            let current_node_to_goal = (pathfinding.goal_position - node.position).length_squared();
+            let start_node_to_goal = (goal_position - start_position).length_squared();
+            let current_node_to_goal = (goal_position - node.position).length_squared();
 
             if current_node_to_goal > start_node_to_goal {
                 continue;
@@ -113,7 +403,8 @@ fn get_start_node(pathfinding: &Pathfinding, start_position: Vec2) -> AStarNode
     let mut start_a_star_node = AStarNode::new(&start_graph_node);
 
     // Set the h-cost to the distance to the goal
-    start_a_star_node.h_cost = (pathfinding.goal_position - start_a_star_node.position).length();
+    start_a_star_node.h_cost =
+        (goal_position - start_a_star_node.position).length() * pathfinding.heuristic_weight;
 
     return start_a_star_node;
 }
@@ -123,6 +414,8 @@ pub struct AStarNode {
     pub position: Vec2,
     pub id: usize,
     pub connections: Vec<PathfindingGraphConnection>,
+    /// The nodes along the walkable run the search skipped to get here from the parent
+    pub skipped_node_ids: Vec<usize>,
     pub g_cost: f32,
     pub h_cost: f32,
     pub parent: Option<usize>,
@@ -132,16 +425,11 @@ pub struct AStarNode {
 
 impl AStarNode {
     pub fn new(graph_node: &PathfindingGraphNode) -> AStarNode {
-        let connections = [
-            graph_node.walkable_connections.as_slice(),
-            graph_node.jumpable_connections.as_slice(),
-        ]
-        .concat();
-
         AStarNode {
             position: graph_node.position,
             id: graph_node.id,
-            connections,
+            connections: graph_node.connections.clone(),
+            skipped_node_ids: Vec::new(),
             g_cost: 0.0,
             h_cost: 0.0,
             parent: None,
@@ -185,6 +473,20 @@ impl PartialEq for AStarNode {
     }
 }
 
+/// The total distance travelled along a path, starting from the given position
+pub fn path_length(start_position: Vec2, path: &[PathNode]) -> f32 {
+    let mut length = 0.0;
+    let mut prev_pos = start_position;
+
+    for path_node in path.iter() {
+        length += (path_node.position - prev_pos).length();
+        prev_pos = path_node.position;
+    }
+
+    length
+}
+
+#[derive(Debug, Clone, Reflect)]
 pub struct PathNode {
     pub id: usize,
     pub position: Vec2,
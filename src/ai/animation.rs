@@ -0,0 +1,190 @@
+use bevy::{
+    app::{App, FixedUpdate, Plugin},
+    ecs::{
+        component::Component,
+        entity::Entity,
+        event::{Event, EventWriter},
+        query::Without,
+        reflect::ReflectComponent,
+        schedule::IntoSystemConfigs,
+        system::{Commands, Query},
+    },
+    math::{Vec2, Vec3Swizzles},
+    reflect::Reflect,
+    render::color::Color,
+    transform::components::Transform,
+};
+
+use crate::{collisions::s_collision, Physics, PursueAISet};
+
+use super::platformer_ai::{PlatformerAI, PlatformerAIState};
+
+/// How long an agent flashes for when it starts pursuing
+const PURSUE_FLASH_TICKS: u32 = 30;
+/// How many ticks each on and off of the pursue flash lasts
+const PURSUE_FLASH_INTERVAL_TICKS: u32 = 5;
+/// How many ticks one pulse of the attack telegraph takes
+const ATTACK_TELEGRAPH_PERIOD_TICKS: u32 = 20;
+
+/// Only leaving the ground faster than this upward counts as a jump, rather than walking off a
+/// ledge or going over a bump
+const JUMP_MIN_UPWARD_SPEED: f32 = 0.5;
+
+pub struct AnimationPlugin;
+
+impl Plugin for AnimationPlugin {
+    fn build(&self, app: &mut App) {
+        app.register_type::<AgentAnimation>()
+            .register_type::<AnimationEventKind>()
+            .add_event::<AnimationEvent>()
+            .add_systems(
+                FixedUpdate,
+                s_animate_agents
+                    .in_set(PursueAISet::Collision)
+                    .after(s_collision),
+            );
+    }
+}
+
+/// What an agent's been doing, for telling when it jumps, lands or changes state, and how long
+/// it's been in its state for the state visuals
+#[derive(Component, Debug, Clone, Copy, Default, Reflect)]
+#[reflect(Component)]
+pub struct AgentAnimation {
+    pub state: PlatformerAIState,
+    pub ticks_in_state: u32,
+    /// Whether it was on the ground or a wall last tick, which is where jumps start from
+    pub supported: bool,
+    /// Whether it was hanging off a ledge last tick
+    pub mantling: bool,
+}
+
+/// Something an animation layer might want to play, sent after collision each tick
+#[derive(Event, Debug, Clone, Copy)]
+pub struct AnimationEvent {
+    pub entity: Entity,
+    pub position: Vec2,
+    pub kind: AnimationEventKind,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Reflect)]
+pub enum AnimationEventKind {
+    /// Left the ground or a wall going up
+    JumpStarted,
+    /// Came down on the ground
+    Landed,
+    /// Caught a ledge it came up short of, and started pulling itself up over it
+    LedgeGrabbed,
+    /// Moved into the attack state, for winding up the attack
+    AttackStarted,
+    StateChanged {
+        from: PlatformerAIState,
+        to: PlatformerAIState,
+    },
+}
+
+impl AgentAnimation {
+    /// Moves on a tick, giving what the agent started doing since the last one
+    pub fn advance(
+        &mut self,
+        physics: &Physics,
+        state: PlatformerAIState,
+        mantling: bool,
+    ) -> Vec<AnimationEventKind> {
+        let mut kinds = Vec::new();
+        let supported = physics.grounded || physics.walled != 0;
+
+        if self.supported && !supported && physics.velocity.y > JUMP_MIN_UPWARD_SPEED {
+            kinds.push(AnimationEventKind::JumpStarted);
+        }
+        if !self.supported && physics.grounded {
+            kinds.push(AnimationEventKind::Landed);
+        }
+        if !self.mantling && mantling {
+            kinds.push(AnimationEventKind::LedgeGrabbed);
+        }
+
+        if state != self.state {
+            kinds.push(AnimationEventKind::StateChanged {
+                from: self.state,
+                to: state,
+            });
+            if state == PlatformerAIState::Attack {
+                kinds.push(AnimationEventKind::AttackStarted);
+            }
+
+            self.state = state;
+            self.ticks_in_state = 0;
+        } else {
+            self.ticks_in_state += 1;
+        }
+
+        self.supported = supported;
+        self.mantling = mantling;
+
+        kinds
+    }
+}
+
+/// What color the agent's drawn in for its state
+///
+/// Wandering agents are dim, searching ones orange, agents that just started pursuing flash
+/// and attacking ones pulse yellow as a telegraph
+pub fn agent_color(animation: &AgentAnimation) -> Color {
+    match animation.state {
+        PlatformerAIState::Wander => Color::rgb(0.6, 0.2, 0.2),
+        PlatformerAIState::Search => Color::ORANGE,
+        PlatformerAIState::Pursue => {
+            let flash_on = animation.ticks_in_state < PURSUE_FLASH_TICKS
+                && (animation.ticks_in_state / PURSUE_FLASH_INTERVAL_TICKS).is_multiple_of(2);
+
+            if flash_on {
+                Color::WHITE
+            } else {
+                Color::RED
+            }
+        }
+        PlatformerAIState::Attack => {
+            let phase = (animation.ticks_in_state % ATTACK_TELEGRAPH_PERIOD_TICKS) as f32
+                / ATTACK_TELEGRAPH_PERIOD_TICKS as f32;
+            let pulse = 0.5 - 0.5 * (phase * std::f32::consts::TAU).cos();
+
+            Color::rgb(1.0, pulse * 0.9, 0.0)
+        }
+    }
+}
+
+pub fn s_animate_agents(
+    mut commands: Commands,
+    mut animation_events: EventWriter<AnimationEvent>,
+    new_agent_query: Query<(Entity, &PlatformerAI), Without<AgentAnimation>>,
+    mut agent_query: Query<(
+        Entity,
+        &Transform,
+        &Physics,
+        &PlatformerAI,
+        &mut AgentAnimation,
+    )>,
+) {
+    // Agents start out in whatever state they spawned in, without an event for it
+    for (entity, platformer_ai) in new_agent_query.iter() {
+        commands.entity(entity).insert(AgentAnimation {
+            state: platformer_ai.state,
+            ..Default::default()
+        });
+    }
+
+    for (entity, transform, physics, platformer_ai, mut animation) in agent_query.iter_mut() {
+        for kind in animation.advance(
+            physics,
+            platformer_ai.state,
+            platformer_ai.mantling.is_some(),
+        ) {
+            animation_events.send(AnimationEvent {
+                entity,
+                position: transform.translation.xy(),
+                kind,
+            });
+        }
+    }
+}
@@ -0,0 +1,172 @@
+use std::{cmp::Ordering, collections::BinaryHeap};
+
+use bevy::{
+    app::{App, Plugin, Update},
+    ecs::{
+        reflect::ReflectResource,
+        schedule::IntoSystemConfigs,
+        system::{Res, ResMut, Resource},
+    },
+    gizmos::gizmos::Gizmos,
+    input::{keyboard::KeyCode, ButtonInput},
+    reflect::Reflect,
+    render::color::Color,
+};
+
+use crate::PursueAISet;
+
+use super::pathfinding::Pathfinding;
+
+const HEATMAP_NODE_RADIUS: f32 = 3.0;
+
+pub struct CostHeatmapPlugin;
+
+impl Plugin for CostHeatmapPlugin {
+    fn build(&self, app: &mut App) {
+        app.register_type::<CostHeatmap>()
+            .insert_resource(CostHeatmap::default())
+            .add_systems(
+                Update,
+                (
+                    s_cost_heatmap_input,
+                    s_render_cost_heatmap.in_set(PursueAISet::Render),
+                ),
+            );
+    }
+}
+
+/// Whether every node is colored by what it costs to get from it to the goal
+#[derive(Resource, Default, Reflect)]
+#[reflect(Resource)]
+pub struct CostHeatmap {
+    pub visible: bool,
+}
+
+/// A node waiting in the Dijkstra queue, cheapest first
+struct QueuedNode {
+    id: usize,
+    cost: f32,
+}
+
+impl Ord for QueuedNode {
+    fn cmp(&self, other: &Self) -> Ordering {
+        other
+            .cost
+            .partial_cmp(&self.cost)
+            .unwrap_or(Ordering::Equal)
+    }
+}
+
+impl PartialOrd for QueuedNode {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Eq for QueuedNode {}
+
+impl PartialEq for QueuedNode {
+    fn eq(&self, other: &Self) -> bool {
+        self.id == other.id
+    }
+}
+
+/// What the cheapest path from each node to the goal node costs, the same way A* adds it up
+/// (connection costs plus the danger of the nodes passed through), or `None` if there's no
+/// way there
+///
+/// Runs Dijkstra backwards from the goal, along every connection that's enabled
+pub fn costs_to_goal(pathfinding: &Pathfinding, goal_node_id: usize) -> Vec<Option<f32>> {
+    let mut costs: Vec<Option<f32>> = vec![None; pathfinding.nodes.len()];
+
+    if goal_node_id >= pathfinding.nodes.len() {
+        return costs;
+    }
+
+    // The connections leading into each node, as (from node, cost)
+    let mut incoming: Vec<Vec<(usize, f32)>> = vec![Vec::new(); pathfinding.nodes.len()];
+    for node in pathfinding.nodes.iter() {
+        for connection in node.connections.iter() {
+            if connection.node_id >= pathfinding.nodes.len()
+                || !pathfinding.is_connection_enabled(node.id, connection.node_id)
+            {
+                continue;
+            }
+
+            let cost = pathfinding.connection_cost(node.id, connection)
+                + pathfinding.influence.cost(connection.node_id, None);
+            incoming[connection.node_id].push((node.id, cost));
+        }
+    }
+
+    let mut queue = BinaryHeap::new();
+    queue.push(QueuedNode {
+        id: goal_node_id,
+        cost: 0.0,
+    });
+
+    while let Some(QueuedNode { id, cost }) = queue.pop() {
+        if costs[id].is_some() {
+            continue;
+        }
+        costs[id] = Some(cost);
+
+        for (from_node_id, step_cost) in incoming[id].iter() {
+            if costs[*from_node_id].is_none() {
+                queue.push(QueuedNode {
+                    id: *from_node_id,
+                    cost: cost + step_cost,
+                });
+            }
+        }
+    }
+
+    costs
+}
+
+pub fn s_cost_heatmap_input(
+    keyboard_input: Res<ButtonInput<KeyCode>>,
+    mut cost_heatmap: ResMut<CostHeatmap>,
+) {
+    // Q to show or hide the cost to the goal from every node
+    if keyboard_input.just_pressed(KeyCode::KeyQ) {
+        cost_heatmap.visible = !cost_heatmap.visible;
+        println!("Cost heatmap: {}", cost_heatmap.visible);
+    }
+}
+
+/// Nodes go from green next to the goal to red for the most expensive, with nodes that can't
+/// reach the goal at all in gray
+pub fn s_render_cost_heatmap(
+    mut gizmos: Gizmos,
+    pathfinding: Res<Pathfinding>,
+    cost_heatmap: Res<CostHeatmap>,
+) {
+    if !cost_heatmap.visible {
+        return;
+    }
+
+    let Some(goal_node_id) = pathfinding.goal_graph_node.as_ref().map(|node| node.id) else {
+        return;
+    };
+
+    // Rerun every frame, since the danger the costs include never stops changing
+    let costs = costs_to_goal(&pathfinding, goal_node_id);
+
+    let max_cost = costs
+        .iter()
+        .flatten()
+        .fold(f32::EPSILON, |max_cost, cost| max_cost.max(*cost));
+
+    for (node, cost) in pathfinding.nodes.iter().zip(costs.iter()) {
+        let color = match cost {
+            Some(cost) => {
+                let t = (cost / max_cost).clamp(0.0, 1.0);
+                Color::rgb(t, 1.0 - t, 0.0)
+            }
+            None => Color::GRAY.with_a(0.3),
+        };
+
+        gizmos.circle_2d(node.position, HEATMAP_NODE_RADIUS, color);
+    }
+}
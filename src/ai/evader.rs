@@ -0,0 +1,251 @@
+use bevy::{
+    app::{App, FixedUpdate, Plugin, Startup, Update},
+    ecs::{
+        query::{With, Without},
+        reflect::ReflectResource,
+        schedule::IntoSystemConfigs,
+        system::{Query, Res, ResMut, Resource},
+    },
+    gizmos::gizmos::Gizmos,
+    input::{keyboard::KeyCode, ButtonInput},
+    math::{Vec2, Vec3Swizzles},
+    reflect::Reflect,
+    render::color::Color,
+    transform::components::Transform,
+};
+
+use crate::{
+    collisions::GOAL_POINT_RADIUS,
+    level::Level,
+    s_init,
+    settings::{EvaderSettings, Settings},
+    simulation::Simulation,
+    toggle_goal, GizmosVisible, GoalPoint, PursueAISet,
+};
+
+use super::{
+    a_star::{find_path_to, PathNode},
+    pathfinding::Pathfinding,
+    platformer_ai::PlatformerAI,
+};
+
+/// How far off the surface the evader keeps, so it doesn't grind along the floor
+const EVADER_HOVER_HEIGHT: f32 = GOAL_POINT_RADIUS * 2.0;
+
+pub struct EvaderPlugin;
+
+impl Plugin for EvaderPlugin {
+    fn build(&self, app: &mut App) {
+        app.register_type::<Evader>()
+            .insert_resource(Evader::default())
+            .add_systems(Startup, s_start_evader.after(s_init))
+            .add_systems(
+                Update,
+                (s_evader_input, s_render_evader.in_set(PursueAISet::Render)),
+            )
+            .add_systems(FixedUpdate, s_evader_movement.in_set(PursueAISet::Decision));
+    }
+}
+
+/// Runs the goal point away from the agents over the pathfinding graph, so the sandbox plays
+/// pursuit and evasion against itself
+///
+/// Every so often the evader scores every node by how far it is from the closest pursuer,
+/// how far the evader would have to go to get there, and whether any pursuer can see it, then
+/// follows the graph to the best one
+#[derive(Resource, Default, Reflect)]
+#[reflect(Resource)]
+pub struct Evader {
+    pub active: bool,
+    pub target_node_id: Option<usize>,
+    pub path: Vec<PathNode>,
+    pub ticks_since_replan: u32,
+    /// How far the evader moves the goal this tick, if it's active
+    pub velocity: Option<Vec2>,
+}
+
+impl Evader {
+    pub fn toggle(&mut self, pathfinding: &mut Pathfinding) {
+        self.active = !self.active;
+        self.target_node_id = None;
+        self.path.clear();
+        self.velocity = None;
+
+        // Nobody's chasing an inactive goal
+        if self.active && !pathfinding.active {
+            toggle_goal(pathfinding);
+        }
+
+        println!("Evader: {}", self.active);
+    }
+}
+
+/// How good a place the node is to run to, or `None` if a pursuer would get there first
+pub fn evader_node_score(
+    level: &Level,
+    node_position: Vec2,
+    evader_position: Vec2,
+    pursuer_positions: &[Vec2],
+    settings: &EvaderSettings,
+) -> Option<f32> {
+    let evader_distance = evader_position.distance(node_position);
+    let pursuer_distance = pursuer_positions
+        .iter()
+        .map(|pursuer_position| pursuer_position.distance(node_position))
+        .fold(f32::MAX, f32::min);
+
+    if evader_distance >= pursuer_distance {
+        return None;
+    }
+
+    let hidden = pursuer_positions
+        .iter()
+        .all(|pursuer_position| !level.line_of_sight_check(*pursuer_position, node_position));
+
+    Some(
+        pursuer_distance - evader_distance * settings.travel_weight
+            + if hidden { settings.hidden_bonus } else { 0.0 },
+    )
+}
+
+/// Sets the evader running from the start with `--evader`
+pub fn s_start_evader(
+    simulation: Res<Simulation>,
+    mut evader: ResMut<Evader>,
+    mut pathfinding: ResMut<Pathfinding>,
+) {
+    if simulation.evader {
+        evader.toggle(&mut pathfinding);
+    }
+}
+
+pub fn s_evader_input(
+    keyboard_input: Res<ButtonInput<KeyCode>>,
+    mut evader: ResMut<Evader>,
+    mut pathfinding: ResMut<Pathfinding>,
+) {
+    let shift = keyboard_input.any_pressed([KeyCode::ShiftLeft, KeyCode::ShiftRight]);
+
+    // Shift+Z to let the goal run from the agents on its own, or to take it back
+    if shift && keyboard_input.just_pressed(KeyCode::KeyZ) {
+        evader.toggle(&mut pathfinding);
+    }
+}
+
+pub fn s_evader_movement(
+    mut evader: ResMut<Evader>,
+    level: Res<Level>,
+    pathfinding: Res<Pathfinding>,
+    settings: Res<Settings>,
+    goal_query: Query<&Transform, With<GoalPoint>>,
+    pursuer_query: Query<&Transform, (With<PlatformerAI>, Without<GoalPoint>)>,
+) {
+    if !evader.active {
+        evader.velocity = None;
+        return;
+    }
+
+    let settings = &settings.evader;
+
+    let Ok(goal_transform) = goal_query.get_single() else {
+        return;
+    };
+    let evader_position = goal_transform.translation.xy();
+
+    let pursuer_positions: Vec<Vec2> = pursuer_query
+        .iter()
+        .map(|transform| transform.translation.xy())
+        .collect();
+
+    evader.ticks_since_replan += 1;
+
+    if evader.path.is_empty() || evader.ticks_since_replan >= settings.replan_interval {
+        evader.ticks_since_replan = 0;
+
+        let target_node_id = pathfinding
+            .nodes
+            .iter()
+            .filter(|node| pathfinding.is_node_enabled(node.id))
+            .filter_map(|node| {
+                evader_node_score(
+                    &level,
+                    node.position,
+                    evader_position,
+                    &pursuer_positions,
+                    settings,
+                )
+                .map(|score| (node.id, score))
+            })
+            .max_by(|(_, score_a), (_, score_b)| score_a.total_cmp(score_b))
+            .map(|(node_id, _)| node_id);
+
+        evader.target_node_id = target_node_id;
+        evader.path = target_node_id
+            .and_then(|node_id| {
+                find_path_to(
+                    &pathfinding,
+                    evader_position,
+                    node_id,
+                    pathfinding.nodes[node_id].position,
+                )
+            })
+            .unwrap_or_default();
+    }
+
+    // Hover over each node on the way, rather than flying into the surface it's on
+    let hover_position = |path_node: &PathNode| {
+        path_node.position
+            + pathfinding
+                .nodes
+                .get(path_node.id)
+                .map_or(Vec2::ZERO, |node| node.normal * EVADER_HOVER_HEIGHT)
+    };
+
+    while evader.path.first().is_some_and(|path_node| {
+        hover_position(path_node).distance(evader_position) < settings.node_reached_distance
+    }) {
+        evader.path.remove(0);
+    }
+
+    evader.velocity = Some(match evader.path.first() {
+        Some(path_node) => {
+            (hover_position(path_node) - evader_position).clamp_length_max(settings.max_speed)
+        }
+        // Cornered, or already somewhere safe
+        None => Vec2::ZERO,
+    });
+}
+
+pub fn s_render_evader(
+    mut gizmos: Gizmos,
+    evader: Res<Evader>,
+    pathfinding: Res<Pathfinding>,
+    settings: Res<Settings>,
+    gizmos_visible: Res<GizmosVisible>,
+    goal_query: Query<&Transform, With<GoalPoint>>,
+) {
+    if !evader.active || !gizmos_visible.visible {
+        return;
+    }
+
+    let Ok(goal_transform) = goal_query.get_single() else {
+        return;
+    };
+
+    gizmos.linestrip_2d(
+        std::iter::once(goal_transform.translation.xy())
+            .chain(evader.path.iter().map(|path_node| path_node.position)),
+        Color::CYAN,
+    );
+
+    if let Some(target_node) = evader
+        .target_node_id
+        .and_then(|node_id| pathfinding.nodes.get(node_id))
+    {
+        gizmos.circle_2d(
+            target_node.position,
+            settings.evader.node_reached_distance,
+            Color::CYAN,
+        );
+    }
+}
@@ -0,0 +1,158 @@
+use bevy::math::Vec2;
+
+use super::{
+    a_star::PathNode,
+    pathfinding::{Pathfinding, PathfindingGraphConnectionType},
+};
+
+/// How far above the agent's center line the corridor reaches
+pub const FUNNEL_CORRIDOR_HEIGHT: f32 = 32.0;
+
+/// Builds a corridor of portals hugging the surfaces along the path
+///
+/// Each portal runs along a node's normal, from where the agent's center would be
+/// when standing on the node up to the top of the corridor. The corridor stops at
+/// the first leg that isn't walkable (a jump or a drop), since those can't be cut
+pub fn build_corridor(
+    pathfinding: &Pathfinding,
+    path: &[PathNode],
+    start_position: Vec2,
+    radius: f32,
+) -> Vec<(Vec2, Vec2)> {
+    let mut portals: Vec<(Vec2, Vec2)> = vec![(start_position, start_position)];
+
+    if path.is_empty() {
+        return portals;
+    }
+
+    // Skip the first node if the agent is already past it
+    let mut first_index = 0;
+    if path.len() > 1 {
+        let first_leg = path[1].position - path[0].position;
+        if first_leg.dot(start_position - path[0].position) > 0.0 {
+            first_index = 1;
+        }
+    }
+
+    let mut prev_position = start_position;
+
+    for i in first_index..path.len() {
+        let node = &pathfinding.nodes[path[i].id];
+
+        let near = node.position + node.normal * radius;
+
+        // The corridor ends where the walking does
+        let next_leg_walkable = i + 1 < path.len()
+            && node
+                .connections_of_type(PathfindingGraphConnectionType::Walkable)
+                .any(|connection| connection.node_id == path[i + 1].id);
+
+        if i + 1 < path.len() && !next_leg_walkable {
+            portals.push((near, near));
+            return portals;
+        }
+
+        let far = node.position + node.normal * (radius + FUNNEL_CORRIDOR_HEIGHT);
+
+        let travel_dir = if i + 1 < path.len() {
+            path[i + 1].position - node.position
+        } else {
+            node.position - prev_position
+        }
+        .normalize_or_zero();
+
+        // Work out which end of the portal is on the left of the direction of travel
+        let left_of_travel = Vec2::new(-travel_dir.y, travel_dir.x);
+
+        portals.push(if node.normal.dot(left_of_travel) > 0.0 {
+            (far, near)
+        } else {
+            (near, far)
+        });
+
+        prev_position = node.position;
+    }
+
+    // End the corridor where the agent would stand on the last node
+    let last_node = &pathfinding.nodes[path[path.len() - 1].id];
+    let end = last_node.position + last_node.normal * radius;
+    portals.push((end, end));
+
+    portals
+}
+
+/// The Simple Stupid Funnel Algorithm: pulls a string tight through the portals
+///
+/// Returns the points the string bends at, ending with the end of the corridor
+/// (the start of the corridor isn't included)
+pub fn string_pull(portals: &[(Vec2, Vec2)]) -> Vec<Vec2> {
+    let mut points: Vec<Vec2> = Vec::new();
+
+    if portals.is_empty() {
+        return points;
+    }
+
+    let mut apex = portals[0].0;
+    let mut left = portals[0].0;
+    let mut right = portals[0].1;
+    let mut left_index = 0;
+    let mut right_index = 0;
+
+    let mut i = 1;
+    while i < portals.len() {
+        let (portal_left, portal_right) = portals[i];
+
+        // Try to narrow the right side of the funnel
+        if triangle_area_2(apex, right, portal_right) <= 0.0 {
+            if apex == right || triangle_area_2(apex, left, portal_right) > 0.0 {
+                right = portal_right;
+                right_index = i;
+            } else {
+                // The right side crossed over the left, so the left point is a bend
+                points.push(left);
+
+                apex = left;
+                right = apex;
+                right_index = left_index;
+
+                i = left_index + 1;
+                continue;
+            }
+        }
+
+        // Try to narrow the left side of the funnel
+        if triangle_area_2(apex, left, portal_left) >= 0.0 {
+            if apex == left || triangle_area_2(apex, right, portal_left) < 0.0 {
+                left = portal_left;
+                left_index = i;
+            } else {
+                // The left side crossed over the right, so the right point is a bend
+                points.push(right);
+
+                apex = right;
+                left = apex;
+                left_index = right_index;
+
+                i = right_index + 1;
+                continue;
+            }
+        }
+
+        i += 1;
+    }
+
+    let end = portals[portals.len() - 1].0;
+    if points.last() != Some(&end) {
+        points.push(end);
+    }
+
+    points
+}
+
+/// Twice the signed area of the triangle, used to tell which side of a line a point is on
+fn triangle_area_2(a: Vec2, b: Vec2, c: Vec2) -> f32 {
+    let ab = b - a;
+    let ac = c - a;
+
+    ac.x * ab.y - ab.x * ac.y
+}
@@ -0,0 +1,209 @@
+use std::fmt::Write;
+
+use bevy::{
+    app::{App, Plugin, Update},
+    ecs::system::Res,
+    input::{keyboard::KeyCode, ButtonInput},
+};
+use serde::Serialize;
+
+use super::pathfinding::{
+    Pathfinding, PathfindingGraphConnection, PathfindingGraphConnectionType, SurfaceKind,
+};
+
+#[cfg(not(target_arch = "wasm32"))]
+const GRAPH_DOT_FILE_PATH: &str = "graph.dot";
+
+#[cfg(not(target_arch = "wasm32"))]
+const GRAPH_JSON_FILE_PATH: &str = "graph.json";
+
+/// How many world units one inch is in the DOT layout (GraphViz positions are in inches)
+const DOT_UNITS_PER_INCH: f32 = 32.0;
+
+pub struct GraphExportPlugin;
+
+impl Plugin for GraphExportPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Update, s_graph_export_input);
+    }
+}
+
+#[derive(Serialize)]
+pub struct ExportedGraph {
+    pub nodes: Vec<ExportedNode>,
+    pub connections: Vec<ExportedConnection>,
+}
+
+#[derive(Serialize)]
+pub struct ExportedNode {
+    pub id: usize,
+    pub x: f32,
+    pub y: f32,
+    pub normal: [f32; 2],
+    pub surface: &'static str,
+    pub polygon_index: usize,
+    pub is_corner: bool,
+    pub enabled: bool,
+}
+
+#[derive(Serialize)]
+pub struct ExportedConnection {
+    pub from: usize,
+    pub to: usize,
+    pub connection_type: &'static str,
+    pub dist: f32,
+    pub effort: f32,
+    pub needs_run_up: bool,
+    pub enabled: bool,
+}
+
+/// Flattens the graph into plain nodes and typed connections
+pub fn export_graph(pathfinding: &Pathfinding) -> ExportedGraph {
+    let nodes = pathfinding
+        .nodes
+        .iter()
+        .map(|node| ExportedNode {
+            id: node.id,
+            x: node.position.x,
+            y: node.position.y,
+            normal: node.normal.to_array(),
+            surface: match node.surface_kind() {
+                SurfaceKind::Floor => "floor",
+                SurfaceKind::Wall => "wall",
+                SurfaceKind::Ceiling => "ceiling",
+            },
+            polygon_index: node.polygon_index,
+            is_corner: node.is_corner,
+            enabled: pathfinding.is_node_enabled(node.id),
+        })
+        .collect();
+
+    let connections = pathfinding
+        .nodes
+        .iter()
+        .flat_map(|node| {
+            node.connections
+                .iter()
+                .map(move |connection| (node.id, connection))
+        })
+        .map(|(from_node_id, connection)| ExportedConnection {
+            from: from_node_id,
+            to: connection.node_id,
+            connection_type: connection_type_name(connection),
+            dist: connection.dist,
+            effort: connection.effort,
+            needs_run_up: connection.run_up.is_some(),
+            enabled: pathfinding.is_connection_enabled(from_node_id, connection.node_id),
+        })
+        .collect();
+
+    ExportedGraph { nodes, connections }
+}
+
+/// Writes the graph as a GraphViz digraph, with the nodes pinned to their level positions
+///
+/// Meant to be laid out with `neato -n` so the pinned positions are kept
+pub fn export_graph_dot(pathfinding: &Pathfinding) -> String {
+    let graph = export_graph(pathfinding);
+
+    let mut dot = String::new();
+
+    writeln!(dot, "digraph pathfinding {{").unwrap();
+    writeln!(dot, "    node [shape=point];").unwrap();
+
+    for node in &graph.nodes {
+        writeln!(
+            dot,
+            "    {} [pos=\"{},{}!\", color={}];",
+            node.id,
+            node.x / DOT_UNITS_PER_INCH,
+            node.y / DOT_UNITS_PER_INCH,
+            if node.enabled { "black" } else { "gray" },
+        )
+        .unwrap();
+    }
+
+    for connection in &graph.connections {
+        let color = match connection.connection_type {
+            "walkable" => "blue",
+            "jumpable" => "green",
+            "climbable" => "purple",
+            "teleport" => "magenta",
+            "swimmable" => "cyan",
+            "bounce" => "gold",
+            _ => "orange",
+        };
+
+        writeln!(
+            dot,
+            "    {} -> {} [color={}, label=\"{:.1}\"{}];",
+            connection.from,
+            connection.to,
+            color,
+            connection.dist,
+            if connection.enabled {
+                ""
+            } else {
+                ", style=dashed"
+            },
+        )
+        .unwrap();
+    }
+
+    writeln!(dot, "}}").unwrap();
+
+    dot
+}
+
+fn connection_type_name(connection: &PathfindingGraphConnection) -> &'static str {
+    match connection.connection_type {
+        PathfindingGraphConnectionType::Walkable => "walkable",
+        PathfindingGraphConnectionType::Jumpable => "jumpable",
+        PathfindingGraphConnectionType::Droppable => "droppable",
+        PathfindingGraphConnectionType::Climbable => "climbable",
+        PathfindingGraphConnectionType::Teleport => "teleport",
+        PathfindingGraphConnectionType::Swimmable => "swimmable",
+        PathfindingGraphConnectionType::Bounce => "bounce",
+        PathfindingGraphConnectionType::WallJumpable => "wall_jumpable",
+        PathfindingGraphConnectionType::Mantle => "mantle",
+        PathfindingGraphConnectionType::Dashable => "dashable",
+        PathfindingGraphConnectionType::DoubleJumpable => "double_jumpable",
+    }
+}
+
+pub fn s_graph_export_input(
+    keyboard_input: Res<ButtonInput<KeyCode>>,
+    pathfinding: Res<Pathfinding>,
+) {
+    // X to export the graph
+    if !keyboard_input.just_pressed(KeyCode::KeyX) {
+        return;
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    {
+        match std::fs::write(GRAPH_DOT_FILE_PATH, export_graph_dot(&pathfinding)) {
+            Ok(()) => println!("Exported graph to {}", GRAPH_DOT_FILE_PATH),
+            Err(error) => println!(
+                "Failed to export graph to {}: {}",
+                GRAPH_DOT_FILE_PATH, error
+            ),
+        }
+
+        match serde_json::to_string_pretty(&export_graph(&pathfinding)) {
+            Ok(graph_json) => match std::fs::write(GRAPH_JSON_FILE_PATH, graph_json) {
+                Ok(()) => println!("Exported graph to {}", GRAPH_JSON_FILE_PATH),
+                Err(error) => {
+                    println!(
+                        "Failed to export graph to {}: {}",
+                        GRAPH_JSON_FILE_PATH, error
+                    )
+                }
+            },
+            Err(error) => println!("Failed to serialize graph: {}", error),
+        }
+    }
+
+    #[cfg(target_arch = "wasm32")]
+    let _ = pathfinding;
+}
@@ -0,0 +1,136 @@
+use bevy::{
+    app::{App, Plugin, Update},
+    ecs::{schedule::IntoSystemConfigs, system::Res},
+    gizmos::gizmos::Gizmos,
+    math::Vec2,
+    render::color::Color,
+};
+
+use crate::{GizmosVisible, PursueAISet};
+
+use super::pathfinding::{
+    connection_arc_points, double_jump_midpoint, jump_trajectory_points, Pathfinding,
+    PathfindingGraphConnectionType,
+};
+
+/// How long each dash (and each gap) of a drop is
+const DROP_DASH_LENGTH: f32 = 4.0;
+
+const NODE_RADIUS: f32 = 2.0;
+
+pub struct GraphOverlayPlugin;
+
+impl Plugin for GraphOverlayPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            Update,
+            s_render_pathfinding_graph.in_set(PursueAISet::Render),
+        );
+    }
+}
+
+fn connection_color(connection_type: &PathfindingGraphConnectionType) -> Color {
+    match connection_type {
+        PathfindingGraphConnectionType::Walkable => Color::GREEN,
+        PathfindingGraphConnectionType::Jumpable => Color::YELLOW,
+        PathfindingGraphConnectionType::Droppable => Color::ORANGE,
+        PathfindingGraphConnectionType::Climbable => Color::BEIGE,
+        PathfindingGraphConnectionType::Teleport => Color::FUCHSIA,
+        PathfindingGraphConnectionType::Swimmable => Color::BLUE,
+        PathfindingGraphConnectionType::Bounce => Color::PINK,
+        PathfindingGraphConnectionType::WallJumpable => Color::LIME_GREEN,
+        PathfindingGraphConnectionType::Mantle => Color::GOLD,
+        PathfindingGraphConnectionType::Dashable => Color::TURQUOISE,
+        PathfindingGraphConnectionType::DoubleJumpable => Color::VIOLET,
+    }
+}
+
+/// The arc an agent follows on the minimum energy jump from start to end
+fn draw_jump_arc(gizmos: &mut Gizmos, start: Vec2, end: Vec2, color: Color) {
+    gizmos.linestrip_2d(jump_trajectory_points(start, end), color);
+}
+
+fn draw_dashed_line(gizmos: &mut Gizmos, start: Vec2, end: Vec2, color: Color) {
+    let length = start.distance(end);
+    let direction = (end - start).normalize_or_zero();
+
+    let mut dash_start = 0.0;
+    while dash_start < length {
+        let dash_end = (dash_start + DROP_DASH_LENGTH).min(length);
+        gizmos.line_2d(
+            start + direction * dash_start,
+            start + direction * dash_end,
+            color,
+        );
+        dash_start += DROP_DASH_LENGTH * 2.0;
+    }
+}
+
+/// Draws the whole pathfinding graph, with each kind of connection in its own color and
+/// disabled nodes and connections faded out
+///
+/// Corners are ringed, in red if they're external and purple if they're internal
+pub fn s_render_pathfinding_graph(
+    mut gizmos: Gizmos,
+    pathfinding: Res<Pathfinding>,
+    gizmos_visible: Res<GizmosVisible>,
+) {
+    if !gizmos_visible.visible {
+        return;
+    }
+
+    for node in pathfinding.nodes.iter() {
+        for connection in node.connections.iter() {
+            let Some(other_node) = pathfinding.nodes.get(connection.node_id) else {
+                continue;
+            };
+
+            let mut color = connection_color(&connection.connection_type).with_a(0.4);
+            if !pathfinding.is_connection_enabled(node.id, connection.node_id) {
+                color = color.with_a(0.1);
+            }
+
+            match connection.connection_type {
+                PathfindingGraphConnectionType::Jumpable
+                | PathfindingGraphConnectionType::WallJumpable
+                | PathfindingGraphConnectionType::Mantle => {
+                    gizmos.linestrip_2d(
+                        connection_arc_points(node.position, other_node.position, connection),
+                        color,
+                    );
+                }
+                PathfindingGraphConnectionType::DoubleJumpable => {
+                    let midpoint = double_jump_midpoint(node.position, other_node.position);
+                    draw_jump_arc(&mut gizmos, node.position, midpoint, color);
+                    draw_jump_arc(&mut gizmos, midpoint, other_node.position, color);
+                }
+                PathfindingGraphConnectionType::Droppable => {
+                    draw_dashed_line(&mut gizmos, node.position, other_node.position, color);
+                }
+                _ => {
+                    gizmos.line_2d(node.position, other_node.position, color);
+                }
+            }
+        }
+    }
+
+    for node in pathfinding.nodes.iter() {
+        let color = if pathfinding.is_node_enabled(node.id) {
+            Color::WHITE
+        } else {
+            Color::GRAY.with_a(0.3)
+        };
+
+        gizmos.circle_2d(node.position, NODE_RADIUS, color);
+
+        match node.is_external_corner {
+            Some(true) => {
+                gizmos.circle_2d(node.position, NODE_RADIUS * 2.5, Color::ORANGE_RED);
+            }
+            Some(false) => {
+                gizmos.circle_2d(node.position, NODE_RADIUS * 2.5, Color::PURPLE);
+            }
+            None => {}
+        }
+    }
+}
@@ -0,0 +1,171 @@
+use bevy::{
+    app::{App, FixedUpdate, Plugin, Update},
+    ecs::{
+        entity::Entity,
+        query::With,
+        schedule::IntoSystemConfigs,
+        system::{Query, Res, ResMut},
+    },
+    input::{keyboard::KeyCode, ButtonInput},
+    math::{Vec2, Vec3Swizzles},
+    reflect::Reflect,
+    transform::components::Transform,
+};
+
+use crate::PursueAISet;
+
+use super::{pathfinding::Pathfinding, platformer_ai::PlatformerAI};
+
+/// How much of the danger on a node is left after each tick
+pub const DANGER_DECAY_PER_TICK: f32 = 0.97;
+
+/// How far from the goal point its danger reaches (roughly its attack range)
+pub const GOAL_DANGER_RADIUS: f32 = 64.0;
+
+/// How far from an agent its danger reaches, pushing other agents to spread out
+pub const AGENT_DANGER_RADIUS: f32 = 48.0;
+
+/// How much a fully dangerous node adds to the g-cost of reaching it
+pub const DANGER_COST_WEIGHT: f32 = 200.0;
+
+pub struct InfluenceMapPlugin;
+
+impl Plugin for InfluenceMapPlugin {
+    fn build(&self, app: &mut App) {
+        app.register_type::<InfluenceMap>()
+            .add_systems(Update, s_influence_map_input)
+            .add_systems(
+                FixedUpdate,
+                s_update_influence_map.in_set(PursueAISet::Perception),
+            );
+    }
+}
+
+/// A per-node dynamic cost layer that A* adds on top of the connection costs
+#[derive(Reflect)]
+pub struct InfluenceMap {
+    pub enabled: bool,
+    /// Danger of each node, from 0.0 (safe) to 1.0 (as dangerous as it gets)
+    pub danger: Vec<f32>,
+    /// The agent that left the danger on each node, or nothing if the goal did, so that a
+    /// search can leave out the danger of the agent it's searching for
+    pub sources: Vec<Option<Entity>>,
+    pub decay_per_tick: f32,
+    pub goal_danger_radius: f32,
+    pub agent_danger_radius: f32,
+    pub cost_weight: f32,
+}
+
+impl Default for InfluenceMap {
+    fn default() -> Self {
+        InfluenceMap {
+            enabled: false,
+            danger: Vec::new(),
+            sources: Vec::new(),
+            decay_per_tick: DANGER_DECAY_PER_TICK,
+            goal_danger_radius: GOAL_DANGER_RADIUS,
+            agent_danger_radius: AGENT_DANGER_RADIUS,
+            cost_weight: DANGER_COST_WEIGHT,
+        }
+    }
+}
+
+impl InfluenceMap {
+    /// The extra g-cost of stepping onto a node, for the given agent if it's an agent that's
+    /// searching
+    ///
+    /// An agent's own danger doesn't count against it, otherwise it'd flee its own footprint
+    pub fn cost(&self, node_id: usize, searcher: Option<Entity>) -> f32 {
+        if !self.enabled {
+            return 0.0;
+        }
+
+        if searcher.is_some() && self.sources.get(node_id) == Some(&searcher) {
+            return 0.0;
+        }
+
+        self.danger.get(node_id).unwrap_or(&0.0) * self.cost_weight
+    }
+
+    /// Raises the danger of the given nodes, strongest at the center of the radius
+    ///
+    /// Each node remembers the source of whichever danger on it is strongest
+    pub fn stamp(
+        &mut self,
+        nodes: &[(usize, Vec2)],
+        center: Vec2,
+        radius: f32,
+        source: Option<Entity>,
+    ) {
+        for (node_index, node_position) in nodes.iter().cloned() {
+            let distance = (node_position - center).length();
+
+            if distance >= radius {
+                continue;
+            }
+
+            let danger = 1.0 - distance / radius;
+
+            if danger > self.danger[node_index] {
+                self.danger[node_index] = danger;
+                self.sources[node_index] = source;
+            }
+        }
+    }
+
+    pub fn decay(&mut self) {
+        for danger in self.danger.iter_mut() {
+            *danger *= self.decay_per_tick;
+        }
+    }
+}
+
+pub fn s_influence_map_input(
+    keyboard_input: Res<ButtonInput<KeyCode>>,
+    mut pathfinding: ResMut<Pathfinding>,
+) {
+    // I to toggle the influence map
+    if keyboard_input.just_pressed(KeyCode::KeyI) {
+        pathfinding.influence.enabled = !pathfinding.influence.enabled;
+        println!("Influence map: {}", pathfinding.influence.enabled);
+    }
+}
+
+pub fn s_update_influence_map(
+    mut pathfinding: ResMut<Pathfinding>,
+    agent_query: Query<(Entity, &Transform), With<PlatformerAI>>,
+) {
+    let node_count = pathfinding.nodes.len();
+    pathfinding.influence.danger.resize(node_count, 0.0);
+    pathfinding.influence.sources.resize(node_count, None);
+
+    pathfinding.influence.decay();
+
+    // Danger around the goal point
+    if pathfinding.active {
+        let goal_position = pathfinding.goal_position;
+        let radius = pathfinding.influence.goal_danger_radius;
+        let nodes = nodes_within_radius(&pathfinding, goal_position, radius);
+        pathfinding
+            .influence
+            .stamp(&nodes, goal_position, radius, None);
+    }
+
+    // Danger around each agent
+    for (entity, transform) in agent_query.iter() {
+        let agent_position = transform.translation.xy();
+        let radius = pathfinding.influence.agent_danger_radius;
+        let nodes = nodes_within_radius(&pathfinding, agent_position, radius);
+        pathfinding
+            .influence
+            .stamp(&nodes, agent_position, radius, Some(entity));
+    }
+}
+
+fn nodes_within_radius(pathfinding: &Pathfinding, center: Vec2, radius: f32) -> Vec<(usize, Vec2)> {
+    pathfinding
+        .node_ids_within_radius(center, radius)
+        .iter()
+        .map(|node_id| (*node_id, pathfinding.nodes[*node_id].position))
+        .collect()
+}
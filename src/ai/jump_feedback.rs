@@ -0,0 +1,166 @@
+use bevy::{
+    app::{App, FixedUpdate, Plugin, Update},
+    ecs::{
+        change_detection::DetectChanges,
+        entity::Entity,
+        reflect::ReflectResource,
+        schedule::IntoSystemConfigs,
+        system::{Query, Res, ResMut, Resource},
+    },
+    math::{Vec2, Vec3Swizzles},
+    reflect::Reflect,
+    transform::components::Transform,
+    utils::HashMap,
+};
+
+use crate::{
+    collisions::s_collision,
+    event_log::{EventLog, LogCategory},
+    settings::{JumpFeedbackSettings, Settings},
+    Physics, PursueAISet,
+};
+
+use super::{
+    pathfinding::{s_rebuild_pathfinding_graph, Pathfinding, PathfindingSettings},
+    platformer_ai::PlatformerAI,
+};
+
+pub struct JumpFeedbackPlugin;
+
+impl Plugin for JumpFeedbackPlugin {
+    fn build(&self, app: &mut App) {
+        app.register_type::<JumpRecord>()
+            .register_type::<JumpFeedback>()
+            .insert_resource(JumpFeedback::default())
+            .add_systems(
+                FixedUpdate,
+                s_jump_feedback
+                    .in_set(PursueAISet::Collision)
+                    .after(s_collision),
+            )
+            .add_systems(
+                Update,
+                s_reset_jump_feedback.after(s_rebuild_pathfinding_graph),
+            );
+    }
+}
+
+/// How the jumps along one connection have been going
+#[derive(Debug, Clone, Copy, Default, Reflect)]
+pub struct JumpRecord {
+    pub successes: u32,
+    pub failures: u32,
+    /// Failures not yet made up for by successes, which is what the penalty's based on
+    pub failure_streak: u32,
+    /// The tick the penalty wears off
+    pub penalty_until: u32,
+}
+
+impl JumpRecord {
+    pub fn penalty(&self, settings: &JumpFeedbackSettings) -> f32 {
+        (1.0 + self.failure_streak as f32 * settings.failure_penalty).min(settings.max_penalty)
+    }
+}
+
+/// Watches how the agents' jumps turn out and makes the connections they keep missing cost
+/// more for a while, so the planner goes around jumps the graph thinks work but the physics
+/// doesn't agree with
+#[derive(Resource, Default, Reflect)]
+#[reflect(Resource)]
+pub struct JumpFeedback {
+    pub tick: u32,
+    pub records: HashMap<(usize, usize), JumpRecord>,
+    /// The connection and landing spot of each jump still in the air
+    pub pending_jumps: HashMap<Entity, ((usize, usize), Vec2)>,
+}
+
+pub fn s_jump_feedback(
+    mut jump_feedback: ResMut<JumpFeedback>,
+    mut pathfinding: ResMut<Pathfinding>,
+    settings: Res<Settings>,
+    agent_query: Query<(Entity, &Transform, &Physics, &PlatformerAI)>,
+    mut event_log: ResMut<EventLog>,
+) {
+    let settings = &settings.jump_feedback;
+    let tick = jump_feedback.tick;
+    jump_feedback.tick += 1;
+
+    for (entity, transform, physics, platformer_ai) in agent_query.iter() {
+        let pending_jump = jump_feedback.pending_jumps.get(&entity).copied();
+
+        // Jumps are over once whatever ended them (landing, a head bump, a teleport) clears
+        // the target
+        let (connection, jump_to_pos) = match (platformer_ai.jump_to_pos, pending_jump) {
+            (Some(jump_to_pos), None) => {
+                if let Some(connection) = platformer_ai.jump_connection {
+                    jump_feedback
+                        .pending_jumps
+                        .insert(entity, (connection, jump_to_pos));
+                }
+                continue;
+            }
+            (None, Some(pending_jump)) => {
+                jump_feedback.pending_jumps.remove(&entity);
+                pending_jump
+            }
+            _ => continue,
+        };
+
+        let made_it = physics.grounded
+            && transform.translation.xy().distance(jump_to_pos) <= settings.landing_tolerance;
+
+        let record = jump_feedback.records.entry(connection).or_default();
+        if made_it {
+            record.successes += 1;
+            record.failure_streak = record.failure_streak.saturating_sub(1);
+        } else {
+            record.failures += 1;
+            record.failure_streak += 1;
+            record.penalty_until = tick + settings.penalty_ticks;
+
+            event_log.log(
+                LogCategory::Jump,
+                format!(
+                    "Agent {} missed jump {} -> {} ({} of {} missed)",
+                    entity.index(),
+                    connection.0,
+                    connection.1,
+                    record.failures,
+                    record.failures + record.successes
+                ),
+            );
+        }
+
+        let penalty = record.penalty(settings);
+        pathfinding.set_connection_penalty(connection.0, connection.1, penalty);
+    }
+
+    // Penalties wear off, so a jump gets tried again once whatever was in the way might not be
+    let expired: Vec<(usize, usize)> = jump_feedback
+        .records
+        .iter()
+        .filter(|(_, record)| record.failure_streak > 0 && record.penalty_until <= tick)
+        .map(|(connection, _)| *connection)
+        .collect();
+
+    for connection in expired {
+        if let Some(record) = jump_feedback.records.get_mut(&connection) {
+            record.failure_streak = 0;
+        }
+        pathfinding.set_connection_penalty(connection.0, connection.1, 1.0);
+    }
+}
+
+/// Rebuilding the graph renumbers the nodes, so what was learned about the old connections
+/// doesn't apply any more
+pub fn s_reset_jump_feedback(
+    settings: Res<PathfindingSettings>,
+    mut jump_feedback: ResMut<JumpFeedback>,
+) {
+    if !settings.is_changed() || settings.is_added() {
+        return;
+    }
+
+    jump_feedback.records.clear();
+    jump_feedback.pending_jumps.clear();
+}
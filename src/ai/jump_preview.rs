@@ -0,0 +1,160 @@
+use bevy::{
+    app::{App, FixedUpdate, Plugin, Update},
+    ecs::{
+        entity::Entity,
+        schedule::IntoSystemConfigs,
+        system::{Query, Res, ResMut, Resource},
+    },
+    gizmos::gizmos::Gizmos,
+    math::{Vec2, Vec3Swizzles},
+    render::color::Color,
+    transform::components::Transform,
+    utils::HashMap,
+};
+
+use crate::{collisions::s_collision, GizmosVisible, PursueAISet};
+
+use super::{
+    pathfinding::{connection_arc_points, Pathfinding, PathfindingGraphConnectionType},
+    platformer_ai::PlatformerAI,
+};
+
+/// The most points of a flight kept, so an agent that never lands can't grow it forever
+const JUMP_FLIGHT_MAX_POINTS: usize = 600;
+
+pub struct JumpPreviewPlugin;
+
+impl Plugin for JumpPreviewPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(JumpFlights::default())
+            .add_systems(
+                FixedUpdate,
+                s_record_jump_flights
+                    .in_set(PursueAISet::Collision)
+                    .after(s_collision),
+            )
+            .add_systems(Update, s_render_jump_preview.in_set(PursueAISet::Render));
+    }
+}
+
+/// A jump as it was planned and as it actually went
+#[derive(Debug, Clone, Default)]
+pub struct JumpFlight {
+    /// The arc the graph was built with, between the connection's nodes
+    pub planned: Vec<Vec2>,
+    /// Where the agent really was on every tick of the jump
+    pub actual: Vec<Vec2>,
+    pub in_air: bool,
+}
+
+/// The latest jump of each agent, kept after it lands until the next one so the two paths can
+/// be compared
+#[derive(Resource, Default)]
+pub struct JumpFlights {
+    pub flights: HashMap<Entity, JumpFlight>,
+}
+
+pub fn s_record_jump_flights(
+    mut jump_flights: ResMut<JumpFlights>,
+    pathfinding: Res<Pathfinding>,
+    agent_query: Query<(Entity, &Transform, &PlatformerAI)>,
+) {
+    for (entity, transform, platformer_ai) in agent_query.iter() {
+        let position = transform.translation.xy();
+        let in_air = platformer_ai.jump_to_pos.is_some();
+        let flight = jump_flights.flights.entry(entity).or_default();
+
+        match (in_air, flight.in_air) {
+            // Just took off, so the last jump makes way for this one
+            (true, false) => {
+                let planned = platformer_ai
+                    .jump_connection
+                    .and_then(|(from_node_id, to_node_id)| {
+                        let from = pathfinding.nodes.get(from_node_id)?;
+                        let to = pathfinding.nodes.get(to_node_id)?;
+                        let connection = from
+                            .connections
+                            .iter()
+                            .find(|connection| connection.node_id == to_node_id)?;
+
+                        Some(connection_arc_points(
+                            from.position,
+                            to.position,
+                            connection,
+                        ))
+                    })
+                    .unwrap_or_default();
+
+                *flight = JumpFlight {
+                    planned,
+                    actual: vec![position],
+                    in_air: true,
+                };
+            }
+            (true, true) => {
+                if flight.actual.len() < JUMP_FLIGHT_MAX_POINTS {
+                    flight.actual.push(position);
+                }
+            }
+            // Landed (or was knocked out of it), with the landing spot as the last point
+            (false, true) => {
+                flight.actual.push(position);
+                flight.in_air = false;
+            }
+            (false, false) => {}
+        }
+    }
+
+    jump_flights
+        .flights
+        .retain(|entity, _| agent_query.contains(*entity));
+}
+
+/// Draws the arc of the jump each agent's about to make, and how its last jump went next to how
+/// it was planned
+pub fn s_render_jump_preview(
+    mut gizmos: Gizmos,
+    gizmos_visible: Res<GizmosVisible>,
+    pathfinding: Res<Pathfinding>,
+    jump_flights: Res<JumpFlights>,
+    agent_query: Query<&PlatformerAI>,
+) {
+    if !gizmos_visible.visible {
+        return;
+    }
+
+    // The next leg, if it's a jump the agent hasn't made yet
+    for platformer_ai in agent_query.iter() {
+        if platformer_ai.jump_to_pos.is_some() {
+            continue;
+        }
+
+        let [from, to, ..] = platformer_ai.path.as_slice() else {
+            continue;
+        };
+
+        let jump_connection = pathfinding.nodes.get(from.id).and_then(|node| {
+            node.connections.iter().find(|connection| {
+                connection.node_id == to.id
+                    && matches!(
+                        connection.connection_type,
+                        PathfindingGraphConnectionType::Jumpable
+                            | PathfindingGraphConnectionType::WallJumpable
+                            | PathfindingGraphConnectionType::Mantle
+                    )
+            })
+        });
+
+        if let Some(connection) = jump_connection {
+            gizmos.linestrip_2d(
+                connection_arc_points(from.position, to.position, connection),
+                Color::YELLOW.with_a(0.5),
+            );
+        }
+    }
+
+    for flight in jump_flights.flights.values() {
+        gizmos.linestrip_2d(flight.planned.iter().copied(), Color::YELLOW);
+        gizmos.linestrip_2d(flight.actual.iter().copied(), Color::ORANGE_RED);
+    }
+}
@@ -0,0 +1,197 @@
+use std::cmp::Ordering;
+
+use bevy::math::Vec2;
+
+/// A static 2D KD-tree over points (like the pathfinding nodes), for nearest and radius queries
+///
+/// The tree is stored implicitly: each slice of `entries` has its splitting
+/// entry in the middle, with the entries before it on one side of the split
+/// and the entries after it on the other
+#[derive(Default)]
+pub struct KdTree {
+    entries: Vec<KdTreeEntry>,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct KdTreeEntry {
+    position: Vec2,
+    id: usize,
+}
+
+struct KNearestQuery<'a> {
+    position: Vec2,
+    k: usize,
+    filter: &'a dyn Fn(usize) -> bool,
+}
+
+impl KdTree {
+    pub fn build(points: impl Iterator<Item = (usize, Vec2)>) -> KdTree {
+        let mut entries: Vec<KdTreeEntry> = points
+            .map(|(id, position)| KdTreeEntry { position, id })
+            .collect();
+
+        build_recursive(&mut entries, 0);
+
+        KdTree { entries }
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// The id of the point closest to the position
+    pub fn nearest(&self, position: Vec2) -> Option<usize> {
+        self.nearest_filtered(position, |_| true)
+    }
+
+    /// The id of the closest point that passes the filter
+    pub fn nearest_filtered(
+        &self,
+        position: Vec2,
+        filter: impl Fn(usize) -> bool,
+    ) -> Option<usize> {
+        self.k_nearest_filtered(position, 1, filter)
+            .first()
+            .map(|(id, _)| *id)
+    }
+
+    /// The ids and squared distances of the k closest points, closest first
+    pub fn k_nearest(&self, position: Vec2, k: usize) -> Vec<(usize, f32)> {
+        self.k_nearest_filtered(position, k, |_| true)
+    }
+
+    /// The ids and squared distances of the k closest points that pass the filter, closest first
+    pub fn k_nearest_filtered(
+        &self,
+        position: Vec2,
+        k: usize,
+        filter: impl Fn(usize) -> bool,
+    ) -> Vec<(usize, f32)> {
+        let mut best: Vec<(usize, f32)> = Vec::with_capacity(k + 1);
+
+        let query = KNearestQuery {
+            position,
+            k,
+            filter: &filter,
+        };
+
+        if k > 0 {
+            self.k_nearest_recursive(0, self.entries.len(), 0, &query, &mut best);
+        }
+
+        best
+    }
+
+    /// The ids of every point within the radius of the position
+    pub fn within_radius(&self, position: Vec2, radius: f32) -> Vec<usize> {
+        let mut found: Vec<usize> = Vec::new();
+
+        self.within_radius_recursive(0, self.entries.len(), 0, position, radius, &mut found);
+
+        found
+    }
+
+    fn k_nearest_recursive(
+        &self,
+        start: usize,
+        end: usize,
+        depth: usize,
+        query: &KNearestQuery,
+        best: &mut Vec<(usize, f32)>,
+    ) {
+        let (position, k) = (query.position, query.k);
+
+        if start >= end {
+            return;
+        }
+
+        let mid = start + (end - start) / 2;
+        let entry = self.entries[mid];
+
+        // Consider the splitting point itself
+        if (query.filter)(entry.id) {
+            let distance = (entry.position - position).length_squared();
+
+            if best.len() < k || distance < best[best.len() - 1].1 {
+                let insert_index = best
+                    .iter()
+                    .position(|(_, best_distance)| distance < *best_distance)
+                    .unwrap_or(best.len());
+                best.insert(insert_index, (entry.id, distance));
+                best.truncate(k);
+            }
+        }
+
+        let axis = depth % 2;
+        let axis_delta = position[axis] - entry.position[axis];
+
+        let (near, far) = if axis_delta < 0.0 {
+            ((start, mid), (mid + 1, end))
+        } else {
+            ((mid + 1, end), (start, mid))
+        };
+
+        // Search the side the position is on first
+        self.k_nearest_recursive(near.0, near.1, depth + 1, query, best);
+
+        // Only search the other side if it could hold something closer
+        if best.len() < k || axis_delta * axis_delta < best[best.len() - 1].1 {
+            self.k_nearest_recursive(far.0, far.1, depth + 1, query, best);
+        }
+    }
+
+    fn within_radius_recursive(
+        &self,
+        start: usize,
+        end: usize,
+        depth: usize,
+        position: Vec2,
+        radius: f32,
+        found: &mut Vec<usize>,
+    ) {
+        if start >= end {
+            return;
+        }
+
+        let mid = start + (end - start) / 2;
+        let entry = self.entries[mid];
+
+        if (entry.position - position).length_squared() <= radius * radius {
+            found.push(entry.id);
+        }
+
+        let axis = depth % 2;
+        let axis_delta = position[axis] - entry.position[axis];
+
+        if axis_delta - radius <= 0.0 {
+            self.within_radius_recursive(start, mid, depth + 1, position, radius, found);
+        }
+        if axis_delta + radius >= 0.0 {
+            self.within_radius_recursive(mid + 1, end, depth + 1, position, radius, found);
+        }
+    }
+}
+
+fn build_recursive(entries: &mut [KdTreeEntry], depth: usize) {
+    if entries.len() <= 1 {
+        return;
+    }
+
+    let axis = depth % 2;
+    let mid = entries.len() / 2;
+
+    // Put the median in the middle with smaller entries before and larger ones after
+    entries.select_nth_unstable_by(mid, |a, b| {
+        a.position[axis]
+            .partial_cmp(&b.position[axis])
+            .unwrap_or(Ordering::Equal)
+    });
+
+    let (before, after) = entries.split_at_mut(mid);
+    build_recursive(before, depth + 1);
+    build_recursive(&mut after[1..], depth + 1);
+}
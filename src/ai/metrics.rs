@@ -0,0 +1,295 @@
+use std::fmt::Write;
+
+use bevy::{
+    app::{App, AppExit, FixedUpdate, Last, Plugin},
+    ecs::{
+        entity::Entity,
+        event::EventReader,
+        query::{With, Without},
+        schedule::IntoSystemConfigs,
+        system::{Query, Res, ResMut, Resource},
+    },
+    math::{Vec2, Vec3Swizzles},
+    transform::components::Transform,
+    utils::HashMap,
+};
+
+use crate::{
+    collisions::s_collision, settings::Settings, simulation::SIMULATION_TICK_RATE_HZ, GoalPoint,
+    Physics, PursueAISet,
+};
+
+use super::{
+    a_star::path_length,
+    pathfinding::Pathfinding,
+    platformer_ai::{PlatformerAI, PLATFORMER_AI_AGENT_RADIUS},
+};
+
+#[cfg(not(target_arch = "wasm32"))]
+const METRICS_FILE_PATH: &str = "metrics.csv";
+
+/// How many ticks go into each row of the CSV
+const METRICS_SAMPLE_TICKS: u32 = SIMULATION_TICK_RATE_HZ as u32;
+
+/// How far an agent has to move to not be counted as stuck
+const METRICS_STUCK_DISTANCE: f32 = 8.0;
+
+/// How long an agent can go without getting anywhere before it counts as stuck
+const METRICS_STUCK_TICKS: u32 = (SIMULATION_TICK_RATE_HZ * 5.0) as u32;
+
+/// How far every agent has to get from the goal after catching it before the next catch counts
+const METRICS_RECAPTURE_DISTANCE: f32 = PLATFORMER_AI_AGENT_RADIUS * 4.0;
+
+pub struct MetricsPlugin;
+
+impl Plugin for MetricsPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(AIMetrics::default())
+            .add_systems(
+                FixedUpdate,
+                s_collect_metrics
+                    .in_set(PursueAISet::Collision)
+                    .after(s_collision),
+            )
+            .add_systems(Last, s_save_metrics_on_exit);
+    }
+}
+
+/// The totals for one stretch of the run, which ends up as a row of the CSV
+#[derive(Debug, Clone, Copy, Default)]
+pub struct MetricsSample {
+    pub path_searches: u32,
+    pub nodes_expanded: usize,
+    pub path_length: f32,
+    pub captures: u32,
+    pub capture_ticks: u32,
+    pub stuck_events: u32,
+    pub jumps_succeeded: u32,
+    pub jumps_failed: u32,
+}
+
+impl MetricsSample {
+    fn add(&mut self, other: &MetricsSample) {
+        self.path_searches += other.path_searches;
+        self.nodes_expanded += other.nodes_expanded;
+        self.path_length += other.path_length;
+        self.captures += other.captures;
+        self.capture_ticks += other.capture_ticks;
+        self.stuck_events += other.stuck_events;
+        self.jumps_succeeded += other.jumps_succeeded;
+        self.jumps_failed += other.jumps_failed;
+    }
+
+    fn average(total: f32, count: u32) -> f32 {
+        if count == 0 {
+            0.0
+        } else {
+            total / count as f32
+        }
+    }
+
+    pub fn average_nodes_expanded(&self) -> f32 {
+        Self::average(self.nodes_expanded as f32, self.path_searches)
+    }
+
+    pub fn average_path_length(&self) -> f32 {
+        Self::average(self.path_length, self.path_searches)
+    }
+
+    /// The average time to capture, in seconds
+    pub fn average_capture_time(&self) -> f32 {
+        Self::average(self.capture_ticks as f32, self.captures) / SIMULATION_TICK_RATE_HZ as f32
+    }
+
+    pub fn jump_success_rate(&self) -> f32 {
+        Self::average(
+            self.jumps_succeeded as f32,
+            self.jumps_succeeded + self.jumps_failed,
+        )
+    }
+}
+
+/// Statistics on how the agents are doing, so tuning changes can be compared offline
+///
+/// Collected every tick into one sample per second of simulated time, and written out as
+/// `metrics.csv` when the app exits
+#[derive(Resource, Default)]
+pub struct AIMetrics {
+    pub tick: u32,
+    pub samples: Vec<MetricsSample>,
+    pub current: MetricsSample,
+    /// The tick the goal last became catchable, either by being switched on or by being let go
+    pub chase_start_tick: Option<u32>,
+    /// Whether an agent's caught the goal and not let go yet
+    pub captured: bool,
+    /// Where each agent last got somewhere, on which tick, and whether it's been counted as stuck
+    pub last_progress: HashMap<Entity, (Vec2, u32, bool)>,
+    /// The node each airborne agent's jumping to
+    pub pending_jumps: HashMap<Entity, Vec2>,
+}
+
+impl AIMetrics {
+    pub fn totals(&self) -> MetricsSample {
+        let mut totals = self.current;
+        for sample in self.samples.iter() {
+            totals.add(sample);
+        }
+        totals
+    }
+
+    /// How many path searches the agents run per second, on average
+    pub fn searches_per_second(&self) -> f32 {
+        if self.tick == 0 {
+            return 0.0;
+        }
+
+        self.totals().path_searches as f32 * SIMULATION_TICK_RATE_HZ as f32 / self.tick as f32
+    }
+
+    pub fn to_csv(&self) -> String {
+        let mut csv = String::from(
+            "second,path_searches,avg_nodes_expanded,avg_path_length,captures,avg_capture_time,stuck_events,jumps_succeeded,jumps_failed\n",
+        );
+
+        for (second, sample) in self.samples.iter().chain([&self.current]).enumerate() {
+            let _ = writeln!(
+                csv,
+                "{},{},{:.2},{:.2},{},{:.2},{},{},{}",
+                second,
+                sample.path_searches,
+                sample.average_nodes_expanded(),
+                sample.average_path_length(),
+                sample.captures,
+                sample.average_capture_time(),
+                sample.stuck_events,
+                sample.jumps_succeeded,
+                sample.jumps_failed
+            );
+        }
+
+        csv
+    }
+}
+
+pub fn s_collect_metrics(
+    mut metrics: ResMut<AIMetrics>,
+    pathfinding: Res<Pathfinding>,
+    settings: Res<Settings>,
+    agent_query: Query<(Entity, &Transform, &Physics, &PlatformerAI), Without<GoalPoint>>,
+    goal_query: Query<(&Transform, &Physics), With<GoalPoint>>,
+) {
+    let tick = metrics.tick;
+    metrics.tick += 1;
+
+    let goal = goal_query
+        .get_single()
+        .ok()
+        .map(|(transform, physics)| (transform.translation.xy(), physics.radius));
+
+    if !pathfinding.active {
+        metrics.chase_start_tick = None;
+    } else if metrics.chase_start_tick.is_none() {
+        metrics.chase_start_tick = Some(tick);
+    }
+
+    let mut any_touching = false;
+    let mut all_clear = true;
+
+    for (entity, transform, physics, platformer_ai) in agent_query.iter() {
+        let position = transform.translation.xy();
+
+        // The counter's only back at zero on the tick a new path was searched
+        if platformer_ai.ticks_since_replan == 0 {
+            metrics.current.path_searches += 1;
+            metrics.current.nodes_expanded += platformer_ai.last_search_stats.nodes_expanded;
+            metrics.current.path_length += path_length(position, &platformer_ai.path);
+        }
+
+        // Jumps are over once whatever ended them clears the target
+        match (
+            platformer_ai.jump_to_pos,
+            metrics.pending_jumps.get(&entity).copied(),
+        ) {
+            (Some(jump_to_pos), None) => {
+                metrics.pending_jumps.insert(entity, jump_to_pos);
+            }
+            (None, Some(jump_to_pos)) => {
+                metrics.pending_jumps.remove(&entity);
+
+                if physics.grounded
+                    && position.distance(jump_to_pos) <= settings.jump_feedback.landing_tolerance
+                {
+                    metrics.current.jumps_succeeded += 1;
+                } else {
+                    metrics.current.jumps_failed += 1;
+                }
+            }
+            _ => {}
+        }
+
+        if let Some((goal_position, goal_radius)) = goal {
+            let distance = position.distance(goal_position) - physics.radius - goal_radius;
+            any_touching |= distance <= 0.0;
+            all_clear &= distance > METRICS_RECAPTURE_DISTANCE;
+        }
+
+        // Standing still is only being stuck while there's something to chase
+        let (progress_position, progress_tick, counted) = *metrics
+            .last_progress
+            .entry(entity)
+            .or_insert((position, tick, false));
+
+        if position.distance(progress_position) > METRICS_STUCK_DISTANCE || !pathfinding.active {
+            metrics
+                .last_progress
+                .insert(entity, (position, tick, false));
+        } else if tick - progress_tick >= METRICS_STUCK_TICKS && !counted {
+            metrics.current.stuck_events += 1;
+            metrics
+                .last_progress
+                .insert(entity, (progress_position, progress_tick, true));
+        }
+    }
+
+    if let Some(chase_start_tick) = metrics.chase_start_tick {
+        if any_touching && !metrics.captured {
+            metrics.captured = true;
+            metrics.current.captures += 1;
+            metrics.current.capture_ticks += tick - chase_start_tick;
+        } else if all_clear && metrics.captured {
+            metrics.captured = false;
+            metrics.chase_start_tick = Some(tick);
+        }
+    }
+
+    if metrics.tick.is_multiple_of(METRICS_SAMPLE_TICKS) {
+        let sample = std::mem::take(&mut metrics.current);
+        metrics.samples.push(sample);
+    }
+}
+
+pub fn save_metrics(metrics: &AIMetrics) {
+    let totals = metrics.totals();
+    println!(
+        "Path searches/s: {:.1}, avg nodes expanded: {:.1}, avg path length: {:.1}, captures: {} (avg {:.2}s), stuck events: {}, jump success rate: {:.2}",
+        metrics.searches_per_second(),
+        totals.average_nodes_expanded(),
+        totals.average_path_length(),
+        totals.captures,
+        totals.average_capture_time(),
+        totals.stuck_events,
+        totals.jump_success_rate()
+    );
+
+    #[cfg(not(target_arch = "wasm32"))]
+    match std::fs::write(METRICS_FILE_PATH, metrics.to_csv()) {
+        Ok(()) => println!("Saved metrics to {}", METRICS_FILE_PATH),
+        Err(error) => println!("Failed to save metrics: {}", error),
+    }
+}
+
+pub fn s_save_metrics_on_exit(mut exit_events: EventReader<AppExit>, metrics: Res<AIMetrics>) {
+    if exit_events.read().next().is_some() {
+        save_metrics(&metrics);
+    }
+}
@@ -1,3 +1,27 @@
 pub mod a_star;
+pub mod animation;
+#[cfg(feature = "debug-draw")]
+pub mod cost_heatmap;
+pub mod evader;
+pub mod funnel;
+pub mod graph_export;
+#[cfg(feature = "debug-draw")]
+pub mod graph_overlay;
+pub mod influence_map;
+pub mod jump_feedback;
+#[cfg(feature = "debug-draw")]
+pub mod jump_preview;
+pub mod kd_tree;
+pub mod metrics;
+pub mod navmesh;
 pub mod pathfinding;
+#[cfg(feature = "petgraph")]
+pub mod petgraph_graph;
 pub mod platformer_ai;
+pub mod pursuit;
+#[cfg(feature = "scripting")]
+pub mod scripting;
+#[cfg(feature = "debug-draw")]
+pub mod search_replay;
+#[cfg(feature = "pursue")]
+pub mod squad;
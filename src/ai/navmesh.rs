@@ -0,0 +1,506 @@
+use std::{
+    cmp::Ordering,
+    collections::{BinaryHeap, HashMap},
+};
+
+use bevy::{
+    app::{App, Plugin, Update},
+    ecs::{
+        change_detection::DetectChanges,
+        query::With,
+        schedule::IntoSystemConfigs,
+        system::{Query, Res, ResMut, Resource},
+    },
+    gizmos::gizmos::Gizmos,
+    math::{Vec2, Vec3Swizzles},
+    render::color::Color,
+    transform::components::Transform,
+};
+
+use crate::{level::Level, GizmosVisible, PursueAISet};
+
+use super::{
+    pathfinding::{
+        s_rebuild_pathfinding_graph, walkable_lines, Pathfinding, PathfindingGraphConnectionType,
+        PathfindingGraphNode, PathfindingSettings,
+    },
+    platformer_ai::PlatformerAI,
+};
+
+pub struct NavMeshPlugin;
+
+impl Plugin for NavMeshPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(NavMesh::default()).add_systems(
+            Update,
+            (
+                s_rebuild_navmesh.after(s_rebuild_pathfinding_graph),
+                s_render_navmesh.in_set(PursueAISet::Render),
+            ),
+        );
+    }
+}
+
+/// A walkable polygon line, kept whole instead of being cut up into nodes
+#[derive(Debug, Clone)]
+pub struct SurfaceSegment {
+    pub id: usize,
+    pub start: Vec2,
+    pub end: Vec2,
+    pub polygon_index: usize,
+    pub line_index: usize,
+    /// Points along the segment a path can turn at: its ends and the ends of its links
+    pub anchor_ids: Vec<usize>,
+}
+
+impl SurfaceSegment {
+    pub fn point_at(&self, t: f32) -> Vec2 {
+        self.start.lerp(self.end, t)
+    }
+
+    /// The parameter of the point on the segment closest to the position
+    pub fn closest_t(&self, position: Vec2) -> f32 {
+        let segment = self.end - self.start;
+
+        if segment.length_squared() == 0.0 {
+            return 0.0;
+        }
+
+        ((position - self.start).dot(segment) / segment.length_squared()).clamp(0.0, 1.0)
+    }
+}
+
+/// A position on a surface, given as how far along its segment it is
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SurfacePoint {
+    pub segment_id: usize,
+    pub t: f32,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum OffMeshLinkType {
+    Jump,
+    Drop,
+    Climb,
+    Teleport,
+    Swim,
+    Bounce,
+    WallJump,
+    Mantle,
+    Dash,
+    DoubleJump,
+}
+
+/// A jump, drop, climb, teleport, swim, bounce, wall jump, mantle, dash or double jump between
+/// two surfaces
+#[derive(Debug, Clone)]
+pub struct OffMeshLink {
+    pub from_anchor_id: usize,
+    pub to_anchor_id: usize,
+    pub link_type: OffMeshLinkType,
+    pub dist: f32,
+}
+
+/// A point on a segment that a path can pass through
+#[derive(Debug, Clone)]
+pub struct Anchor {
+    pub point: SurfacePoint,
+    pub position: Vec2,
+    /// (anchor id, cost) of every anchor reachable straight from this one
+    pub edges: Vec<(usize, f32)>,
+}
+
+/// Walkable surfaces stored as continuous segments, joined by off-mesh jump and drop links
+///
+/// Paths over it can start, end and turn anywhere along a surface, so they aren't bent
+/// toward wherever the graph nodes happen to be placed
+#[derive(Resource, Default)]
+pub struct NavMesh {
+    pub segments: Vec<SurfaceSegment>,
+    pub anchors: Vec<Anchor>,
+    pub links: Vec<OffMeshLink>,
+}
+
+impl NavMesh {
+    /// Builds the navmesh from the level's walkable lines, reusing the jumps already worked
+    /// out for the pathfinding graph as its off-mesh links
+    pub fn build(level: &Level, pathfinding: &Pathfinding) -> NavMesh {
+        let mut navmesh = NavMesh::default();
+
+        let mut segment_lookup: HashMap<(usize, usize), usize> = HashMap::new();
+
+        for (polygon_index, line_index) in walkable_lines(level, false) {
+            let polygon = &level.polygons[polygon_index];
+
+            let segment_id = navmesh.segments.len();
+            navmesh.segments.push(SurfaceSegment {
+                id: segment_id,
+                start: polygon.points[line_index],
+                end: polygon.points[line_index + 1],
+                polygon_index,
+                line_index,
+                anchor_ids: Vec::new(),
+            });
+            segment_lookup.insert((polygon_index, line_index), segment_id);
+
+            navmesh.add_anchor(SurfacePoint { segment_id, t: 0.0 });
+            navmesh.add_anchor(SurfacePoint { segment_id, t: 1.0 });
+        }
+
+        // Turn the graph's jumps and other moves off the surface into links between the surfaces
+        // the nodes are on
+        for node in &pathfinding.nodes {
+            let Some(from_point) = navmesh.node_surface_point(node, &segment_lookup) else {
+                continue;
+            };
+
+            for connection in &node.connections {
+                let link_type = match connection.connection_type {
+                    PathfindingGraphConnectionType::Walkable => continue,
+                    PathfindingGraphConnectionType::Jumpable => OffMeshLinkType::Jump,
+                    PathfindingGraphConnectionType::Droppable => OffMeshLinkType::Drop,
+                    PathfindingGraphConnectionType::Climbable => OffMeshLinkType::Climb,
+                    PathfindingGraphConnectionType::Teleport => OffMeshLinkType::Teleport,
+                    PathfindingGraphConnectionType::Swimmable => OffMeshLinkType::Swim,
+                    PathfindingGraphConnectionType::Bounce => OffMeshLinkType::Bounce,
+                    PathfindingGraphConnectionType::WallJumpable => OffMeshLinkType::WallJump,
+                    PathfindingGraphConnectionType::Mantle => OffMeshLinkType::Mantle,
+                    PathfindingGraphConnectionType::Dashable => OffMeshLinkType::Dash,
+                    PathfindingGraphConnectionType::DoubleJumpable => OffMeshLinkType::DoubleJump,
+                };
+
+                // Jumps cut off at runtime (by obstacles and the like) aren't links anymore
+                if matches!(link_type, OffMeshLinkType::Jump)
+                    && !pathfinding.is_connection_enabled(node.id, connection.node_id)
+                {
+                    continue;
+                }
+
+                let other_node = &pathfinding.nodes[connection.node_id];
+                let Some(to_point) = navmesh.node_surface_point(other_node, &segment_lookup) else {
+                    continue;
+                };
+
+                let from_anchor_id = navmesh.add_anchor(from_point);
+                let to_anchor_id = navmesh.add_anchor(to_point);
+
+                navmesh.links.push(OffMeshLink {
+                    from_anchor_id,
+                    to_anchor_id,
+                    link_type,
+                    dist: connection.dist,
+                });
+            }
+        }
+
+        navmesh.connect_anchors();
+
+        navmesh
+    }
+
+    /// Adds an anchor at the point, or returns the one already there
+    fn add_anchor(&mut self, point: SurfacePoint) -> usize {
+        let existing_anchor_id = self.segments[point.segment_id]
+            .anchor_ids
+            .iter()
+            .find(|anchor_id| (self.anchors[**anchor_id].point.t - point.t).abs() < 0.0001);
+
+        if let Some(&anchor_id) = existing_anchor_id {
+            return anchor_id;
+        }
+
+        let anchor_id = self.anchors.len();
+        let position = self.position(point);
+        self.anchors.push(Anchor {
+            point,
+            position,
+            edges: Vec::new(),
+        });
+        self.segments[point.segment_id].anchor_ids.push(anchor_id);
+
+        anchor_id
+    }
+
+    fn node_surface_point(
+        &self,
+        node: &PathfindingGraphNode,
+        segment_lookup: &HashMap<(usize, usize), usize>,
+    ) -> Option<SurfacePoint> {
+        node.line_indicies.iter().find_map(|line_index| {
+            let segment_id = *segment_lookup.get(&(node.polygon_index, *line_index))?;
+
+            Some(SurfacePoint {
+                segment_id,
+                t: self.segments[segment_id].closest_t(node.position),
+            })
+        })
+    }
+
+    /// Links every anchor to its neighbours along its segment, to the anchors at the same
+    /// spot on touching segments, and across its off-mesh links
+    fn connect_anchors(&mut self) {
+        for segment_index in 0..self.segments.len() {
+            let mut anchor_ids = self.segments[segment_index].anchor_ids.clone();
+            anchor_ids.sort_by(|a, b| {
+                self.anchors[*a]
+                    .point
+                    .t
+                    .partial_cmp(&self.anchors[*b].point.t)
+                    .unwrap()
+            });
+
+            for pair in anchor_ids.windows(2) {
+                let dist =
+                    (self.anchors[pair[0]].position - self.anchors[pair[1]].position).length();
+
+                self.anchors[pair[0]].edges.push((pair[1], dist));
+                self.anchors[pair[1]].edges.push((pair[0], dist));
+            }
+
+            self.segments[segment_index].anchor_ids = anchor_ids;
+        }
+
+        // Segment ends that share a point join the surfaces together
+        let mut anchors_by_position: HashMap<(i32, i32), Vec<usize>> = HashMap::new();
+        for (anchor_id, anchor) in self.anchors.iter().enumerate() {
+            if anchor.point.t == 0.0 || anchor.point.t == 1.0 {
+                anchors_by_position
+                    .entry((
+                        anchor.position.x.round() as i32,
+                        anchor.position.y.round() as i32,
+                    ))
+                    .or_default()
+                    .push(anchor_id);
+            }
+        }
+
+        for anchor_ids in anchors_by_position.values() {
+            for a in anchor_ids {
+                for b in anchor_ids {
+                    if a != b {
+                        self.anchors[*a].edges.push((*b, 0.0));
+                    }
+                }
+            }
+        }
+
+        for link in &self.links {
+            self.anchors[link.from_anchor_id]
+                .edges
+                .push((link.to_anchor_id, link.dist));
+        }
+    }
+
+    /// The surface point closest to the position
+    pub fn project(&self, position: Vec2) -> Option<SurfacePoint> {
+        self.segments
+            .iter()
+            .map(|segment| {
+                let t = segment.closest_t(position);
+                let distance = (segment.point_at(t) - position).length_squared();
+
+                (
+                    SurfacePoint {
+                        segment_id: segment.id,
+                        t,
+                    },
+                    distance,
+                )
+            })
+            .min_by(|a, b| a.1.partial_cmp(&b.1).unwrap_or(Ordering::Equal))
+            .map(|(point, _)| point)
+    }
+
+    pub fn position(&self, point: SurfacePoint) -> Vec2 {
+        self.segments[point.segment_id].point_at(point.t)
+    }
+
+    /// A* over the anchors, from and to arbitrary points on the surfaces
+    ///
+    /// Returns the surface points the path turns at, including the start and goal
+    pub fn find_path(&self, start: SurfacePoint, goal: SurfacePoint) -> Option<Vec<SurfacePoint>> {
+        let start_position = self.position(start);
+        let goal_position = self.position(goal);
+
+        // Both ends on the same surface, so just walk
+        if start.segment_id == goal.segment_id {
+            return Some(vec![start, goal]);
+        }
+
+        // The start and goal get the ids just past the real anchors
+        let start_id = self.anchors.len();
+        let goal_id = self.anchors.len() + 1;
+
+        let position_of = |id: usize| match id {
+            id if id == start_id => start_position,
+            id if id == goal_id => goal_position,
+            id => self.anchors[id].position,
+        };
+
+        // The goal can be reached from the anchors either side of it on its segment
+        let goal_entries: HashMap<usize, f32> = self.segments[goal.segment_id]
+            .anchor_ids
+            .iter()
+            .map(|anchor_id| {
+                (
+                    *anchor_id,
+                    (self.anchors[*anchor_id].position - goal_position).length(),
+                )
+            })
+            .collect();
+
+        let mut open_list: BinaryHeap<SearchEntry> = BinaryHeap::new();
+        let mut g_costs: HashMap<usize, f32> = HashMap::new();
+        let mut parents: HashMap<usize, usize> = HashMap::new();
+
+        g_costs.insert(start_id, 0.0);
+        open_list.push(SearchEntry {
+            id: start_id,
+            f_cost: (goal_position - start_position).length(),
+        });
+
+        while let Some(current) = open_list.pop() {
+            if current.id == goal_id {
+                let mut path: Vec<SurfacePoint> = vec![goal];
+
+                let mut id = goal_id;
+                while let Some(&parent_id) = parents.get(&id) {
+                    path.push(if parent_id == start_id {
+                        start
+                    } else {
+                        self.anchors[parent_id].point
+                    });
+                    id = parent_id;
+                }
+
+                path.reverse();
+
+                return Some(path);
+            }
+
+            let current_g_cost = g_costs[&current.id];
+
+            let edges: Vec<(usize, f32)> = if current.id == start_id {
+                self.segments[start.segment_id]
+                    .anchor_ids
+                    .iter()
+                    .map(|anchor_id| {
+                        (
+                            *anchor_id,
+                            (self.anchors[*anchor_id].position - start_position).length(),
+                        )
+                    })
+                    .collect()
+            } else {
+                let mut edges = self.anchors[current.id].edges.clone();
+                if let Some(dist) = goal_entries.get(&current.id) {
+                    edges.push((goal_id, *dist));
+                }
+                edges
+            };
+
+            for (next_id, cost) in edges {
+                let g_cost = current_g_cost + cost;
+
+                if g_costs.get(&next_id).is_some_and(|best| *best <= g_cost) {
+                    continue;
+                }
+
+                g_costs.insert(next_id, g_cost);
+                parents.insert(next_id, current.id);
+                open_list.push(SearchEntry {
+                    id: next_id,
+                    f_cost: g_cost + (goal_position - position_of(next_id)).length(),
+                });
+            }
+        }
+
+        None
+    }
+}
+
+struct SearchEntry {
+    id: usize,
+    f_cost: f32,
+}
+
+impl PartialEq for SearchEntry {
+    fn eq(&self, other: &Self) -> bool {
+        self.f_cost == other.f_cost
+    }
+}
+
+impl Eq for SearchEntry {}
+
+impl PartialOrd for SearchEntry {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for SearchEntry {
+    // Reversed so the binary heap pops the lowest f-cost first
+    fn cmp(&self, other: &Self) -> Ordering {
+        other
+            .f_cost
+            .partial_cmp(&self.f_cost)
+            .unwrap_or(Ordering::Equal)
+    }
+}
+
+/// Rebuilds the navmesh along with the graph it takes its links from
+pub fn s_rebuild_navmesh(
+    settings: Res<PathfindingSettings>,
+    level: Res<Level>,
+    pathfinding: Res<Pathfinding>,
+    mut navmesh: ResMut<NavMesh>,
+) {
+    if !settings.is_changed() || settings.is_added() {
+        return;
+    }
+
+    *navmesh = NavMesh::build(&level, &pathfinding);
+}
+
+/// Draws the navmesh and each agent's navmesh path to the goal, for comparing against the
+/// node graph's paths
+pub fn s_render_navmesh(
+    navmesh: Res<NavMesh>,
+    pathfinding: Res<Pathfinding>,
+    gizmos_visible: Res<GizmosVisible>,
+    agent_query: Query<&Transform, With<PlatformerAI>>,
+    mut gizmos: Gizmos,
+) {
+    if !gizmos_visible.visible || navmesh.segments.is_empty() {
+        return;
+    }
+
+    for link in &navmesh.links {
+        gizmos.line_2d(
+            navmesh.anchors[link.from_anchor_id].position,
+            navmesh.anchors[link.to_anchor_id].position,
+            Color::CYAN.with_a(0.1),
+        );
+    }
+
+    if !pathfinding.active {
+        return;
+    }
+
+    let Some(goal) = navmesh.project(pathfinding.goal_position) else {
+        return;
+    };
+
+    for transform in agent_query.iter() {
+        let Some(start) = navmesh.project(transform.translation.xy()) else {
+            continue;
+        };
+
+        if let Some(path) = navmesh.find_path(start, goal) {
+            gizmos.linestrip_2d(
+                path.iter().map(|point| navmesh.position(*point)),
+                Color::CYAN,
+            );
+        }
+    }
+}
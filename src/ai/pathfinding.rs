@@ -1,85 +1,1100 @@
+use std::{
+    collections::{HashMap, HashSet},
+    ops::Range,
+};
+
 use bevy::{
-    app::{App, Plugin},
-    ecs::system::{ResMut, Resource},
+    app::{App, FixedUpdate, Plugin, Update},
+    ecs::{
+        change_detection::DetectChanges,
+        component::Component,
+        entity::Entity,
+        event::Event,
+        reflect::{ReflectComponent, ReflectResource},
+        schedule::IntoSystemConfigs,
+        system::{Commands, Query, Res, ResMut, Resource},
+    },
+    input::{keyboard::KeyCode, ButtonInput},
     math::Vec2,
+    reflect::Reflect,
+};
+
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    level::{point_in_polygon, Level, DEFAULT_FRICTION},
+    simulation::Simulation,
+    utils::{line_intersect, vec2_serde},
+    PursueAISet, GRAVITY_STRENGTH,
 };
 
-use crate::{level::Level, utils::line_intersect, GRAVITY_STRENGTH};
+use super::{
+    a_star::{find_path_from, PathNode, SearchStats},
+    influence_map::InfluenceMap,
+    kd_tree::KdTree,
+    platformer_ai::{
+        PlatformerAI, ACCELERATION_SCALERS, PLATFORMER_AI_AGENT_RADIUS, PLATFORMER_AI_DASH_SPEED,
+        PLATFORMER_AI_DASH_TICKS, PLATFORMER_AI_DOUBLE_JUMP_FORCE, PLATFORMER_AI_JUMP_FORCE,
+        PLATFORMER_AI_JUMP_HORIZONTAL_BOOST, PLATFORMER_AI_SWIM_SPEED, WANDER_MAX_SPEED,
+    },
+};
+
+/// The default distance between nodes placed along a walkable line
+pub const PATHFINDING_NODE_SPACING: f32 = 20.0;
+
+/// How much the node spacing changes per key press
+pub const PATHFINDING_NODE_SPACING_STEP: f32 = 5.0;
+
+/// How many of the closest nodes get checked for reachability
+pub const REACHABLE_NODE_CANDIDATES: usize = 8;
+
+/// How closely a node's normal has to match the agent's surface normal
+pub const REACHABLE_NODE_MIN_NORMAL_DOT: f32 = 0.5;
+
+/// How long (in ticks) a bounce pad launch is followed before giving up on it landing
+pub const BOUNCE_MAX_TICKS: usize = 240;
 
-use super::platformer_ai::{PLATFORMER_AI_AGENT_RADIUS, PLATFORMER_AI_JUMP_FORCE};
+/// The least a walk can cost (as a multiple of its length), however fast a conveyor helps it along
+pub const CONVEYOR_MIN_COST_SCALE: f32 = 0.25;
+
+/// The furthest apart two nodes in the same water can be and still get a swim between them
+pub const SWIM_MAX_DISTANCE: f32 = 160.0;
+
+/// The widest gap between two walls that agents wall jump across
+pub const WALL_JUMP_MAX_GAP: f32 = 64.0;
+
+/// How squarely two walls have to face each other to be wall jumped between
+pub const WALL_JUMP_MIN_OPPOSITION: f32 = 0.9;
+
+/// How far below a ledge an agent can still catch it and mantle up over it
+pub const LEDGE_GRAB_REACH: f32 = 16.0;
+
+/// How much more a jump that needs a mantle at the end costs than a clean one (as a multiple of
+/// its length), since pulling up over the ledge is slow
+pub const MANTLE_COST_SCALE: f32 = 1.5;
+
+/// How far above the higher end of a double jump the second jump is made from
+pub const DOUBLE_JUMP_RISE: f32 = 32.0;
+
+/// How much more a double jump costs than a single one (as a multiple of its length), since
+/// missing the second jump drops the agent wherever it is
+pub const DOUBLE_JUMP_COST_SCALE: f32 = 1.25;
 
 pub struct PathfindingPlugin;
 
 impl Plugin for PathfindingPlugin {
     fn build(&self, app: &mut App) {
-        app.insert_resource(Pathfinding {
-            nodes: Vec::new(),
-            goal_graph_node: None,
-            goal_position: Vec2::ZERO,
-            active: false,
-        });
+        app.register_type::<PathfindingSettings>()
+            .register_type::<MovementCapabilities>()
+            .register_type::<PathfindingGraphConnectionType>()
+            .register_type::<PathfindingGraphConnection>()
+            .register_type::<RunUp>()
+            .register_type::<PathfindingGraphNode>()
+            .register_type::<SurfaceKind>()
+            .register_type::<Pathfinding>()
+            .register_type::<GraphModifiers>()
+            .register_type::<RequestPath>()
+            .register_type::<ComputedPath>()
+            .insert_resource(Pathfinding::default())
+            .insert_resource(PathfindingSettings::default())
+            .add_event::<GoalTeleported>()
+            .add_event::<PathsInvalidated>()
+            .add_systems(
+                Update,
+                (
+                    s_bake_input,
+                    (s_pathfinding_settings_input, s_rebuild_pathfinding_graph).chain(),
+                ),
+            )
+            .add_systems(FixedUpdate, s_compute_paths.in_set(PursueAISet::Planning));
     }
 }
 
-pub fn init_pathfinding_graph(level: &Level, mut pathfinding: ResMut<Pathfinding>) {
-    place_nodes(&mut pathfinding, level);
+/// Settings the pathfinding graph is built with, changing them rebuilds the graph
+#[derive(Resource, Debug, Clone, PartialEq, Serialize, Deserialize, Reflect)]
+#[reflect(Resource)]
+#[serde(default)]
+pub struct PathfindingSettings {
+    /// Distance between nodes on polygons that don't set their own spacing
+    pub node_spacing: f32,
+    /// Whether ceilings get nodes too, for agents that can crawl along them
+    pub ceiling_nodes: bool,
+    /// What the agents can do, so the graph only has the moves they're able to make
+    pub capabilities: MovementCapabilities,
+}
+
+impl Default for PathfindingSettings {
+    fn default() -> Self {
+        PathfindingSettings {
+            node_spacing: PATHFINDING_NODE_SPACING,
+            ceiling_nodes: false,
+            capabilities: MovementCapabilities::default(),
+        }
+    }
+}
 
-    make_walkable_connections_2_way(&mut pathfinding);
+/// The moves beyond running and jumping that the agents have, which the graph's built for
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize, Reflect)]
+#[serde(default)]
+pub struct MovementCapabilities {
+    /// Whether agents can jump again once in the air, for gaps one jump can't make
+    pub double_jump: bool,
+}
+
+/// Sent when the goal point jumps to a new position instead of moving there
+#[derive(Event, Debug, Clone, Copy)]
+pub struct GoalTeleported {
+    pub from: Vec2,
+    pub to: Vec2,
+}
+
+/// Sent when nodes are taken out of the graph for good, so agents with a path through any
+/// of them drop it straight away
+#[derive(Event, Debug, Clone)]
+pub struct PathsInvalidated {
+    pub node_ids: Vec<usize>,
+}
 
-    remove_duplicate_nodes(&mut pathfinding);
+/// Asks for a path for the entity it's on, which `s_compute_paths` answers with a
+/// [`ComputedPath`] on the same tick (and takes the request off)
+#[derive(Component, Debug, Clone, Reflect)]
+#[reflect(Component)]
+pub struct RequestPath {
+    pub start_position: Vec2,
+    /// The node to search from, or the one nearest the start position if it's not given
+    pub start_node_id: Option<usize>,
+    pub goal_node_id: usize,
+    pub goal_position: Vec2,
+}
 
-    make_node_ids_indices(&mut pathfinding);
+/// The answer to the entity's last [`RequestPath`]
+#[derive(Component, Debug, Clone, Default, Reflect)]
+#[reflect(Component)]
+pub struct ComputedPath {
+    /// The path found, or nothing if there's no way there
+    pub path: Option<Vec<PathNode>>,
+    /// The goal node and position it was asked for
+    pub goal: (usize, Vec2),
+    /// The graph modifiers' revision it was found with
+    pub revision: u32,
+    pub stats: SearchStats,
+}
 
-    make_jumpable_connections(&mut pathfinding, level, PLATFORMER_AI_AGENT_RADIUS);
+/// Loads the graph baked for the level, or builds it (and bakes it, if asked to) when
+/// there's no up to date one
+pub fn load_or_build_pathfinding_graph(
+    level: &Level,
+    pathfinding: &mut Pathfinding,
+    settings: &PathfindingSettings,
+    baked_graph_path: &str,
+    bake: bool,
+) {
+    if !bake && load_baked_graph(pathfinding, level, settings, baked_graph_path) {
+        println!("Loaded baked graph from {}", baked_graph_path);
+        return;
+    }
 
-    calculate_normals(&mut pathfinding, level);
+    init_pathfinding_graph(level, pathfinding, settings);
 
-    setup_corners(&mut pathfinding);
+    #[cfg(feature = "petgraph")]
+    println!(
+        "Pathfinding graph has {} strongly connected components",
+        pathfinding.strongly_connected_components().len()
+    );
 
-    // make_droppable_connections(&mut pathfinding, level);
+    if bake {
+        save_baked_graph(pathfinding, level, settings, baked_graph_path);
+    }
 }
 
-#[derive(Debug, Clone)]
+pub fn init_pathfinding_graph(
+    level: &Level,
+    pathfinding: &mut Pathfinding,
+    settings: &PathfindingSettings,
+) {
+    place_nodes(pathfinding, level, settings);
+
+    make_walkable_connections_2_way(pathfinding);
+
+    calculate_normals(pathfinding, level);
+
+    // The jumpable connections use the spatial index to find nodes in range
+    build_spatial_index(pathfinding);
+
+    make_jumpable_connections(pathfinding, level, PLATFORMER_AI_AGENT_RADIUS);
+
+    setup_corners(pathfinding);
+
+    // Wall jumps leave corners to the ordinary jumps
+    make_wall_jump_connections(pathfinding, level, PLATFORMER_AI_AGENT_RADIUS);
+
+    // Mantles need to know which corners are ledges
+    make_mantle_connections(pathfinding, level, PLATFORMER_AI_AGENT_RADIUS);
+
+    // Dashes only go where the jumps and mantles don't
+    make_dash_connections(pathfinding, level, PLATFORMER_AI_AGENT_RADIUS);
+
+    // A second pass, for what none of the single moves reach
+    if settings.capabilities.double_jump {
+        make_double_jump_connections(pathfinding, level, PLATFORMER_AI_AGENT_RADIUS);
+    }
+
+    // Drops use the spatial index to find where they land
+    make_droppable_connections(pathfinding, level);
+
+    make_climbable_connections(pathfinding, level);
+
+    make_teleport_connections(pathfinding, level);
+
+    make_swimmable_connections(pathfinding, level);
+
+    make_bounce_connections(pathfinding, level);
+}
+
+/// A fully built graph saved to disk, along with the level it was built for
+#[derive(Serialize, Deserialize)]
+pub struct BakedGraph {
+    pub level_fingerprint: u64,
+    pub node_spacing: f32,
+    #[serde(default)]
+    pub ceiling_nodes: bool,
+    #[serde(default)]
+    pub capabilities: MovementCapabilities,
+    pub nodes: Vec<PathfindingGraphNode>,
+}
+
+/// Where the graph for the named suite level (or the built-in one) gets baked to
+pub fn baked_graph_path(level_name: Option<&str>) -> String {
+    format!(
+        "assets/baked_graph_{}.json",
+        level_name.unwrap_or("default")
+    )
+}
+
+pub fn save_baked_graph(
+    pathfinding: &Pathfinding,
+    level: &Level,
+    settings: &PathfindingSettings,
+    path: &str,
+) {
+    #[cfg(not(target_arch = "wasm32"))]
+    {
+        let baked_graph = BakedGraph {
+            level_fingerprint: level.fingerprint(),
+            node_spacing: settings.node_spacing,
+            ceiling_nodes: settings.ceiling_nodes,
+            capabilities: settings.capabilities,
+            nodes: pathfinding.nodes.clone(),
+        };
+
+        match serde_json::to_string(&baked_graph) {
+            Ok(baked_graph_json) => match std::fs::write(path, baked_graph_json) {
+                Ok(()) => println!("Baked {} nodes to {}", baked_graph.nodes.len(), path),
+                Err(error) => println!("Failed to bake graph: {}", error),
+            },
+            Err(error) => println!("Failed to serialize graph: {}", error),
+        }
+    }
+
+    #[cfg(target_arch = "wasm32")]
+    let _ = (pathfinding, level, settings, path);
+}
+
+pub fn s_bake_input(
+    keyboard_input: Res<ButtonInput<KeyCode>>,
+    pathfinding: Res<Pathfinding>,
+    level: Res<Level>,
+    settings: Res<PathfindingSettings>,
+    simulation: Res<Simulation>,
+) {
+    // B to bake the graph
+    if keyboard_input.just_pressed(KeyCode::KeyB) {
+        save_baked_graph(
+            &pathfinding,
+            &level,
+            &settings,
+            &baked_graph_path(simulation.level_name.as_deref()),
+        );
+    }
+}
+
+pub fn s_pathfinding_settings_input(
+    keyboard_input: Res<ButtonInput<KeyCode>>,
+    mut settings: ResMut<PathfindingSettings>,
+) {
+    // C to toggle nodes on ceilings
+    if keyboard_input.just_pressed(KeyCode::KeyC) {
+        settings.ceiling_nodes = !settings.ceiling_nodes;
+        println!("Ceiling nodes: {}", settings.ceiling_nodes);
+    }
+
+    // Shift + - and Shift + = to space the nodes further apart or closer together
+    if !keyboard_input.any_pressed([KeyCode::ShiftLeft, KeyCode::ShiftRight]) {
+        return;
+    }
+
+    let step = if keyboard_input.just_pressed(KeyCode::Minus) {
+        PATHFINDING_NODE_SPACING_STEP
+    } else if keyboard_input.just_pressed(KeyCode::Equal) {
+        -PATHFINDING_NODE_SPACING_STEP
+    } else {
+        return;
+    };
+
+    settings.node_spacing = (settings.node_spacing + step).max(PATHFINDING_NODE_SPACING_STEP);
+    println!("Node spacing: {}", settings.node_spacing);
+}
+
+/// Rebuilds the graph from scratch whenever the settings it was built with change
+pub fn s_rebuild_pathfinding_graph(
+    settings: Res<PathfindingSettings>,
+    level: Res<Level>,
+    mut pathfinding: ResMut<Pathfinding>,
+    mut platformer_ai_query: Query<&mut PlatformerAI>,
+) {
+    if !settings.is_changed() || settings.is_added() {
+        return;
+    }
+
+    pathfinding.rebuild(&level, &settings);
+
+    // Node ids from the old graph mean nothing in the new one
+    for mut platformer_ai in platformer_ai_query.iter_mut() {
+        platformer_ai.current_target_node = None;
+        platformer_ai.landing_node = None;
+        platformer_ai.path.clear();
+        platformer_ai.path_goal = None;
+    }
+}
+
+/// Answers every path request, searching for them all at once
+pub fn s_compute_paths(
+    mut commands: Commands,
+    pathfinding: Res<Pathfinding>,
+    request_query: Query<(Entity, &RequestPath)>,
+) {
+    let requests: Vec<(Entity, &RequestPath)> = request_query.iter().collect();
+
+    let computed_paths: Vec<(Entity, ComputedPath)> = requests
+        .into_par_iter()
+        .map(|(entity, request)| {
+            let mut stats = SearchStats::default();
+            let path = request
+                .start_node_id
+                .or_else(|| pathfinding.nearest_node_id(request.start_position))
+                .and_then(|start_node_id| {
+                    find_path_from(
+                        &pathfinding,
+                        start_node_id,
+                        request.goal_node_id,
+                        request.goal_position,
+                        Some(&mut stats),
+                        Some(entity),
+                    )
+                });
+
+            (
+                entity,
+                ComputedPath {
+                    path,
+                    goal: (request.goal_node_id, request.goal_position),
+                    revision: pathfinding.modifiers.revision,
+                    stats,
+                },
+            )
+        })
+        .collect();
+
+    for (entity, computed_path) in computed_paths {
+        commands
+            .entity(entity)
+            .remove::<RequestPath>()
+            .insert(computed_path);
+    }
+}
+
+/// Replaces the graph with the baked one, if there is one and it was baked for this level
+pub fn load_baked_graph(
+    pathfinding: &mut Pathfinding,
+    level: &Level,
+    settings: &PathfindingSettings,
+    path: &str,
+) -> bool {
+    #[cfg(not(target_arch = "wasm32"))]
+    if let Ok(baked_graph_json) = std::fs::read_to_string(path) {
+        match serde_json::from_str::<BakedGraph>(&baked_graph_json) {
+            Ok(baked_graph)
+                if baked_graph.level_fingerprint == level.fingerprint()
+                    && baked_graph.node_spacing == settings.node_spacing
+                    && baked_graph.ceiling_nodes == settings.ceiling_nodes
+                    && baked_graph.capabilities == settings.capabilities =>
+            {
+                pathfinding.nodes = baked_graph.nodes;
+                build_spatial_index(pathfinding);
+
+                return true;
+            }
+            Ok(_) => println!(
+                "Ignoring baked graph {}, the level or settings have changed",
+                path
+            ),
+            Err(error) => println!("Ignoring unreadable baked graph {}: {}", path, error),
+        }
+    }
+
+    #[cfg(target_arch = "wasm32")]
+    let _ = (pathfinding, level, settings, path);
+
+    false
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Reflect)]
 pub enum PathfindingGraphConnectionType {
     Walkable,
     Jumpable,
     Droppable,
+    Climbable,
+    Teleport,
+    Swimmable,
+    Bounce,
+    /// Jumps off a wall to the wall facing it, which chain up shafts too narrow to jump up
+    WallJumpable,
+    /// Jumps that fall just short of a ledge, catching it and mantling up over it
+    Mantle,
+    /// Dashes straight across gaps between ledges that can't be jumped, like under low ceilings
+    Dashable,
+    /// Jumps that need a second jump in mid-air, for agents that can double jump
+    DoubleJumpable,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize, Reflect)]
 pub struct PathfindingGraphConnection {
     pub node_id: usize,
     pub dist: f32,
     pub connection_type: PathfindingGraphConnectionType,
+    /// The launch speed of jumps, and how much harder than usual walks are on slippery ground
     pub effort: f32,
+    pub run_up: Option<RunUp>,
+    /// How fast the ground carries agents along the connection, negative when it's against them
+    #[serde(default)]
+    pub surface_speed: f32,
+}
+
+/// The run-up a jump needs when a standing jump can't reach the launch speed
+#[derive(Debug, Clone, Serialize, Deserialize, Reflect)]
+pub struct RunUp {
+    /// Horizontal speed the agent needs to already have when it jumps
+    pub speed: f32,
+    /// How far the agent has to run from a standstill to reach that speed
+    pub distance: f32,
+    /// Direction of the run (-1.0 for left, 1.0 for right)
+    pub direction: f32,
+    /// The node the agent backs up to before starting the run
+    pub start_node_id: usize,
 }
 
+/// How an agent can get up to a jump's launch speed
 #[derive(Debug, Clone)]
+pub enum Takeoff {
+    /// A standing jump is enough
+    Standing,
+    RunUp(RunUp),
+    /// The agent can't reach the launch speed at all, or the surface behind the node is too
+    /// short to do so
+    Unreachable,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Reflect)]
 pub struct PathfindingGraphNode {
     pub id: usize,
+    #[serde(with = "vec2_serde")]
     pub position: Vec2,
     pub polygon_index: usize,
     pub line_indicies: Vec<usize>,
-    pub walkable_connections: Vec<PathfindingGraphConnection>,
-    pub jumpable_connections: Vec<PathfindingGraphConnection>,
-    pub droppable_connections: Vec<PathfindingGraphConnection>,
+    /// Every connection leaving the node, whatever its type
+    pub connections: Vec<PathfindingGraphConnection>,
+    #[serde(with = "vec2_serde")]
     pub normal: Vec2,
     pub is_corner: bool,
     pub is_external_corner: Option<bool>,
 }
 
-#[derive(Resource)]
+/// What kind of surface a node sits on, going by its normal
+#[derive(Debug, Clone, Copy, PartialEq, Reflect)]
+pub enum SurfaceKind {
+    Floor,
+    Wall,
+    Ceiling,
+}
+
+impl PathfindingGraphNode {
+    pub fn surface_kind(&self) -> SurfaceKind {
+        if self.normal.y > REACHABLE_NODE_MIN_NORMAL_DOT {
+            SurfaceKind::Floor
+        } else if self.normal.y < -REACHABLE_NODE_MIN_NORMAL_DOT {
+            SurfaceKind::Ceiling
+        } else {
+            SurfaceKind::Wall
+        }
+    }
+
+    /// The connections leaving the node that are of the type
+    pub fn connections_of_type(
+        &self,
+        connection_type: PathfindingGraphConnectionType,
+    ) -> impl Iterator<Item = &PathfindingGraphConnection> {
+        self.connections
+            .iter()
+            .filter(move |connection| connection.connection_type == connection_type)
+    }
+
+    /// Drops the node's connections of the type, ready for them to be worked out again
+    pub fn remove_connections_of_type(&mut self, connection_type: PathfindingGraphConnectionType) {
+        self.connections
+            .retain(|connection| connection.connection_type != connection_type);
+    }
+
+    /// Whether the node's the top corner of a ledge, which agents can grab onto from the side
+    pub fn is_ledge(&self) -> bool {
+        self.is_external_corner == Some(true) && self.normal.y > 0.0 && self.normal.x != 0.0
+    }
+}
+
+#[derive(Resource, Reflect)]
+#[reflect(Resource)]
 pub struct Pathfinding {
     pub nodes: Vec<PathfindingGraphNode>,
     pub goal_graph_node: Option<PathfindingGraphNode>,
     pub goal_position: Vec2,
     pub active: bool,
+    pub modifiers: GraphModifiers,
+    pub influence: InfluenceMap,
+    /// Rebuilt from the nodes along with them, so there's nothing in it worth inspecting
+    #[reflect(ignore)]
+    pub spatial_index: KdTree,
+    /// How much the straight-line distance to the goal counts for in A*, where anything over
+    /// 1.0 trades the shortest paths for faster searches
+    pub heuristic_weight: f32,
+    /// Whether A* crosses plain stretches of floor and wall in one go instead of a node at a
+    /// time, which finds the same paths with fewer expansions
+    pub skip_walkable_runs: bool,
+}
+
+impl Default for Pathfinding {
+    fn default() -> Self {
+        Pathfinding {
+            nodes: Vec::new(),
+            goal_graph_node: None,
+            goal_position: Vec2::ZERO,
+            active: false,
+            modifiers: GraphModifiers::default(),
+            influence: InfluenceMap::default(),
+            spatial_index: KdTree::default(),
+            heuristic_weight: 1.0,
+            skip_walkable_runs: true,
+        }
+    }
+}
+
+/// Runtime changes layered on top of the built graph (blocked platforms, hazard zones, etc.)
+#[derive(Default, Reflect)]
+pub struct GraphModifiers {
+    // Sets can't be reflected, so inspectors only see the cost scales and penalties
+    #[reflect(ignore)]
+    pub disabled_nodes: HashSet<usize>,
+    #[reflect(ignore)]
+    pub disabled_connections: HashSet<(usize, usize)>,
+    pub node_cost_scales: HashMap<usize, f32>,
+    pub connection_cost_scales: HashMap<(usize, usize), f32>,
+    /// Extra cost on connections agents have been failing to make, kept apart from the cost
+    /// scales so neither overwrites the other
+    pub connection_penalties: HashMap<(usize, usize), f32>,
+    /// Bumped every time a modifier changes so cached paths can tell they're stale
+    pub revision: u32,
+    /// Nodes touched since the last call to `take_changed_nodes`
+    pub changed_nodes: Vec<usize>,
+}
+
+impl Pathfinding {
+    /// Throws away the graph and everything layered on top of it and builds it again
+    pub fn rebuild(&mut self, level: &Level, settings: &PathfindingSettings) {
+        self.nodes.clear();
+        self.influence.danger.clear();
+        self.influence.sources.clear();
+
+        // Keep counting revisions up so nothing mistakes the new graph for the old one
+        self.modifiers = GraphModifiers {
+            revision: self.modifiers.revision.wrapping_add(1),
+            ..Default::default()
+        };
+
+        init_pathfinding_graph(level, self, settings);
+
+        if self.active {
+            self.goal_graph_node = self
+                .nearest_node_id(self.goal_position)
+                .map(|node_id| self.nodes[node_id].clone());
+        }
+    }
+
+    /// Fits a polygon that was just added to the level into the graph, without rebuilding it
+    ///
+    /// Nodes the polygon covers and connections it cuts through are disabled, then the
+    /// polygon's own surfaces get nodes, joined to the rest of the graph by jumps.
+    /// The next full rebuild replaces all of this with the graph built from scratch
+    pub fn add_polygon(
+        &mut self,
+        level: &Level,
+        polygon_index: usize,
+        settings: &PathfindingSettings,
+    ) {
+        let radius = PLATFORMER_AI_AGENT_RADIUS;
+        let polygon = &level.polygons[polygon_index];
+
+        // Nodes an agent can no longer stand on
+        for node_id in self.nodes_covered_by(&polygon.points) {
+            self.disable_node(node_id);
+        }
+
+        // Walks along the floor and jumps through the air that now run into the polygon
+        for (from_node_id, to_node_id) in
+            self.connections_cut_by(&polygon.points, polygon.is_one_way)
+        {
+            self.disable_connection(from_node_id, to_node_id);
+        }
+
+        // Give the polygon its own nodes, the same way a full build would
+        let first_new_node_id = self.nodes.len();
+
+        let lines: Vec<(usize, usize)> = walkable_lines(level, settings.ceiling_nodes)
+            .into_iter()
+            .filter(|(line_polygon_index, _)| *line_polygon_index == polygon_index)
+            .collect();
+        place_nodes_on_lines(self, level, settings, &lines);
+
+        let new_node_ids = first_new_node_id..self.nodes.len();
+
+        make_walkable_connections_2_way_for(self, new_node_ids.clone());
+        calculate_normals_for(self, level, new_node_ids.clone());
+        build_spatial_index(self);
+
+        // Jumps off the new nodes, and jumps from the rest of the graph onto them
+        for (node_id, connection) in find_jumps_touching(self, level, radius, new_node_ids.clone())
+        {
+            self.nodes[node_id].connections.push(connection);
+        }
+
+        setup_corners_for(self, new_node_ids);
+
+        // Cached paths can't know about the new nodes, so make them replan
+        self.modifiers.revision = self.modifiers.revision.wrapping_add(1);
+
+        if self.active {
+            self.goal_graph_node = self
+                .nearest_node_id(self.goal_position)
+                .map(|node_id| self.nodes[node_id].clone());
+        }
+    }
+
+    /// The nodes an agent couldn't stand on if the outline was solid
+    pub fn nodes_covered_by(&self, points: &Vec<Vec2>) -> Vec<usize> {
+        let radius = PLATFORMER_AI_AGENT_RADIUS;
+
+        self.nodes
+            .iter()
+            .filter(|node| point_in_polygon(points, node.position + node.normal * radius))
+            .map(|node| node.id)
+            .collect()
+    }
+
+    /// The enabled connections that would run into the outline if it was solid, going by the
+    /// arc for jumps and the straight line between standing positions for everything else
+    pub fn connections_cut_by(&self, points: &[Vec2], one_way: bool) -> Vec<(usize, usize)> {
+        let radius = PLATFORMER_AI_AGENT_RADIUS;
+
+        self.nodes
+            .par_iter()
+            .flat_map_iter(|node| {
+                node.connections
+                    .iter()
+                    .filter(|connection| self.is_connection_enabled(node.id, connection.node_id))
+                    .filter(|connection| {
+                        let other_node = &self.nodes[connection.node_id];
+                        let start = node.position + node.normal * radius;
+                        let end = other_node.position + other_node.normal * radius;
+
+                        points
+                            .windows(2)
+                            .any(|line| match connection.connection_type {
+                                PathfindingGraphConnectionType::Jumpable
+                                | PathfindingGraphConnectionType::WallJumpable
+                                | PathfindingGraphConnectionType::Mantle => trajectory_hits_line(
+                                    &connection_arc_points(
+                                        node.position,
+                                        other_node.position,
+                                        connection,
+                                    ),
+                                    line[0],
+                                    line[1],
+                                    radius,
+                                    one_way,
+                                ),
+                                PathfindingGraphConnectionType::DoubleJumpable => {
+                                    let midpoint =
+                                        double_jump_midpoint(node.position, other_node.position);
+
+                                    [(node.position, midpoint), (midpoint, other_node.position)]
+                                        .iter()
+                                        .any(|(leg_start, leg_end)| {
+                                            jump_trajectory_hits_line(
+                                                *leg_start, *leg_end, line[0], line[1], radius,
+                                                one_way,
+                                            )
+                                        })
+                                }
+                                _ => line_intersect(start, end, line[0], line[1]).is_some(),
+                            })
+                    })
+                    .map(|connection| (node.id, connection.node_id))
+                    .collect::<Vec<(usize, usize)>>()
+            })
+            .collect()
+    }
+
+    /// Updates the graph for a level line that was just broken, without rebuilding it
+    ///
+    /// Walking along the line is cut off and the jumps of the nodes around it are worked out
+    /// again, since they can go through the hole now. Only nodes within a flat jump of the
+    /// hole are redone, which covers every jump that can reasonably pass through it
+    pub fn break_line(&mut self, level: &Level, polygon_index: usize, line_index: usize) {
+        let Some((&start, &end)) = level.get_line(polygon_index, line_index) else {
+            return;
+        };
+
+        let on_line = |node: &PathfindingGraphNode| {
+            node.polygon_index == polygon_index && node.line_indicies.contains(&line_index)
+        };
+
+        // Nodes partway along the line have nothing left to stand on,
+        // while the ones on its corners still have the neighbouring lines
+        let stranded_node_ids: Vec<usize> = self
+            .nodes
+            .iter()
+            .filter(|node| on_line(node) && node.line_indicies.len() == 1)
+            .map(|node| node.id)
+            .collect();
+
+        for node_id in stranded_node_ids {
+            self.disable_node(node_id);
+        }
+
+        let graph: &Pathfinding = self;
+        let cut_connections: Vec<(usize, usize)> = graph
+            .nodes
+            .iter()
+            .filter(|node| on_line(node))
+            .flat_map(|node| {
+                node.connections_of_type(PathfindingGraphConnectionType::Walkable)
+                    .filter(|connection| on_line(&graph.nodes[connection.node_id]))
+                    .map(|connection| (node.id, connection.node_id))
+                    .collect::<Vec<(usize, usize)>>()
+            })
+            .collect();
+
+        for (from_node_id, to_node_id) in cut_connections {
+            self.disable_connection(from_node_id, to_node_id);
+        }
+
+        // Work the jumps around the hole out again
+        let graph: &Pathfinding = self;
+        let radius = PLATFORMER_AI_AGENT_RADIUS;
+
+        let lowest_node_y = graph
+            .nodes
+            .iter()
+            .map(|node| node.position.y)
+            .fold(f32::MAX, f32::min);
+
+        let hole_center = (start + end) / 2.0;
+        let reach = max_jump_distance(0.0) + (end - start).length() / 2.0;
+
+        let jumpable_connections: Vec<(usize, Vec<PathfindingGraphConnection>)> = graph
+            .spatial_index
+            .within_radius(hole_center, reach)
+            .into_par_iter()
+            .filter(|node_id| graph.is_node_enabled(*node_id))
+            .map(|i| {
+                let max_range = max_jump_distance(lowest_node_y - graph.nodes[i].position.y);
+
+                (
+                    i,
+                    find_jumpable_connections(graph, level, radius, i, max_range),
+                )
+            })
+            .collect();
+
+        for (node_id, connections) in jumpable_connections {
+            let node = &mut self.nodes[node_id];
+            node.remove_connections_of_type(PathfindingGraphConnectionType::Jumpable);
+            node.connections.extend(connections);
+        }
+
+        // Cached paths can't know about the new jumps, so make them replan
+        self.modifiers.revision = self.modifiers.revision.wrapping_add(1);
+
+        if self.active {
+            self.goal_graph_node = self
+                .nearest_node_id(self.goal_position)
+                .map(|node_id| self.nodes[node_id].clone());
+        }
+    }
+
+    /// Moves the goal point straight to the position
+    ///
+    /// The returned event should be sent so agents drop paths planned toward the old goal
+    pub fn teleport_goal(&mut self, position: Vec2) -> GoalTeleported {
+        let from = self.goal_position;
+
+        self.goal_position = position;
+
+        if self.active {
+            self.goal_graph_node = self
+                .nearest_node_id(position)
+                .map(|node_id| self.nodes[node_id].clone());
+        }
+
+        GoalTeleported { from, to: position }
+    }
+
+    /// The id of the enabled node closest to the position
+    pub fn nearest_node_id(&self, position: Vec2) -> Option<usize> {
+        self.spatial_index
+            .nearest_filtered(position, |node_id| self.is_node_enabled(node_id))
+    }
+
+    /// The ids of the k enabled nodes closest to the position, closest first
+    pub fn k_nearest_node_ids(&self, position: Vec2, k: usize) -> Vec<usize> {
+        self.spatial_index
+            .k_nearest_filtered(position, k, |node_id| self.is_node_enabled(node_id))
+            .iter()
+            .map(|(node_id, _)| *node_id)
+            .collect()
+    }
+
+    /// The id of the closest node the agent can actually stand on and get to
+    ///
+    /// Candidates have to face roughly the same way as the surface the agent is on
+    /// (or face up if it's in the air) and be in line of sight of the agent, so an
+    /// agent on a floor doesn't snap to the underside of the platform above it.
+    /// Falls back to the plain nearest node if none of the candidates qualify
+    pub fn nearest_reachable_node_id(
+        &self,
+        level: &Level,
+        position: Vec2,
+        surface_normal: Vec2,
+        radius: f32,
+    ) -> Option<usize> {
+        let candidates = self.k_nearest_node_ids(position, REACHABLE_NODE_CANDIDATES);
+
+        let reachable_node_id = candidates.iter().cloned().find(|node_id| {
+            let node = &self.nodes[*node_id];
+
+            let normal_compatible = if surface_normal.length_squared() > 0.0 {
+                node.normal.dot(surface_normal) > REACHABLE_NODE_MIN_NORMAL_DOT
+            } else {
+                node.normal.y > -0.01
+            };
+
+            // Check the sight line to where the agent would be when standing on the node
+            normal_compatible
+                && level.line_of_sight_check(position, node.position + node.normal * radius)
+        });
+
+        reachable_node_id.or(candidates.first().cloned())
+    }
+
+    /// The ids of every node within the radius of the position
+    pub fn node_ids_within_radius(&self, position: Vec2, radius: f32) -> Vec<usize> {
+        self.spatial_index.within_radius(position, radius)
+    }
+
+    pub fn disable_node(&mut self, node_id: usize) {
+        if self.modifiers.disabled_nodes.insert(node_id) {
+            self.mark_changed(node_id);
+        }
+    }
+
+    pub fn enable_node(&mut self, node_id: usize) {
+        if self.modifiers.disabled_nodes.remove(&node_id) {
+            self.mark_changed(node_id);
+        }
+    }
+
+    pub fn is_node_enabled(&self, node_id: usize) -> bool {
+        !self.modifiers.disabled_nodes.contains(&node_id)
+    }
+
+    /// Disables the connection going from one node to another (only that direction)
+    pub fn disable_connection(&mut self, from_node_id: usize, to_node_id: usize) {
+        if self
+            .modifiers
+            .disabled_connections
+            .insert((from_node_id, to_node_id))
+        {
+            self.mark_changed(from_node_id);
+        }
+    }
+
+    pub fn enable_connection(&mut self, from_node_id: usize, to_node_id: usize) {
+        if self
+            .modifiers
+            .disabled_connections
+            .remove(&(from_node_id, to_node_id))
+        {
+            self.mark_changed(from_node_id);
+        }
+    }
+
+    /// A connection is usable if it and both of its nodes are enabled
+    pub fn is_connection_enabled(&self, from_node_id: usize, to_node_id: usize) -> bool {
+        self.is_node_enabled(from_node_id)
+            && self.is_node_enabled(to_node_id)
+            && !self
+                .modifiers
+                .disabled_connections
+                .contains(&(from_node_id, to_node_id))
+    }
+
+    /// Scales the cost of every connection going into the node (1.0 resets it)
+    pub fn set_node_cost_scale(&mut self, node_id: usize, scale: f32) {
+        if scale == 1.0 {
+            self.modifiers.node_cost_scales.remove(&node_id);
+        } else {
+            self.modifiers.node_cost_scales.insert(node_id, scale);
+        }
+        self.mark_changed(node_id);
+    }
+
+    /// Scales the cost of the connection going from one node to another (1.0 resets it)
+    pub fn set_connection_cost_scale(
+        &mut self,
+        from_node_id: usize,
+        to_node_id: usize,
+        scale: f32,
+    ) {
+        if scale == 1.0 {
+            self.modifiers
+                .connection_cost_scales
+                .remove(&(from_node_id, to_node_id));
+        } else {
+            self.modifiers
+                .connection_cost_scales
+                .insert((from_node_id, to_node_id), scale);
+        }
+        self.mark_changed(from_node_id);
+    }
+
+    /// Scales the cost of a connection on top of its cost scale, for connections that don't
+    /// work as well in practice as the graph thinks (1.0 resets it)
+    pub fn set_connection_penalty(&mut self, from_node_id: usize, to_node_id: usize, scale: f32) {
+        if scale == 1.0 {
+            self.modifiers
+                .connection_penalties
+                .remove(&(from_node_id, to_node_id));
+        } else {
+            self.modifiers
+                .connection_penalties
+                .insert((from_node_id, to_node_id), scale);
+        }
+        self.mark_changed(from_node_id);
+    }
+
+    /// The cost of travelling along a connection with all the modifiers applied
+    pub fn connection_cost(
+        &self,
+        from_node_id: usize,
+        connection: &PathfindingGraphConnection,
+    ) -> f32 {
+        let node_scale = self
+            .modifiers
+            .node_cost_scales
+            .get(&connection.node_id)
+            .unwrap_or(&1.0);
+        let connection_scale = self
+            .modifiers
+            .connection_cost_scales
+            .get(&(from_node_id, connection.node_id))
+            .unwrap_or(&1.0);
+        let connection_penalty = self
+            .modifiers
+            .connection_penalties
+            .get(&(from_node_id, connection.node_id))
+            .unwrap_or(&1.0);
+
+        // Walking effort is how much harder than usual the surface is to walk on, and walking
+        // with a conveyor is cheaper than walking against it
+        let effort_scale = match connection.connection_type {
+            PathfindingGraphConnectionType::Walkable => (1.0 + connection.effort
+                - connection.surface_speed / WANDER_MAX_SPEED)
+                .max(CONVEYOR_MIN_COST_SCALE),
+            PathfindingGraphConnectionType::Mantle => MANTLE_COST_SCALE,
+            PathfindingGraphConnectionType::DoubleJumpable => DOUBLE_JUMP_COST_SCALE,
+            _ => 1.0,
+        };
+
+        connection.dist * effort_scale * node_scale * connection_scale * connection_penalty
+    }
+
+    /// Removes every modifier, restoring the graph as it was built
+    pub fn clear_modifiers(&mut self) {
+        let mut changed_nodes: Vec<usize> = self.modifiers.disabled_nodes.drain().collect();
+        changed_nodes.extend(self.modifiers.node_cost_scales.drain().map(|(id, _)| id));
+        changed_nodes.extend(
+            self.modifiers
+                .disabled_connections
+                .drain()
+                .map(|(id, _)| id),
+        );
+        changed_nodes.extend(
+            self.modifiers
+                .connection_cost_scales
+                .drain()
+                .map(|((id, _), _)| id),
+        );
+        changed_nodes.extend(
+            self.modifiers
+                .connection_penalties
+                .drain()
+                .map(|((id, _), _)| id),
+        );
+
+        for node_id in changed_nodes {
+            self.mark_changed(node_id);
+        }
+    }
+
+    /// Returns (and forgets) the nodes whose modifiers changed since the last call
+    pub fn take_changed_nodes(&mut self) -> Vec<usize> {
+        std::mem::take(&mut self.modifiers.changed_nodes)
+    }
+
+    fn mark_changed(&mut self, node_id: usize) {
+        self.modifiers.revision = self.modifiers.revision.wrapping_add(1);
+        if !self.modifiers.changed_nodes.contains(&node_id) {
+            self.modifiers.changed_nodes.push(node_id);
+        }
+    }
 }
 
-pub fn place_nodes(pathfinding: &mut Pathfinding, level: &Level) {
+/// The (polygon index, line index) of every polygon line agents can walk along
+///
+/// Floors and walls always count, ceilings only if asked for
+pub fn walkable_lines(level: &Level, include_ceilings: bool) -> Vec<(usize, usize)> {
     let mut outer_container_seen = false;
 
-    // Place nodes
+    let mut lines: Vec<(usize, usize)> = Vec::new();
+
     for polygon_index in 0..level.polygons.len() {
         let polygon = &level.polygons[polygon_index];
         if polygon.is_container {
@@ -91,354 +1106,762 @@ pub fn place_nodes(pathfinding: &mut Pathfinding, level: &Level) {
         }
 
         for line_index in 1..polygon.points.len() {
+            // Skip lines the level author doesn't want walked on, and lines nothing stands on
+            if polygon.is_line_excluded(line_index - 1) || polygon.is_line_passable(line_index - 1)
+            {
+                continue;
+            }
+
+            let start_to_end =
+                (polygon.points[line_index] - polygon.points[line_index - 1]).normalize();
+
+            // Zero length lines have no direction to go by
+            if start_to_end.is_nan() {
+                continue;
+            }
+
+            if start_to_end.dot(Vec2::X) > -0.1 || include_ceilings {
+                lines.push((polygon_index, line_index - 1));
+            }
+        }
+    }
+
+    lines
+}
+
+pub fn place_nodes(pathfinding: &mut Pathfinding, level: &Level, settings: &PathfindingSettings) {
+    let lines = walkable_lines(level, settings.ceiling_nodes);
+
+    place_nodes_on_lines(pathfinding, level, settings, &lines);
+}
+
+/// Places nodes along the (polygon index, line index) lines, joined by walkable connections
+pub fn place_nodes_on_lines(
+    pathfinding: &mut Pathfinding,
+    level: &Level,
+    settings: &PathfindingSettings,
+    lines: &[(usize, usize)],
+) {
+    // Polygons share corner points, so nodes are looked up by position to avoid placing them twice
+    let mut node_lookup: HashMap<(i32, i32), usize> = HashMap::new();
+
+    // Place nodes
+    for &(polygon_index, line_index) in lines {
+        let polygon = &level.polygons[polygon_index];
+
+        let start = polygon.points[line_index];
+        let end = polygon.points[line_index + 1];
+
+        let mut start_to_end = end - start;
+
+        let length = start_to_end.length();
+
+        let node_spacing = polygon.node_spacing.unwrap_or(settings.node_spacing);
+        let nodes_on_line_count = (length.abs() / node_spacing).ceil() as f32;
+        let dist_between_nodes_on_line = length / nodes_on_line_count;
+
+        start_to_end = start_to_end.normalize();
+
+        let mut previous_node_id: Option<usize> = None;
+
+        for j in 0..=(nodes_on_line_count as i32) {
+            // Land the last node exactly on the end point so it matches the next line's start
+            let node_pos = if j == nodes_on_line_count as i32 {
+                end
+            } else {
+                start + start_to_end * (j as f32 * dist_between_nodes_on_line)
+            };
+
+            let node_id = place_node(
+                pathfinding,
+                &mut node_lookup,
+                node_pos,
+                polygon_index,
+                line_index,
+            );
+
+            if let Some(previous_node_id) = previous_node_id {
+                pathfinding.nodes[node_id]
+                    .connections
+                    .push(PathfindingGraphConnection {
+                        node_id: previous_node_id,
+                        dist: dist_between_nodes_on_line,
+                        connection_type: PathfindingGraphConnectionType::Walkable,
+                        effort: walking_effort(polygon.line_friction(line_index)),
+                        run_up: None,
+                        // Going back toward the previous node, against the line
+                        surface_speed: -polygon.line_conveyor_speed(line_index),
+                    });
+            }
+
+            previous_node_id = Some(node_id);
+        }
+    }
+}
+
+/// How much harder than usual it is to walk along a surface with the friction
+///
+/// Slippery surfaces are slow to get going on and easy to overshoot, so walking them costs up
+/// to twice as much. Grippier surfaces than usual don't make walking any easier
+pub fn walking_effort(friction: f32) -> f32 {
+    (DEFAULT_FRICTION - friction).clamp(0.0, 1.0)
+}
+
+/// Places a node at the position, or adds the line to the node already there
+///
+/// Returns the id of the node, which is also its index
+fn place_node(
+    pathfinding: &mut Pathfinding,
+    node_lookup: &mut HashMap<(i32, i32), usize>,
+    position: Vec2,
+    polygon_index: usize,
+    line_index: usize,
+) -> usize {
+    let cell = (position.x.round() as i32, position.y.round() as i32);
+
+    if let Some(&node_id) = node_lookup.get(&cell) {
+        let node = &mut pathfinding.nodes[node_id];
+        if !node.line_indicies.contains(&line_index) {
+            node.line_indicies.push(line_index);
+        }
+
+        return node_id;
+    }
+
+    let node_id = pathfinding.nodes.len();
+
+    pathfinding.nodes.push(PathfindingGraphNode {
+        id: node_id,
+        position,
+        polygon_index,
+        line_indicies: vec![line_index],
+        connections: Vec::new(),
+        normal: Vec2::ZERO,
+        is_corner: false,
+        is_external_corner: None,
+    });
+    node_lookup.insert(cell, node_id);
+
+    node_id
+}
+
+/// Makes all of the connections between nodes 2-way
+pub fn make_walkable_connections_2_way(pathfinding: &mut Pathfinding) {
+    make_walkable_connections_2_way_for(pathfinding, 0..pathfinding.nodes.len());
+}
+
+/// Makes the connections of just the nodes in the range 2-way
+pub fn make_walkable_connections_2_way_for(pathfinding: &mut Pathfinding, node_ids: Range<usize>) {
+    for node_index in node_ids {
+        // Make a clone of the current node to appease the borrow checker
+        let node = pathfinding.nodes[node_index].clone();
+
+        // For each connection of the current node
+        for connection in node.connections_of_type(PathfindingGraphConnectionType::Walkable) {
+            // Add the current node to the connections of the other node
+            pathfinding.nodes[connection.node_id]
+                .connections
+                .push(PathfindingGraphConnection {
+                    node_id: node_index,
+                    dist: connection.dist,
+                    connection_type: PathfindingGraphConnectionType::Walkable,
+                    effort: connection.effort,
+                    run_up: None,
+                    surface_speed: -connection.surface_speed,
+                });
+        }
+    }
+}
+
+pub fn make_jumpable_connections(pathfinding: &mut Pathfinding, level: &Level, radius: f32) {
+    let graph: &Pathfinding = pathfinding;
+
+    // No jump can drop further than down to the lowest node
+    let lowest_node_y = graph
+        .nodes
+        .iter()
+        .map(|node| node.position.y)
+        .fold(f32::MAX, f32::min);
+
+    // Each node's jumps only depend on the rest of the graph, so they can be found in parallel
+    let jumpable_connections: Vec<Vec<PathfindingGraphConnection>> = (0..graph.nodes.len())
+        .into_par_iter()
+        .map(|i| {
+            let max_range = max_jump_distance(lowest_node_y - graph.nodes[i].position.y);
+
+            find_jumpable_connections(graph, level, radius, i, max_range)
+        })
+        .collect();
+
+    for (node, connections) in pathfinding.nodes.iter_mut().zip(jumpable_connections) {
+        node.remove_connections_of_type(PathfindingGraphConnectionType::Jumpable);
+        node.connections.extend(connections);
+    }
+}
+
+/// The furthest away a node can be and still be jumped to, given how much higher it is
+///
+/// This is where the discriminant in `jumpability_check` hits zero, so anything past
+/// it can be skipped without doing the (much more expensive) trajectory checks
+pub fn max_jump_distance(height_delta: f32) -> f32 {
+    let v_max = PLATFORMER_AI_JUMP_FORCE;
+
+    ((v_max * v_max - GRAVITY_STRENGTH * height_delta) / GRAVITY_STRENGTH).max(0.0)
+}
+
+/// The jumps off the nodes in the range, and from the rest of the graph onto them
+///
+/// Returned as (from node id, connection) pairs, without touching the graph
+pub fn find_jumps_touching(
+    pathfinding: &Pathfinding,
+    level: &Level,
+    radius: f32,
+    node_ids: Range<usize>,
+) -> Vec<(usize, PathfindingGraphConnection)> {
+    let lowest_node_y = pathfinding
+        .nodes
+        .iter()
+        .map(|node| node.position.y)
+        .fold(f32::MAX, f32::min);
+    let highest_node_y = pathfinding
+        .nodes
+        .iter()
+        .map(|node| node.position.y)
+        .fold(f32::MIN, f32::max);
+
+    let jumps_off: Vec<(usize, PathfindingGraphConnection)> = node_ids
+        .clone()
+        .into_par_iter()
+        .flat_map_iter(|i| {
+            let max_range = max_jump_distance(lowest_node_y - pathfinding.nodes[i].position.y);
+
+            find_jumpable_connections(pathfinding, level, radius, i, max_range)
+                .into_iter()
+                .map(move |connection| (i, connection))
+        })
+        .collect();
+
+    let jumps_on: Vec<(usize, PathfindingGraphConnection)> = node_ids
+        .clone()
+        .into_par_iter()
+        .flat_map_iter(|j| {
+            // Nothing can jump further than a jump down from the highest node
+            let max_range = max_jump_distance(pathfinding.nodes[j].position.y - highest_node_y);
+
+            pathfinding
+                .spatial_index
+                .within_radius(pathfinding.nodes[j].position, max_range)
+                .into_iter()
+                .filter(|i| !node_ids.contains(i) && pathfinding.is_node_enabled(*i))
+                .filter_map(|i| {
+                    find_jumpable_connection(pathfinding, level, radius, i, j)
+                        .map(|connection| (i, connection))
+                })
+                .collect::<Vec<(usize, PathfindingGraphConnection)>>()
+        })
+        .collect();
+
+    jumps_off.into_iter().chain(jumps_on).collect()
+}
+
+fn find_jumpable_connections(
+    pathfinding: &Pathfinding,
+    level: &Level,
+    radius: f32,
+    i: usize,
+    max_range: f32,
+) -> Vec<PathfindingGraphConnection> {
+    let main_node = &pathfinding.nodes[i];
+
+    // Only nodes that could possibly be in range are worth checking
+    let mut candidate_node_ids = pathfinding
+        .spatial_index
+        .within_radius(main_node.position, max_range);
+    candidate_node_ids.sort_unstable();
+
+    candidate_node_ids
+        .into_iter()
+        .filter_map(|j| find_jumpable_connection(pathfinding, level, radius, i, j))
+        .collect()
+}
+
+/// The jump from node i to node j, if there is one
+fn find_jumpable_connection(
+    pathfinding: &Pathfinding,
+    level: &Level,
+    radius: f32,
+    i: usize,
+    j: usize,
+) -> Option<PathfindingGraphConnection> {
+    // Make sure we're not comparing the same node
+    if i == j {
+        return None;
+    }
+
+    let main_node = &pathfinding.nodes[i];
+    let other_node = &pathfinding.nodes[j];
+
+    // Make sure the nodes are not on the same polygon
+    if main_node.polygon_index == other_node.polygon_index {
+        return None;
+    }
+
+    // Make sure the other node isn't out of jumping range
+    let delta = other_node.position - main_node.position;
+    if delta.length() > max_jump_distance(delta.y) {
+        return None;
+    }
+
+    for polygon_index in 0..level.polygons.len() {
+        let polygon = &level.polygons[polygon_index];
+
+        'polygon_lines: for line_index in 1..polygon.points.len() {
+            if main_node.polygon_index == polygon_index
+                && main_node.line_indicies.contains(&(line_index - 1))
+                || other_node.polygon_index == polygon_index
+                    && other_node.line_indicies.contains(&(line_index - 1))
+                || polygon.is_line_passable(line_index - 1)
+            {
+                continue 'polygon_lines;
+            }
+
+            // One-way floors only stop things coming down through them
+            if polygon.is_one_way && other_node.position.y > main_node.position.y {
+                continue 'polygon_lines;
+            }
+
             let start = polygon.points[line_index - 1];
             let end = polygon.points[line_index];
 
-            let mut start_to_end = end - start;
+            let intersection = line_intersect(start, end, main_node.position, other_node.position);
+
+            if intersection.is_some() {
+                return None;
+            }
+        }
+    }
+
+    jumpability_check(main_node, other_node, level, radius)?;
 
-            let length = start_to_end.length();
+    // Pick how fast to launch, and skip the jump if there's no room to run up for it
+    let (effort, run_up) = pick_jump_launch(pathfinding, level, radius, i, other_node)?;
+
+    Some(PathfindingGraphConnection {
+        node_id: j,
+        dist: (main_node.position - other_node.position).length(),
+        connection_type: PathfindingGraphConnectionType::Jumpable,
+        effort,
+        run_up,
+        surface_speed: 0.0,
+    })
+}
+
+pub fn jumpability_check(
+    start_graph_node: &PathfindingGraphNode,
+    goal_graph_node: &PathfindingGraphNode,
+    level: &Level,
+    radius: f32,
+) -> Option<f32> {
+    jumpability_check_with_force(
+        start_graph_node,
+        goal_graph_node,
+        level,
+        radius,
+        PLATFORMER_AI_JUMP_FORCE,
+    )
+}
+
+/// Like `jumpability_check`, for a jump that can be launched at most as fast as the force
+pub fn jumpability_check_with_force(
+    start_graph_node: &PathfindingGraphNode,
+    goal_graph_node: &PathfindingGraphNode,
+    level: &Level,
+    radius: f32,
+    v_max: f32,
+) -> Option<f32> {
+    let start_node = start_graph_node;
+    let start_pos = start_node.position;
+
+    let goal_node = goal_graph_node;
+    let goal_pos = goal_node.position;
+
+    let delta_p = goal_pos - start_pos;
+    let acceleration = Vec2::new(0.0, -GRAVITY_STRENGTH);
+    let b1 = delta_p.dot(acceleration) + v_max * v_max;
+    let discriminant = b1 * b1 - acceleration.dot(acceleration) * delta_p.dot(delta_p);
 
-            let nodes_on_line_count = (length.abs() / 20.0).ceil() as f32;
-            let dist_between_nodes_on_line = length / nodes_on_line_count;
+    if discriminant < 0.0 {
+        return None;
+    }
 
-            start_to_end = start_to_end.normalize();
+    let points = jump_trajectory_points(start_pos, goal_pos);
 
-            if start_to_end.dot(Vec2::X) > -0.1 {
-                for j in 0..(nodes_on_line_count as i32) {
-                    let node_pos = start + start_to_end * (j as f32 * dist_between_nodes_on_line);
+    arc_clears_level(&points, start_node, goal_node, level, radius)
+        .then(|| launch_velocity(delta_p).length())
+}
 
-                    let mut new_node = PathfindingGraphNode {
-                        id: pathfinding.nodes.len(),
-                        position: node_pos,
-                        polygon_index: polygon_index,
-                        line_indicies: vec![(line_index - 1)],
-                        walkable_connections: Vec::new(),
-                        jumpable_connections: Vec::new(),
-                        droppable_connections: Vec::new(),
-                        normal: Vec2::ZERO,
-                        is_corner: false,
-                        is_external_corner: None,
-                    };
+/// Whether an agent with the radius gets along the arc without clipping the level
+///
+/// Lines the start or goal sits on are left out, since the arc starts and ends touching them,
+/// as are lines things can pass through
+pub fn arc_clears_level(
+    points: &[Vec2],
+    start_node: &PathfindingGraphNode,
+    goal_node: &PathfindingGraphNode,
+    level: &Level,
+    radius: f32,
+) -> bool {
+    for polygon_index in 0..level.polygons.len() {
+        let polygon = &level.polygons[polygon_index];
+        for line_index in 1..polygon.points.len() {
+            let start_node_on_line = start_node.polygon_index == polygon_index
+                && start_node.line_indicies.contains(&(line_index - 1));
+            let goal_node_on_line = goal_node.polygon_index == polygon_index
+                && goal_node.line_indicies.contains(&(line_index - 1));
 
-                    if j > 0 {
-                        new_node
-                            .walkable_connections
-                            .push(PathfindingGraphConnection {
-                                node_id: pathfinding.nodes.len() - 1,
-                                dist: dist_between_nodes_on_line,
-                                connection_type: PathfindingGraphConnectionType::Walkable,
-                                effort: 0.0,
-                            });
-                    }
+            if start_node_on_line || goal_node_on_line || polygon.is_line_passable(line_index - 1) {
+                continue;
+            }
 
-                    pathfinding.nodes.push(new_node);
-                }
-                let new_node = PathfindingGraphNode {
-                    id: pathfinding.nodes.len(),
-                    position: end,
-                    polygon_index: polygon_index,
-                    line_indicies: vec![(line_index - 1)],
-                    walkable_connections: vec![PathfindingGraphConnection {
-                        node_id: pathfinding.nodes.len() - 1,
-                        dist: dist_between_nodes_on_line,
-                        connection_type: PathfindingGraphConnectionType::Walkable,
-                        effort: 0.0,
-                    }],
-                    jumpable_connections: Vec::new(),
-                    droppable_connections: Vec::new(),
-                    normal: Vec2::ZERO,
-                    is_corner: false,
-                    is_external_corner: None,
-                };
-
-                pathfinding.nodes.push(new_node);
+            if trajectory_hits_line(
+                points,
+                polygon.points[line_index - 1],
+                polygon.points[line_index],
+                radius,
+                polygon.is_one_way,
+            ) {
+                return false;
             }
         }
     }
+
+    true
 }
 
-/// Makes all of the connections between nodes 2-way
-pub fn make_walkable_connections_2_way(pathfinding: &mut Pathfinding) {
-    for node_index in 0..pathfinding.nodes.len() {
-        // Make a clone of the current node to appease the borrow checker
-        let node = pathfinding.nodes[node_index].clone();
+/// How many straight steps a jump's arc is split into when it's checked (and drawn)
+pub const JUMP_TRAJECTORY_STEPS: usize = 10;
 
-        // For each connection of the current node
-        for connection in node.walkable_connections.iter() {
-            // Add the current node to the connections of the other node
-            pathfinding.nodes[connection.node_id]
-                .walkable_connections
-                .push(PathfindingGraphConnection {
-                    node_id: node_index,
-                    dist: connection.dist,
-                    connection_type: PathfindingGraphConnectionType::Walkable,
-                    effort: 0.0,
-                });
-        }
-    }
-}
+/// How far above the higher end of a short hop its arc can peak, when a faster, flatter launch
+/// keeps it that low
+pub const JUMP_HOP_APEX_HEIGHT: f32 = 16.0;
 
-/// Removes redundant nodes that occupy the same position
-pub fn remove_duplicate_nodes(pathfinding: &mut Pathfinding) {
-    let mut i = 0;
-    while i < pathfinding.nodes.len() {
-        let mut j = i + 1;
-        while j < pathfinding.nodes.len() {
-            if (pathfinding.nodes[i].position - pathfinding.nodes[j].position).length_squared()
-                < 1.0
-            {
-                // Append the connections to the first node
-                let mut j_connections = pathfinding.nodes[j].walkable_connections.clone();
-                pathfinding.nodes[i]
-                    .walkable_connections
-                    .append(&mut j_connections);
+/// How many launch speeds between the minimum energy one and the jump force are tried for a
+/// short hop
+pub const JUMP_SPEED_STEPS: usize = 8;
 
-                // Record the id of the nodes
-                let first_node_id = pathfinding.nodes[i].id;
-                let second_node_id = pathfinding.nodes[j].id;
+/// Points along the minimum energy jump from start to goal, starting at the start and landing
+/// exactly on the goal
+pub fn jump_trajectory_points(start_pos: Vec2, goal_pos: Vec2) -> Vec<Vec2> {
+    let delta_p = goal_pos - start_pos;
 
-                // Append the line indicies to the first node
-                let second_node_line_index = pathfinding.nodes[j].line_indicies[0];
-                pathfinding.nodes[i]
-                    .line_indicies
-                    .push(second_node_line_index);
-
-                // Remove the second node
-                pathfinding.nodes.remove(j);
-
-                // Update the connections of the nodes that were connected to the second node
-                for node in &mut pathfinding.nodes {
-                    for connection in &mut node.walkable_connections {
-                        if connection.node_id == second_node_id {
-                            connection.node_id = first_node_id;
-                        }
-                    }
-                }
-            } else {
-                j += 1;
-            }
-        }
-        i += 1;
-    }
+    arc_points(
+        start_pos,
+        goal_pos,
+        launch_velocity(delta_p),
+        low_energy_jump_time(delta_p),
+    )
 }
 
-/// Updates the ids and connections to reflect the indices of the nodes
-pub fn make_node_ids_indices(pathfinding: &mut Pathfinding) {
-    let pathfinding_nodes_copy = pathfinding.nodes.clone();
+/// Points along the flatter jump from start to goal launched at the speed, like
+/// `jump_trajectory_points`
+pub fn jump_trajectory_points_at_speed(start_pos: Vec2, goal_pos: Vec2, speed: f32) -> Vec<Vec2> {
+    let delta_p = goal_pos - start_pos;
 
-    for node_index in 0..pathfinding.nodes.len() {
-        pathfinding.nodes[node_index].id = node_index;
+    arc_points(
+        start_pos,
+        goal_pos,
+        jump_launch_velocity(delta_p, speed),
+        jump_flight_time(delta_p, speed),
+    )
+}
 
-        for connection_index in 0..pathfinding.nodes[node_index].walkable_connections.len() {
-            let connected_node = pathfinding_nodes_copy
-                .iter()
-                .find(|n| {
-                    n.id == pathfinding.nodes[node_index].walkable_connections[connection_index]
-                        .node_id
-                })
-                .unwrap();
+fn arc_points(
+    start_pos: Vec2,
+    goal_pos: Vec2,
+    launch_velocity: Vec2,
+    flight_time: f32,
+) -> Vec<Vec2> {
+    let acceleration = Vec2::new(0.0, -GRAVITY_STRENGTH);
+    let timestep = flight_time / JUMP_TRAJECTORY_STEPS as f32;
 
-            let connected_node_id = pathfinding_nodes_copy
-                .iter()
-                .position(|n| n.id == connected_node.id)
-                .unwrap();
+    (0..=JUMP_TRAJECTORY_STEPS)
+        .map(|i| {
+            // The last step lands exactly on the goal
+            if i == JUMP_TRAJECTORY_STEPS {
+                goal_pos
+            } else {
+                let t = timestep * i as f32;
+                start_pos + launch_velocity * t + acceleration * t * t / 2.0
+            }
+        })
+        .collect()
+}
 
-            pathfinding.nodes[node_index].walkable_connections[connection_index].node_id =
-                connected_node_id;
+/// Points along the arc of a jump connection between the two positions
+///
+/// Jumps follow the arc of the speed picked for them. Mantles are taken to the ledge itself
+/// rather than the grab point, on the minimum energy arc
+pub fn connection_arc_points(
+    start_pos: Vec2,
+    goal_pos: Vec2,
+    connection: &PathfindingGraphConnection,
+) -> Vec<Vec2> {
+    match connection.connection_type {
+        PathfindingGraphConnectionType::Jumpable | PathfindingGraphConnectionType::WallJumpable => {
+            jump_trajectory_points_at_speed(start_pos, goal_pos, connection.effort)
         }
+        _ => jump_trajectory_points(start_pos, goal_pos),
     }
 }
 
-pub fn make_jumpable_connections(pathfinding: &mut Pathfinding, level: &Level, radius: f32) {
-    for i in 0..pathfinding.nodes.len() {
-        let main_node = &pathfinding.nodes[i];
-
-        let mut jumpable_connections: Vec<PathfindingGraphConnection> = Vec::new();
-
-        'other_nodes: for j in 0..pathfinding.nodes.len() {
-            // Make sure we're not comparing the same node
-            if i == j {
-                continue;
-            }
-
-            let other_node = &pathfinding.nodes[j];
+/// Whether an agent with the radius would clip the line while jumping from start to goal
+///
+/// The arc is checked as a chain of straight steps, each one swept out to the agent's width.
+/// A one-way line only gets in the way of the steps coming down
+pub fn jump_trajectory_hits_line(
+    start_pos: Vec2,
+    goal_pos: Vec2,
+    line_start: Vec2,
+    line_end: Vec2,
+    radius: f32,
+    one_way: bool,
+) -> bool {
+    trajectory_hits_line(
+        &jump_trajectory_points(start_pos, goal_pos),
+        line_start,
+        line_end,
+        radius,
+        one_way,
+    )
+}
 
-            // Make sure the nodes are not on the same polygon
-            if main_node.polygon_index == other_node.polygon_index {
-                continue;
-            }
+/// Whether an agent with the radius would clip the line while following the arc's points
+pub fn trajectory_hits_line(
+    points: &[Vec2],
+    line_start: Vec2,
+    line_end: Vec2,
+    radius: f32,
+    one_way: bool,
+) -> bool {
+    for (prev_pos, pos) in points.iter().zip(points.iter().skip(1)) {
+        let (prev_pos, pos) = (*prev_pos, *pos);
 
-            for polygon_index in 0..level.polygons.len() {
-                let polygon = &level.polygons[polygon_index];
+        if one_way && pos.y > prev_pos.y {
+            continue;
+        }
 
-                'polygon_lines: for line_index in 1..polygon.points.len() {
-                    if main_node.polygon_index == polygon_index
-                        && main_node.line_indicies.contains(&(line_index - 1))
-                        || other_node.polygon_index == polygon_index
-                            && other_node.line_indicies.contains(&(line_index - 1))
-                    {
-                        continue 'polygon_lines;
-                    }
+        let line_dir = (pos - prev_pos).normalize();
 
-                    let start = polygon.points[line_index - 1];
-                    let end = polygon.points[line_index];
+        let line_normal = Vec2::new(-line_dir.y, line_dir.x);
 
-                    let intersection =
-                        line_intersect(start, end, main_node.position, other_node.position);
+        let line_beginning_offset_1 = prev_pos + line_normal * radius;
+        let line_beginning_offset_2 = prev_pos - line_normal * radius;
+        let line_end_offset_1 = pos + line_normal * radius;
+        let line_end_offset_2 = pos - line_normal * radius;
 
-                    if intersection.is_some() {
-                        continue 'other_nodes;
-                    }
-                }
-            }
+        let offset_1_intersection = line_intersect(
+            line_beginning_offset_1,
+            line_end_offset_1,
+            line_start,
+            line_end,
+        );
 
-            let jumpable_velocity = jumpability_check(main_node, other_node, level, radius);
+        if offset_1_intersection.is_some() {
+            return true;
+        }
 
-            if jumpable_velocity.is_none() {
-                continue 'other_nodes;
-            }
+        let offset_2_intersection = line_intersect(
+            line_beginning_offset_2,
+            line_end_offset_2,
+            line_start,
+            line_end,
+        );
 
-            jumpable_connections.push(PathfindingGraphConnection {
-                node_id: j,
-                dist: (main_node.position - other_node.position).length(),
-                connection_type: PathfindingGraphConnectionType::Jumpable,
-                effort: jumpable_velocity.unwrap(),
-            });
+        if offset_2_intersection.is_some() {
+            return true;
         }
-
-        pathfinding.nodes[i].jumpable_connections = jumpable_connections;
     }
+
+    false
 }
 
-pub fn jumpability_check(
-    start_graph_node: &PathfindingGraphNode,
-    goal_graph_node: &PathfindingGraphNode,
-    level: &Level,
-    radius: f32,
-) -> Option<f32> {
-    let start_node = start_graph_node;
-    let start_pos = start_node.position;
+pub fn low_energy_jump_time(delta_p: Vec2) -> f32 {
+    let acceleration = Vec2::new(0.0, -GRAVITY_STRENGTH);
 
-    let goal_node = goal_graph_node;
-    let goal_pos = goal_node.position;
+    (4.0 * delta_p.dot(delta_p) / acceleration.dot(acceleration))
+        .sqrt()
+        .sqrt()
+}
 
-    let delta_p = goal_pos - start_pos;
+/// The launch velocity of the minimum energy jump covering the given displacement
+pub fn launch_velocity(delta_p: Vec2) -> Vec2 {
     let acceleration = Vec2::new(0.0, -GRAVITY_STRENGTH);
-    let v_max = PLATFORMER_AI_JUMP_FORCE;
-    let b1 = delta_p.dot(acceleration) + v_max * v_max;
-    let discriminant = b1 * b1 - acceleration.dot(acceleration) * delta_p.dot(delta_p);
+    let t_low_energy = low_energy_jump_time(delta_p);
 
-    let mut jump_possible = discriminant >= 0.0;
-
-    let t_low_energy = (4.0 * delta_p.dot(delta_p) / acceleration.dot(acceleration))
-        .sqrt()
-        .sqrt();
-    let launch_velocity = delta_p / t_low_energy - acceleration * t_low_energy / 2.0;
-    let timestep = t_low_energy / 10 as f32;
-
-    if jump_possible {
-        'polygon: for polygon_index in 0..level.polygons.len() {
-            let polygon = &level.polygons[polygon_index];
-            'line: for line_index in 1..polygon.points.len() {
-                let start_node_on_line = start_node.polygon_index == polygon_index
-                    && start_node.line_indicies.contains(&(line_index - 1));
-                let goal_node_on_line = goal_node.polygon_index == polygon_index
-                    && goal_node.line_indicies.contains(&(line_index - 1));
-
-                if start_node_on_line || goal_node_on_line {
-                    continue 'line;
-                }
+    delta_p / t_low_energy - acceleration * t_low_energy / 2.0
+}
 
-                let line_start = polygon.points[line_index - 1];
-                let line_end = polygon.points[line_index];
+/// The launch velocity of a jump covering the given displacement at the connection's effort
+///
+/// The speed is held to what the graph picked for the jump, raised to what the displacement
+/// needs and capped at the jump force, so a launch from further back than the node still makes
+/// it
+pub fn effort_launch_velocity(delta_p: Vec2, effort: f32) -> Vec2 {
+    let speed = effort
+        .max(launch_velocity(delta_p).length())
+        .min(PLATFORMER_AI_JUMP_FORCE);
+
+    jump_launch_velocity(delta_p, speed)
+}
 
-                let mut prev_pos = start_pos;
+/// How long the flatter of the two jumps covering the displacement at the speed is in the air
+///
+/// The flight times at a speed are the roots of a quadratic in t^2, the same one whose
+/// discriminant `jumpability_check` uses. A speed too slow to make it at all gets the minimum
+/// energy jump's time
+pub fn jump_flight_time(delta_p: Vec2, speed: f32) -> f32 {
+    let acceleration = Vec2::new(0.0, -GRAVITY_STRENGTH);
+    let b = speed * speed + delta_p.dot(acceleration);
+    let discriminant = b * b - acceleration.dot(acceleration) * delta_p.dot(delta_p);
+    if discriminant < 0.0 {
+        return low_energy_jump_time(delta_p);
+    }
 
-                for i in 1..10 {
-                    let t = timestep * i as f32;
-                    let pos = start_pos + launch_velocity * t + acceleration * t * t / 2.0;
+    let t_squared = 2.0 * (b - discriminant.sqrt()) / acceleration.dot(acceleration);
+    if t_squared <= 0.0 {
+        return low_energy_jump_time(delta_p);
+    }
 
-                    let line_dir = (pos - prev_pos).normalize();
+    t_squared.sqrt()
+}
 
-                    let line_normal = Vec2::new(-line_dir.y, line_dir.x);
+/// The launch velocity of the flatter jump covering the displacement at the speed
+pub fn jump_launch_velocity(delta_p: Vec2, speed: f32) -> Vec2 {
+    let acceleration = Vec2::new(0.0, -GRAVITY_STRENGTH);
+    let t = jump_flight_time(delta_p, speed);
 
-                    let line_beginning_offset_1 = prev_pos + line_normal * radius;
-                    let line_beginning_offset_2 = prev_pos - line_normal * radius;
-                    let line_end_offset_1 = pos + line_normal * radius;
-                    let line_end_offset_2 = pos - line_normal * radius;
+    delta_p / t - acceleration * t / 2.0
+}
 
-                    let offset_1_intersection = line_intersect(
-                        line_beginning_offset_1,
-                        line_end_offset_1,
-                        line_start,
-                        line_end,
-                    );
+/// Picks how fast a jump from node i to the target is launched, and the run-up that needs
+///
+/// The jump's minimum energy arc has to have passed `jumpability_check` already. Short hops are
+/// launched faster and flatter than that, at the slowest speed whose arc peaks no more than
+/// `JUMP_HOP_APEX_HEIGHT` above the higher end, clears the level and has room for its run-up.
+/// Jumps with no such speed go at the minimum energy one, which is what they need. None if
+/// there's no room to run up to that
+fn pick_jump_launch(
+    pathfinding: &Pathfinding,
+    level: &Level,
+    radius: f32,
+    i: usize,
+    target: &PathfindingGraphNode,
+) -> Option<(f32, Option<RunUp>)> {
+    let main_node = &pathfinding.nodes[i];
+    let delta_p = target.position - main_node.position;
+    let min_energy_velocity = launch_velocity(delta_p);
+    let min_energy_speed = min_energy_velocity.length();
+    let highest_apex = delta_p.y.max(0.0) + JUMP_HOP_APEX_HEIGHT;
+
+    let takeoff = |velocity: Vec2| match calculate_run_up(pathfinding, i, velocity) {
+        Takeoff::Standing => Some(None),
+        Takeoff::RunUp(run_up) => Some(Some(run_up)),
+        Takeoff::Unreachable => None,
+    };
+    let apex = |velocity: Vec2| velocity.y.max(0.0).powi(2) / (2.0 * GRAVITY_STRENGTH);
+
+    if apex(min_energy_velocity) > highest_apex {
+        let hop = (1..=JUMP_SPEED_STEPS).find_map(|step| {
+            let speed = min_energy_speed
+                + (PLATFORMER_AI_JUMP_FORCE - min_energy_speed) * step as f32
+                    / JUMP_SPEED_STEPS as f32;
+            let velocity = jump_launch_velocity(delta_p, speed);
+            let points =
+                jump_trajectory_points_at_speed(main_node.position, target.position, speed);
+
+            if apex(velocity) > highest_apex
+                || !arc_clears_level(&points, main_node, target, level, radius)
+            {
+                return None;
+            }
 
-                    if offset_1_intersection.is_some() {
-                        jump_possible = false;
-                        break 'polygon;
-                    }
+            Some((speed, takeoff(velocity)?))
+        });
 
-                    let offset_2_intersection = line_intersect(
-                        line_beginning_offset_2,
-                        line_end_offset_2,
-                        line_start,
-                        line_end,
-                    );
+        if hop.is_some() {
+            return hop;
+        }
+    }
 
-                    if offset_2_intersection.is_some() {
-                        jump_possible = false;
-                        break 'polygon;
-                    }
+    Some((min_energy_speed, takeoff(min_energy_velocity)?))
+}
 
-                    prev_pos = pos;
-                }
+/// Works out the run-up a jump from the given node needs
+pub fn calculate_run_up(
+    pathfinding: &Pathfinding,
+    node_id: usize,
+    launch_velocity: Vec2,
+) -> Takeoff {
+    let speed = launch_velocity.x.abs() - PLATFORMER_AI_JUMP_HORIZONTAL_BOOST;
+
+    // A standing jump is enough
+    if speed <= 0.0 {
+        return Takeoff::Standing;
+    }
 
-                let line_dir = (goal_pos - prev_pos).normalize();
+    // The agent approaches its max speed asymptotically, so leave a little margin
+    if speed >= WANDER_MAX_SPEED * 0.95 {
+        return Takeoff::Unreachable;
+    }
 
-                let line_normal = Vec2::new(-line_dir.y, line_dir.x);
+    // Simulate the agent accelerating from a standstill
+    let mut velocity = 0.0;
+    let mut distance = 0.0;
+    while velocity < speed {
+        velocity += (WANDER_MAX_SPEED - velocity) * ACCELERATION_SCALERS.0;
+        distance += velocity;
+    }
 
-                let line_beginning_offset_1 = prev_pos + line_normal * radius;
-                let line_beginning_offset_2 = prev_pos - line_normal * radius;
-                let line_end_offset_1 = goal_pos + line_normal * radius;
-                let line_end_offset_2 = goal_pos - line_normal * radius;
+    let direction = launch_velocity.x.signum();
 
-                let offset_1_intersection = line_intersect(
-                    line_beginning_offset_1,
-                    line_end_offset_1,
-                    line_start,
-                    line_end,
-                );
+    // Walk back along the floor, away from the jump, until there's enough room
+    let mut current_node_id = node_id;
+    let mut distance_walked = 0.0;
+    while distance_walked < distance {
+        let current_node = &pathfinding.nodes[current_node_id];
 
-                if offset_1_intersection.is_some() {
-                    jump_possible = false;
-                    break 'polygon;
-                }
+        let next_connection = current_node
+            .connections_of_type(PathfindingGraphConnectionType::Walkable)
+            .find(|connection| {
+                let next_node = &pathfinding.nodes[connection.node_id];
 
-                let offset_2_intersection = line_intersect(
-                    line_beginning_offset_2,
-                    line_end_offset_2,
-                    line_start,
-                    line_end,
-                );
+                (next_node.position.x - current_node.position.x) * direction < 0.0
+                    && next_node.normal.y > 0.5
+            });
 
-                if offset_2_intersection.is_some() {
-                    jump_possible = false;
-                    break 'polygon;
-                }
+        match next_connection {
+            Some(connection) => {
+                distance_walked += connection.dist;
+                current_node_id = connection.node_id;
             }
+            None => return Takeoff::Unreachable,
         }
     }
 
-    return if jump_possible {
-        Some(launch_velocity.length())
-    } else {
-        None
-    };
+    Takeoff::RunUp(RunUp {
+        speed,
+        distance,
+        direction,
+        start_node_id: current_node_id,
+    })
 }
 
 pub fn calculate_normals(pathfinding: &mut Pathfinding, level: &Level) {
-    for node_index in 0..pathfinding.nodes.len() {
+    calculate_normals_for(pathfinding, level, 0..pathfinding.nodes.len());
+}
+
+pub fn calculate_normals_for(pathfinding: &mut Pathfinding, level: &Level, node_ids: Range<usize>) {
+    for node_index in node_ids {
         let node = &pathfinding.nodes[node_index];
 
         let mut normal = Vec2::ZERO;
@@ -456,8 +1879,21 @@ pub fn calculate_normals(pathfinding: &mut Pathfinding, level: &Level) {
     }
 }
 
+pub fn build_spatial_index(pathfinding: &mut Pathfinding) {
+    pathfinding.spatial_index = KdTree::build(
+        pathfinding
+            .nodes
+            .iter()
+            .map(|node| (node.id, node.position)),
+    );
+}
+
 pub fn setup_corners(pathfinding: &mut Pathfinding) {
-    for node_index in 0..pathfinding.nodes.len() {
+    setup_corners_for(pathfinding, 0..pathfinding.nodes.len());
+}
+
+pub fn setup_corners_for(pathfinding: &mut Pathfinding, node_ids: Range<usize>) {
+    for node_index in node_ids {
         // let node = &mut pathfinding.nodes[node_index];
 
         pathfinding.nodes[node_index].is_corner =
@@ -466,7 +1902,9 @@ pub fn setup_corners(pathfinding: &mut Pathfinding) {
         if pathfinding.nodes[node_index].is_corner {
             let mut line_dir = Vec2::ZERO;
 
-            for connection in pathfinding.nodes[node_index].walkable_connections.iter() {
+            for connection in pathfinding.nodes[node_index]
+                .connections_of_type(PathfindingGraphConnectionType::Walkable)
+            {
                 let line = pathfinding.nodes[connection.node_id].position
                     - pathfinding.nodes[node_index].position;
                 line_dir += line;
@@ -478,36 +1916,685 @@ pub fn setup_corners(pathfinding: &mut Pathfinding) {
     }
 }
 
-// pub fn make_droppable_connections(pathfinding: &mut Pathfinding, level: &Level) {
-//     // For each node
+/// Joins each wall node to the nodes up the wall facing it that it can wall jump to
+///
+/// Ordinary jumps need room to run up to their launch speed, which there isn't on a wall, so
+/// without these the agents can only climb up shafts, never jump across them
+pub fn make_wall_jump_connections(pathfinding: &mut Pathfinding, level: &Level, radius: f32) {
+    let graph: &Pathfinding = pathfinding;
+
+    let is_wall = |node: &PathfindingGraphNode| {
+        node.surface_kind() == SurfaceKind::Wall
+            && !node.is_corner
+            && graph.is_node_enabled(node.id)
+    };
+
+    let wall_jump_connections: Vec<Vec<PathfindingGraphConnection>> = (0..graph.nodes.len())
+        .into_par_iter()
+        .map(|i| {
+            let main_node = &graph.nodes[i];
+            if !is_wall(main_node) {
+                return Vec::new();
+            }
+
+            let mut candidate_node_ids = graph
+                .spatial_index
+                .within_radius(main_node.position, max_jump_distance(0.0));
+            candidate_node_ids.sort_unstable();
+
+            candidate_node_ids
+                .into_iter()
+                .filter_map(|j| {
+                    let other_node = &graph.nodes[j];
+                    let delta = other_node.position - main_node.position;
+                    let gap = delta.dot(main_node.normal);
+
+                    // Up the wall across the way, with room for the agent in between
+                    let facing = is_wall(other_node)
+                        && main_node.normal.dot(other_node.normal) < -WALL_JUMP_MIN_OPPOSITION
+                        && delta.y > 0.0
+                        && gap > radius * 2.0
+                        && gap <= WALL_JUMP_MAX_GAP;
+
+                    // Nodes an ordinary jump already reaches don't need a second way there
+                    let already_jumpable = main_node
+                        .connections_of_type(PathfindingGraphConnectionType::Jumpable)
+                        .any(|connection| connection.node_id == j);
+
+                    if !facing || already_jumpable {
+                        return None;
+                    }
+
+                    let effort = jumpability_check(main_node, other_node, level, radius)?;
+
+                    Some(PathfindingGraphConnection {
+                        node_id: j,
+                        dist: delta.length(),
+                        connection_type: PathfindingGraphConnectionType::WallJumpable,
+                        effort,
+                        run_up: None,
+                        surface_speed: 0.0,
+                    })
+                })
+                .collect()
+        })
+        .collect();
+
+    for (node, connections) in pathfinding.nodes.iter_mut().zip(wall_jump_connections) {
+        node.remove_connections_of_type(PathfindingGraphConnectionType::WallJumpable);
+        node.connections.extend(connections);
+    }
+}
+
+/// Where an agent jumping for the ledge aims, just below it on its open side, so it catches
+/// the ledge on the way down
+pub fn ledge_grab_point(ledge: &PathfindingGraphNode, radius: f32) -> Vec2 {
+    ledge.position
+        + Vec2::new(
+            ledge.normal.x.signum() * radius * 1.5,
+            -LEDGE_GRAB_REACH / 2.0,
+        )
+}
+
+/// Joins floor nodes to the ledges they can't quite jump onto, but can jump to just below and
+/// mantle up over
+pub fn make_mantle_connections(pathfinding: &mut Pathfinding, level: &Level, radius: f32) {
+    let graph: &Pathfinding = pathfinding;
+
+    let highest_node_y = graph
+        .nodes
+        .iter()
+        .map(|node| node.position.y)
+        .fold(f32::MIN, f32::max);
+
+    let mantle_connections: Vec<(usize, PathfindingGraphConnection)> = (0..graph.nodes.len())
+        .into_par_iter()
+        .filter(|j| graph.nodes[*j].is_ledge() && graph.is_node_enabled(*j))
+        .flat_map_iter(|j| {
+            let ledge = &graph.nodes[j];
+
+            // The grab point isn't on any line, so nothing's skipped when checking the arc
+            let grab_node = PathfindingGraphNode {
+                position: ledge_grab_point(ledge, radius),
+                line_indicies: Vec::new(),
+                ..ledge.clone()
+            };
+
+            // Nothing can jump further than a jump down from the highest node
+            let max_range = max_jump_distance(grab_node.position.y - highest_node_y);
+
+            graph
+                .spatial_index
+                .within_radius(grab_node.position, max_range)
+                .into_iter()
+                .filter_map(|i| {
+                    let main_node = &graph.nodes[i];
+
+                    // Ledges a clean jump already reaches don't need a mantle
+                    let already_jumpable = main_node
+                        .connections_of_type(PathfindingGraphConnectionType::Jumpable)
+                        .chain(
+                            main_node
+                                .connections_of_type(PathfindingGraphConnectionType::WallJumpable),
+                        )
+                        .any(|connection| connection.node_id == j);
+
+                    if i == j
+                        || already_jumpable
+                        || main_node.surface_kind() != SurfaceKind::Floor
+                        || !graph.is_node_enabled(i)
+                    {
+                        return None;
+                    }
+
+                    jumpability_check(main_node, &grab_node, level, radius)?;
+
+                    let launch_velocity = launch_velocity(grab_node.position - main_node.position);
+                    let run_up = match calculate_run_up(graph, i, launch_velocity) {
+                        Takeoff::Standing => None,
+                        Takeoff::RunUp(run_up) => Some(run_up),
+                        Takeoff::Unreachable => return None,
+                    };
+
+                    Some((
+                        i,
+                        PathfindingGraphConnection {
+                            node_id: j,
+                            dist: (ledge.position - main_node.position).length(),
+                            connection_type: PathfindingGraphConnectionType::Mantle,
+                            effort: launch_velocity.length(),
+                            run_up,
+                            surface_speed: 0.0,
+                        },
+                    ))
+                })
+                .collect::<Vec<(usize, PathfindingGraphConnection)>>()
+        })
+        .collect();
+
+    for node in pathfinding.nodes.iter_mut() {
+        node.remove_connections_of_type(PathfindingGraphConnectionType::Mantle);
+    }
+    for (node_id, connection) in mantle_connections {
+        pathfinding.nodes[node_id].connections.push(connection);
+    }
+}
+
+/// Joins ledges facing each other across a gap that none of the jumps or mantles cross, but a
+/// dash can, with nothing in the way at standing height
+pub fn make_dash_connections(pathfinding: &mut Pathfinding, level: &Level, radius: f32) {
+    let graph: &Pathfinding = pathfinding;
+    let dash_distance = PLATFORMER_AI_DASH_SPEED * PLATFORMER_AI_DASH_TICKS as f32;
+
+    let dash_connections: Vec<(usize, PathfindingGraphConnection)> = (0..graph.nodes.len())
+        .into_par_iter()
+        .filter(|i| graph.nodes[*i].is_ledge() && graph.is_node_enabled(*i))
+        .flat_map_iter(|i| {
+            let main_node = &graph.nodes[i];
+
+            graph
+                .spatial_index
+                .within_radius(main_node.position, dash_distance)
+                .into_iter()
+                .filter_map(|j| {
+                    let other_node = &graph.nodes[j];
+                    let delta_p = other_node.position - main_node.position;
+
+                    // The other ledge has to be across the gap from this one and level with it
+                    let facing = delta_p.x * main_node.normal.x > 0.0
+                        && delta_p.x * other_node.normal.x < 0.0;
+
+                    let already_connected = main_node
+                        .connections_of_type(PathfindingGraphConnectionType::Jumpable)
+                        .chain(
+                            main_node.connections_of_type(PathfindingGraphConnectionType::Mantle),
+                        )
+                        .any(|connection| connection.node_id == j);
+
+                    if i == j
+                        || !other_node.is_ledge()
+                        || !facing
+                        || delta_p.y.abs() > radius
+                        || delta_p.x.abs() > dash_distance
+                        || already_connected
+                        || !graph.is_node_enabled(j)
+                    {
+                        return None;
+                    }
+
+                    let standing_offset = Vec2::new(0.0, radius);
+                    if !level.line_of_sight_check(
+                        main_node.position + standing_offset,
+                        other_node.position + standing_offset,
+                    ) {
+                        return None;
+                    }
+
+                    Some((
+                        i,
+                        PathfindingGraphConnection {
+                            node_id: j,
+                            dist: delta_p.length(),
+                            connection_type: PathfindingGraphConnectionType::Dashable,
+                            effort: PLATFORMER_AI_DASH_SPEED,
+                            run_up: None,
+                            surface_speed: 0.0,
+                        },
+                    ))
+                })
+                .collect::<Vec<(usize, PathfindingGraphConnection)>>()
+        })
+        .collect();
+
+    for node in pathfinding.nodes.iter_mut() {
+        node.remove_connections_of_type(PathfindingGraphConnectionType::Dashable);
+    }
+    for (node_id, connection) in dash_connections {
+        pathfinding.nodes[node_id].connections.push(connection);
+    }
+}
+
+/// Where a double jump between the two points makes its second jump, halfway across and above
+/// the higher of them
+pub fn double_jump_midpoint(start: Vec2, end: Vec2) -> Vec2 {
+    Vec2::new(
+        (start.x + end.x) / 2.0,
+        start.y.max(end.y) + DOUBLE_JUMP_RISE,
+    )
+}
+
+/// Joins floor nodes to the floors no single move reaches, but a jump up to the midpoint and a
+/// second jump from there does
+pub fn make_double_jump_connections(pathfinding: &mut Pathfinding, level: &Level, radius: f32) {
+    let graph: &Pathfinding = pathfinding;
+
+    // The midpoint's halfway across, so nothing past twice the first jump's reach can be made
+    let max_range = 2.0 * max_jump_distance(DOUBLE_JUMP_RISE);
+
+    let double_jump_connections: Vec<(usize, PathfindingGraphConnection)> = (0..graph.nodes.len())
+        .into_par_iter()
+        .filter(|i| {
+            graph.nodes[*i].surface_kind() == SurfaceKind::Floor && graph.is_node_enabled(*i)
+        })
+        .flat_map_iter(|i| {
+            let main_node = &graph.nodes[i];
+
+            graph
+                .spatial_index
+                .within_radius(main_node.position, max_range)
+                .into_iter()
+                .filter_map(|j| {
+                    let other_node = &graph.nodes[j];
+
+                    let already_connected = main_node
+                        .connections
+                        .iter()
+                        .any(|connection| connection.node_id == j);
+
+                    if i == j
+                        || already_connected
+                        || other_node.surface_kind() != SurfaceKind::Floor
+                        || !graph.is_node_enabled(j)
+                    {
+                        return None;
+                    }
+
+                    // The midpoint isn't on any line, so nothing's skipped when checking the arcs
+                    let mid_node = PathfindingGraphNode {
+                        position: double_jump_midpoint(main_node.position, other_node.position),
+                        line_indicies: Vec::new(),
+                        ..main_node.clone()
+                    };
+
+                    jumpability_check(main_node, &mid_node, level, radius)?;
+                    jumpability_check_with_force(
+                        &mid_node,
+                        other_node,
+                        level,
+                        radius,
+                        PLATFORMER_AI_DOUBLE_JUMP_FORCE,
+                    )?;
+
+                    let launch_velocity = launch_velocity(mid_node.position - main_node.position);
+                    let run_up = match calculate_run_up(graph, i, launch_velocity) {
+                        Takeoff::Standing => None,
+                        Takeoff::RunUp(run_up) => Some(run_up),
+                        Takeoff::Unreachable => return None,
+                    };
+
+                    Some((
+                        i,
+                        PathfindingGraphConnection {
+                            node_id: j,
+                            dist: (other_node.position - main_node.position).length(),
+                            connection_type: PathfindingGraphConnectionType::DoubleJumpable,
+                            effort: launch_velocity.length(),
+                            run_up,
+                            surface_speed: 0.0,
+                        },
+                    ))
+                })
+                .collect::<Vec<(usize, PathfindingGraphConnection)>>()
+        })
+        .collect();
+
+    for node in pathfinding.nodes.iter_mut() {
+        node.remove_connections_of_type(PathfindingGraphConnectionType::DoubleJumpable);
+    }
+    for (node_id, connection) in double_jump_connections {
+        pathfinding.nodes[node_id].connections.push(connection);
+    }
+}
+
+/// Gives every node on the floor of a one-way platform a drop down to the floor below it
+pub fn make_droppable_connections(pathfinding: &mut Pathfinding, level: &Level) {
+    let fall_distance = level.size.y * level.grid_size;
+
+    for i in 0..pathfinding.nodes.len() {
+        let node = &pathfinding.nodes[i];
+
+        if !level.polygons[node.polygon_index].is_one_way
+            || node.surface_kind() != SurfaceKind::Floor
+        {
+            continue;
+        }
+
+        // Cast straight down from just under the platform's floor
+        let fall_start = node.position - Vec2::Y;
+        let fall_end = node.position - Vec2::Y * fall_distance;
+
+        let landing = level
+            .polygons
+            .iter()
+            .enumerate()
+            .flat_map(|(polygon_index, polygon)| {
+                (1..polygon.points.len())
+                    .filter(move |line_index| !polygon.is_line_passable(line_index - 1))
+                    .filter_map(move |line_index| {
+                        line_intersect(
+                            polygon.points[line_index - 1],
+                            polygon.points[line_index],
+                            fall_start,
+                            fall_end,
+                        )
+                        .map(|hit| (polygon_index, line_index - 1, hit))
+                    })
+            })
+            .max_by(|(_, _, a), (_, _, b)| a.y.total_cmp(&b.y));
+
+        // Falling into something that isn't a floor isn't a drop anyone can land
+        let Some((polygon_index, line_index, hit)) = landing else {
+            continue;
+        };
+        if !level.polygons[polygon_index].is_line_floor(line_index) {
+            continue;
+        }
+
+        let landing_node_id = pathfinding.spatial_index.nearest_filtered(hit, |node_id| {
+            let landing_node = &pathfinding.nodes[node_id];
+
+            landing_node.polygon_index == polygon_index
+                && landing_node.line_indicies.contains(&line_index)
+        });
 
-//     for i in 0..pathfinding.nodes.len() {
-//         let node = &pathfinding.nodes[i];
+        let Some(landing_node_id) = landing_node_id else {
+            continue;
+        };
+
+        let dist = (pathfinding.nodes[landing_node_id].position - node.position).length();
+
+        pathfinding.nodes[i]
+            .connections
+            .push(PathfindingGraphConnection {
+                node_id: landing_node_id,
+                dist,
+                connection_type: PathfindingGraphConnectionType::Droppable,
+                effort: 0.0,
+                run_up: None,
+                surface_speed: 0.0,
+            });
+    }
+}
+
+/// Joins the floors each ladder passes, one to the next from the bottom up, both ways
+pub fn make_climbable_connections(pathfinding: &mut Pathfinding, level: &Level) {
+    for ladder in level.ladders.iter() {
+        let center_x = ladder.center_x();
+
+        let mut candidate_node_ids: Vec<usize> = pathfinding
+            .nodes
+            .iter()
+            .filter(|node| {
+                node.surface_kind() == SurfaceKind::Floor
+                    && ladder.contains(node.position, PLATFORMER_AI_AGENT_RADIUS)
+            })
+            .map(|node| node.id)
+            .collect();
+
+        // Bottom up, with the node closest to the middle of the ladder first on each floor
+        candidate_node_ids.sort_by_key(|node_id| {
+            let position = pathfinding.nodes[*node_id].position;
+
+            (
+                position.y.round() as i32,
+                ((position.x - center_x).abs() * 100.0) as i32,
+            )
+        });
+
+        let mut rung_node_ids: Vec<usize> = Vec::new();
+        for node_id in candidate_node_ids {
+            let on_last_floor = rung_node_ids.last().is_some_and(|last_node_id| {
+                pathfinding.nodes[*last_node_id].position.y.round()
+                    == pathfinding.nodes[node_id].position.y.round()
+            });
+
+            if !on_last_floor {
+                rung_node_ids.push(node_id);
+            }
+        }
+
+        for rung in rung_node_ids.windows(2) {
+            let (bottom_node_id, top_node_id) = (rung[0], rung[1]);
+            let dist = (pathfinding.nodes[top_node_id].position
+                - pathfinding.nodes[bottom_node_id].position)
+                .length();
+
+            for (from_node_id, to_node_id) in
+                [(bottom_node_id, top_node_id), (top_node_id, bottom_node_id)]
+            {
+                pathfinding.nodes[from_node_id]
+                    .connections
+                    .push(PathfindingGraphConnection {
+                        node_id: to_node_id,
+                        dist,
+                        connection_type: PathfindingGraphConnectionType::Climbable,
+                        effort: 0.0,
+                        run_up: None,
+                        surface_speed: 0.0,
+                    });
+            }
+        }
+    }
+}
+
+/// Joins the floor nodes closest to each teleporter's entrance and exit
+pub fn make_teleport_connections(pathfinding: &mut Pathfinding, level: &Level) {
+    for teleporter in level.teleporters.iter() {
+        let graph: &Pathfinding = pathfinding;
+        let nearest_floor_node_id = |position: Vec2| {
+            graph.spatial_index.nearest_filtered(position, |node_id| {
+                graph.nodes[node_id].surface_kind() == SurfaceKind::Floor
+            })
+        };
+
+        let (Some(entrance_node_id), Some(exit_node_id)) = (
+            nearest_floor_node_id(teleporter.entrance),
+            nearest_floor_node_id(teleporter.exit),
+        ) else {
+            println!(
+                "No floor near teleporter from {} to {}",
+                teleporter.entrance, teleporter.exit
+            );
+            continue;
+        };
+
+        if entrance_node_id == exit_node_id {
+            continue;
+        }
+
+        pathfinding.nodes[entrance_node_id]
+            .connections
+            .push(PathfindingGraphConnection {
+                node_id: exit_node_id,
+                dist: teleporter.cost,
+                connection_type: PathfindingGraphConnectionType::Teleport,
+                effort: 0.0,
+                run_up: None,
+                surface_speed: 0.0,
+            });
+    }
+}
+
+/// Joins every pair of nodes in the same water that an agent can swim straight between
+///
+/// A node's in the water if an agent standing on it would be, which takes in the banks right
+/// at the water's edge. Swims cost more than their length, since swimming is slower than walking
+pub fn make_swimmable_connections(pathfinding: &mut Pathfinding, level: &Level) {
+    let radius = PLATFORMER_AI_AGENT_RADIUS;
+
+    for water in level.water.iter() {
+        let node_ids: Vec<usize> = pathfinding
+            .nodes
+            .iter()
+            .filter(|node| water.contains(node.position + node.normal * radius, radius))
+            .map(|node| node.id)
+            .collect();
+
+        for &i in node_ids.iter() {
+            for &j in node_ids.iter() {
+                let main_node = &pathfinding.nodes[i];
+                let other_node = &pathfinding.nodes[j];
+
+                // Neighbours on the same surface can just walk
+                if i == j
+                    || main_node
+                        .connections_of_type(PathfindingGraphConnectionType::Walkable)
+                        .any(|connection| connection.node_id == j)
+                {
+                    continue;
+                }
+
+                let start = main_node.position + main_node.normal * radius;
+                let end = other_node.position + other_node.normal * radius;
+
+                let dist = (end - start).length();
+                if dist > SWIM_MAX_DISTANCE {
+                    continue;
+                }
+
+                let blocked = level.polygons.iter().any(|polygon| {
+                    (1..polygon.points.len()).any(|line_index| {
+                        !polygon.is_line_passable(line_index - 1)
+                            && line_intersect(
+                                polygon.points[line_index - 1],
+                                polygon.points[line_index],
+                                start,
+                                end,
+                            )
+                            .is_some()
+                    })
+                });
+                if blocked {
+                    continue;
+                }
+
+                pathfinding.nodes[i]
+                    .connections
+                    .push(PathfindingGraphConnection {
+                        node_id: j,
+                        dist: dist * WANDER_MAX_SPEED / PLATFORMER_AI_SWIM_SPEED,
+                        connection_type: PathfindingGraphConnectionType::Swimmable,
+                        effort: 0.0,
+                        run_up: None,
+                        surface_speed: 0.0,
+                    });
+            }
+        }
+    }
+}
+
+/// Joins the floor node under each bounce pad to wherever the pad's launch lands
+///
+/// Pads always launch with the same velocity, so there's exactly one place each one can reach
+pub fn make_bounce_connections(pathfinding: &mut Pathfinding, level: &Level) {
+    for bounce_pad in level.bounce_pads.iter() {
+        let pad_node_id = pathfinding
+            .spatial_index
+            .nearest_filtered(bounce_pad.position, |node_id| {
+                pathfinding.nodes[node_id].surface_kind() == SurfaceKind::Floor
+            });
+
+        let Some(pad_node_id) = pad_node_id else {
+            println!("No floor under bounce pad at {}", bounce_pad.position);
+            continue;
+        };
+
+        let Some((polygon_index, line_index, hit, dist)) =
+            find_bounce_landing(level, bounce_pad.position, bounce_pad.impulse)
+        else {
+            println!("Bounce pad at {} never lands", bounce_pad.position);
+            continue;
+        };
+
+        if !level.polygons[polygon_index].is_line_floor(line_index) {
+            continue;
+        }
 
-//         if node.normal.y > 0.1 {
-//             continue;
-//         }
+        let landing_node_id = pathfinding.spatial_index.nearest_filtered(hit, |node_id| {
+            let landing_node = &pathfinding.nodes[node_id];
 
-//         let mut droppable_connections: Vec<usize> = Vec::new();
+            landing_node.polygon_index == polygon_index
+                && landing_node.line_indicies.contains(&line_index)
+        });
 
-//         // Boxcast down
+        let Some(landing_node_id) = landing_node_id else {
+            continue;
+        };
 
-//         // // For each line in the level
-//         // for polygon_index in 0..level.polygons.len() {
-//         //     let polygon = &level.polygons[polygon_index];
+        if landing_node_id == pad_node_id {
+            continue;
+        }
 
-//         //     for line_index in 1..polygon.points.len() {
-//         //         let start = polygon.points[line_index - 1];
-//         //         let end = polygon.points[line_index];
+        pathfinding.nodes[pad_node_id]
+            .connections
+            .push(PathfindingGraphConnection {
+                node_id: landing_node_id,
+                dist,
+                connection_type: PathfindingGraphConnectionType::Bounce,
+                effort: bounce_pad.impulse.length(),
+                run_up: None,
+                surface_speed: 0.0,
+            });
+    }
+}
 
-//         //         // If the boxcast hits a node below, add it to the droppable connections
-//         //     }
-//         // }
+/// Follows the feet of something launched from the position until they hit the level
+///
+/// Returns the (polygon index, line index) of the line hit, where it was hit and how far the
+/// feet travelled to get there
+pub fn find_bounce_landing(
+    level: &Level,
+    position: Vec2,
+    velocity: Vec2,
+) -> Option<(usize, usize, Vec2, f32)> {
+    // Start just above the pad, so the floor it's on doesn't count as the landing
+    let mut position = position + Vec2::Y;
+    let mut velocity = velocity;
+    let mut dist = 0.0;
+
+    for _ in 0..BOUNCE_MAX_TICKS {
+        let next_position = position + velocity;
+        let rising = velocity.y > 0.0;
+
+        let hit = level
+            .polygons
+            .iter()
+            .enumerate()
+            .flat_map(|(polygon_index, polygon)| {
+                (1..polygon.points.len())
+                    .filter(move |line_index| {
+                        // Rising bodies go straight through one-way floors
+                        !(polygon.is_line_passable(line_index - 1) || polygon.is_one_way && rising)
+                    })
+                    .filter_map(move |line_index| {
+                        line_intersect(
+                            polygon.points[line_index - 1],
+                            polygon.points[line_index],
+                            position,
+                            next_position,
+                        )
+                        .map(|hit| (polygon_index, line_index - 1, hit))
+                    })
+            })
+            .min_by(|(_, _, a), (_, _, b)| {
+                (*a - position)
+                    .length_squared()
+                    .total_cmp(&(*b - position).length_squared())
+            });
 
-//         // If the boxcast hits a node below, add it to the droppable connections
-//     }
+        if let Some((polygon_index, line_index, hit)) = hit {
+            return Some((
+                polygon_index,
+                line_index,
+                hit,
+                dist + (hit - position).length(),
+            ));
+        }
 
-//     // Boxcast down
+        dist += velocity.length();
+        position = next_position;
+        velocity.y -= GRAVITY_STRENGTH;
+    }
 
-//     // If the boxcast hits a node below, add it to the droppable connections
-// }
+    None
+}
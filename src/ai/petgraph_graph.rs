@@ -0,0 +1,97 @@
+use std::collections::HashMap;
+
+use petgraph::{
+    algo::{floyd_warshall, min_spanning_tree, tarjan_scc},
+    data::Element,
+    graph::{DiGraph, NodeIndex},
+    visit::EdgeRef,
+};
+
+use super::pathfinding::{Pathfinding, PathfindingGraphConnection};
+
+/// The pathfinding graph as a petgraph graph
+///
+/// Node weights are the pathfinding node ids and petgraph node indices line up with them,
+/// so results can be used with the rest of the pathfinding code as they are
+pub type PetGraph = DiGraph<usize, PathfindingGraphConnection>;
+
+impl Pathfinding {
+    /// Builds a petgraph copy of the enabled nodes and connections
+    pub fn to_petgraph(&self) -> PetGraph {
+        let connection_count = self.nodes.iter().map(|node| node.connections.len()).sum();
+        let mut graph = PetGraph::with_capacity(self.nodes.len(), connection_count);
+
+        // Disabled nodes still get added so the indices keep matching the ids
+        for node in &self.nodes {
+            graph.add_node(node.id);
+        }
+
+        for node in &self.nodes {
+            if !self.is_node_enabled(node.id) {
+                continue;
+            }
+
+            for connection in node.connections.iter() {
+                if self.is_node_enabled(connection.node_id)
+                    && self.is_connection_enabled(node.id, connection.node_id)
+                {
+                    graph.add_edge(
+                        NodeIndex::new(node.id),
+                        NodeIndex::new(connection.node_id),
+                        connection.clone(),
+                    );
+                }
+            }
+        }
+
+        graph
+    }
+
+    /// Groups of node ids that can all reach each other
+    ///
+    /// More than one group means some part of the level can be reached but not left (or
+    /// the other way around)
+    pub fn strongly_connected_components(&self) -> Vec<Vec<usize>> {
+        let graph = self.to_petgraph();
+
+        tarjan_scc(&graph)
+            .into_iter()
+            .map(|component| component.into_iter().map(|index| graph[index]).collect())
+            .collect()
+    }
+
+    /// The (from, to) node id pairs of a minimum spanning forest, treating connections
+    /// as 2-way and weighing them by distance
+    pub fn minimum_spanning_tree(&self) -> Vec<(usize, usize)> {
+        let graph = self
+            .to_petgraph()
+            .map(|_, node_id| *node_id, |_, connection| connection.dist);
+
+        min_spanning_tree(&graph)
+            .filter_map(|element| match element {
+                Element::Edge { source, target, .. } => {
+                    Some((graph[NodeIndex::new(source)], graph[NodeIndex::new(target)]))
+                }
+                Element::Node { .. } => None,
+            })
+            .collect()
+    }
+
+    /// The cost of the cheapest route between every pair of nodes that are connected at all
+    ///
+    /// Runs in cubic time, so it's meant for offline inspection rather than every tick
+    pub fn all_pairs_path_costs(&self) -> HashMap<(usize, usize), f32> {
+        let graph = self.to_petgraph();
+
+        let costs = floyd_warshall(&graph, |edge| {
+            self.connection_cost(edge.source().index(), edge.weight())
+        })
+        .unwrap_or_default();
+
+        costs
+            .into_iter()
+            .filter(|(_, cost)| *cost < f32::MAX)
+            .map(|((from, to), cost)| ((graph[from], graph[to]), cost))
+            .collect()
+    }
+}
@@ -1,19 +1,43 @@
+use std::collections::HashSet;
+
 use bevy::{
-    app::{App, Plugin, Update},
+    app::{App, FixedUpdate, Plugin},
     ecs::{
         component::Component,
+        entity::Entity,
+        event::EventReader,
+        query::Changed,
+        reflect::ReflectComponent,
         schedule::IntoSystemConfigs,
-        system::{Query, Res},
+        system::{Commands, Query, Res, ResMut},
     },
     gizmos::gizmos::Gizmos,
     math::{Vec2, Vec3Swizzles},
+    reflect::Reflect,
     render::color::Color,
     transform::components::Transform,
 };
+use serde::{Deserialize, Serialize};
 
-use crate::{s_move_goal_point, GizmosVisible, Physics, GRAVITY_STRENGTH};
+use crate::{
+    collisions::{find_projection, s_collision, HeadBump},
+    event_log::{EventLog, LogCategory},
+    level::{Level, DEFAULT_FRICTION},
+    settings::Settings,
+    utils::line_intersect,
+    GizmosVisible, Physics, PursueAISet, GRAVITY_STRENGTH,
+};
 
-use super::{a_star::find_path, pathfinding::Pathfinding};
+use super::{
+    a_star::{PathNode, SearchStats},
+    funnel::{build_corridor, string_pull},
+    navmesh::NavMesh,
+    pathfinding::{
+        double_jump_midpoint, effort_launch_velocity, launch_velocity, ledge_grab_point,
+        s_compute_paths, ComputedPath, GoalTeleported, Pathfinding, PathfindingGraphConnectionType,
+        PathsInvalidated, RequestPath, LEDGE_GRAB_REACH,
+    },
+};
 
 #[derive(Debug, Clone, Copy, PartialEq)]
 pub enum PathFollowingStrategy {
@@ -24,12 +48,14 @@ pub enum PathFollowingStrategy {
     AgentToNextNode,
     AgentToNextNodeOffset,
     AgentToGoal,
+    AgentToFunnelWaypoint(Vec2),
+    AgentToJumpTarget,
     None,
 }
 
 pub const PLATFORMER_AI_AGENT_RADIUS: f32 = 8.0;
 
-const WANDER_MAX_SPEED: f32 = 3.0;
+pub const WANDER_MAX_SPEED: f32 = 3.0;
 // const PURSUE_MAX_SPEED: f32 = 5.0;
 // const ATTACK_MAX_SPEED: f32 = 7.0;
 
@@ -37,37 +63,479 @@ const WANDER_MAX_SPEED: f32 = 3.0;
 
 pub const PLATFORMER_AI_JUMP_FORCE: f32 = 8.0;
 
+/// How much horizontal speed a jump can add on top of what the agent already has
+pub const PLATFORMER_AI_JUMP_HORIZONTAL_BOOST: f32 = 3.0;
+
 pub const ACCELERATION_SCALERS: (f32, f32) = (0.2, 0.4);
 
+/// How much of its ground acceleration the agent has to steer with in the air
+pub const PLATFORMER_AI_AIR_CONTROL: f32 = 0.25;
+
+/// How fast agents climb ladders (per tick)
+pub const PLATFORMER_AI_CLIMB_SPEED: f32 = 2.0;
+
+/// How fast agents swim (per tick)
+pub const PLATFORMER_AI_SWIM_SPEED: f32 = 2.0;
+
+/// How much of gravity still pulls on agents in water
+const SWIM_GRAVITY_SCALE: f32 = 0.15;
+
+/// How close to the middle of a ladder an agent has to be to grab onto it
+const LADDER_GRAB_DISTANCE: f32 = PLATFORMER_AI_AGENT_RADIUS / 2.0;
+
+/// How long an agent keeps trying to pull itself up over a ledge before letting go
+const MANTLE_MAX_TICKS: u32 = 90;
+
+/// How fast agents dash (per tick), and for how many ticks
+pub const PLATFORMER_AI_DASH_SPEED: f32 = 9.0;
+pub const PLATFORMER_AI_DASH_TICKS: u32 = 10;
+
+/// How fast the second jump of a double jump launches the agent, slower than the first
+pub const PLATFORMER_AI_DOUBLE_JUMP_FORCE: f32 = 6.0;
+
+/// How long an agent has to wait after a dash before it can dash again
+pub const PLATFORMER_AI_DASH_COOLDOWN_TICKS: u32 = 90;
+
+/// Pursuing agents only dash at targets further than this, so they don't dash past them
+const PURSUE_DASH_MIN_DISTANCE: f32 = PLATFORMER_AI_AGENT_RADIUS * 4.0;
+
+/// How far ahead (in ticks) to look when predicting where a falling agent lands
+const LANDING_PREDICTION_MAX_TICKS: usize = 240;
+
+/// How far a grounded agent can stray from its cached path before it replans
+const PATH_DEVIATION_DISTANCE: f32 = PLATFORMER_AI_AGENT_RADIUS * 3.0;
+
 pub struct PlatformerAIPlugin;
 
 impl Plugin for PlatformerAIPlugin {
     fn build(&self, app: &mut App) {
-        app.add_systems(Update, s_platformer_ai_movement.after(s_move_goal_point));
+        app.register_type::<PlatformerAI>()
+            .register_type::<Climb>()
+            .register_type::<Mantle>()
+            .register_type::<Dash>()
+            .register_type::<PlatformerAIState>()
+            .register_type::<ReplanPolicy>()
+            .register_type::<PlatformerAITuning>()
+            .register_type::<PathNode>()
+            .register_type::<SearchStats>()
+            .add_systems(
+                FixedUpdate,
+                (
+                    (s_handle_goal_teleports, s_handle_path_invalidations)
+                        .in_set(PursueAISet::Perception),
+                    (
+                        s_request_agent_paths.before(s_compute_paths),
+                        s_receive_agent_paths.after(s_compute_paths),
+                    )
+                        .in_set(PursueAISet::Planning),
+                    s_platformer_ai_movement.in_set(PursueAISet::Movement),
+                    (s_handle_head_bumps, s_grab_ledges, s_break_floors)
+                        .in_set(PursueAISet::Collision)
+                        .after(s_collision),
+                ),
+            );
     }
 }
 
-#[derive(Component)]
+#[derive(Component, Default, Reflect)]
+#[reflect(Component)]
 pub struct PlatformerAI {
     pub current_target_node: Option<usize>,
+    /// The entity the agent's pursuing, out of everything marked as a pursuit target
+    pub target: Option<Entity>,
+    /// The node nearest the target and where the target is, which the path is planned to
+    pub target_goal: Option<(usize, Vec2)>,
     pub jump_from_pos: Option<Vec2>,
     pub jump_to_pos: Option<Vec2>,
+    /// The (from node, to node) connection of the last jump the agent made
+    pub jump_connection: Option<(usize, usize)>,
+    pub running_up: bool,
+    /// The (from node, to node) jump the agent's backing up or running up for, kept across
+    /// replans that still take it until the agent takes off
+    pub run_up_jump: Option<(usize, usize)>,
+    /// Where the agent is predicted to land after a head bump, planned from until it lands
+    pub landing_node: Option<usize>,
+    /// The one-way platform the agent is dropping through, which it doesn't collide with
+    /// until it's fallen clear of it
+    pub dropping_through: Option<usize>,
+    /// The ladder the agent is hanging on, if it's climbing
+    pub climbing: Option<Climb>,
+    /// The ledge the agent caught at the end of a jump and is pulling itself up over
+    pub mantling: Option<Mantle>,
+    pub dashing: Option<Dash>,
+    /// How many ticks are left before the agent can dash again
+    pub dash_cooldown: u32,
+    /// The teleporter exit the agent last came out of, which can't send it anywhere until it's
+    /// walked off it
+    pub teleported_to: Option<Vec2>,
+    /// What the agent is currently doing, which decides how often it replans
+    pub state: PlatformerAIState,
+    pub path: Vec<PathNode>,
+    /// The goal node and position the cached path was searched for
+    pub path_goal: Option<(usize, Vec2)>,
+    /// The graph modifier revision the cached path was searched with
+    pub path_revision: u32,
+    pub ticks_since_replan: u32,
+    /// How much work the last path search took
+    pub last_search_stats: SearchStats,
+}
+
+/// An agent on a ladder, which hangs on without gravity until it reaches the target
+#[derive(Debug, Clone, Copy, Reflect)]
+pub struct Climb {
+    pub ladder_index: usize,
+    /// Where the agent gets off, at the end of the ladder
+    pub target: Vec2,
+}
+
+/// An agent hanging off a ledge, which pulls itself up and then over onto the target
+#[derive(Debug, Clone, Copy, Reflect)]
+pub struct Mantle {
+    pub ledge: Vec2,
+    /// Where the agent ends up standing, on top of the ledge
+    pub target: Vec2,
+    pub ticks: u32,
+}
+
+/// An agent dashing straight along, which ignores gravity until the dash runs out
+#[derive(Debug, Clone, Copy, Reflect)]
+pub struct Dash {
+    /// Which way it's dashing, -1 for left and 1 for right
+    pub direction: f32,
+    pub ticks: u32,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize, Reflect)]
+pub enum PlatformerAIState {
+    Wander,
+    #[default]
+    Pursue,
+    Search,
+    Attack,
+}
+
+/// When an agent searches for a new path instead of reusing its cached one
+///
+/// Regardless of the policy, agents always replan when they have no path, when the
+/// graph changes under them, or when they wander off their path
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize, Reflect)]
+pub enum ReplanPolicy {
+    /// Replan whenever the goal moves further than the threshold
+    GoalMoved { threshold: f32 },
+    /// Replan only when the goal snaps to a different node
+    GoalNodeChanged,
+    /// Replan every so many ticks
+    Timer { ticks: u32 },
+}
+
+/// Per-agent tuning of how the AI moves and how it behaves in each of its states
+///
+/// The graph is built for the default movement, so agents tuned too far from it can miss
+/// jumps the graph thinks they can make
+#[derive(Component, Debug, Clone, PartialEq, Serialize, Deserialize, Reflect)]
+#[reflect(Component)]
+#[serde(default)]
+pub struct PlatformerAITuning {
+    pub wander_replan: ReplanPolicy,
+    pub pursue_replan: ReplanPolicy,
+    pub search_replan: ReplanPolicy,
+    pub attack_replan: ReplanPolicy,
+    /// How fast the agent runs (per tick)
+    pub max_speed: f32,
+    /// How much of the gap to its running speed the agent closes each tick while speeding up
+    pub acceleration: f32,
+    /// How much of its speed the agent loses each tick while stopping
+    pub deceleration: f32,
+    /// How much horizontal speed a jump can add on top of what the agent already has
+    pub jump_horizontal_boost: f32,
+    /// How much of its ground acceleration the agent has to steer with in the air
+    pub air_control: f32,
+    pub climb_speed: f32,
+    pub swim_speed: f32,
+    /// How far a grounded agent can stray from its cached path before it replans
+    pub path_deviation_distance: f32,
+}
+
+impl Default for PlatformerAITuning {
+    fn default() -> Self {
+        PlatformerAITuning {
+            wander_replan: ReplanPolicy::GoalNodeChanged,
+            pursue_replan: ReplanPolicy::GoalMoved { threshold: 8.0 },
+            search_replan: ReplanPolicy::Timer { ticks: 60 },
+            attack_replan: ReplanPolicy::GoalMoved { threshold: 2.0 },
+            max_speed: WANDER_MAX_SPEED,
+            acceleration: ACCELERATION_SCALERS.0,
+            deceleration: ACCELERATION_SCALERS.1,
+            jump_horizontal_boost: PLATFORMER_AI_JUMP_HORIZONTAL_BOOST,
+            air_control: PLATFORMER_AI_AIR_CONTROL,
+            climb_speed: PLATFORMER_AI_CLIMB_SPEED,
+            swim_speed: PLATFORMER_AI_SWIM_SPEED,
+            path_deviation_distance: PATH_DEVIATION_DISTANCE,
+        }
+    }
+}
+
+impl PlatformerAITuning {
+    pub fn replan_policy(&self, state: PlatformerAIState) -> ReplanPolicy {
+        match state {
+            PlatformerAIState::Wander => self.wander_replan,
+            PlatformerAIState::Pursue => self.pursue_replan,
+            PlatformerAIState::Search => self.search_replan,
+            PlatformerAIState::Attack => self.attack_replan,
+        }
+    }
+}
+
+impl PlatformerAI {
+    /// Where the agent's headed, as a graph node and the position in it
+    ///
+    /// Agents given their own target node (e.g. by the squad coordinator) head there instead,
+    /// and agents with no target to pursue fall back on the goal point
+    pub fn current_goal(&self, pathfinding: &Pathfinding) -> Option<(usize, Vec2)> {
+        match self.current_target_node {
+            Some(target_node_id) => {
+                Some((target_node_id, pathfinding.nodes[target_node_id].position))
+            }
+            None => self.pursuit_goal(pathfinding),
+        }
+    }
+
+    /// The goal the agent's pursuing, as a graph node and the position in it: its target's, or
+    /// the goal point if it has no target
+    pub fn pursuit_goal(&self, pathfinding: &Pathfinding) -> Option<(usize, Vec2)> {
+        self.target_goal.or_else(|| {
+            pathfinding
+                .goal_graph_node
+                .as_ref()
+                .map(|goal_graph_node| (goal_graph_node.id, pathfinding.goal_position))
+        })
+    }
+
+    /// The node to search for the agent's paths from: where it's going to land if a head bump
+    /// knocked it out of a jump, otherwise a node it can actually stand on and reach
+    pub fn path_start_node_id(
+        &self,
+        pathfinding: &Pathfinding,
+        level: &Level,
+        agent_position: Vec2,
+        physics: &Physics,
+    ) -> Option<usize> {
+        self.landing_node.or_else(|| {
+            pathfinding.nearest_reachable_node_id(
+                level,
+                agent_position,
+                physics.normal,
+                physics.radius,
+            )
+        })
+    }
+
+    /// Takes on a path found for the agent
+    pub fn follow_computed_path(&mut self, computed_path: &ComputedPath) {
+        self.path = computed_path.path.clone().unwrap_or_default();
+        self.last_search_stats = computed_path.stats;
+        self.path_goal = Some(computed_path.goal);
+        self.path_revision = computed_path.revision;
+        self.ticks_since_replan = 0;
+    }
+
+    /// Whether the cached path has to be searched for again
+    pub fn needs_replan(
+        &self,
+        replan_policy: ReplanPolicy,
+        goal: (usize, Vec2),
+        graph_revision: u32,
+    ) -> bool {
+        let Some(path_goal) = self.path_goal else {
+            return true;
+        };
+
+        if self.path.is_empty() || self.path_revision != graph_revision {
+            return true;
+        }
+
+        match replan_policy {
+            ReplanPolicy::GoalMoved { threshold } => {
+                path_goal.0 != goal.0 || (path_goal.1 - goal.1).length_squared() > threshold.powi(2)
+            }
+            ReplanPolicy::GoalNodeChanged => path_goal.0 != goal.0,
+            ReplanPolicy::Timer { ticks } => self.ticks_since_replan >= ticks,
+        }
+    }
+
+    /// Starts a dash the given way, if the agent isn't already dashing and it's cooled down
+    ///
+    /// Returns whether it dashed
+    pub fn start_dash(&mut self, direction: f32) -> bool {
+        if self.dashing.is_some() || self.dash_cooldown > 0 || direction == 0.0 {
+            return false;
+        }
+
+        self.dashing = Some(Dash {
+            direction: direction.signum(),
+            ticks: 0,
+        });
+        self.dash_cooldown = PLATFORMER_AI_DASH_COOLDOWN_TICKS;
+
+        true
+    }
+
+    /// Drops the part of the cached path the agent has already walked past
+    ///
+    /// Returns false if the agent has strayed too far from the path to keep following it
+    pub fn advance_cached_path(&mut self, agent_position: Vec2, deviation_distance: f32) -> bool {
+        if self.path.len() < 2 {
+            return !self.path.is_empty()
+                && (self.path[0].position - agent_position).length_squared()
+                    < deviation_distance.powi(2);
+        }
+
+        // Find the leg of the path the agent is closest to
+        let mut closest_leg_index = 0;
+        let mut closest_distance = f32::MAX;
+
+        for leg_index in 0..self.path.len() - 1 {
+            let distance = distance_to_segment(
+                agent_position,
+                self.path[leg_index].position,
+                self.path[leg_index + 1].position,
+            );
+
+            if distance < closest_distance {
+                closest_distance = distance;
+                closest_leg_index = leg_index;
+            }
+        }
+
+        if closest_distance > deviation_distance {
+            return false;
+        }
+
+        self.path.drain(..closest_leg_index);
+
+        true
+    }
+}
+
+fn distance_to_segment(point: Vec2, segment_start: Vec2, segment_end: Vec2) -> f32 {
+    let segment = segment_end - segment_start;
+
+    let t = if segment.length_squared() > 0.0 {
+        ((point - segment_start).dot(segment) / segment.length_squared()).clamp(0.0, 1.0)
+    } else {
+        0.0
+    };
+
+    (segment_start + segment * t - point).length()
+}
+
+/// Asks for new paths for the agents whose cached ones are stale or that strayed off them
+pub fn s_request_agent_paths(
+    mut commands: Commands,
+    pathfinding: Res<Pathfinding>,
+    level: Res<Level>,
+    settings: Res<Settings>,
+    mut platformer_ai_query: Query<(
+        Entity,
+        &Transform,
+        &Physics,
+        &mut PlatformerAI,
+        Option<&PlatformerAITuning>,
+    )>,
+) {
+    for (entity, transform, physics, mut platformer_ai, tuning) in platformer_ai_query.iter_mut() {
+        let tuning = tuning.unwrap_or(&settings.agent);
+        let agent_position = transform.translation.xy();
+
+        platformer_ai.ticks_since_replan += 1;
+
+        let Some(goal) = platformer_ai.current_goal(&pathfinding) else {
+            platformer_ai.path.clear();
+            platformer_ai.path_goal = None;
+            continue;
+        };
+
+        let airborne = physics.normal.length_squared() == 0.0;
+
+        // Keep following the cached path unless it's stale or the agent wandered off it
+        // (while airborne the agent is expected to be off the straight line between nodes, and
+        // while backing up for a run-up it goes back past the start of the path on purpose)
+        let on_cached_path = airborne
+            && !platformer_ai.path.is_empty()
+            && platformer_ai.landing_node.is_none()
+            || platformer_ai.advance_cached_path(agent_position, tuning.path_deviation_distance)
+            || platformer_ai.run_up_jump.is_some() && !platformer_ai.path.is_empty();
+
+        let replan_policy = tuning.replan_policy(platformer_ai.state);
+        if !platformer_ai.needs_replan(replan_policy, goal, pathfinding.modifiers.revision)
+            && on_cached_path
+        {
+            continue;
+        }
+
+        let start_node_id =
+            platformer_ai.path_start_node_id(&pathfinding, &level, agent_position, physics);
+
+        match start_node_id {
+            Some(start_node_id) => {
+                commands.entity(entity).insert(RequestPath {
+                    start_position: agent_position,
+                    start_node_id: Some(start_node_id),
+                    goal_node_id: goal.0,
+                    goal_position: goal.1,
+                });
+            }
+            // Nowhere to search from, so no path until the agent's somewhere it can stand
+            None => platformer_ai.follow_computed_path(&ComputedPath {
+                path: None,
+                goal,
+                revision: pathfinding.modifiers.revision,
+                stats: SearchStats::default(),
+            }),
+        }
+    }
+}
+
+/// Gives the agents the paths found for them
+pub fn s_receive_agent_paths(
+    mut platformer_ai_query: Query<(&mut PlatformerAI, &ComputedPath), Changed<ComputedPath>>,
+) {
+    for (mut platformer_ai, computed_path) in platformer_ai_query.iter_mut() {
+        platformer_ai.follow_computed_path(computed_path);
+    }
 }
 
 pub fn s_platformer_ai_movement(
-    mut platformer_ai_query: Query<(&mut Transform, &mut Physics, &mut PlatformerAI)>,
+    mut platformer_ai_query: Query<(
+        Entity,
+        &mut Transform,
+        &mut Physics,
+        &mut PlatformerAI,
+        Option<&PlatformerAITuning>,
+    )>,
     pathfinding: Res<Pathfinding>,
+    level: Res<Level>,
+    settings: Res<Settings>,
     gismo_visible: Res<GizmosVisible>,
     mut gizmos: Gizmos,
+    mut event_log: ResMut<EventLog>,
 ) {
-    for (mut transform, mut physics, mut platformer_ai) in platformer_ai_query.iter_mut() {
-        let (move_dir, jump_velocity, jump_from_node, jump_to_node) = get_move_inputs(
-            pathfinding.as_ref(),
-            transform.translation.xy(),
-            &physics,
-            &mut gizmos,
-            gismo_visible.visible,
-        );
+    for (entity, mut transform, mut physics, mut platformer_ai, tuning) in
+        platformer_ai_query.iter_mut()
+    {
+        let tuning = tuning.unwrap_or(&settings.agent);
+        platformer_ai.dash_cooldown = platformer_ai.dash_cooldown.saturating_sub(1);
+
+        let (move_dir, jump_velocity, jump_from_node, jump_to_node, jump_connection) =
+            get_move_inputs(
+                pathfinding.as_ref(),
+                level.as_ref(),
+                transform.translation.xy(),
+                &physics,
+                &mut platformer_ai,
+                &mut gizmos,
+                gismo_visible.visible,
+            );
 
         if gismo_visible.visible {
             gizmos.line_2d(
@@ -77,10 +545,108 @@ pub fn s_platformer_ai_movement(
             );
         }
 
+        // Climbing agents hang on the ladder, with no gravity and no momentum
+        if let Some(climb) = platformer_ai.climbing {
+            match level.ladders.get(climb.ladder_index) {
+                Some(ladder) => {
+                    physics.acceleration = Vec2::ZERO;
+                    physics.velocity = move_dir * tuning.climb_speed;
+                    physics.grounded = false;
+
+                    // Keep to the middle of the ladder while going up or down it
+                    if move_dir.x == 0.0 {
+                        transform.translation.x = ladder.center_x();
+                    }
+
+                    update_physics_and_transform(&mut physics, &mut transform);
+                    continue;
+                }
+                None => platformer_ai.climbing = None,
+            }
+        }
+
+        // Pursuing agents close in on a target that's level with them and in the clear
+        if jump_velocity.length_squared() == 0.0 {
+            if let Some(direction) = pursue_dash_direction(
+                pathfinding.as_ref(),
+                level.as_ref(),
+                transform.translation.xy(),
+                &physics,
+                &platformer_ai,
+            ) {
+                if platformer_ai.start_dash(direction) {
+                    event_log.log(
+                        LogCategory::Jump,
+                        format!("Agent {} dashed at its target", entity.index()),
+                    );
+                }
+            }
+        }
+
+        // Dashing agents shoot straight along with no gravity, until the dash runs out or they
+        // run into a wall
+        if let Some(mut dash) = platformer_ai.dashing {
+            if dash.ticks < PLATFORMER_AI_DASH_TICKS && physics.walled == 0 {
+                dash.ticks += 1;
+                platformer_ai.dashing = Some(dash);
+
+                physics.acceleration = Vec2::ZERO;
+                physics.velocity = Vec2::new(dash.direction * PLATFORMER_AI_DASH_SPEED, 0.0);
+
+                update_physics_and_transform(&mut physics, &mut transform);
+                continue;
+            }
+
+            // Back to running speed once it's over
+            platformer_ai.dashing = None;
+            physics.velocity.x = physics
+                .velocity
+                .x
+                .clamp(-tuning.max_speed, tuning.max_speed);
+        }
+
+        // Mantling agents pull themselves up over the ledge, with no gravity and no momentum
+        if platformer_ai.mantling.is_some() {
+            physics.acceleration = Vec2::ZERO;
+            physics.velocity = move_dir * tuning.climb_speed;
+            physics.grounded = false;
+
+            update_physics_and_transform(&mut physics, &mut transform);
+            continue;
+        }
+
+        // Swimming agents move every which way, slower and sinking gently when they stop
+        if level.water_at(transform.translation.xy()).is_some() {
+            if jump_velocity.length_squared() > 0.0 {
+                // Leap out of the water
+                physics.velocity = jump_velocity;
+                physics.acceleration = Vec2::new(0.0, -GRAVITY_STRENGTH);
+                physics.grounded = false;
+                platformer_ai.jump_from_pos = jump_from_node;
+                platformer_ai.jump_to_pos = jump_to_node;
+                platformer_ai.jump_connection = jump_connection;
+            } else {
+                physics.acceleration =
+                    (move_dir * tuning.swim_speed - physics.velocity) * tuning.acceleration;
+                physics.acceleration.y -= GRAVITY_STRENGTH * SWIM_GRAVITY_SCALE;
+            }
+
+            update_physics_and_transform(&mut physics, &mut transform);
+            continue;
+        }
+
         let falling = physics.normal.length_squared() == 0.0;
         let no_move_dir = move_dir.length_squared() == 0.0;
 
-        apply_movement_acceleration(&mut physics, &move_dir, falling, no_move_dir);
+        apply_movement_acceleration(
+            &mut physics,
+            &move_dir,
+            falling,
+            no_move_dir,
+            tuning.max_speed,
+            (tuning.acceleration, tuning.deceleration),
+            tuning.air_control,
+        );
 
         apply_gravity_toward_normal(&mut physics, falling /*, player_move_off_wall*/);
 
@@ -90,6 +656,13 @@ pub fn s_platformer_ai_movement(
             if jump_velocity.length_squared() > 0.0 && !falling {
                 // If on the ground
                 if physics.grounded {
+                    // A jump can only add so much horizontal speed to the current speed
+                    let mut jump_velocity = jump_velocity;
+                    jump_velocity.x = jump_velocity.x.clamp(
+                        physics.velocity.x - tuning.jump_horizontal_boost,
+                        physics.velocity.x + tuning.jump_horizontal_boost,
+                    );
+
                     // Jump
                     physics.velocity = jump_velocity;
                     physics.acceleration.x = 0.0;
@@ -98,10 +671,19 @@ pub fn s_platformer_ai_movement(
                     physics.has_wall_jumped = false;
                     physics.walled = 0;
 
-                    println!("Jump!!!");
+                    event_log.log(
+                        LogCategory::Jump,
+                        format!(
+                            "Agent {} jumped at {:.1}",
+                            entity.index(),
+                            jump_velocity.length()
+                        ),
+                    );
+                    platformer_ai.running_up = false;
+                    platformer_ai.run_up_jump = None;
                     platformer_ai.jump_from_pos = jump_from_node;
                     platformer_ai.jump_to_pos = jump_to_node;
-                    println!("Initial Jump Velocity: {}", jump_velocity.length());
+                    platformer_ai.jump_connection = jump_connection;
                 }
                 // If on a wall
                 else if physics.walled != 0 {
@@ -112,35 +694,169 @@ pub fn s_platformer_ai_movement(
                     physics.walled = 0;
                     physics.grounded = false;
                     physics.has_wall_jumped = true;
-                    println!("Wall Jump!!!");
-                    dbg!(move_dir);
-                    dbg!(transform.translation.xy());
+                    event_log.log(
+                        LogCategory::Jump,
+                        format!(
+                            "Agent {} wall jumped at {:.1} from ({:.1}, {:.1})",
+                            entity.index(),
+                            jump_velocity.length(),
+                            transform.translation.x,
+                            transform.translation.y
+                        ),
+                    );
                     platformer_ai.jump_from_pos = jump_from_node;
                     platformer_ai.jump_to_pos = jump_to_node;
-                    println!("Initial Jump Velocity: {}", jump_velocity.length());
+                    platformer_ai.jump_connection = jump_connection;
                 }
             }
+            // Jumping again in mid-air, which only happens partway through a double jump
+            else if jump_velocity.length_squared() > 0.0 && !physics.has_double_jumped {
+                physics.velocity = jump_velocity;
+                physics.acceleration.x = 0.0;
+                physics.acceleration.y = -GRAVITY_STRENGTH;
+                physics.has_double_jumped = true;
+                event_log.log(
+                    LogCategory::Jump,
+                    format!(
+                        "Agent {} double jumped at {:.1} from ({:.1}, {:.1})",
+                        entity.index(),
+                        jump_velocity.length(),
+                        transform.translation.x,
+                        transform.translation.y
+                    ),
+                );
+            }
+        }
+
+        // Conveyors carry whatever's standing on them along, on top of its own movement
+        if physics.grounded {
+            transform.translation += physics.surface_velocity.extend(0.0);
         }
 
         update_physics_and_transform(&mut physics, &mut transform);
+    }
+}
 
-        // dbg!(physics.velocity);
+/// Which way a pursuing agent should dash to close in on its target, if it should
+///
+/// Only grounded agents dash, at targets level with them, in the clear and within a dash
+fn pursue_dash_direction(
+    pathfinding: &Pathfinding,
+    level: &Level,
+    agent_position: Vec2,
+    agent_physics: &Physics,
+    platformer_ai: &PlatformerAI,
+) -> Option<f32> {
+    if platformer_ai.state != PlatformerAIState::Pursue
+        || !agent_physics.grounded
+        || platformer_ai.climbing.is_some()
+        || platformer_ai.mantling.is_some()
+    {
+        return None;
     }
+
+    let target_position = platformer_ai
+        .target_goal
+        .map_or(pathfinding.goal_position, |(_, target_position)| {
+            target_position
+        });
+    let to_target = target_position - agent_position;
+    let dash_distance = PLATFORMER_AI_DASH_SPEED * PLATFORMER_AI_DASH_TICKS as f32;
+
+    let in_range = to_target.x.abs() >= PURSUE_DASH_MIN_DISTANCE
+        && to_target.x.abs() <= dash_distance
+        && to_target.y.abs() <= agent_physics.radius;
+
+    (in_range && level.line_of_sight_check(agent_position, target_position))
+        .then(|| to_target.x.signum())
 }
 
+/// Which way to move, how to jump, and where the jump goes from, to and along
+type MoveInputs = (
+    Vec2,
+    Vec2,
+    Option<Vec2>,
+    Option<Vec2>,
+    Option<(usize, usize)>,
+);
+
 fn get_move_inputs(
     pathfinding: &Pathfinding,
+    level: &Level,
     agent_position: Vec2,
     agent_physics: &Physics,
+    platformer_ai: &mut PlatformerAI,
     gizmos: &mut Gizmos,
     gizmos_visible: bool,
-) -> (Vec2, Vec2, Option<Vec2>, Option<Vec2>) {
+) -> MoveInputs {
     let mut move_dir = Vec2::ZERO;
     let mut jump_velocity = Vec2::ZERO;
     let mut jump_from_node = None;
     let mut jump_to_node = None;
+    let mut jump_connection = None;
+
+    let target_position = platformer_ai
+        .target_goal
+        .map_or(pathfinding.goal_position, |(_, target_position)| {
+            target_position
+        });
+
+    // The path was brought up to date by s_request_agent_paths, before the agent moves
+    let path = if platformer_ai.path.is_empty() {
+        None
+    } else {
+        Some(platformer_ai.path.clone())
+    };
+
+    // Once on a ladder, the agent climbs to the end of it whatever the path does meanwhile
+    if let Some(climb) = platformer_ai.climbing {
+        let to_target = climb.target - agent_position;
+
+        if to_target.length() > agent_physics.radius {
+            // Up or down the ladder first, then off it onto the floor
+            move_dir = if to_target.y.abs() > 1.0 {
+                Vec2::new(0.0, to_target.y.signum())
+            } else {
+                Vec2::new(to_target.x.signum(), 0.0)
+            };
 
-    let path = find_path(&pathfinding, agent_position);
+            return (
+                move_dir,
+                jump_velocity,
+                jump_from_node,
+                jump_to_node,
+                jump_connection,
+            );
+        }
+
+        platformer_ai.climbing = None;
+    }
+
+    // Once hanging off a ledge, the agent pulls itself up and over it, unless it's stuck
+    if let Some(mut mantle) = platformer_ai.mantling {
+        let to_target = mantle.target - agent_position;
+        mantle.ticks += 1;
+
+        if to_target.length() > agent_physics.radius / 2.0 && mantle.ticks <= MANTLE_MAX_TICKS {
+            // Up past the top of the ledge first, then over onto it
+            move_dir = if to_target.y > 1.0 {
+                Vec2::Y
+            } else {
+                Vec2::new(to_target.x.signum(), 0.0)
+            };
+            platformer_ai.mantling = Some(mantle);
+
+            return (
+                move_dir,
+                jump_velocity,
+                jump_from_node,
+                jump_to_node,
+                jump_connection,
+            );
+        }
+
+        platformer_ai.mantling = None;
+    }
 
     if let Some(path) = path {
         if gizmos_visible {
@@ -152,7 +868,7 @@ fn get_move_inputs(
                 prev_pos = path[i].position;
             }
 
-            gizmos.line_2d(prev_pos, pathfinding.goal_position, Color::GREEN);
+            gizmos.line_2d(prev_pos, target_position, Color::GREEN);
         }
 
         if path.len() > 1 {
@@ -167,19 +883,126 @@ fn get_move_inputs(
 
             let current_node_is_corner = corner_is_external.is_some();
 
-            let is_jumpable_connection = pathfinding.nodes[path[0].id]
-                .jumpable_connections
+            // Wall jumps and mantles are followed like any other jump, and wall jumps chain up a
+            // shaft one after another
+            let jumpable_connection = pathfinding.nodes[path[0].id]
+                .connections
                 .iter()
-                .any(|jumpable_connection| jumpable_connection.node_id == path[1].id);
+                .filter(|connection| {
+                    matches!(
+                        connection.connection_type,
+                        PathfindingGraphConnectionType::Jumpable
+                            | PathfindingGraphConnectionType::WallJumpable
+                            | PathfindingGraphConnectionType::Mantle
+                            | PathfindingGraphConnectionType::DoubleJumpable
+                    )
+                })
+                .find(|jumpable_connection| jumpable_connection.node_id == path[1].id);
+
+            let is_jumpable_connection = jumpable_connection.is_some();
+
+            // Whether the path goes between the nodes by a connection of the type
+            let is_connection_of_type = |connection_type| {
+                pathfinding.nodes[path[0].id]
+                    .connections_of_type(connection_type)
+                    .any(|connection| connection.node_id == path[1].id)
+            };
+
+            let is_dash_connection =
+                is_connection_of_type(PathfindingGraphConnectionType::Dashable);
+
+            let is_droppable_connection =
+                is_connection_of_type(PathfindingGraphConnectionType::Droppable);
+
+            // The ladder joining the nodes, if the path climbs between them
+            let climbable_ladder_index =
+                is_connection_of_type(PathfindingGraphConnectionType::Climbable)
+                    .then(|| {
+                        level.ladders.iter().position(|ladder| {
+                            ladder.contains(path[0].position, agent_physics.radius)
+                                && ladder.contains(path[1].position, agent_physics.radius)
+                        })
+                    })
+                    .flatten();
+
+            // The teleporter to walk into, if the path goes through one between the nodes
+            let teleporter_entrance =
+                is_connection_of_type(PathfindingGraphConnectionType::Teleport)
+                    .then(|| {
+                        level
+                            .teleporters
+                            .iter()
+                            .map(|teleporter| teleporter.entrance)
+                            .min_by(|a, b| {
+                                a.distance_squared(path[0].position)
+                                    .total_cmp(&b.distance_squared(path[0].position))
+                            })
+                    })
+                    .flatten();
+
+            let is_swimmable_connection =
+                is_connection_of_type(PathfindingGraphConnectionType::Swimmable);
+
+            // The bounce pad to step onto, if the path bounces between the nodes
+            let bounce_pad_position = is_connection_of_type(PathfindingGraphConnectionType::Bounce)
+                .then(|| {
+                    level
+                        .bounce_pads
+                        .iter()
+                        .map(|bounce_pad| bounce_pad.position)
+                        .min_by(|a, b| {
+                            a.distance_squared(path[0].position)
+                                .total_cmp(&b.distance_squared(path[0].position))
+                        })
+                })
+                .flatten();
 
             let falling = agent_physics.normal.length_squared() <= 0.0;
 
+            // Pull a string through the walkable corridor ahead to find the next real bend
+            let funnel_waypoint = string_pull(&build_corridor(
+                pathfinding,
+                &path,
+                agent_position,
+                agent_physics.radius,
+            ))
+            .first()
+            .cloned();
+
+            if gizmos_visible {
+                if let Some(funnel_waypoint) = funnel_waypoint {
+                    gizmos.line_2d(agent_position, funnel_waypoint, Color::CYAN);
+                }
+            }
+
             let path_following_strategy: PathFollowingStrategy;
 
             // Agent not falling
             if !falling {
+                // Agent dropping through a one-way platform, once it's over the drop
+                if is_droppable_connection {
+                    if (path[0].position.x - agent_position.x).abs() <= agent_physics.radius {
+                        platformer_ai.dropping_through =
+                            Some(pathfinding.nodes[path[0].id].polygon_index);
+                        path_following_strategy = PathFollowingStrategy::AgentToNextNodeOffset;
+                    } else {
+                        path_following_strategy = PathFollowingStrategy::AgentToCurrentNodeOffset;
+                    }
+                }
+                // Agent dashing across a gap, from the edge once the dash is ready
+                else if is_dash_connection {
+                    let at_edge =
+                        (path[0].position.x - agent_position.x).abs() <= agent_physics.radius;
+
+                    if at_edge && platformer_ai.start_dash(path[1].position.x - path[0].position.x)
+                    {
+                        path_following_strategy = PathFollowingStrategy::AgentToNextNodeOffset;
+                    } else {
+                        path_following_strategy = PathFollowingStrategy::AgentToCurrentNodeOffset;
+                    }
+                }
                 // Agent jumping
-                if is_jumpable_connection {
+                else if is_jumpable_connection {
                     let agent_on_other_side_next_frame = agent_on_other_side_next_frame(
                         agent_position,
                         agent_physics.velocity,
@@ -191,18 +1014,20 @@ fn get_move_inputs(
 
                     path_following_strategy = if agent_on_other_side_next_frame || agent_not_moving
                     {
-                        println!("Test 1");
                         PathFollowingStrategy::AgentToNextNodeOffset
                     } else {
-                        println!("Test 2");
                         PathFollowingStrategy::AgentToCurrentNodeOffset
                     };
                 } else {
                     // Non-jumping corner
                     if current_node_is_corner {
-                        println!("Test 3");
                         path_following_strategy = PathFollowingStrategy::AgentToNextNode;
                     }
+                    // Non-jumping flat surface, cutting across it along the funnel
+                    else if let Some(funnel_waypoint) = funnel_waypoint {
+                        path_following_strategy =
+                            PathFollowingStrategy::AgentToFunnelWaypoint(funnel_waypoint);
+                    }
                     // Non-jumping flat surface
                     else {
                         let current_pos_to_next_offset = offset_next_node - agent_position;
@@ -211,19 +1036,20 @@ fn get_move_inputs(
                         if current_pos_to_next_offset.length_squared()
                             <= current_offset_to_next_offset.length_squared()
                         {
-                            println!("Test 4");
                             path_following_strategy = PathFollowingStrategy::AgentToNextNodeOffset;
                         } else {
-                            println!("Test 5");
                             path_following_strategy =
                                 PathFollowingStrategy::AgentToCurrentNodeOffset;
                         }
                     }
                 }
             }
+            // Agent mid-jump, steering for where the jump lands so it doesn't drift off
+            else if platformer_ai.jump_to_pos.is_some() {
+                path_following_strategy = PathFollowingStrategy::AgentToJumpTarget;
+            }
             // Agent falling
             else {
-                println!("Test 6");
                 path_following_strategy = PathFollowingStrategy::AgentToNextNodeOffset;
             }
 
@@ -238,7 +1064,13 @@ fn get_move_inputs(
                 }
                 PathFollowingStrategy::AgentToNextNode => path[1].position - agent_position,
                 PathFollowingStrategy::AgentToNextNodeOffset => offset_next_node - agent_position,
-                PathFollowingStrategy::AgentToGoal => pathfinding.goal_position - agent_position,
+                PathFollowingStrategy::AgentToGoal => target_position - agent_position,
+                PathFollowingStrategy::AgentToFunnelWaypoint(funnel_waypoint) => {
+                    funnel_waypoint - agent_position
+                }
+                PathFollowingStrategy::AgentToJumpTarget => {
+                    platformer_ai.jump_to_pos.unwrap_or(agent_position) - agent_position
+                }
                 PathFollowingStrategy::None => Vec2::ZERO,
                 _ => Vec2::ZERO,
             }
@@ -248,48 +1080,446 @@ fn get_move_inputs(
             if path_following_strategy == PathFollowingStrategy::AgentToNextNodeOffset
                 || path_following_strategy == PathFollowingStrategy::AgentToNextNode
             {
-                if is_jumpable_connection {
-                    let node_position_delta = path[1].position - path[0].position;
-                    let gravity_acceleration = Vec2::new(0.0, -GRAVITY_STRENGTH);
-                    let jump_time = 1.0
-                        * (4.0 * node_position_delta.dot(node_position_delta)
-                            / gravity_acceleration.dot(gravity_acceleration))
-                        .sqrt()
-                        .sqrt();
-                    jump_velocity =
-                        node_position_delta / jump_time - gravity_acceleration * jump_time / 2.0;
+                if let Some(jumpable_connection) = jumpable_connection {
+                    // Mantles aim just below the ledge, to catch it on the way down, and double
+                    // jumps for where the second jump's made
+                    let jump_target = match jumpable_connection.connection_type {
+                        PathfindingGraphConnectionType::Mantle => {
+                            ledge_grab_point(&pathfinding.nodes[path[1].id], agent_physics.radius)
+                        }
+                        PathfindingGraphConnectionType::DoubleJumpable => {
+                            double_jump_midpoint(path[0].position, path[1].position)
+                        }
+                        _ => path[1].position,
+                    };
+                    // Launch from wherever along the surface the agent actually is, at the
+                    // speed the graph picked for the jump
+                    let launch_point = path[0].position + agent_position - offset_current_node;
+                    jump_velocity = effort_launch_velocity(
+                        jump_target - launch_point,
+                        jumpable_connection.effort,
+                    );
 
                     jump_from_node = Some(offset_current_node);
                     jump_to_node = Some(offset_next_node);
+                    jump_connection = Some((path[0].id, path[1].id));
+                }
+            }
+
+            // Jumps that need a run-up. The jump's remembered until the agent takes off, so
+            // replans that start the path further back, where the agent backed up to, keep it
+            if !falling && jumpable_connection.is_some_and(|connection| connection.run_up.is_some())
+            {
+                platformer_ai.run_up_jump = Some((path[0].id, path[1].id));
+            }
+            let run_up_jump = platformer_ai
+                .run_up_jump
+                .filter(|(from_node_id, to_node_id)| {
+                    path.windows(2)
+                        .any(|leg| leg[0].id == *from_node_id && leg[1].id == *to_node_id)
+                })
+                .and_then(|(from_node_id, to_node_id)| {
+                    let run_up = pathfinding.nodes[from_node_id]
+                        .connections
+                        .iter()
+                        .filter(|connection| connection.node_id == to_node_id)
+                        .find_map(|connection| connection.run_up.as_ref())?;
+
+                    Some((from_node_id, run_up))
+                });
+
+            match run_up_jump {
+                Some((from_node_id, run_up)) if !falling => {
+                    let distance_behind_node = (pathfinding.nodes[from_node_id].position.x
+                        - agent_position.x)
+                        * run_up.direction;
+
+                    // Far enough back to start the run
+                    if distance_behind_node >= run_up.distance {
+                        platformer_ai.running_up = true;
+                    }
+
+                    if platformer_ai.running_up {
+                        // Run toward the jump and only take off once fast enough
+                        move_dir = Vec2::new(run_up.direction, 0.0);
+
+                        if agent_physics.velocity.x * run_up.direction < run_up.speed {
+                            jump_velocity = Vec2::ZERO;
+                            jump_from_node = None;
+                            jump_to_node = None;
+                            jump_connection = None;
+                        }
+                    } else {
+                        // Back up along the surface to the start of the run
+                        move_dir = (pathfinding.nodes[run_up.start_node_id].position
+                            - agent_position)
+                            .normalize_or_zero();
+                        jump_velocity = Vec2::ZERO;
+                        jump_from_node = None;
+                        jump_to_node = None;
+                        jump_connection = None;
+                    }
+                }
+                Some(_) => {}
+                None => {
+                    platformer_ai.running_up = false;
+                    platformer_ai.run_up_jump = None;
+                }
+            }
+
+            // Ladders: get to the middle of one, then grab on and climb to the next node
+            if let Some(ladder_index) = climbable_ladder_index {
+                let ladder_center_x = level.ladders[ladder_index].center_x();
+
+                if (agent_position.x - ladder_center_x).abs() <= LADDER_GRAB_DISTANCE {
+                    platformer_ai.climbing = Some(Climb {
+                        ladder_index,
+                        target: offset_next_node,
+                    });
+                    move_dir = Vec2::new(0.0, (offset_next_node.y - agent_position.y).signum());
+                } else {
+                    move_dir = Vec2::new((ladder_center_x - agent_position.x).signum(), 0.0);
+                }
+
+                jump_velocity = Vec2::ZERO;
+                jump_from_node = None;
+                jump_to_node = None;
+                jump_connection = None;
+            }
+
+            // Water: swim straight for the next node, leaping out when it's up on the bank
+            if is_swimmable_connection {
+                move_dir = (offset_next_node - agent_position).normalize_or_zero();
+
+                jump_velocity = Vec2::ZERO;
+                jump_from_node = None;
+                jump_to_node = None;
+                jump_connection = None;
+
+                if let Some(water) = level.water_at(agent_position) {
+                    if offset_next_node.y > water.max.y
+                        && agent_position.y >= water.max.y - agent_physics.radius
+                    {
+                        jump_velocity = launch_velocity(offset_next_node - agent_position);
+                    }
+                }
+            }
+
+            // Bounce pads: walk onto the pad and let it launch the agent
+            if let Some(bounce_pad_position) = bounce_pad_position {
+                if !falling {
+                    move_dir = Vec2::new((bounce_pad_position.x - agent_position.x).signum(), 0.0);
+
+                    jump_velocity = Vec2::ZERO;
+                    jump_from_node = None;
+                    jump_to_node = None;
+                    jump_connection = None;
+                }
+            }
+
+            // Teleporters: walk straight into the entrance and let it do the rest
+            if let Some(entrance) = teleporter_entrance {
+                move_dir = Vec2::new((entrance.x - agent_position.x).signum(), 0.0);
+
+                jump_velocity = Vec2::ZERO;
+                jump_from_node = None;
+                jump_to_node = None;
+                jump_connection = None;
+            }
+        }
+    }
+
+    // Halfway through a double jump, the second jump goes at the top of the first one
+    let in_double_jump = platformer_ai
+        .jump_connection
+        .is_some_and(|(from_node_id, to_node_id)| {
+            pathfinding.nodes.get(from_node_id).is_some_and(|node| {
+                node.connections_of_type(PathfindingGraphConnectionType::DoubleJumpable)
+                    .any(|connection| connection.node_id == to_node_id)
+            })
+        });
+    if let Some(landing) = platformer_ai.jump_to_pos.filter(|_| in_double_jump) {
+        let falling = agent_physics.normal.length_squared() <= 0.0;
+
+        if falling && !agent_physics.has_double_jumped && agent_physics.velocity.y <= 0.0 {
+            jump_velocity = launch_velocity(landing - agent_position)
+                .clamp_length_max(PLATFORMER_AI_DOUBLE_JUMP_FORCE);
+            jump_from_node = Some(agent_position);
+            jump_to_node = Some(landing);
+            jump_connection = platformer_ai.jump_connection;
+        }
+    }
+
+    (
+        move_dir,
+        jump_velocity,
+        jump_from_node,
+        jump_to_node,
+        jump_connection,
+    )
+}
+
+/// Attacking agents smash through any destructible floor they're standing on
+pub fn s_break_floors(
+    platformer_ai_query: Query<(Entity, &Transform, &Physics, &PlatformerAI)>,
+    mut level: ResMut<Level>,
+    mut pathfinding: ResMut<Pathfinding>,
+    mut navmesh: ResMut<NavMesh>,
+    mut event_log: ResMut<EventLog>,
+) {
+    for (entity, transform, physics, platformer_ai) in platformer_ai_query.iter() {
+        if platformer_ai.state != PlatformerAIState::Attack || !physics.grounded {
+            continue;
+        }
+
+        let agent_position = transform.translation.xy();
+        let touch_radius = physics.radius + 0.5;
+
+        let floor_line = level
+            .polygons
+            .iter()
+            .enumerate()
+            .flat_map(|(polygon_index, polygon)| {
+                polygon
+                    .destructible_lines
+                    .iter()
+                    .filter(|line_index| !polygon.is_line_broken(**line_index))
+                    .map(move |line_index| (polygon_index, *line_index))
+            })
+            .find(|(polygon_index, line_index)| {
+                let (start, end) = level.get_line(*polygon_index, *line_index).unwrap();
+                let (distance_sq, projection) =
+                    find_projection(*start, *end, agent_position, physics.radius);
+
+                // Only the line under the agent, not walls it's brushing past
+                distance_sq <= touch_radius.powi(2)
+                    && (agent_position - projection).normalize_or_zero().y > 0.5
+            });
+
+        let Some((polygon_index, line_index)) = floor_line else {
+            continue;
+        };
+
+        if level.break_line(polygon_index, line_index) {
+            event_log.log(
+                LogCategory::Collision,
+                format!(
+                    "Agent {} broke line {} of polygon {}",
+                    entity.index(),
+                    line_index,
+                    polygon_index
+                ),
+            );
+
+            pathfinding.break_line(&level, polygon_index, line_index);
+            *navmesh = NavMesh::build(&level, &pathfinding);
+        }
+    }
+}
+
+/// Catches the ledges agents jumping through the air come up just short of, so small errors in
+/// their arcs don't cost them the jump
+pub fn s_grab_ledges(
+    mut platformer_ai_query: Query<(Entity, &Transform, &mut Physics, &mut PlatformerAI)>,
+    pathfinding: Res<Pathfinding>,
+    mut event_log: ResMut<EventLog>,
+) {
+    for (entity, transform, mut physics, mut platformer_ai) in platformer_ai_query.iter_mut() {
+        let in_a_jump = platformer_ai.jump_to_pos.is_some()
+            && physics.normal.length_squared() == 0.0
+            && physics.velocity.y <= 0.0;
+
+        if !in_a_jump || platformer_ai.mantling.is_some() || platformer_ai.climbing.is_some() {
+            continue;
+        }
+
+        let position = transform.translation.xy();
+        let radius = physics.radius;
+
+        let ledge = pathfinding
+            .node_ids_within_radius(position, LEDGE_GRAB_REACH + radius * 2.0)
+            .into_iter()
+            .map(|node_id| &pathfinding.nodes[node_id])
+            .find(|node| {
+                let offset = position - node.position;
+
+                // Beside the ledge on its open side, and not too far below the top of it
+                node.is_ledge()
+                    && pathfinding.is_node_enabled(node.id)
+                    && offset.x * node.normal.x > 0.0
+                    && offset.x.abs() <= radius * 2.0
+                    && offset.y <= radius
+                    && offset.y >= -LEDGE_GRAB_REACH - radius
+            });
+
+        let Some(ledge) = ledge else {
+            continue;
+        };
+
+        let side = ledge.normal.x.signum();
+        platformer_ai.mantling = Some(Mantle {
+            ledge: ledge.position,
+            target: ledge.position + Vec2::new(-side * radius, radius),
+            ticks: 0,
+        });
+        physics.velocity = Vec2::ZERO;
+        physics.acceleration = Vec2::ZERO;
+
+        event_log.log(
+            LogCategory::Jump,
+            format!(
+                "Agent {} grabbed the ledge at ({:.1}, {:.1})",
+                entity.index(),
+                ledge.position.x,
+                ledge.position.y
+            ),
+        );
+    }
+}
+
+/// Cancels the planned jump of agents that hit a ceiling and replans from where they'll land
+pub fn s_handle_head_bumps(
+    mut head_bump_events: EventReader<HeadBump>,
+    mut platformer_ai_query: Query<(&Physics, &mut PlatformerAI)>,
+    pathfinding: Res<Pathfinding>,
+    level: Res<Level>,
+) {
+    for head_bump in head_bump_events.read() {
+        let Ok((physics, mut platformer_ai)) = platformer_ai_query.get_mut(head_bump.entity) else {
+            continue;
+        };
+
+        platformer_ai.jump_from_pos = None;
+        platformer_ai.jump_to_pos = None;
+        platformer_ai.running_up = false;
+        platformer_ai.run_up_jump = None;
+
+        let mut velocity = physics.velocity;
+        velocity.y = velocity.y.min(0.0);
+
+        platformer_ai.landing_node = predict_landing_position(&level, head_bump.position, velocity)
+            .and_then(|landing_position| pathfinding.nearest_node_id(landing_position));
+    }
+}
+
+/// Throws away every agent's cached path when the goal teleports, so none of them keep
+/// heading for where it used to be
+pub fn s_handle_goal_teleports(
+    mut goal_teleported_events: EventReader<GoalTeleported>,
+    mut platformer_ai_query: Query<&mut PlatformerAI>,
+) {
+    if goal_teleported_events.read().count() == 0 {
+        return;
+    }
+
+    for mut platformer_ai in platformer_ai_query.iter_mut() {
+        platformer_ai.path.clear();
+        platformer_ai.path_goal = None;
+        platformer_ai.ticks_since_replan = 0;
+    }
+}
+
+/// Throws away the cached paths that run through nodes that were taken out of the graph
+pub fn s_handle_path_invalidations(
+    mut paths_invalidated_events: EventReader<PathsInvalidated>,
+    mut platformer_ai_query: Query<&mut PlatformerAI>,
+) {
+    let invalid_node_ids: HashSet<usize> = paths_invalidated_events
+        .read()
+        .flat_map(|paths_invalidated| paths_invalidated.node_ids.iter().copied())
+        .collect();
+
+    if invalid_node_ids.is_empty() {
+        return;
+    }
+
+    for mut platformer_ai in platformer_ai_query.iter_mut() {
+        let path_invalid = platformer_ai
+            .path
+            .iter()
+            .any(|path_node| invalid_node_ids.contains(&path_node.id));
+
+        if path_invalid {
+            platformer_ai.path.clear();
+            platformer_ai.path_goal = None;
+            platformer_ai.ticks_since_replan = 0;
+        }
+    }
+}
+
+/// Steps a falling body forward until it hits the level, returning where it does
+pub fn predict_landing_position(level: &Level, position: Vec2, velocity: Vec2) -> Option<Vec2> {
+    let mut position = position;
+    let mut velocity = velocity;
+
+    for _ in 0..LANDING_PREDICTION_MAX_TICKS {
+        velocity.y -= GRAVITY_STRENGTH;
+
+        let next_position = position + velocity;
+
+        for polygon in level.polygons.iter() {
+            for line_index in 1..polygon.points.len() {
+                // Rising bodies go straight through one-way floors
+                if polygon.is_line_passable(line_index - 1)
+                    || polygon.is_one_way && next_position.y > position.y
+                {
+                    continue;
+                }
+
+                let intersection = line_intersect(
+                    polygon.points[line_index - 1],
+                    polygon.points[line_index],
+                    position,
+                    next_position,
+                );
+
+                if intersection.is_some() {
+                    return intersection;
                 }
             }
         }
+
+        position = next_position;
     }
 
-    (move_dir, jump_velocity, jump_from_node, jump_to_node)
+    None
 }
 
-fn apply_movement_acceleration(
+/// The acceleration scalers are (speeding up, slowing down)
+pub fn apply_movement_acceleration(
     physics: &mut Physics,
     move_dir: &Vec2,
     falling: bool,
     no_move_dir: bool,
+    max_speed: f32,
+    acceleration_scalers: (f32, f32),
+    air_control: f32,
 ) {
-    // If the player is falling
+    // If the player is falling, it can only steer sideways, as much as the air control allows
     if falling {
-        physics.acceleration = Vec2::ZERO;
+        let steering =
+            (move_dir.x * max_speed - physics.velocity.x) * acceleration_scalers.0 * air_control;
+
+        // Steering only ever pushes the way it's steering, so jumps faster than running speed
+        // aren't slowed back down to it
+        physics.acceleration = if steering * move_dir.x > 0.0 {
+            Vec2::new(steering, 0.0)
+        } else {
+            Vec2::ZERO
+        };
         return;
     }
 
-    // Apply acceleration
-    physics.acceleration = (*move_dir * WANDER_MAX_SPEED - physics.velocity)
+    // Apply acceleration, which slippery surfaces give less grip for both ways
+    physics.acceleration = (*move_dir * max_speed - physics.velocity)
         * if no_move_dir {
             // Deacceleration
-            ACCELERATION_SCALERS.1
+            acceleration_scalers.1
         } else {
             // Acceleration
-            ACCELERATION_SCALERS.0
-        };
+            acceleration_scalers.0
+        }
+        * physics.friction.min(DEFAULT_FRICTION);
 
     // // Unless the player is on a wall and is trying to move away from it
     // if !player_move_off_wall {
@@ -300,7 +1530,7 @@ fn apply_movement_acceleration(
     // }
 }
 
-fn apply_gravity_toward_normal(
+pub fn apply_gravity_toward_normal(
     physics: &mut Physics,
     falling: bool,
     // player_move_off_wall: bool,
@@ -315,7 +1545,7 @@ fn apply_gravity_toward_normal(
     }
 }
 
-fn update_physics_and_transform(physics: &mut Physics, transform: &mut Transform) {
+pub fn update_physics_and_transform(physics: &mut Physics, transform: &mut Transform) {
     // Update velocity
     let new_velocity = physics.velocity + physics.acceleration;
     physics.velocity = new_velocity;
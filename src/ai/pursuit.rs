@@ -0,0 +1,119 @@
+use bevy::{
+    app::{App, FixedUpdate, Plugin},
+    ecs::{
+        component::Component,
+        entity::Entity,
+        query::With,
+        reflect::ReflectComponent,
+        schedule::IntoSystemConfigs,
+        system::{Query, Res, ResMut},
+    },
+    math::Vec3Swizzles,
+    reflect::Reflect,
+    transform::components::Transform,
+};
+
+use crate::{
+    event_log::{EventLog, LogCategory},
+    PursueAISet,
+};
+
+use super::{pathfinding::Pathfinding, platformer_ai::PlatformerAI};
+
+pub struct PursuitPlugin;
+
+impl Plugin for PursuitPlugin {
+    fn build(&self, app: &mut App) {
+        app.register_type::<PursuitTarget>()
+            .register_type::<TargetSelection>()
+            .add_systems(
+                FixedUpdate,
+                s_select_pursuit_targets.in_set(PursueAISet::Perception),
+            );
+    }
+}
+
+/// Marks an entity the agents can pursue, which can be anything with a transform
+#[derive(Component, Default, Reflect)]
+#[reflect(Component)]
+pub struct PursuitTarget;
+
+/// How an agent picks which target to pursue when there's more than one
+#[derive(Component, Debug, Clone, Copy, PartialEq, Default, Reflect)]
+#[reflect(Component)]
+pub enum TargetSelection {
+    /// Switch to whichever target is closest, every tick
+    #[default]
+    Nearest,
+    /// Stick with the target the agent has (or was given) until it's gone, then take the
+    /// closest one
+    Keep,
+}
+
+/// Picks a target for every agent and finds the node it's nearest to, which the agents then
+/// plan their paths to
+///
+/// Runs before anything moves this tick, so targets are where collision left them
+pub fn s_select_pursuit_targets(
+    pathfinding: Res<Pathfinding>,
+    mut event_log: ResMut<EventLog>,
+    target_query: Query<(Entity, &Transform), With<PursuitTarget>>,
+    mut agent_query: Query<(
+        Entity,
+        &Transform,
+        &mut PlatformerAI,
+        Option<&TargetSelection>,
+    )>,
+) {
+    for (entity, transform, mut platformer_ai, target_selection) in agent_query.iter_mut() {
+        // With the goal switched off nobody pursues anything
+        if !pathfinding.active {
+            platformer_ai.target_goal = None;
+            continue;
+        }
+
+        let agent_position = transform.translation.xy();
+        let target_selection = target_selection.copied().unwrap_or_default();
+
+        let kept_target = match target_selection {
+            TargetSelection::Nearest => None,
+            TargetSelection::Keep => platformer_ai
+                .target
+                .filter(|target| target_query.contains(*target)),
+        };
+
+        let target = kept_target.or_else(|| {
+            target_query
+                .iter()
+                .min_by(|(_, a), (_, b)| {
+                    let a = a.translation.xy().distance_squared(agent_position);
+                    let b = b.translation.xy().distance_squared(agent_position);
+                    a.total_cmp(&b)
+                })
+                .map(|(target, _)| target)
+        });
+
+        if target != platformer_ai.target {
+            if let Some(target) = target {
+                event_log.log(
+                    LogCategory::Detection,
+                    format!(
+                        "Agent {} now pursuing entity {}",
+                        entity.index(),
+                        target.index()
+                    ),
+                );
+            }
+            platformer_ai.target = target;
+        }
+
+        platformer_ai.target_goal = target
+            .and_then(|target| target_query.get(target).ok())
+            .and_then(|(_, target_transform)| {
+                let target_position = target_transform.translation.xy();
+                pathfinding
+                    .nearest_node_id(target_position)
+                    .map(|node_id| (node_id, target_position))
+            });
+    }
+}
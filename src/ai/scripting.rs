@@ -0,0 +1,441 @@
+use std::fmt;
+
+use bevy::{
+    app::{App, FixedUpdate, Plugin, Update},
+    asset::{
+        io::Reader, Asset, AssetApp, AssetEvent, AssetLoader, AssetServer, Assets, AsyncReadExt,
+        Handle, LoadContext,
+    },
+    ecs::{
+        component::Component,
+        entity::Entity,
+        event::EventReader,
+        query::{With, Without},
+        schedule::IntoSystemConfigs,
+        system::{Commands, Query, Res, ResMut, Resource},
+    },
+    math::{Vec2, Vec3Swizzles},
+    reflect::TypePath,
+    transform::components::Transform,
+    utils::BoxedFuture,
+};
+use rhai::{Array, CallFnOptions, Dynamic, Engine, EvalAltResult, Map, Scope, AST, FLOAT};
+
+use crate::{
+    event_log::{EventLog, LogCategory},
+    level::Level,
+    simulation::Simulation,
+    Physics, PursueAISet,
+};
+
+use super::{
+    a_star::{find_path_to, path_length},
+    pathfinding::Pathfinding,
+    platformer_ai::{PlatformerAI, PlatformerAIState},
+};
+
+/// The most operations a script gets per agent per tick, so a runaway loop can't hang the
+/// simulation
+const BEHAVIOR_SCRIPT_MAX_OPERATIONS: u64 = 100_000;
+
+/// The function every behavior script defines, called once a tick for each agent running it
+const BEHAVIOR_SCRIPT_ENTRY_POINT: &str = "think";
+
+pub struct ScriptingPlugin;
+
+impl Plugin for ScriptingPlugin {
+    fn build(&self, app: &mut App) {
+        let run_behavior_scripts = s_run_behavior_scripts.in_set(PursueAISet::Decision);
+        // Scripts get the last word over the squad's roles
+        #[cfg(feature = "pursue")]
+        let run_behavior_scripts = run_behavior_scripts.after(super::squad::s_coordinate_squad);
+
+        app.init_asset::<BehaviorScript>()
+            .init_asset_loader::<BehaviorScriptLoader>()
+            .insert_resource(ScriptEngine(behavior_script_engine()))
+            .add_systems(
+                Update,
+                (s_attach_behavior_scripts, s_reload_behavior_scripts),
+            )
+            .add_systems(FixedUpdate, run_behavior_scripts);
+    }
+}
+
+#[derive(Resource)]
+pub struct ScriptEngine(pub Engine);
+
+pub fn behavior_script_engine() -> Engine {
+    let mut engine = Engine::new();
+    engine.set_max_operations(BEHAVIOR_SCRIPT_MAX_OPERATIONS);
+    engine
+}
+
+/// A compiled rhai script deciding what the agents running it do
+///
+/// The script defines `fn think(agent)`, where `agent` is a map of what the agent knows:
+///
+/// - `position`, `velocity`: `#{ x, y }`
+/// - `grounded`: whether it's standing on something
+/// - `state`: `"wander"`, `"pursue"`, `"search"` or `"attack"`
+/// - `target`: where what it's pursuing is, or `()` if there's nothing
+/// - `target_distance`: how far away that is in a straight line, or `()`
+/// - `target_visible`: whether nothing's in the way of it
+/// - `path_nodes`: how many nodes are left on its current path
+/// - `allies`: where the other agents are, as an array of `#{ x, y }`
+/// - `requested_path`, `requested_path_length`: the answer to the last path request, or `()`
+///   if the point couldn't be reached
+/// - `ticks`: how many ticks it's been running the script
+///
+/// and returns a map of what to do, where anything left out is left to the state machine:
+///
+/// - `state`: one of the states above
+/// - `move_to`: `#{ x, y }` to head there over the graph instead of to the target
+/// - `hold`: `true` to stay where it is
+/// - `path_request`: `#{ x, y }` to find a path there, answered on the next tick
+///
+/// `this` is a map kept between ticks (until the script's reloaded) for anything the script
+/// wants to remember
+#[derive(Asset, TypePath)]
+pub struct BehaviorScript {
+    pub ast: AST,
+}
+
+#[derive(Debug)]
+pub enum BehaviorScriptError {
+    Io(std::io::Error),
+    Utf8(std::string::FromUtf8Error),
+    Parse(rhai::ParseError),
+}
+
+impl fmt::Display for BehaviorScriptError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BehaviorScriptError::Io(error) => write!(f, "couldn't read script: {}", error),
+            BehaviorScriptError::Utf8(error) => write!(f, "script isn't UTF-8: {}", error),
+            BehaviorScriptError::Parse(error) => write!(f, "couldn't parse script: {}", error),
+        }
+    }
+}
+
+impl std::error::Error for BehaviorScriptError {}
+
+impl From<std::io::Error> for BehaviorScriptError {
+    fn from(error: std::io::Error) -> Self {
+        BehaviorScriptError::Io(error)
+    }
+}
+
+impl From<std::string::FromUtf8Error> for BehaviorScriptError {
+    fn from(error: std::string::FromUtf8Error) -> Self {
+        BehaviorScriptError::Utf8(error)
+    }
+}
+
+impl From<rhai::ParseError> for BehaviorScriptError {
+    fn from(error: rhai::ParseError) -> Self {
+        BehaviorScriptError::Parse(error)
+    }
+}
+
+#[derive(Default)]
+pub struct BehaviorScriptLoader;
+
+impl AssetLoader for BehaviorScriptLoader {
+    type Asset = BehaviorScript;
+    type Settings = ();
+    type Error = BehaviorScriptError;
+
+    fn load<'a>(
+        &'a self,
+        reader: &'a mut Reader,
+        _settings: &'a (),
+        _load_context: &'a mut LoadContext,
+    ) -> BoxedFuture<'a, Result<BehaviorScript, BehaviorScriptError>> {
+        Box::pin(async move {
+            let mut bytes = Vec::new();
+            reader.read_to_end(&mut bytes).await?;
+
+            // Compiling doesn't need anything registered, only running does
+            let ast = Engine::new_raw().compile(String::from_utf8(bytes)?)?;
+
+            Ok(BehaviorScript { ast })
+        })
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["rhai"]
+    }
+}
+
+/// Hands an agent's decisions over to a behavior script
+#[derive(Component)]
+pub struct ScriptedBehavior {
+    pub script: Handle<BehaviorScript>,
+    /// What the script keeps between ticks, as `this`
+    pub memory: Map,
+    pub ticks: u32,
+    /// Where the script last asked for a path to, to be found before it next runs
+    pub path_request: Option<Vec2>,
+    /// The last path the script asked for and how long it is, if there was a way there
+    pub requested_path: Option<(Vec<Vec2>, f32)>,
+    /// Set when the script fails, so the error's only reported once per load
+    pub failed: bool,
+}
+
+impl ScriptedBehavior {
+    pub fn new(script: Handle<BehaviorScript>) -> ScriptedBehavior {
+        ScriptedBehavior {
+            script,
+            memory: Map::new(),
+            ticks: 0,
+            path_request: None,
+            requested_path: None,
+            failed: false,
+        }
+    }
+
+    /// Forgets everything the script worked out, for when it's been changed
+    pub fn restart(&mut self) {
+        *self = ScriptedBehavior::new(self.script.clone());
+    }
+}
+
+/// Gives every agent the script from `--behavior-script`, including ones spawned later
+pub fn s_attach_behavior_scripts(
+    mut commands: Commands,
+    simulation: Res<Simulation>,
+    asset_server: Res<AssetServer>,
+    agent_query: Query<Entity, (With<PlatformerAI>, Without<ScriptedBehavior>)>,
+) {
+    let Some(behavior_script_path) = &simulation.behavior_script else {
+        return;
+    };
+
+    for entity in agent_query.iter() {
+        commands.entity(entity).insert(ScriptedBehavior::new(
+            asset_server.load(behavior_script_path),
+        ));
+    }
+}
+
+/// Starts agents over on a script whenever it's edited, with the `hot_reload` feature
+pub fn s_reload_behavior_scripts(
+    mut asset_events: EventReader<AssetEvent<BehaviorScript>>,
+    mut scripted_query: Query<&mut ScriptedBehavior>,
+) {
+    for asset_event in asset_events.read() {
+        let AssetEvent::Modified { id } = asset_event else {
+            continue;
+        };
+
+        for mut scripted_behavior in scripted_query.iter_mut() {
+            if scripted_behavior.script.id() == *id {
+                scripted_behavior.restart();
+            }
+        }
+
+        println!("Reloaded behavior script");
+    }
+}
+
+fn point(position: Vec2) -> Dynamic {
+    let mut point = Map::new();
+    point.insert("x".into(), (position.x as FLOAT).into());
+    point.insert("y".into(), (position.y as FLOAT).into());
+    point.into()
+}
+
+fn as_point(value: &Dynamic) -> Option<Vec2> {
+    let point = value.read_lock::<Map>()?;
+    let coordinate = |name: &str| point.get(name)?.as_float().ok().map(|value| value as f32);
+
+    Some(Vec2::new(coordinate("x")?, coordinate("y")?))
+}
+
+/// Calls a script's `think` with what an agent knows, keeping `memory` as `this` between calls
+pub fn think(
+    engine: &Engine,
+    ast: &AST,
+    memory: &mut Map,
+    agent: Map,
+) -> Result<Dynamic, Box<EvalAltResult>> {
+    let mut this: Dynamic = std::mem::take(memory).into();
+    let result = engine.call_fn_with_options::<Dynamic>(
+        CallFnOptions::new().bind_this_ptr(&mut this),
+        &mut Scope::new(),
+        ast,
+        BEHAVIOR_SCRIPT_ENTRY_POINT,
+        (agent,),
+    );
+    *memory = this.try_cast::<Map>().unwrap_or_default();
+
+    result
+}
+
+fn state_name(state: PlatformerAIState) -> &'static str {
+    match state {
+        PlatformerAIState::Wander => "wander",
+        PlatformerAIState::Pursue => "pursue",
+        PlatformerAIState::Search => "search",
+        PlatformerAIState::Attack => "attack",
+    }
+}
+
+fn parse_state(name: &str) -> Option<PlatformerAIState> {
+    match name {
+        "wander" => Some(PlatformerAIState::Wander),
+        "pursue" => Some(PlatformerAIState::Pursue),
+        "search" => Some(PlatformerAIState::Search),
+        "attack" => Some(PlatformerAIState::Attack),
+        _ => None,
+    }
+}
+
+/// Runs each agent's script and carries out what it decides, before the agents move
+pub fn s_run_behavior_scripts(
+    script_engine: Res<ScriptEngine>,
+    behavior_scripts: Res<Assets<BehaviorScript>>,
+    pathfinding: Res<Pathfinding>,
+    level: Res<Level>,
+    mut event_log: ResMut<EventLog>,
+    mut agent_query: Query<(
+        Entity,
+        &Transform,
+        &Physics,
+        &mut PlatformerAI,
+        &mut ScriptedBehavior,
+    )>,
+) {
+    let agent_positions: Vec<(Entity, Vec2)> = agent_query
+        .iter()
+        .map(|(entity, transform, ..)| (entity, transform.translation.xy()))
+        .collect();
+
+    for (entity, transform, physics, mut platformer_ai, mut scripted_behavior) in
+        agent_query.iter_mut()
+    {
+        if scripted_behavior.failed {
+            continue;
+        }
+        let Some(behavior_script) = behavior_scripts.get(&scripted_behavior.script) else {
+            continue;
+        };
+
+        let position = transform.translation.xy();
+
+        // Answer last tick's path request
+        if let Some(goal_position) = scripted_behavior.path_request.take() {
+            scripted_behavior.requested_path = pathfinding
+                .nearest_node_id(goal_position)
+                .and_then(|node_id| find_path_to(&pathfinding, position, node_id, goal_position))
+                .map(|path| {
+                    let length = path_length(position, &path);
+                    let points = path.iter().map(|path_node| path_node.position).collect();
+                    (points, length)
+                });
+        }
+
+        let target_position = platformer_ai
+            .target_goal
+            .map(|(_, target_position)| target_position);
+
+        let mut agent = Map::new();
+        agent.insert("position".into(), point(position));
+        agent.insert("velocity".into(), point(physics.velocity));
+        agent.insert("grounded".into(), physics.grounded.into());
+        agent.insert("state".into(), state_name(platformer_ai.state).into());
+        agent.insert(
+            "target".into(),
+            target_position.map_or(Dynamic::UNIT, point),
+        );
+        agent.insert(
+            "target_distance".into(),
+            target_position.map_or(Dynamic::UNIT, |target_position| {
+                (position.distance(target_position) as FLOAT).into()
+            }),
+        );
+        agent.insert(
+            "target_visible".into(),
+            target_position
+                .is_some_and(|target_position| level.line_of_sight_check(position, target_position))
+                .into(),
+        );
+        agent.insert(
+            "path_nodes".into(),
+            (platformer_ai.path.len() as rhai::INT).into(),
+        );
+        agent.insert(
+            "allies".into(),
+            agent_positions
+                .iter()
+                .filter(|(other, _)| *other != entity)
+                .map(|(_, other_position)| point(*other_position))
+                .collect::<Array>()
+                .into(),
+        );
+        let (requested_path, requested_path_length) = match &scripted_behavior.requested_path {
+            Some((points, length)) => (
+                points.iter().copied().map(point).collect::<Array>().into(),
+                (*length as FLOAT).into(),
+            ),
+            None => (Dynamic::UNIT, Dynamic::UNIT),
+        };
+        agent.insert("requested_path".into(), requested_path);
+        agent.insert("requested_path_length".into(), requested_path_length);
+        agent.insert(
+            "ticks".into(),
+            (scripted_behavior.ticks as rhai::INT).into(),
+        );
+
+        scripted_behavior.ticks += 1;
+
+        let decision = match think(
+            &script_engine.0,
+            &behavior_script.ast,
+            &mut scripted_behavior.memory,
+            agent,
+        ) {
+            Ok(decision) => decision,
+            Err(error) => {
+                event_log.log(
+                    LogCategory::State,
+                    format!("Agent {} behavior script failed: {}", entity.index(), error),
+                );
+                scripted_behavior.failed = true;
+                continue;
+            }
+        };
+
+        // Returning nothing leaves everything to the state machine
+        let Some(decision) = decision.try_cast::<Map>() else {
+            continue;
+        };
+
+        if let Some(state) = decision
+            .get("state")
+            .and_then(|state| state.clone().into_string().ok())
+            .and_then(|state| parse_state(&state))
+        {
+            platformer_ai.state = state;
+        }
+
+        let hold = decision
+            .get("hold")
+            .and_then(|hold| hold.as_bool().ok())
+            .unwrap_or(false);
+        let move_to = if hold {
+            Some(position)
+        } else {
+            decision.get("move_to").and_then(as_point)
+        };
+
+        if let Some(move_to) = move_to {
+            platformer_ai.current_target_node = None;
+            platformer_ai.target_goal = pathfinding
+                .nearest_node_id(move_to)
+                .map(|node_id| (node_id, move_to));
+        }
+
+        scripted_behavior.path_request = decision.get("path_request").and_then(as_point);
+    }
+}
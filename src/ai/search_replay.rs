@@ -0,0 +1,225 @@
+use std::collections::HashMap;
+
+use bevy::{
+    app::{App, Plugin, Update},
+    ecs::{
+        query::With,
+        schedule::IntoSystemConfigs,
+        system::{Query, Res, ResMut, Resource},
+    },
+    gizmos::gizmos::Gizmos,
+    input::{keyboard::KeyCode, ButtonInput},
+    math::Vec3Swizzles,
+    render::color::Color,
+    transform::components::Transform,
+};
+
+use crate::PursueAISet;
+
+use super::{
+    a_star::{find_path_traced, PathNode, SearchTrace, SearchTraceNode},
+    pathfinding::Pathfinding,
+    platformer_ai::PlatformerAI,
+};
+
+const REPLAY_NODE_RADIUS: f32 = 4.0;
+
+pub struct SearchReplayPlugin;
+
+impl Plugin for SearchReplayPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(SearchReplay::default()).add_systems(
+            Update,
+            (
+                s_search_replay_input,
+                s_render_search_replay.in_set(PursueAISet::Render),
+            ),
+        );
+    }
+}
+
+/// A recorded search from the first agent to the goal, played back one expansion at a time
+#[derive(Resource, Default)]
+pub struct SearchReplay {
+    pub trace: Option<SearchTrace>,
+    pub path: Option<Vec<PathNode>>,
+    /// How many of the trace's steps have been played
+    pub step: usize,
+}
+
+impl SearchReplay {
+    /// The closed nodes and the nodes still open after the steps played so far, with the
+    /// costs they were last seen with
+    pub fn lists(&self) -> (Vec<SearchTraceNode>, HashMap<usize, SearchTraceNode>) {
+        let mut closed = Vec::new();
+        let mut open: HashMap<usize, SearchTraceNode> = HashMap::new();
+
+        let Some(trace) = self.trace.as_ref() else {
+            return (closed, open);
+        };
+
+        for step in trace.steps.iter().take(self.step) {
+            open.remove(&step.current.id);
+            closed.push(step.current);
+
+            for opened in step.opened.iter() {
+                if closed.iter().any(|node| node.id == opened.id) {
+                    continue;
+                }
+
+                // Only the cheapest way into a node is the one the search will take
+                let cheaper = open.get(&opened.id).is_none_or(|existing| {
+                    opened.g_cost + opened.h_cost < existing.g_cost + existing.h_cost
+                });
+                if cheaper {
+                    open.insert(opened.id, *opened);
+                }
+            }
+        }
+
+        (closed, open)
+    }
+}
+
+pub fn s_search_replay_input(
+    keyboard_input: Res<ButtonInput<KeyCode>>,
+    pathfinding: Res<Pathfinding>,
+    agent_query: Query<&Transform, With<PlatformerAI>>,
+    mut search_replay: ResMut<SearchReplay>,
+) {
+    // Y to record the first agent's search to the goal, or to stop replaying it
+    if keyboard_input.just_pressed(KeyCode::KeyY) {
+        if search_replay.trace.is_some() {
+            *search_replay = SearchReplay::default();
+            println!("Search replay cleared");
+            return;
+        }
+
+        let Some(transform) = agent_query.iter().next() else {
+            return;
+        };
+
+        let (path, trace) = find_path_traced(&pathfinding, transform.translation.xy());
+
+        println!(
+            "Recorded a search of {} steps, {}",
+            trace.steps.len(),
+            if path.is_some() {
+                "which found a path"
+            } else {
+                "which found no path"
+            }
+        );
+
+        *search_replay = SearchReplay {
+            trace: Some(trace),
+            path,
+            step: 0,
+        };
+    }
+
+    let Some(step_count) = search_replay.trace.as_ref().map(|trace| trace.steps.len()) else {
+        return;
+    };
+
+    // U to play the next step, Shift+U to go back one
+    if keyboard_input.just_pressed(KeyCode::KeyU) {
+        let shift = keyboard_input.any_pressed([KeyCode::ShiftLeft, KeyCode::ShiftRight]);
+
+        search_replay.step = if shift {
+            search_replay.step.saturating_sub(1)
+        } else {
+            (search_replay.step + 1).min(step_count)
+        };
+
+        if let Some(step) = search_replay.step.checked_sub(1).and_then(|step_index| {
+            search_replay
+                .trace
+                .as_ref()
+                .and_then(|trace| trace.steps.get(step_index))
+        }) {
+            println!(
+                "Step {}/{}: node {} (g {:.1}, h {:.1}, f {:.1})",
+                search_replay.step,
+                step_count,
+                step.current.id,
+                step.current.g_cost,
+                step.current.h_cost,
+                step.current.g_cost + step.current.h_cost
+            );
+        }
+    }
+}
+
+/// Closed nodes are colored by how far they are from the start (blue near, red far) and
+/// open nodes ringed by how far they think they are from the goal (green near, yellow far),
+/// with the node being expanded in white
+pub fn s_render_search_replay(
+    mut gizmos: Gizmos,
+    pathfinding: Res<Pathfinding>,
+    search_replay: Res<SearchReplay>,
+) {
+    let Some(trace) = search_replay.trace.as_ref() else {
+        return;
+    };
+
+    let (closed, open) = search_replay.lists();
+
+    let max_g_cost = trace
+        .steps
+        .iter()
+        .map(|step| step.current.g_cost)
+        .fold(f32::EPSILON, f32::max);
+    let max_h_cost = trace
+        .steps
+        .iter()
+        .flat_map(|step| step.opened.iter().map(|node| node.h_cost))
+        .fold(f32::EPSILON, f32::max);
+
+    let position = |node_id: usize| pathfinding.nodes.get(node_id).map(|node| node.position);
+
+    for node in closed.iter() {
+        let Some(node_position) = position(node.id) else {
+            continue;
+        };
+
+        let t = (node.g_cost / max_g_cost).clamp(0.0, 1.0);
+        let color = Color::rgb(t, 0.0, 1.0 - t);
+
+        gizmos.circle_2d(node_position, REPLAY_NODE_RADIUS, color);
+        gizmos.circle_2d(node_position, REPLAY_NODE_RADIUS / 2.0, color);
+    }
+
+    for node in open.values() {
+        let Some(node_position) = position(node.id) else {
+            continue;
+        };
+
+        let t = (node.h_cost / max_h_cost).clamp(0.0, 1.0);
+
+        gizmos.circle_2d(
+            node_position,
+            REPLAY_NODE_RADIUS * 1.5,
+            Color::rgb(t, 1.0, 0.0),
+        );
+    }
+
+    if let Some(current_position) = search_replay
+        .step
+        .checked_sub(1)
+        .and_then(|step_index| trace.steps.get(step_index))
+        .and_then(|step| position(step.current.id))
+    {
+        gizmos.circle_2d(current_position, REPLAY_NODE_RADIUS * 2.5, Color::WHITE);
+    }
+
+    // The path it ended up with, once the whole search has played
+    if search_replay.step == trace.steps.len() {
+        if let Some(path) = search_replay.path.as_ref() {
+            gizmos.linestrip_2d(
+                path.iter().map(|path_node| path_node.position),
+                Color::WHITE,
+            );
+        }
+    }
+}
@@ -0,0 +1,266 @@
+use std::collections::{HashMap, HashSet, VecDeque};
+
+use bevy::{
+    app::{App, FixedUpdate, Plugin},
+    ecs::{
+        entity::Entity,
+        event::EventReader,
+        reflect::ReflectResource,
+        schedule::IntoSystemConfigs,
+        system::{Query, Res, ResMut, Resource},
+    },
+    math::{Vec2, Vec3Swizzles},
+    reflect::Reflect,
+    transform::components::Transform,
+};
+
+use crate::{
+    settings::{Settings, SquadSettings},
+    Level, Physics, PursueAISet,
+};
+
+use super::{
+    a_star::{find_path_from, SearchStats},
+    pathfinding::{GoalTeleported, Pathfinding, PathfindingGraphConnectionType},
+    platformer_ai::{PlatformerAI, PlatformerAIState},
+};
+
+pub struct SquadPlugin;
+
+impl Plugin for SquadPlugin {
+    fn build(&self, app: &mut App) {
+        app.register_type::<SquadRole>()
+            .register_type::<SquadCoordinator>()
+            .insert_resource(SquadCoordinator::default())
+            .add_systems(
+                FixedUpdate,
+                s_coordinate_squad.in_set(PursueAISet::Decision),
+            );
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Reflect)]
+pub enum SquadRole {
+    /// Heads straight for the goal
+    Chase,
+    /// Covers one of the goal's escape routes instead
+    Support { cover_node_id: usize },
+}
+
+#[derive(Resource, Default, Reflect)]
+#[reflect(Resource)]
+pub struct SquadCoordinator {
+    pub roles: HashMap<Entity, SquadRole>,
+    pub ticks_until_update: u32,
+}
+
+/// An agent's path to the goal it's pursuing, for working out the squad's roles
+struct AgentPath {
+    entity: Entity,
+    position: Vec2,
+    /// Agents only hand off to chasers of the same target
+    target: Option<Entity>,
+    goal_node_id: usize,
+    cost: f32,
+    node_ids: Vec<usize>,
+}
+
+/// Splits the agents pursuing each target into chasers and supports covering its exits
+pub fn s_coordinate_squad(
+    mut squad_coordinator: ResMut<SquadCoordinator>,
+    mut platformer_ai_query: Query<(Entity, &Transform, &Physics, &mut PlatformerAI)>,
+    pathfinding: Res<Pathfinding>,
+    level: Res<Level>,
+    settings: Res<Settings>,
+    mut goal_teleported_events: EventReader<GoalTeleported>,
+) {
+    let settings = &settings.squad;
+
+    // Roles worked out around the old goal are useless once it teleports
+    if goal_teleported_events.read().count() > 0 {
+        squad_coordinator.ticks_until_update = 0;
+    }
+
+    if squad_coordinator.ticks_until_update > 0 {
+        squad_coordinator.ticks_until_update -= 1;
+        return;
+    }
+    squad_coordinator.ticks_until_update = settings.coordination_interval;
+
+    squad_coordinator.roles.clear();
+
+    if !pathfinding.active {
+        // Nothing to coordinate around, so everyone goes back to chasing
+        for (_, _, _, mut platformer_ai) in platformer_ai_query.iter_mut() {
+            platformer_ai.current_target_node = None;
+            platformer_ai.state = PlatformerAIState::Pursue;
+        }
+        return;
+    }
+
+    // Work out every agent's path to the goal it's pursuing and what it costs, searching from
+    // the same node the agent would follow its own path from
+    let mut agent_paths: Vec<AgentPath> = Vec::new();
+    for (entity, transform, physics, platformer_ai) in platformer_ai_query.iter() {
+        let agent_position = transform.translation.xy();
+
+        let Some((goal_node_id, goal_position)) = platformer_ai.pursuit_goal(&pathfinding) else {
+            continue;
+        };
+        let Some(start_node_id) =
+            platformer_ai.path_start_node_id(&pathfinding, &level, agent_position, physics)
+        else {
+            continue;
+        };
+
+        let mut stats = SearchStats::default();
+        if let Some(path) = find_path_from(
+            &pathfinding,
+            start_node_id,
+            goal_node_id,
+            goal_position,
+            Some(&mut stats),
+            Some(entity),
+        ) {
+            agent_paths.push(AgentPath {
+                entity,
+                position: agent_position,
+                target: platformer_ai.target,
+                goal_node_id,
+                cost: stats.path_cost,
+                node_ids: path.iter().map(|path_node| path_node.id).collect(),
+            });
+        }
+    }
+
+    // Cheapest agents get first pick
+    agent_paths.sort_by(|a, b| a.cost.total_cmp(&b.cost));
+
+    let mut chasers: Vec<usize> = Vec::new();
+    let mut covered_node_ids: HashSet<usize> = HashSet::new();
+
+    for agent_index in 0..agent_paths.len() {
+        let agent_path = &agent_paths[agent_index];
+
+        // Hand off if a clearly closer chaser of the same target is already converging on the
+        // same nodes
+        let closer_chaser = chasers.iter().find(|chaser_index| {
+            let chaser_path = &agent_paths[**chaser_index];
+
+            chaser_path.target == agent_path.target
+                && agent_path.cost > chaser_path.cost * settings.handoff_cost_ratio
+                && paths_converge(&agent_path.node_ids, &chaser_path.node_ids, settings)
+        });
+
+        let role = match closer_chaser {
+            Some(chaser_index) => {
+                let chaser_position = agent_paths[*chaser_index].position;
+
+                match choose_cover_node(
+                    &pathfinding,
+                    agent_path.goal_node_id,
+                    chaser_position,
+                    &covered_node_ids,
+                    settings,
+                ) {
+                    Some(cover_node_id) => {
+                        covered_node_ids.insert(cover_node_id);
+                        SquadRole::Support { cover_node_id }
+                    }
+                    None => SquadRole::Chase,
+                }
+            }
+            None => SquadRole::Chase,
+        };
+
+        if role == SquadRole::Chase {
+            chasers.push(agent_index);
+        }
+
+        squad_coordinator.roles.insert(agent_path.entity, role);
+    }
+
+    // Point the agents at their targets
+    for (entity, transform, _, mut platformer_ai) in platformer_ai_query.iter_mut() {
+        let goal_close =
+            platformer_ai
+                .pursuit_goal(&pathfinding)
+                .is_some_and(|(_, goal_position)| {
+                    (transform.translation.xy() - goal_position).length() < settings.attack_distance
+                });
+
+        (platformer_ai.current_target_node, platformer_ai.state) =
+            match squad_coordinator.roles.get(&entity) {
+                // Support agents hold an escape route, so they only need to check in now and then
+                Some(SquadRole::Support { cover_node_id }) => {
+                    (Some(*cover_node_id), PlatformerAIState::Search)
+                }
+                _ if goal_close => (None, PlatformerAIState::Attack),
+                _ => (None, PlatformerAIState::Pursue),
+            };
+    }
+}
+
+/// Two paths converge if they end on the same few nodes
+fn paths_converge(path_a: &[usize], path_b: &[usize], settings: &SquadSettings) -> bool {
+    let tail_a = &path_a[path_a.len().saturating_sub(settings.convergence_nodes)..];
+
+    !tail_a.is_empty() && tail_a.iter().all(|node_id| path_b.contains(node_id))
+}
+
+/// Picks the goal's likeliest unguarded exit, preferring ones on the far side from the chaser
+///
+/// Exits are corners and jump-off points within walking/jumping distance of the goal
+fn choose_cover_node(
+    pathfinding: &Pathfinding,
+    goal_node_id: usize,
+    chaser_position: Vec2,
+    covered_node_ids: &HashSet<usize>,
+    settings: &SquadSettings,
+) -> Option<usize> {
+    let mut best_node_id = None;
+    let mut best_distance_from_chaser = 0.0;
+
+    let mut visited: HashSet<usize> = HashSet::new();
+    let mut queue: VecDeque<(usize, f32)> = VecDeque::new();
+
+    visited.insert(goal_node_id);
+    queue.push_back((goal_node_id, 0.0));
+
+    while let Some((node_id, distance_from_goal)) = queue.pop_front() {
+        let node = &pathfinding.nodes[node_id];
+
+        let is_exit = node.is_corner
+            || node
+                .connections_of_type(PathfindingGraphConnectionType::Jumpable)
+                .next()
+                .is_some();
+
+        if is_exit && node_id != goal_node_id && !covered_node_ids.contains(&node_id) {
+            let distance_from_chaser = (node.position - chaser_position).length_squared();
+
+            if distance_from_chaser > best_distance_from_chaser {
+                best_distance_from_chaser = distance_from_chaser;
+                best_node_id = Some(node_id);
+            }
+        }
+
+        for connection in node
+            .connections_of_type(PathfindingGraphConnectionType::Walkable)
+            .chain(node.connections_of_type(PathfindingGraphConnectionType::Jumpable))
+        {
+            let next_distance = distance_from_goal + connection.dist;
+
+            if next_distance > settings.exit_search_distance
+                || !pathfinding.is_connection_enabled(node_id, connection.node_id)
+                || !visited.insert(connection.node_id)
+            {
+                continue;
+            }
+
+            queue.push_back((connection.node_id, next_distance));
+        }
+    }
+
+    best_node_id
+}
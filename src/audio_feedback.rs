@@ -0,0 +1,165 @@
+use std::time::Duration;
+
+use bevy::{
+    app::{App, Plugin, Startup, Update},
+    asset::{Assets, Handle},
+    audio::{Pitch, PitchBundle, PlaybackSettings, Volume},
+    ecs::{
+        event::EventReader,
+        reflect::ReflectResource,
+        schedule::IntoSystemConfigs,
+        system::{Commands, Res, ResMut, Resource},
+    },
+    input::{keyboard::KeyCode, ButtonInput},
+    reflect::Reflect,
+    utils::HashMap,
+};
+
+use crate::ai::{
+    animation::{AnimationEvent, AnimationEventKind},
+    platformer_ai::PlatformerAIState,
+};
+
+pub struct AudioFeedbackPlugin;
+
+impl Plugin for AudioFeedbackPlugin {
+    fn build(&self, app: &mut App) {
+        app.register_type::<AudioFeedback>()
+            .insert_resource(AudioFeedback {
+                muted: false,
+                volume: 0.3,
+            })
+            .add_systems(Startup, s_init_audio_feedback)
+            .add_systems(
+                Update,
+                (s_audio_feedback_input, s_play_audio_feedback).chain(),
+            );
+    }
+}
+
+/// Whether the AI's events make sounds, and how loud
+#[derive(Resource, Default, Reflect)]
+#[reflect(Resource)]
+pub struct AudioFeedback {
+    pub muted: bool,
+    pub volume: f32,
+}
+
+/// Something the agents did that's worth hearing
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum AudioCue {
+    /// Spotted the target and started after it
+    Detected,
+    /// Lost track of the target and went looking for it
+    LostTarget,
+    Jump,
+    Land,
+    LedgeGrab,
+    Attack,
+}
+
+impl AudioCue {
+    pub const ALL: [AudioCue; 6] = [
+        AudioCue::Detected,
+        AudioCue::LostTarget,
+        AudioCue::Jump,
+        AudioCue::Land,
+        AudioCue::LedgeGrab,
+        AudioCue::Attack,
+    ];
+
+    /// The cue an animation event is heard as, if it has one
+    pub fn from_animation(kind: AnimationEventKind) -> Option<AudioCue> {
+        match kind {
+            AnimationEventKind::JumpStarted => Some(AudioCue::Jump),
+            AnimationEventKind::Landed => Some(AudioCue::Land),
+            AnimationEventKind::LedgeGrabbed => Some(AudioCue::LedgeGrab),
+            AnimationEventKind::AttackStarted => Some(AudioCue::Attack),
+            AnimationEventKind::StateChanged {
+                from: PlatformerAIState::Wander | PlatformerAIState::Search,
+                to: PlatformerAIState::Pursue,
+            } => Some(AudioCue::Detected),
+            AnimationEventKind::StateChanged {
+                from: PlatformerAIState::Pursue | PlatformerAIState::Attack,
+                to: PlatformerAIState::Search | PlatformerAIState::Wander,
+            } => Some(AudioCue::LostTarget),
+            AnimationEventKind::StateChanged { .. } => None,
+        }
+    }
+
+    /// The tone the cue's played as, high and short for the sharp ones and low for the rest
+    fn pitch(&self) -> Pitch {
+        let (frequency, milliseconds) = match self {
+            AudioCue::Detected => (880.0, 120),
+            AudioCue::LostTarget => (330.0, 200),
+            AudioCue::Jump => (660.0, 60),
+            AudioCue::Land => (220.0, 50),
+            AudioCue::LedgeGrab => (440.0, 80),
+            AudioCue::Attack => (990.0, 150),
+        };
+
+        Pitch::new(frequency, Duration::from_millis(milliseconds))
+    }
+}
+
+/// The tone each cue plays, made once at startup
+#[derive(Resource)]
+pub struct AudioFeedbackSounds {
+    pub cues: HashMap<AudioCue, Handle<Pitch>>,
+}
+
+pub fn s_init_audio_feedback(mut commands: Commands, mut pitches: ResMut<Assets<Pitch>>) {
+    commands.insert_resource(AudioFeedbackSounds {
+        cues: AudioCue::ALL
+            .iter()
+            .map(|cue| (*cue, pitches.add(cue.pitch())))
+            .collect(),
+    });
+}
+
+pub fn s_audio_feedback_input(
+    keyboard_input: Res<ButtonInput<KeyCode>>,
+    mut audio_feedback: ResMut<AudioFeedback>,
+) {
+    // F7 to mute the AI's sounds
+    if keyboard_input.just_pressed(KeyCode::F7) {
+        audio_feedback.muted = !audio_feedback.muted;
+        println!("AI sounds muted: {}", audio_feedback.muted);
+    }
+}
+
+pub fn s_play_audio_feedback(
+    mut commands: Commands,
+    mut animation_events: EventReader<AnimationEvent>,
+    audio_feedback: Res<AudioFeedback>,
+    sounds: Option<Res<AudioFeedbackSounds>>,
+) {
+    let cues: Vec<AudioCue> = animation_events
+        .read()
+        .filter_map(|animation_event| AudioCue::from_animation(animation_event.kind))
+        .collect();
+
+    let Some(sounds) = sounds else {
+        return;
+    };
+    if audio_feedback.muted {
+        return;
+    }
+
+    // Each cue plays once a frame at most, however many agents set it off
+    let mut played = Vec::new();
+    for cue in cues {
+        if played.contains(&cue) {
+            continue;
+        }
+        played.push(cue);
+
+        let Some(pitch) = sounds.cues.get(&cue) else {
+            continue;
+        };
+        commands.spawn(PitchBundle {
+            source: pitch.clone(),
+            settings: PlaybackSettings::DESPAWN.with_volume(Volume::new(audio_feedback.volume)),
+        });
+    }
+}
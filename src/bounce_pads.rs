@@ -0,0 +1,84 @@
+use bevy::{
+    app::{App, FixedUpdate, Plugin, Update},
+    ecs::{
+        schedule::IntoSystemConfigs,
+        system::{Query, Res},
+    },
+    gizmos::gizmos::Gizmos,
+    math::{Vec2, Vec3Swizzles},
+    render::color::Color,
+    transform::components::Transform,
+};
+
+use crate::{
+    ai::platformer_ai::PlatformerAI,
+    collisions::s_collision,
+    level::{BouncePad, Level},
+    Physics, PursueAISet, GRAVITY_STRENGTH,
+};
+
+pub struct BouncePadsPlugin;
+
+impl Plugin for BouncePadsPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            FixedUpdate,
+            s_bounce_agents
+                .in_set(PursueAISet::Collision)
+                .after(s_collision),
+        )
+        .add_systems(Update, s_render_bounce_pads.in_set(PursueAISet::Render));
+    }
+}
+
+/// Launches grounded agents standing on a bounce pad with the pad's impulse
+pub fn s_bounce_agents(
+    mut platformer_ai_query: Query<(&Transform, &mut Physics, &mut PlatformerAI)>,
+    level: Res<Level>,
+) {
+    for (transform, mut physics, mut platformer_ai) in platformer_ai_query.iter_mut() {
+        if !physics.grounded {
+            continue;
+        }
+
+        let agent_position = transform.translation.xy();
+
+        let Some(bounce_pad) = level
+            .bounce_pads
+            .iter()
+            .find(|bounce_pad| bounce_pad.is_under(agent_position, physics.radius))
+        else {
+            continue;
+        };
+
+        // The pad takes over completely, whatever the agent was doing before
+        physics.velocity = bounce_pad.impulse;
+        physics.acceleration = Vec2::new(0.0, -GRAVITY_STRENGTH);
+        physics.normal = Vec2::ZERO;
+        physics.grounded = false;
+        physics.walled = 0;
+        physics.has_wall_jumped = false;
+        physics.has_double_jumped = false;
+
+        platformer_ai.running_up = false;
+        platformer_ai.run_up_jump = None;
+        platformer_ai.jump_from_pos = None;
+        platformer_ai.jump_to_pos = None;
+    }
+}
+
+pub fn s_render_bounce_pads(mut gizmos: Gizmos, level: Res<Level>) {
+    for bounce_pad in level.bounce_pads.iter() {
+        gizmos.rect_2d(
+            bounce_pad.position + Vec2::Y * 2.0,
+            0.0,
+            Vec2::new(BouncePad::HALF_WIDTH * 2.0, 4.0),
+            Color::GOLD,
+        );
+        gizmos.line_2d(
+            bounce_pad.position,
+            bounce_pad.position + bounce_pad.impulse * 3.0,
+            Color::GOLD.with_a(0.3),
+        );
+    }
+}
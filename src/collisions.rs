@@ -1,162 +1,818 @@
+use std::collections::HashMap;
+
 use bevy::{
-    app::{App, Plugin, Update},
+    app::{App, FixedUpdate, Plugin, Update},
     ecs::{
+        change_detection::DetectChanges,
+        entity::Entity,
+        event::{Event, EventWriter},
+        query::{With, Without},
+        reflect::ReflectResource,
         schedule::IntoSystemConfigs,
-        system::{Query, Res},
+        system::{Local, Query, Res, ResMut, Resource},
     },
     gizmos::gizmos::Gizmos,
+    input::{keyboard::KeyCode, ButtonInput},
     math::{Vec2, Vec3Swizzles},
+    reflect::Reflect,
     transform::components::Transform,
 };
 
 use crate::{
-    ai::platformer_ai::{s_platformer_ai_movement, PlatformerAI},
-    level::Level,
+    ai::{kd_tree::KdTree, platformer_ai::PlatformerAI},
+    edge_grid::EdgeGrid,
+    event_log::{EventLog, LogCategory},
+    level::{point_in_polygon, Level, DEFAULT_FRICTION},
+    moving_platforms::MovingPlatform,
     utils::{line_intersect, side_of_line_detection},
-    Physics,
+    GoalPoint, Physics, PursueAISet,
 };
 
+/// How much of their closing speed two bodies keep (bounced back the other way) when they hit
+const BODY_RESTITUTION: f32 = 0.2;
+
+/// Bodies moving further than this fraction of their radius in a tick are swept along their
+/// path, since the overlap test alone could miss a line they passed clean through
+const SWEEP_MIN_MOTION_RATIO: f32 = 0.5;
+
+/// How many times a swept body can hit something and slide along it in a single tick
+const SWEEP_ITERATIONS: usize = 3;
+
+/// How far off a line a swept body is left, so it ends up touching it without overlapping
+const SWEEP_SKIN: f32 = 0.05;
+
+/// Bodies hitting something slower than this don't bounce off it, so resting ones stay put
+const BOUNCE_MIN_SPEED: f32 = 1.0;
+
+/// How many passes the contact solver gets at pushing a body out of everything it overlaps
+const CONTACT_SOLVER_ITERATIONS: usize = 8;
+
+/// How much overlap the contact solver leaves alone
+const CONTACT_SOLVER_TOLERANCE: f32 = 0.001;
+
+/// How big the goal point is, as far as the level and bodies pushing it around go
+pub const GOAL_POINT_RADIUS: f32 = 7.5;
+
 pub struct CollisionPlugin;
 
 impl Plugin for CollisionPlugin {
     fn build(&self, app: &mut App) {
-        app.add_systems(Update, s_collision.after(s_platformer_ai_movement));
+        app.register_type::<BodyCollisionSettings>()
+            .register_type::<ColliderShape>()
+            .add_event::<HeadBump>()
+            .add_event::<CollisionStarted>()
+            .add_event::<CollisionEnded>()
+            .insert_resource(BodyCollisionSettings { push_goal: false })
+            .insert_resource(LevelEdgeIndex::default())
+            .add_systems(
+                FixedUpdate,
+                (
+                    s_rebuild_level_edge_index.before(s_collision),
+                    s_body_collision.before(s_collision),
+                    s_collision,
+                )
+                    .in_set(PursueAISet::Collision),
+            )
+            .add_systems(Update, (s_body_collision_input, s_collider_shape_input));
+    }
+}
+
+/// The level's lines, indexed by where they are so bodies only test the ones near them
+#[derive(Resource, Default)]
+pub struct LevelEdgeIndex {
+    pub grid: EdgeGrid,
+}
+
+#[derive(Resource, Default, Reflect)]
+#[reflect(Resource)]
+pub struct BodyCollisionSettings {
+    /// Whether bodies shove the goal point out of their way
+    pub push_goal: bool,
+}
+
+/// Sent when an entity moving upward hits a ceiling
+#[derive(Event, Debug, Clone, Copy)]
+pub struct HeadBump {
+    pub entity: Entity,
+    pub position: Vec2,
+    pub ceiling_normal: Vec2,
+}
+
+/// The shape a body collides with the level as
+#[derive(Debug, Clone, Copy, PartialEq, Reflect)]
+pub enum ColliderShape {
+    /// A circle of the body's radius
+    Circle,
+    /// A circle of the body's radius stretched up and down by the half height, for tall bodies
+    Capsule { half_height: f32 },
+    /// A box that doesn't turn, which ignores the body's radius (crates and the like)
+    Aabb { half_extents: Vec2 },
+}
+
+impl ColliderShape {
+    /// Half the size of the box around the shape
+    pub fn extents(&self, radius: f32) -> Vec2 {
+        match self {
+            ColliderShape::Circle => Vec2::splat(radius),
+            ColliderShape::Capsule { half_height } => Vec2::new(radius, radius + half_height),
+            ColliderShape::Aabb { half_extents } => *half_extents,
+        }
+    }
+
+    /// The radius of the biggest circle that fits in the shape, which is what gets swept
+    pub fn inner_radius(&self, radius: f32) -> f32 {
+        match self {
+            ColliderShape::Circle | ColliderShape::Capsule { .. } => radius,
+            ColliderShape::Aabb { half_extents } => half_extents.min_element(),
+        }
+    }
+
+    /// Where the swept circle sits relative to the body's position, which for a capsule is the
+    /// end it's moving toward
+    pub fn leading_offset(&self, motion: Vec2) -> Vec2 {
+        match self {
+            ColliderShape::Capsule { half_height } if motion.y != 0.0 => {
+                Vec2::Y * *half_height * motion.y.signum()
+            }
+            _ => Vec2::ZERO,
+        }
+    }
+
+    /// The point in the middle of the shape closest to the line, and how far the shape reaches
+    /// out from it toward the line
+    ///
+    /// A line is touching the shape if it's within that reach of that point, which makes every
+    /// shape a circle as far as a single line goes
+    pub fn core_toward_line(
+        &self,
+        position: Vec2,
+        radius: f32,
+        start: Vec2,
+        end: Vec2,
+    ) -> (Vec2, f32) {
+        match self {
+            ColliderShape::Circle => (position, radius),
+            ColliderShape::Capsule { half_height } => {
+                let core_start = position - Vec2::Y * *half_height;
+                let core_end = position + Vec2::Y * *half_height;
+
+                (
+                    closest_point_to_segment(core_start, core_end, start, end),
+                    radius,
+                )
+            }
+            ColliderShape::Aabb { half_extents } => {
+                // How far the box reaches along the line's normal
+                let normal = (end - start).perp().normalize_or_zero();
+                let reach = normal.x.abs() * half_extents.x + normal.y.abs() * half_extents.y;
+
+                (position, reach)
+            }
+        }
     }
 }
 
+/// The point on the first segment closest to the second one
+fn closest_point_to_segment(a_start: Vec2, a_end: Vec2, b_start: Vec2, b_end: Vec2) -> Vec2 {
+    if let Some(intersection) = line_intersect(a_start, a_end, b_start, b_end) {
+        return intersection;
+    }
+
+    // Segments that don't cross are closest at one of their ends
+    let on_a = |point: Vec2| {
+        let a_vec = a_end - a_start;
+        if a_vec.length_squared() == 0.0 {
+            return a_start;
+        }
+        let t = ((point - a_start).dot(a_vec) / a_vec.length_squared()).clamp(0.0, 1.0);
+        a_start + a_vec * t
+    };
+    let distance_to_b = |point: Vec2| {
+        let b_vec = b_end - b_start;
+        if b_vec.length_squared() == 0.0 {
+            return (point - b_start).length_squared();
+        }
+        let t = ((point - b_start).dot(b_vec) / b_vec.length_squared()).clamp(0.0, 1.0);
+        (point - (b_start + b_vec * t)).length_squared()
+    };
+
+    [a_start, a_end, on_a(b_start), on_a(b_end)]
+        .into_iter()
+        .min_by(|x, y| distance_to_b(*x).total_cmp(&distance_to_b(*y)))
+        .unwrap()
+}
+
+/// A body touching a level line
+#[derive(Debug, Clone, Copy)]
+pub struct Contact {
+    pub polygon_index: usize,
+    pub line_index: usize,
+    /// Pointing out of the line toward the body
+    pub normal: Vec2,
+}
+
+/// Sent when a body starts touching a level line
+#[derive(Event, Debug, Clone, Copy)]
+pub struct CollisionStarted {
+    pub entity: Entity,
+    pub polygon_index: usize,
+    pub line_index: usize,
+    /// Pointing out of the line toward the body
+    pub normal: Vec2,
+    /// How fast the body was moving into the line when it hit (for hard landings and the like)
+    pub impact_speed: f32,
+}
+
+/// Sent when a body stops touching a level line
+#[derive(Event, Debug, Clone, Copy)]
+pub struct CollisionEnded {
+    pub entity: Entity,
+    pub polygon_index: usize,
+    pub line_index: usize,
+    /// The normal it was last touched along
+    pub normal: Vec2,
+}
+
+/// Collides every physics body with the level, agents and anything else alike
+#[allow(clippy::too_many_arguments)]
 pub fn s_collision(
-    mut entity_query: Query<(&mut Transform, &mut Physics, &mut PlatformerAI)>,
+    mut entity_query: Query<(
+        Entity,
+        &mut Transform,
+        &mut Physics,
+        Option<&mut PlatformerAI>,
+    )>,
     level: Res<Level>,
+    level_edge_index: Res<LevelEdgeIndex>,
     mut gizmos: Gizmos,
+    mut head_bump_events: EventWriter<HeadBump>,
+    mut collision_started_events: EventWriter<CollisionStarted>,
+    mut collision_ended_events: EventWriter<CollisionEnded>,
+    mut previous_contacts: Local<HashMap<Entity, Vec<Contact>>>,
+    mut event_log: ResMut<EventLog>,
 ) {
-    if let Ok((mut transform, mut physics, mut platformer_ai)) = entity_query.get_single_mut() {
-        let mut adjustment = Vec2::ZERO;
-        let mut new_normal = Vec2::ZERO;
+    let mut current_contacts: HashMap<Entity, Vec<Contact>> = HashMap::new();
+
+    for (entity, mut transform, mut physics, mut platformer_ai) in entity_query.iter_mut() {
+        let mut head_bumped = false;
+        // With how fast the body was moving into each line, before any of that is taken away
+        let mut contacts: Vec<(Contact, f32)> = Vec::new();
+
+        // Done dropping through a one-way platform once the agent is all the way under it
+        let dropping_through = platformer_ai
+            .as_ref()
+            .and_then(|platformer_ai| platformer_ai.dropping_through);
+        if let Some(polygon_index) = dropping_through {
+            let platform_bottom = level.polygons.get(polygon_index).map(|polygon| {
+                polygon
+                    .points
+                    .iter()
+                    .map(|point| point.y)
+                    .fold(f32::MAX, f32::min)
+            });
+
+            if platform_bottom.is_none_or(|platform_bottom| {
+                transform.translation.y + physics.shape.extents(physics.radius).y < platform_bottom
+            }) {
+                if let Some(platformer_ai) = platformer_ai.as_mut() {
+                    platformer_ai.dropping_through = None;
+                }
+            }
+        }
 
-        for polygon_index in 0..level.polygons.len() {
+        // Fast bodies are stopped where they first hit something on the way
+        let motion = transform.translation.xy() - physics.prev_position;
+        let inner_radius = physics.shape.inner_radius(physics.radius);
+        if motion.length() > inner_radius * SWEEP_MIN_MOTION_RATIO {
+            let rising = physics.velocity.y > 0.01;
+            let leading_offset = physics.shape.leading_offset(motion);
+            let swept_position = sweep_circle(
+                &level,
+                physics.prev_position + leading_offset,
+                motion,
+                inner_radius,
+                |polygon_index| {
+                    rising
+                        || platformer_ai.as_ref().is_some_and(|platformer_ai| {
+                            platformer_ai.dropping_through == Some(polygon_index)
+                        })
+                },
+            ) - leading_offset;
+
+            transform.translation.x = swept_position.x;
+            transform.translation.y = swept_position.y;
+        }
+
+        // (normal, depth) for every line the body's overlapping, solved together at the end
+        let mut penetrations: Vec<(Vec2, f32)> = Vec::new();
+        let mut new_normal = Vec2::ZERO;
+        let mut new_friction: Option<f32> = None;
+        let mut new_surface_velocity = Vec2::ZERO;
+        let mut new_restitution: f32 = 0.0;
+
+        // Only the lines near enough to touch are worth testing
+        let nearby_lines = level_edge_index.grid.lines_near(
+            &level,
+            transform.translation.xy(),
+            physics.shape.extents(physics.radius) + Vec2::splat(1.0),
+        );
+
+        for (polygon_index, line_indices) in nearby_lines {
             let polygon = level.polygons.get(polygon_index).unwrap();
 
-            let mut intersect_counter = 0;
             let mut colliding_with_polygon = false;
 
-            for line_index in 1..polygon.points.len() {
+            for line_index in line_indices.into_iter().map(|line_index| line_index + 1) {
                 let start = polygon.points[line_index - 1];
                 let end = polygon.points[line_index];
 
-                // Intersection detection
-                {
-                    let intersection = line_intersect(
-                        start,
-                        end,
-                        transform.translation.xy(),
-                        transform.translation.xy() + Vec2::new(2.0, 1.0) * 10000.0,
-                    );
+                // Broken lines are holes, and one-way platforms are only solid from above
+                if polygon.is_line_passable(line_index - 1) {
+                    continue;
+                }
 
-                    if intersection.is_some() {
-                        intersect_counter += 1;
-                    }
+                // Agents jump up through one-way floors, and drop through them on purpose
+                if polygon.is_one_way
+                    && (physics.velocity.y > 0.01
+                        || platformer_ai.as_ref().is_some_and(|platformer_ai| {
+                            platformer_ai.dropping_through == Some(polygon_index)
+                        }))
+                {
+                    continue;
                 }
 
                 let previous_side_of_line =
                     side_of_line_detection(start, end, physics.prev_position);
 
-                if previous_side_of_line != 1.0 {
+                // Bodies that were right on the line still count as outside it
+                if previous_side_of_line < 0.0 {
                     continue;
                 }
 
-                let (distance_sq, projection) =
-                    find_projection(start, end, transform.translation.xy(), physics.radius);
+                // Whatever the body's shape, the line is tested against the nearest point in
+                // its middle and how far the shape reaches from there
+                let (core, reach) = physics.shape.core_toward_line(
+                    transform.translation.xy(),
+                    physics.radius,
+                    start,
+                    end,
+                );
+
+                let (distance_sq, projection) = find_projection(start, end, core, reach);
 
-                let colliding_with_line = distance_sq <= physics.radius.powi(2);
+                let colliding_with_line = distance_sq <= reach.powi(2);
                 colliding_with_polygon = colliding_with_polygon || colliding_with_line;
 
-                let touch_radius = physics.radius + 0.5;
+                let touch_radius = reach + 0.5;
 
                 let touching_line = distance_sq <= touch_radius.powi(2);
 
                 if touching_line {
-                    let normal_dir = (transform.translation.xy() - projection).normalize_or_zero();
+                    let normal_dir = (core - projection).normalize_or_zero();
+
+                    contacts.push((
+                        Contact {
+                            polygon_index,
+                            line_index: line_index - 1,
+                            normal: normal_dir,
+                        },
+                        (-physics.velocity.dot(normal_dir)).max(0.0),
+                    ));
 
                     // If the line is not above the player
                     if normal_dir.y >= -0.01 {
                         // Add the normal dir to the players new normal
                         new_normal -= normal_dir;
 
+                        // Hitting two lines at once, the bouncier one wins
+                        new_restitution =
+                            new_restitution.max(polygon.line_restitution(line_index - 1));
+
                         // If the player is on a wall
                         if normal_dir.x.abs() >= 0.8 {
                             physics.walled = normal_dir.x.signum() as i8;
                             physics.has_wall_jumped = false;
+                            physics.has_double_jumped = false;
                             physics.grounded = false;
-                            platformer_ai.jump_from_pos = None;
-                            platformer_ai.jump_to_pos = None;
+                            if let Some(platformer_ai) = platformer_ai.as_mut() {
+                                clear_jump(platformer_ai);
+                            }
                         }
                         // If the player is on the ground
                         else if normal_dir.y > 0.01 {
+                            // Standing across two surfaces, the grippier one wins
+                            let friction = polygon.line_friction(line_index - 1);
+                            new_friction = Some(new_friction.map_or(friction, |f| f.max(friction)));
+
+                            let conveyor_speed = polygon.line_conveyor_speed(line_index - 1);
+                            if conveyor_speed != 0.0 {
+                                new_surface_velocity =
+                                    (end - start).normalize_or_zero() * conveyor_speed;
+                            }
+
                             physics.grounded = true;
                             physics.walled = 0;
                             physics.has_wall_jumped = false;
-                            platformer_ai.jump_from_pos = None;
-                            platformer_ai.jump_to_pos = None;
+                            physics.has_double_jumped = false;
+                            if let Some(platformer_ai) = platformer_ai.as_mut() {
+                                clear_jump(platformer_ai);
+                            }
                         }
                     }
                 }
 
                 if colliding_with_line {
-                    let mut delta = (transform.translation.xy() - projection).normalize_or_zero();
+                    let normal_dir = (core - projection).normalize_or_zero();
+
+                    // Hit a ceiling while moving up
+                    if normal_dir.y < -0.01 {
+                        if physics.velocity.y > 0.0 && !head_bumped {
+                            head_bumped = true;
+                            head_bump_events.send(HeadBump {
+                                entity,
+                                position: transform.translation.xy(),
+                                ceiling_normal: normal_dir,
+                            });
+                        }
 
-                    if delta.y < -0.01 {
-                        // println!("Hit ceiling");
-                        physics.velocity.y = 0.0;
+                        // Bouncy ceilings (or bodies) knock the body back down
+                        let restitution = physics
+                            .restitution
+                            .max(polygon.line_restitution(line_index - 1));
+                        physics.velocity.y = if physics.velocity.y > BOUNCE_MIN_SPEED {
+                            -physics.velocity.y * restitution
+                        } else {
+                            0.0
+                        };
                     }
 
-                    delta *= physics.radius - distance_sq.sqrt();
-
-                    if delta.x.abs() > adjustment.x.abs() {
-                        adjustment.x = delta.x;
-                    }
-                    if delta.y.abs() > adjustment.y.abs() {
-                        adjustment.y = delta.y;
-                    }
+                    penetrations.push((normal_dir, reach - distance_sq.sqrt()));
                 }
             }
 
-            let inside_polygon = if polygon.is_container {
+            if !colliding_with_polygon {
+                continue;
+            }
+
+            // Intersection detection, against the whole outline
+            let intersect_counter = polygon
+                .points
+                .windows(2)
+                .filter(|line| {
+                    line_intersect(
+                        line[0],
+                        line[1],
+                        transform.translation.xy(),
+                        transform.translation.xy() + Vec2::new(2.0, 1.0) * 10000.0,
+                    )
+                    .is_some()
+                })
+                .count();
+
+            // Being in one of a solid's holes isn't being in the solid
+            let inside_polygon = if polygon.is_hollow() {
                 intersect_counter % 2 == 0
             } else {
                 intersect_counter % 2 == 1
+                    && !level
+                        .holes_of(polygon_index)
+                        .any(|hole| point_in_polygon(&hole.points, transform.translation.xy()))
             };
 
-            if colliding_with_polygon && inside_polygon {
-                println!("Clipped");
+            // Once a polygon has been broken into, being inside it is fine, and agents pass
+            // through one-way platforms all the time
+            if inside_polygon && polygon.broken_lines.is_empty() && !polygon.is_one_way {
+                event_log.log(
+                    LogCategory::Collision,
+                    format!(
+                        "Entity {} clipped into polygon {}",
+                        entity.index(),
+                        polygon_index
+                    ),
+                );
                 transform.translation = physics.prev_position.extend(0.0);
             }
         }
 
+        // Lines touched this tick that weren't last tick were just hit
+        let was_touching = previous_contacts.remove(&entity).unwrap_or_default();
+        let same_line = |a: &Contact, b: &Contact| {
+            a.polygon_index == b.polygon_index && a.line_index == b.line_index
+        };
+
+        for (contact, impact_speed) in contacts.iter() {
+            if !was_touching
+                .iter()
+                .any(|previous| same_line(previous, contact))
+            {
+                collision_started_events.send(CollisionStarted {
+                    entity,
+                    polygon_index: contact.polygon_index,
+                    line_index: contact.line_index,
+                    normal: contact.normal,
+                    impact_speed: *impact_speed,
+                });
+            }
+        }
+
+        for previous in was_touching.iter() {
+            if !contacts
+                .iter()
+                .any(|(contact, _)| same_line(contact, previous))
+            {
+                collision_ended_events.send(CollisionEnded {
+                    entity,
+                    polygon_index: previous.polygon_index,
+                    line_index: previous.line_index,
+                    normal: previous.normal,
+                });
+            }
+        }
+
+        current_contacts.insert(
+            entity,
+            contacts.into_iter().map(|(contact, _)| contact).collect(),
+        );
+
         // Update the players normal
         new_normal = new_normal.normalize_or_zero();
         physics.normal = new_normal;
-
-        // Remove the players velocity in the direction of the normal
-        let velocity_adjustment = physics.velocity.dot(new_normal) * new_normal;
+        physics.friction = new_friction.unwrap_or(DEFAULT_FRICTION);
+        physics.surface_velocity = new_surface_velocity;
+
+        // Remove the players velocity in the direction of the normal, and bounce it back out
+        // if either it or what it hit is bouncy
+        let speed_into_normal = physics.velocity.dot(new_normal);
+        let restitution = physics.restitution.max(new_restitution);
+        let bounce = if speed_into_normal > BOUNCE_MIN_SPEED {
+            restitution
+        } else {
+            0.0
+        };
+
+        let velocity_adjustment = speed_into_normal * (1.0 + bounce) * new_normal;
         physics.velocity -= velocity_adjustment;
 
+        // Bounced off the ground isn't on it anymore
+        if bounce > 0.0 && new_normal.y < -0.01 {
+            physics.grounded = false;
+        }
+
+        // Wedged into a corner, the averaged normal can leave some velocity going into one of
+        // the lines, which would only push the body back in next tick
+        for _ in 0..CONTACT_SOLVER_ITERATIONS {
+            for (normal, _) in penetrations.iter() {
+                let speed_into_line = physics.velocity.dot(*normal);
+                if speed_into_line < 0.0 {
+                    physics.velocity -= speed_into_line * *normal;
+                }
+            }
+        }
+
         // Update the players position
-        transform.translation += adjustment.extend(0.0);
+        transform.translation += solve_penetrations(&penetrations).extend(0.0);
+    }
+
+    // Bodies that are gone took their contacts with them
+    *previous_contacts = current_contacts;
+}
+
+/// How far to move a body to get it out of everything it's overlapping at once, pushing it
+/// out along each (normal, depth) in turn until they all agree
+///
+/// A body wedged between lines ends up pushed out the way they all allow, instead of out
+/// along whichever push was biggest (which shoves it sideways out of corners)
+fn solve_penetrations(penetrations: &[(Vec2, f32)]) -> Vec2 {
+    let mut adjustment = Vec2::ZERO;
+
+    for _ in 0..CONTACT_SOLVER_ITERATIONS {
+        let mut resolved = true;
+
+        for (normal, depth) in penetrations.iter() {
+            let remaining_depth = depth - adjustment.dot(*normal);
+
+            if remaining_depth > CONTACT_SOLVER_TOLERANCE {
+                adjustment += *normal * remaining_depth;
+                resolved = false;
+            }
+        }
+
+        if resolved {
+            break;
+        }
+    }
+
+    adjustment
+}
+
+/// Moves a circle along the motion, stopping it where it first hits a solid line and sliding
+/// what's left of the motion along that line
+///
+/// One-way platforms the filter says to ignore are passed through, like in the overlap test
+fn sweep_circle(
+    level: &Level,
+    from: Vec2,
+    motion: Vec2,
+    radius: f32,
+    passing_through_one_way: impl Fn(usize) -> bool,
+) -> Vec2 {
+    let mut position = from;
+    let mut motion = motion;
+
+    for _ in 0..SWEEP_ITERATIONS {
+        let hit = level.circle_cast_filtered(
+            position,
+            motion,
+            motion.length(),
+            radius,
+            |polygon_index| {
+                level.polygons[polygon_index].is_one_way && passing_through_one_way(polygon_index)
+            },
+        );
+
+        let Some(hit) = hit else {
+            return position + motion;
+        };
+
+        let time = hit.distance / motion.length();
+        position += motion * time + hit.normal * SWEEP_SKIN;
+
+        let remaining_motion = motion * (1.0 - time);
+        motion = remaining_motion - hit.normal * remaining_motion.dot(hit.normal);
     }
+
+    position
+}
+
+/// Reindexes the level's lines whenever the level changes, or platforms come and go
+pub fn s_rebuild_level_edge_index(
+    level: Res<Level>,
+    platform_query: Query<&MovingPlatform>,
+    mut level_edge_index: ResMut<LevelEdgeIndex>,
+) {
+    // Moving platforms don't mark the level changed, so they're never indexed
+    let mut dynamic_polygons: Vec<usize> = platform_query
+        .iter()
+        .map(|platform| platform.polygon_index)
+        .collect();
+    dynamic_polygons.sort_unstable();
+
+    if !level.is_changed() && dynamic_polygons == *level_edge_index.grid.dynamic_polygons() {
+        return;
+    }
+
+    level_edge_index.grid = EdgeGrid::build(&level, dynamic_polygons);
+}
+
+pub fn s_body_collision_input(
+    keyboard_input: Res<ButtonInput<KeyCode>>,
+    mut body_collision_settings: ResMut<BodyCollisionSettings>,
+) {
+    // Shift + N to let bodies push the goal point around, or not
+    if keyboard_input.just_pressed(KeyCode::KeyN)
+        && keyboard_input.any_pressed([KeyCode::ShiftLeft, KeyCode::ShiftRight])
+    {
+        body_collision_settings.push_goal = !body_collision_settings.push_goal;
+        println!(
+            "Bodies push the goal: {}",
+            body_collision_settings.push_goal
+        );
+    }
+}
+
+/// Cycles the agents through the collider shapes, so they can all be tried on the same level
+pub fn s_collider_shape_input(
+    keyboard_input: Res<ButtonInput<KeyCode>>,
+    mut agent_query: Query<&mut Physics, With<PlatformerAI>>,
+) {
+    // L to switch the agents to the next collider shape
+    if !keyboard_input.just_pressed(KeyCode::KeyL) {
+        return;
+    }
+
+    for mut physics in agent_query.iter_mut() {
+        physics.shape = match physics.shape {
+            ColliderShape::Circle => ColliderShape::Capsule {
+                half_height: physics.radius,
+            },
+            ColliderShape::Capsule { .. } => ColliderShape::Aabb {
+                half_extents: Vec2::splat(physics.radius),
+            },
+            ColliderShape::Aabb { .. } => ColliderShape::Circle,
+        };
+
+        println!("Agent collider shape: {:?}", physics.shape);
+    }
+}
+
+/// Pushes overlapping bodies apart and trades their closing speed, so agents crowd around
+/// each other instead of stacking up in one spot
+///
+/// Runs before the level collision, which gets the final say on where everything ends up
+pub fn s_body_collision(
+    mut body_query: Query<(&mut Transform, &mut Physics), Without<GoalPoint>>,
+    mut goal_query: Query<&mut Transform, With<GoalPoint>>,
+    body_collision_settings: Res<BodyCollisionSettings>,
+) {
+    let bodies: Vec<(Vec2, f32)> = body_query
+        .iter()
+        .map(|(transform, physics)| (transform.translation.xy(), physics.radius))
+        .collect();
+
+    // The goal gets shoved without shoving back, then the level collision keeps it out of walls
+    if body_collision_settings.push_goal {
+        for mut goal_transform in goal_query.iter_mut() {
+            let mut goal_position = goal_transform.translation.xy();
+
+            for (position, radius) in bodies.iter() {
+                let offset = goal_position - *position;
+                let overlap = radius + GOAL_POINT_RADIUS - offset.length();
+
+                if overlap > 0.0 {
+                    goal_position += offset.try_normalize().unwrap_or(Vec2::Y) * overlap;
+                }
+            }
+
+            goal_transform.translation.x = goal_position.x;
+            goal_transform.translation.y = goal_position.y;
+        }
+    }
+
+    if bodies.len() < 2 {
+        return;
+    }
+
+    // Broad phase, so each body only gets checked against the ones close enough to touch it
+    let broad_phase = KdTree::build(bodies.iter().map(|(position, _)| *position).enumerate());
+    let max_radius = bodies.iter().map(|(_, radius)| *radius).fold(0.0, f32::max);
+
+    let mut adjustments = vec![Vec2::ZERO; bodies.len()];
+    let mut impulses = vec![Vec2::ZERO; bodies.len()];
+
+    let velocities: Vec<Vec2> = body_query
+        .iter()
+        .map(|(_, physics)| physics.velocity)
+        .collect();
+
+    for (body_index, (position, radius)) in bodies.iter().enumerate() {
+        for other_index in broad_phase.within_radius(*position, radius + max_radius) {
+            // Each pair once
+            if other_index <= body_index {
+                continue;
+            }
+
+            let (other_position, other_radius) = bodies[other_index];
+            let offset = other_position - *position;
+            let overlap = radius + other_radius - offset.length();
+
+            if overlap <= 0.0 {
+                continue;
+            }
+
+            // Bodies right on top of each other get split sideways
+            let normal = offset.try_normalize().unwrap_or(Vec2::X);
+
+            adjustments[body_index] -= normal * overlap / 2.0;
+            adjustments[other_index] += normal * overlap / 2.0;
+
+            // Bodies weigh the same, so each takes half of the impulse
+            let closing_speed = (velocities[body_index] - velocities[other_index]).dot(normal);
+            if closing_speed > 0.0 {
+                let impulse = normal * closing_speed * (1.0 + BODY_RESTITUTION) / 2.0;
+
+                impulses[body_index] -= impulse;
+                impulses[other_index] += impulse;
+            }
+        }
+    }
+
+    for (body_index, (mut transform, mut physics)) in body_query.iter_mut().enumerate() {
+        transform.translation += adjustments[body_index].extend(0.0);
+        physics.velocity += impulses[body_index];
+    }
+}
+
+/// Whatever jump the agent was making is over once it lands on a wall or the ground
+fn clear_jump(platformer_ai: &mut PlatformerAI) {
+    platformer_ai.jump_from_pos = None;
+    platformer_ai.jump_to_pos = None;
+    platformer_ai.landing_node = None;
 }
 
 pub fn find_projection(start: Vec2, end: Vec2, point: Vec2, radius: f32) -> (f32, Vec2) {
     let point_vec = point - start;
     let line_vec = end - start;
 
-    let line_vec_normalized = line_vec.normalize();
+    // Zero length lines project everything onto their one point
+    let line_vec_normalized = line_vec.normalize_or_zero();
 
     let dot = point_vec.dot(line_vec_normalized);
 
     let projection_point = line_vec_normalized * dot + start;
 
-    // If the projection point is outside the line past start
-    if (end - projection_point).length_squared() > (end - start).length_squared() {
+    // If the projection point is outside the line past start (measuring from the end instead
+    // would also catch points far enough past the end)
+    if dot < 0.0 {
         return (point_vec.length_squared() + radius * 2.0, start);
     }
     // If the projection point is outside the line past end
@@ -0,0 +1,246 @@
+use bevy::{
+    app::{App, FixedUpdate, Plugin, Update},
+    ecs::{
+        component::Component,
+        entity::Entity,
+        event::EventWriter,
+        query::With,
+        reflect::ReflectComponent,
+        schedule::IntoSystemConfigs,
+        system::{Commands, Query, ResMut},
+    },
+    gizmos::gizmos::Gizmos,
+    math::{Vec2, Vec3Swizzles},
+    reflect::Reflect,
+    render::color::Color,
+    transform::components::Transform,
+};
+use serde::Deserialize;
+
+use crate::{
+    ai::{
+        navmesh::NavMesh,
+        pathfinding::{Pathfinding, PathsInvalidated},
+        platformer_ai::PlatformerAI,
+    },
+    collisions::s_collision,
+    event_log::{EventLog, LogCategory},
+    level::{orient_polygon_points, Level, Polygon},
+    Physics, PursueAISet,
+};
+
+const CRUMBLING_PLATFORMS_DATA: &[u8] = include_bytes!("../assets/crumbling_platforms.json");
+
+/// How long (in ticks) a platform holds once something stands on it, unless it says otherwise
+const DEFAULT_CRUMBLE_DELAY_TICKS: u32 = 45;
+
+pub struct CrumblingPlatformsPlugin;
+
+impl Plugin for CrumblingPlatformsPlugin {
+    fn build(&self, app: &mut App) {
+        app.register_type::<CrumblingPlatform>()
+            .add_systems(
+                FixedUpdate,
+                s_crumble_platforms
+                    .in_set(PursueAISet::Collision)
+                    .after(s_collision),
+            )
+            .add_systems(
+                Update,
+                s_render_crumbling_platforms.in_set(PursueAISet::Render),
+            );
+    }
+}
+
+/// A crumbling platform in `assets/crumbling_platforms.json`, placed in the built-in level
+#[derive(Debug, Clone, Deserialize)]
+pub struct CrumblingPlatformData {
+    pub min: [f32; 2],
+    pub max: [f32; 2],
+    #[serde(default)]
+    pub delay_ticks: Option<u32>,
+}
+
+/// A level polygon that collapses a moment after something stands on it, for good
+#[derive(Component, Debug, Reflect)]
+#[reflect(Component)]
+pub struct CrumblingPlatform {
+    pub polygon_index: usize,
+    pub points: Vec<Vec2>,
+    pub delay_ticks: u32,
+    /// Ticks since something first stood on it, if anything has yet
+    pub crumbling_ticks: Option<u32>,
+    pub collapsed: bool,
+}
+
+impl CrumblingPlatform {
+    /// Whether something standing with its middle at the position is on top of the platform
+    fn is_stood_on(&self, position: Vec2, radius: f32) -> bool {
+        let (min, max) = self
+            .points
+            .iter()
+            .fold((Vec2::MAX, Vec2::MIN), |(min, max), point| {
+                (min.min(*point), max.max(*point))
+            });
+
+        position.x >= min.x - radius / 2.0
+            && position.x <= max.x + radius / 2.0
+            && (position.y - radius - max.y).abs() <= 2.0
+    }
+}
+
+/// Adds the crumbling platforms to the level, solid until they're stood on
+pub fn spawn_crumbling_platforms(commands: &mut Commands, level: &mut Level) {
+    let res = std::str::from_utf8(CRUMBLING_PLATFORMS_DATA);
+    let crumbling_platforms: Vec<CrumblingPlatformData> =
+        serde_json::from_str(res.unwrap()).unwrap();
+
+    for crumbling_platform in crumbling_platforms {
+        let (min, max) = (
+            Vec2::from(crumbling_platform.min),
+            Vec2::from(crumbling_platform.max),
+        );
+
+        let mut points = vec![
+            min,
+            Vec2::new(max.x, min.y),
+            max,
+            Vec2::new(min.x, max.y),
+            min,
+        ];
+        orient_polygon_points(&mut points, false);
+
+        let polygon_index = level.polygons.len();
+        level.polygons.push(Polygon {
+            points: points.clone(),
+            color: Color::rgb(0.82, 0.71, 0.55),
+            is_container: false,
+            no_node_lines: Vec::new(),
+            node_spacing: None,
+            destructible_lines: Vec::new(),
+            broken_lines: Vec::new(),
+            is_one_way: false,
+            line_frictions: Vec::new(),
+            line_conveyor_speeds: Vec::new(),
+            line_restitutions: Vec::new(),
+            hole_in: None,
+        });
+
+        commands.spawn(CrumblingPlatform {
+            polygon_index,
+            points,
+            delay_ticks: crumbling_platform
+                .delay_ticks
+                .unwrap_or(DEFAULT_CRUMBLE_DELAY_TICKS),
+            crumbling_ticks: None,
+            collapsed: false,
+        });
+    }
+}
+
+/// Takes the platform out of the level and the graph, returning the nodes that went with it
+fn collapse_platform(
+    level: &mut Level,
+    pathfinding: &mut Pathfinding,
+    platform: &CrumblingPlatform,
+) -> Vec<usize> {
+    let polygon = &mut level.polygons[platform.polygon_index];
+    polygon.broken_lines = (0..polygon.points.len() - 1).collect();
+
+    let node_ids: Vec<usize> = pathfinding
+        .nodes
+        .iter()
+        .filter(|node| node.polygon_index == platform.polygon_index)
+        .map(|node| node.id)
+        .collect();
+
+    // Disabling the nodes takes every connection to and from them out too
+    for node_id in node_ids.iter() {
+        pathfinding.disable_node(*node_id);
+    }
+
+    node_ids
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn s_crumble_platforms(
+    mut commands: Commands,
+    mut platform_query: Query<(Entity, &mut CrumblingPlatform)>,
+    agent_query: Query<(&Transform, &Physics), With<PlatformerAI>>,
+    mut level: ResMut<Level>,
+    mut pathfinding: ResMut<Pathfinding>,
+    mut navmesh: ResMut<NavMesh>,
+    mut paths_invalidated_events: EventWriter<PathsInvalidated>,
+    mut event_log: ResMut<EventLog>,
+) {
+    let mut collapsed_node_ids = Vec::new();
+
+    for (platform_entity, mut platform) in platform_query.iter_mut() {
+        if platform.collapsed {
+            continue;
+        }
+
+        // The level was swapped out from under the platform
+        let in_level = level
+            .polygons
+            .get(platform.polygon_index)
+            .is_some_and(|polygon| polygon.points == platform.points);
+        if !in_level {
+            commands.entity(platform_entity).despawn();
+            continue;
+        }
+
+        match platform.crumbling_ticks {
+            Some(ticks) if ticks >= platform.delay_ticks => {
+                collapsed_node_ids.extend(collapse_platform(
+                    &mut level,
+                    &mut pathfinding,
+                    &platform,
+                ));
+                platform.collapsed = true;
+
+                event_log.log(
+                    LogCategory::Collision,
+                    format!("Platform {} collapsed", platform.polygon_index),
+                );
+            }
+            Some(ticks) => platform.crumbling_ticks = Some(ticks + 1),
+            None => {
+                let stood_on = agent_query.iter().any(|(transform, physics)| {
+                    physics.grounded
+                        && platform.is_stood_on(transform.translation.xy(), physics.radius)
+                });
+
+                if stood_on {
+                    platform.crumbling_ticks = Some(0);
+                }
+            }
+        }
+    }
+
+    if collapsed_node_ids.is_empty() {
+        return;
+    }
+
+    *navmesh = NavMesh::build(&level, &pathfinding);
+
+    paths_invalidated_events.send(PathsInvalidated {
+        node_ids: collapsed_node_ids,
+    });
+}
+
+pub fn s_render_crumbling_platforms(mut gizmos: Gizmos, platform_query: Query<&CrumblingPlatform>) {
+    // Crumbling platforms shake until they fall
+    for platform in platform_query.iter() {
+        let Some(ticks) = platform.crumbling_ticks.filter(|_| !platform.collapsed) else {
+            continue;
+        };
+
+        let shake = Vec2::new(if ticks % 4 < 2 { 1.0 } else { -1.0 }, 0.0);
+
+        gizmos.linestrip_2d(
+            platform.points.iter().map(|point| *point + shake),
+            Color::ORANGE,
+        );
+    }
+}
@@ -0,0 +1,204 @@
+use std::fmt::Write;
+
+use bevy::{
+    app::{App, FixedUpdate, Plugin, Update},
+    diagnostic::{
+        Diagnostic, DiagnosticPath, Diagnostics, DiagnosticsStore, FrameTimeDiagnosticsPlugin,
+        RegisterDiagnostic,
+    },
+    ecs::{
+        component::Component,
+        entity::Entity,
+        query::With,
+        reflect::ReflectComponent,
+        schedule::IntoSystemConfigs,
+        system::{Commands, Query, Res, ResMut, Resource},
+    },
+    input::{keyboard::KeyCode, ButtonInput},
+    reflect::Reflect,
+    render::color::Color,
+    text::{Text, TextStyle},
+    ui::{node_bundles::TextBundle, PositionType, Style, Val},
+    utils::{default, Instant},
+};
+
+use crate::{
+    ai::{pathfinding::Pathfinding, platformer_ai::PlatformerAI},
+    collisions::{s_body_collision, s_collision},
+    PursueAISet,
+};
+
+/// How long the agents' path searches took in total, per tick
+pub const PATH_SEARCH_TIME: DiagnosticPath = DiagnosticPath::const_new("ai/path_search_time");
+
+/// How long the body and level collision took, per tick
+pub const COLLISION_TIME: DiagnosticPath = DiagnosticPath::const_new("ai/collision_time");
+
+pub const GRAPH_NODES: DiagnosticPath = DiagnosticPath::const_new("ai/graph_nodes");
+
+pub const GRAPH_CONNECTIONS: DiagnosticPath = DiagnosticPath::const_new("ai/graph_connections");
+
+pub struct AIDiagnosticsPlugin;
+
+impl Plugin for AIDiagnosticsPlugin {
+    fn build(&self, app: &mut App) {
+        if !app.is_plugin_added::<FrameTimeDiagnosticsPlugin>() {
+            app.add_plugins(FrameTimeDiagnosticsPlugin);
+        }
+
+        app.register_type::<DiagnosticsOverlayText>()
+            .register_diagnostic(Diagnostic::new(PATH_SEARCH_TIME).with_suffix("ms"))
+            .register_diagnostic(Diagnostic::new(COLLISION_TIME).with_suffix("ms"))
+            .register_diagnostic(Diagnostic::new(GRAPH_NODES))
+            .register_diagnostic(Diagnostic::new(GRAPH_CONNECTIONS))
+            .insert_resource(CollisionTimer::default())
+            .insert_resource(DiagnosticsOverlay::default())
+            .add_systems(
+                FixedUpdate,
+                (
+                    s_measure_path_searches.after(PursueAISet::Planning),
+                    s_start_collision_timer
+                        .in_set(PursueAISet::Collision)
+                        .before(s_body_collision),
+                    s_stop_collision_timer
+                        .in_set(PursueAISet::Collision)
+                        .after(s_collision),
+                ),
+            )
+            .add_systems(
+                Update,
+                (
+                    s_measure_graph_size,
+                    s_diagnostics_overlay_input,
+                    s_render_diagnostics_overlay.in_set(PursueAISet::Render),
+                ),
+            );
+    }
+}
+
+/// When the collision systems started this tick
+#[derive(Resource, Default)]
+pub struct CollisionTimer {
+    pub start_time: Option<Instant>,
+}
+
+/// Whether the frame time and AI diagnostics are shown in the corner of the screen
+#[derive(Resource, Default)]
+pub struct DiagnosticsOverlay {
+    pub visible: bool,
+}
+
+#[derive(Component, Reflect)]
+#[reflect(Component)]
+pub struct DiagnosticsOverlayText;
+
+pub fn s_measure_path_searches(
+    mut diagnostics: Diagnostics,
+    platformer_ai_query: Query<&PlatformerAI>,
+) {
+    diagnostics.add_measurement(&PATH_SEARCH_TIME, || {
+        platformer_ai_query
+            .iter()
+            // The counter's only back at zero on the tick a new path was searched
+            .filter(|platformer_ai| platformer_ai.ticks_since_replan == 0)
+            .map(|platformer_ai| platformer_ai.last_search_stats.duration.as_secs_f64() * 1000.0)
+            .sum()
+    });
+}
+
+pub fn s_start_collision_timer(mut collision_timer: ResMut<CollisionTimer>) {
+    collision_timer.start_time = Some(Instant::now());
+}
+
+pub fn s_stop_collision_timer(
+    mut diagnostics: Diagnostics,
+    mut collision_timer: ResMut<CollisionTimer>,
+) {
+    if let Some(start_time) = collision_timer.start_time.take() {
+        diagnostics.add_measurement(&COLLISION_TIME, || {
+            start_time.elapsed().as_secs_f64() * 1000.0
+        });
+    }
+}
+
+pub fn s_measure_graph_size(mut diagnostics: Diagnostics, pathfinding: Res<Pathfinding>) {
+    diagnostics.add_measurement(&GRAPH_NODES, || pathfinding.nodes.len() as f64);
+    diagnostics.add_measurement(&GRAPH_CONNECTIONS, || {
+        pathfinding
+            .nodes
+            .iter()
+            .map(|node| node.connections.len())
+            .sum::<usize>() as f64
+    });
+}
+
+pub fn s_diagnostics_overlay_input(
+    keyboard_input: Res<ButtonInput<KeyCode>>,
+    mut diagnostics_overlay: ResMut<DiagnosticsOverlay>,
+) {
+    // F3 to show or hide the frame time and AI diagnostics
+    if keyboard_input.just_pressed(KeyCode::F3) {
+        diagnostics_overlay.visible = !diagnostics_overlay.visible;
+    }
+}
+
+pub fn s_render_diagnostics_overlay(
+    mut commands: Commands,
+    diagnostics_overlay: Res<DiagnosticsOverlay>,
+    diagnostics_store: Res<DiagnosticsStore>,
+    mut text_query: Query<(Entity, &mut Text), With<DiagnosticsOverlayText>>,
+) {
+    if !diagnostics_overlay.visible {
+        for (entity, _) in text_query.iter() {
+            commands.entity(entity).despawn();
+        }
+        return;
+    }
+
+    let mut overlay = String::new();
+    for (name, path) in [
+        ("FPS", &FrameTimeDiagnosticsPlugin::FPS),
+        ("Frame time", &FrameTimeDiagnosticsPlugin::FRAME_TIME),
+        ("Path search", &PATH_SEARCH_TIME),
+        ("Collision", &COLLISION_TIME),
+        ("Graph nodes", &GRAPH_NODES),
+        ("Graph connections", &GRAPH_CONNECTIONS),
+    ] {
+        let Some(diagnostic) = diagnostics_store.get(path) else {
+            continue;
+        };
+
+        let _ = writeln!(
+            overlay,
+            "{}: {:.2}{}",
+            name,
+            diagnostic.smoothed().unwrap_or(0.0),
+            diagnostic.suffix
+        );
+    }
+
+    if let Ok((_, mut text)) = text_query.get_single_mut() {
+        if text.sections[0].value != overlay {
+            text.sections[0].value = overlay;
+        }
+        return;
+    }
+
+    commands.spawn((
+        TextBundle::from_section(
+            overlay,
+            TextStyle {
+                font_size: 14.0,
+                color: Color::WHITE,
+                ..default()
+            },
+        )
+        .with_style(Style {
+            position_type: PositionType::Absolute,
+            top: Val::Px(8.0),
+            left: Val::Px(8.0),
+            ..default()
+        }),
+        DiagnosticsOverlayText,
+    ));
+}
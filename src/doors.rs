@@ -0,0 +1,342 @@
+use bevy::{
+    app::{App, Plugin, Startup, Update},
+    ecs::{
+        change_detection::DetectChanges,
+        component::Component,
+        entity::Entity,
+        query::With,
+        reflect::ReflectComponent,
+        schedule::IntoSystemConfigs,
+        system::{Commands, Query, Res, ResMut},
+    },
+    gizmos::gizmos::Gizmos,
+    math::{Vec2, Vec3Swizzles},
+    reflect::Reflect,
+    render::color::Color,
+    transform::components::Transform,
+};
+use serde::Deserialize;
+
+#[cfg(feature = "editor")]
+use crate::editor::s_editor_input;
+use crate::{
+    ai::{
+        navmesh::{s_rebuild_navmesh, NavMesh},
+        pathfinding::{
+            s_pathfinding_settings_input, s_rebuild_pathfinding_graph, Pathfinding,
+            PathfindingSettings,
+        },
+        platformer_ai::PlatformerAI,
+    },
+    level::{orient_polygon_points, Level, Polygon},
+    level_asset::s_apply_level_asset,
+    s_init, Physics, PursueAISet,
+};
+
+const DOORS_DATA: &[u8] = include_bytes!("../assets/doors.json");
+
+/// How close something has to get to a switch to trigger it
+const SWITCH_RADIUS: f32 = 8.0;
+
+pub struct DoorsPlugin;
+
+impl Plugin for DoorsPlugin {
+    fn build(&self, app: &mut App) {
+        let open_doors_for_rebuild = s_open_doors_for_rebuild
+            .after(s_pathfinding_settings_input)
+            .after(s_apply_level_asset)
+            .before(s_rebuild_pathfinding_graph);
+
+        // The editor changes the level too, so the doors have to be open before it's rebuilt
+        #[cfg(feature = "editor")]
+        let open_doors_for_rebuild = open_doors_for_rebuild.after(s_editor_input);
+
+        app.register_type::<Door>()
+            .register_type::<Switch>()
+            .add_systems(Startup, s_setup_doors.after(s_init))
+            .add_systems(
+                Update,
+                (
+                    open_doors_for_rebuild,
+                    s_setup_doors_after_rebuild
+                        .after(s_rebuild_pathfinding_graph)
+                        .before(s_rebuild_navmesh),
+                    s_trigger_switches,
+                    s_render_doors.in_set(PursueAISet::Render),
+                ),
+            );
+    }
+}
+
+/// A door in `assets/doors.json`, placed in the built-in level along with its switch
+#[derive(Debug, Clone, Deserialize)]
+pub struct DoorData {
+    pub min: [f32; 2],
+    pub max: [f32; 2],
+    pub switch: [f32; 2],
+    #[serde(default)]
+    pub open: bool,
+}
+
+/// A level polygon that's solid while closed, cutting off the graph edges through it
+///
+/// The graph is always built with the door open, so the edges through it exist to be disabled
+#[derive(Component, Debug, Reflect)]
+#[reflect(Component)]
+pub struct Door {
+    pub polygon_index: usize,
+    pub points: Vec<Vec2>,
+    pub open: bool,
+    /// The nodes an agent couldn't stand on with the door closed
+    pub covered_node_ids: Vec<usize>,
+    /// The connections running through the door
+    pub cut_connections: Vec<(usize, usize)>,
+}
+
+/// Opens and closes its door whenever an agent or the goal steps onto it
+#[derive(Component, Debug, Reflect)]
+#[reflect(Component)]
+pub struct Switch {
+    pub door: Entity,
+    pub position: Vec2,
+    /// Whether something was on the switch last frame, so standing on it only triggers it once
+    pub pressed: bool,
+}
+
+/// Adds the doors to the level, open, so the graph built for it runs through them
+pub fn spawn_doors(commands: &mut Commands, level: &mut Level) {
+    let res = std::str::from_utf8(DOORS_DATA);
+    let doors: Vec<DoorData> = serde_json::from_str(res.unwrap()).unwrap();
+
+    for door in doors {
+        let (min, max) = (Vec2::from(door.min), Vec2::from(door.max));
+
+        let mut points = vec![
+            min,
+            Vec2::new(max.x, min.y),
+            max,
+            Vec2::new(min.x, max.y),
+            min,
+        ];
+        orient_polygon_points(&mut points, false);
+
+        let polygon_index = level.polygons.len();
+        level.polygons.push(Polygon {
+            points: points.clone(),
+            color: Color::MAROON,
+            is_container: false,
+            no_node_lines: Vec::new(),
+            node_spacing: None,
+            destructible_lines: Vec::new(),
+            broken_lines: (0..points.len() - 1).collect(),
+            is_one_way: false,
+            line_frictions: Vec::new(),
+            line_conveyor_speeds: Vec::new(),
+            line_restitutions: Vec::new(),
+            hole_in: None,
+        });
+
+        let door_entity = commands
+            .spawn(Door {
+                polygon_index,
+                points,
+                open: door.open,
+                covered_node_ids: Vec::new(),
+                cut_connections: Vec::new(),
+            })
+            .id();
+
+        commands.spawn(Switch {
+            door: door_entity,
+            position: Vec2::from(door.switch),
+            pressed: false,
+        });
+    }
+}
+
+/// Whether the door's polygon is still the one it was spawned as
+fn door_in_level(level: &Level, door: &Door) -> bool {
+    level
+        .polygons
+        .get(door.polygon_index)
+        .is_some_and(|polygon| polygon.points == door.points)
+}
+
+/// An open door is a polygon with every line broken, so nothing collides with it
+fn set_door_lines_open(level: &mut Level, door: &Door, open: bool) {
+    let polygon = &mut level.polygons[door.polygon_index];
+
+    polygon.broken_lines = if open {
+        (0..polygon.points.len() - 1).collect()
+    } else {
+        Vec::new()
+    };
+}
+
+/// Works out what the door cuts off, from a graph built with it open
+fn setup_door(pathfinding: &Pathfinding, door: &mut Door) {
+    door.covered_node_ids = pathfinding.nodes_covered_by(&door.points);
+    door.cut_connections = pathfinding.connections_cut_by(&door.points, false);
+}
+
+/// Opens or closes the door, enabling or disabling the graph edges through it to match
+fn apply_door_state(level: &mut Level, pathfinding: &mut Pathfinding, door: &Door) {
+    set_door_lines_open(level, door, door.open);
+
+    for node_id in door.covered_node_ids.iter() {
+        if door.open {
+            pathfinding.enable_node(*node_id);
+        } else {
+            pathfinding.disable_node(*node_id);
+        }
+    }
+
+    for (from_node_id, to_node_id) in door.cut_connections.iter() {
+        if door.open {
+            pathfinding.enable_connection(*from_node_id, *to_node_id);
+        } else {
+            pathfinding.disable_connection(*from_node_id, *to_node_id);
+        }
+    }
+}
+
+pub fn s_setup_doors(
+    mut door_query: Query<&mut Door>,
+    mut level: ResMut<Level>,
+    mut pathfinding: ResMut<Pathfinding>,
+    mut navmesh: ResMut<NavMesh>,
+) {
+    if door_query.is_empty() {
+        return;
+    }
+
+    for mut door in door_query.iter_mut() {
+        setup_door(&pathfinding, &mut door);
+        apply_door_state(&mut level, &mut pathfinding, &door);
+    }
+
+    // The navmesh was built with every door open
+    *navmesh = NavMesh::build(&level, &pathfinding);
+}
+
+/// Opens every door before the graph is rebuilt, so it's built running through them
+pub fn s_open_doors_for_rebuild(
+    settings: Res<PathfindingSettings>,
+    door_query: Query<&Door>,
+    mut level: ResMut<Level>,
+) {
+    if !settings.is_changed() || settings.is_added() {
+        return;
+    }
+
+    for door in door_query.iter() {
+        if door_in_level(&level, door) {
+            set_door_lines_open(&mut level, door, true);
+        }
+    }
+}
+
+/// Closes the doors that were closed again once the graph is rebuilt, dropping doors the
+/// level lost
+pub fn s_setup_doors_after_rebuild(
+    mut commands: Commands,
+    settings: Res<PathfindingSettings>,
+    mut door_query: Query<(Entity, &mut Door)>,
+    switch_query: Query<(Entity, &Switch)>,
+    mut level: ResMut<Level>,
+    mut pathfinding: ResMut<Pathfinding>,
+) {
+    if !settings.is_changed() || settings.is_added() {
+        return;
+    }
+
+    for (door_entity, mut door) in door_query.iter_mut() {
+        if !door_in_level(&level, &door) {
+            println!(
+                "Polygon {} isn't the door anymore, removing it",
+                door.polygon_index
+            );
+
+            commands.entity(door_entity).despawn();
+            for (switch_entity, switch) in switch_query.iter() {
+                if switch.door == door_entity {
+                    commands.entity(switch_entity).despawn();
+                }
+            }
+
+            continue;
+        }
+
+        setup_door(&pathfinding, &mut door);
+        apply_door_state(&mut level, &mut pathfinding, &door);
+    }
+}
+
+pub fn s_trigger_switches(
+    mut switch_query: Query<&mut Switch>,
+    mut door_query: Query<&mut Door>,
+    agent_query: Query<(&Transform, &Physics), With<PlatformerAI>>,
+    mut level: ResMut<Level>,
+    mut pathfinding: ResMut<Pathfinding>,
+    mut navmesh: ResMut<NavMesh>,
+) {
+    let mut doors_changed = false;
+
+    for mut switch in switch_query.iter_mut() {
+        let agent_on_switch = agent_query.iter().any(|(transform, physics)| {
+            (transform.translation.xy() - switch.position).length()
+                <= physics.radius + SWITCH_RADIUS
+        });
+        let goal_on_switch = pathfinding.active
+            && (pathfinding.goal_position - switch.position).length() <= SWITCH_RADIUS;
+
+        let pressed = agent_on_switch || goal_on_switch;
+        let just_pressed = pressed && !switch.pressed;
+        switch.pressed = pressed;
+
+        if !just_pressed {
+            continue;
+        }
+
+        let Ok(mut door) = door_query.get_mut(switch.door) else {
+            continue;
+        };
+
+        door.open = !door.open;
+        apply_door_state(&mut level, &mut pathfinding, &door);
+        doors_changed = true;
+
+        println!(
+            "Door {} {}",
+            door.polygon_index,
+            if door.open { "opened" } else { "closed" }
+        );
+    }
+
+    // Agents replan on their own, since the doors bumped the graph's revision
+    if doors_changed {
+        *navmesh = NavMesh::build(&level, &pathfinding);
+    }
+}
+
+pub fn s_render_doors(mut gizmos: Gizmos, door_query: Query<&Door>, switch_query: Query<&Switch>) {
+    // Closed doors are drawn with the rest of the level
+    for door in door_query.iter().filter(|door| door.open) {
+        gizmos.linestrip_2d(door.points.iter().cloned(), Color::MAROON.with_a(0.3));
+    }
+
+    for switch in switch_query.iter() {
+        let door_open = door_query.get(switch.door).is_ok_and(|door| door.open);
+
+        gizmos.rect_2d(
+            switch.position,
+            0.0,
+            Vec2::splat(SWITCH_RADIUS),
+            if door_open {
+                Color::GREEN
+            } else {
+                Color::MAROON
+            },
+        );
+    }
+}
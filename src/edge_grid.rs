@@ -0,0 +1,113 @@
+use std::collections::{BTreeMap, HashMap};
+
+use bevy::math::Vec2;
+
+use crate::level::Level;
+
+/// How big the grid's cells are, about the size of a few agents
+const EDGE_GRID_CELL_SIZE: f32 = 64.0;
+
+/// A spatial hash of the level's lines, so a body only has to be tested against the lines
+/// near it instead of every line in the level
+///
+/// Polygons that move without the level being marked changed (moving platforms) are kept out
+/// of the grid and handed out with every query instead
+#[derive(Default)]
+pub struct EdgeGrid {
+    /// (polygon index, line index by start point) for the lines crossing each cell
+    cells: HashMap<(i32, i32), Vec<(usize, usize)>>,
+    dynamic_polygons: Vec<usize>,
+}
+
+impl EdgeGrid {
+    pub fn build(level: &Level, dynamic_polygons: Vec<usize>) -> EdgeGrid {
+        let mut cells: HashMap<(i32, i32), Vec<(usize, usize)>> = HashMap::new();
+
+        for (polygon_index, polygon) in level.polygons.iter().enumerate() {
+            if dynamic_polygons.contains(&polygon_index) {
+                continue;
+            }
+
+            for (line_index, line) in polygon.points.windows(2).enumerate() {
+                let (min_cell, max_cell) =
+                    (cell_of(line[0].min(line[1])), cell_of(line[0].max(line[1])));
+
+                for x in min_cell.0..=max_cell.0 {
+                    for y in min_cell.1..=max_cell.1 {
+                        cells
+                            .entry((x, y))
+                            .or_default()
+                            .push((polygon_index, line_index));
+                    }
+                }
+            }
+        }
+
+        EdgeGrid {
+            cells,
+            dynamic_polygons,
+        }
+    }
+
+    pub fn dynamic_polygons(&self) -> &Vec<usize> {
+        &self.dynamic_polygons
+    }
+
+    /// The lines that could be within the half size of the position, grouped by polygon and
+    /// in order, with every line of the dynamic polygons thrown in
+    pub fn lines_near(
+        &self,
+        level: &Level,
+        position: Vec2,
+        half_size: Vec2,
+    ) -> BTreeMap<usize, Vec<usize>> {
+        let mut lines: BTreeMap<usize, Vec<usize>> = BTreeMap::new();
+
+        let (min_cell, max_cell) = (cell_of(position - half_size), cell_of(position + half_size));
+
+        for x in min_cell.0..=max_cell.0 {
+            for y in min_cell.1..=max_cell.1 {
+                let Some(cell) = self.cells.get(&(x, y)) else {
+                    continue;
+                };
+
+                for (polygon_index, line_index) in cell.iter() {
+                    lines.entry(*polygon_index).or_default().push(*line_index);
+                }
+            }
+        }
+
+        for polygon_index in self.dynamic_polygons.iter() {
+            if let Some(polygon) = level.polygons.get(*polygon_index) {
+                lines
+                    .entry(*polygon_index)
+                    .or_default()
+                    .extend(0..polygon.points.len().saturating_sub(1));
+            }
+        }
+
+        // Lines crossing more than one cell turn up more than once, and a stale grid could
+        // hand out lines the level doesn't have anymore
+        lines.retain(|polygon_index, line_indices| {
+            let line_count = level
+                .polygons
+                .get(*polygon_index)
+                .map_or(0, |polygon| polygon.points.len().saturating_sub(1));
+
+            line_indices.retain(|line_index| *line_index < line_count);
+            line_indices.sort_unstable();
+            line_indices.dedup();
+
+            !line_indices.is_empty()
+        });
+
+        lines
+    }
+}
+
+fn cell_of(position: Vec2) -> (i32, i32) {
+    (
+        (position.x / EDGE_GRID_CELL_SIZE).floor() as i32,
+        (position.y / EDGE_GRID_CELL_SIZE).floor() as i32,
+    )
+}
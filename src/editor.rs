@@ -0,0 +1,410 @@
+use bevy::{
+    app::{App, Plugin, Update},
+    ecs::{
+        change_detection::DetectChangesMut,
+        query::With,
+        reflect::ReflectResource,
+        schedule::IntoSystemConfigs,
+        system::{Query, Res, ResMut, Resource},
+    },
+    gizmos::gizmos::Gizmos,
+    input::{keyboard::KeyCode, mouse::MouseButton, ButtonInput},
+    math::Vec2,
+    reflect::Reflect,
+    render::{camera::Camera, color::Color},
+    transform::components::GlobalTransform,
+    window::{PrimaryWindow, Window},
+};
+
+use crate::{
+    ai::pathfinding::{s_rebuild_pathfinding_graph, PathfindingSettings},
+    level::{orient_polygon, orient_polygon_points, Level, Polygon, SpawnPoint, SpawnPointKind},
+    level_asset::save_level_asset,
+    polygon_ops::{apply_shape_to_polygons, merge_overlapping_polygons, BooleanOp},
+    simulation::Simulation,
+    utils::cursor_world_position,
+    MainCamera, PursueAISet,
+};
+
+/// How close the cursor has to be to a vertex or spawn point to grab it
+const EDITOR_GRAB_RADIUS: f32 = 8.0;
+
+/// Placed and dragged points snap to a grid this fine
+const EDITOR_SNAP_SIZE: f32 = 8.0;
+
+/// The level file the editor saves to when the level didn't come from one (relative to `assets/`)
+const EDITOR_LEVEL_FILE: &str = "levels/editor.level.json";
+
+pub struct EditorPlugin;
+
+impl Plugin for EditorPlugin {
+    fn build(&self, app: &mut App) {
+        app.register_type::<Editor>()
+            .insert_resource(Editor::default())
+            .add_systems(
+                Update,
+                (
+                    s_editor_input.before(s_rebuild_pathfinding_graph),
+                    s_render_editor.in_set(PursueAISet::Render),
+                ),
+            );
+    }
+}
+
+#[derive(Resource, Default, Reflect)]
+#[reflect(Resource)]
+pub struct Editor {
+    pub active: bool,
+    /// The (polygon index, point index) of the vertex being dragged
+    pub dragging: Option<(usize, usize)>,
+    /// The points of the polygon being drawn, which isn't part of the level until it's closed
+    pub drawing: Vec<Vec2>,
+    /// Whether the level has changed since the editor was opened
+    pub level_edited: bool,
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn s_editor_input(
+    keyboard_input: Res<ButtonInput<KeyCode>>,
+    mouse_buttons: Res<ButtonInput<MouseButton>>,
+    q_windows: Query<&Window, With<PrimaryWindow>>,
+    camera_query: Query<(&Camera, &GlobalTransform), With<MainCamera>>,
+    simulation: Res<Simulation>,
+    mut editor: ResMut<Editor>,
+    mut level: ResMut<Level>,
+    mut pathfinding_settings: ResMut<PathfindingSettings>,
+) {
+    // Tab to open and close the editor
+    if keyboard_input.just_pressed(KeyCode::Tab) {
+        editor.active = !editor.active;
+
+        if editor.active {
+            editor.level_edited = false;
+            println!("Editor opened");
+        } else {
+            editor.dragging = None;
+            editor.drawing.clear();
+
+            // Rebuilding the graph (and everything that hangs off it) happens on a settings change
+            if editor.level_edited {
+                pathfinding_settings.set_changed();
+            }
+
+            println!("Editor closed");
+        }
+
+        return;
+    }
+
+    if !editor.active {
+        return;
+    }
+
+    // S to save the level
+    if keyboard_input.just_pressed(KeyCode::KeyS) {
+        let level_file = simulation
+            .level_file
+            .as_deref()
+            .unwrap_or(EDITOR_LEVEL_FILE);
+        save_level_asset(&level, &format!("assets/{}", level_file));
+    }
+
+    // Backspace to take back the last point of the polygon being drawn
+    if keyboard_input.just_pressed(KeyCode::Backspace) {
+        editor.drawing.pop();
+    }
+
+    // Enter to close the polygon being drawn, merging it into the solids it overlaps, or
+    // Shift+Enter to cut it out of them and Ctrl+Enter to cut them down to it instead
+    if keyboard_input.just_pressed(KeyCode::Enter) {
+        let shift = keyboard_input.pressed(KeyCode::ShiftLeft);
+        let ctrl = keyboard_input.pressed(KeyCode::ControlLeft);
+
+        if editor.drawing.len() < 3 {
+            println!("A polygon needs at least 3 points");
+        } else if shift || ctrl {
+            let mut points = std::mem::take(&mut editor.drawing);
+            points.push(points[0]);
+
+            let op = if shift {
+                BooleanOp::Difference
+            } else {
+                BooleanOp::Intersection
+            };
+            apply_shape_to_polygons(&mut level, &points, op);
+            editor.level_edited = true;
+        } else {
+            let mut points = std::mem::take(&mut editor.drawing);
+            points.push(points[0]);
+            orient_polygon_points(&mut points, false);
+
+            level.polygons.push(Polygon {
+                points,
+                color: Color::WHITE,
+                is_container: false,
+                no_node_lines: Vec::new(),
+                node_spacing: None,
+                destructible_lines: Vec::new(),
+                broken_lines: Vec::new(),
+                is_one_way: false,
+                line_frictions: Vec::new(),
+                line_conveyor_speeds: Vec::new(),
+                line_restitutions: Vec::new(),
+                hole_in: None,
+            });
+            merge_overlapping_polygons(&mut level);
+            editor.level_edited = true;
+        }
+    }
+
+    let (Ok(window), Ok((camera, camera_transform))) =
+        (q_windows.get_single(), camera_query.get_single())
+    else {
+        return;
+    };
+
+    let Some(mouse_pos_world) = cursor_world_position(window, camera, camera_transform) else {
+        return;
+    };
+
+    let snapped_pos = (mouse_pos_world / EDITOR_SNAP_SIZE).round() * EDITOR_SNAP_SIZE;
+    let hovered_vertex = find_vertex(&level, mouse_pos_world);
+
+    // Left click to grab a vertex, or to add a point to the polygon being drawn
+    if mouse_buttons.just_pressed(MouseButton::Left) {
+        match hovered_vertex {
+            Some(vertex) if editor.drawing.is_empty() => editor.dragging = Some(vertex),
+            // Clicking a vertex while drawing joins the new polygon up with it exactly
+            Some((polygon_index, point_index)) => editor
+                .drawing
+                .push(level.polygons[polygon_index].points[point_index]),
+            None => editor.drawing.push(snapped_pos),
+        }
+    }
+
+    if let Some((polygon_index, point_index)) = editor.dragging {
+        if mouse_buttons.pressed(MouseButton::Left) {
+            if level.polygons[polygon_index].points[point_index] != snapped_pos {
+                move_vertex(&mut level.polygons[polygon_index], point_index, snapped_pos);
+                editor.level_edited = true;
+            }
+        } else {
+            // Dragging a vertex across the polygon can turn it inside out
+            orient_polygon(&mut level.polygons[polygon_index]);
+            editor.dragging = None;
+        }
+    }
+
+    // Delete to remove the vertex under the cursor
+    if keyboard_input.just_pressed(KeyCode::Delete) && editor.dragging.is_none() {
+        if let Some((polygon_index, point_index)) = hovered_vertex {
+            remove_vertex(&mut level, polygon_index, point_index);
+            editor.level_edited = true;
+        }
+    }
+
+    // K to make the polygon under the cursor a container, or a solid again
+    if keyboard_input.just_pressed(KeyCode::KeyK) && editor.dragging.is_none() {
+        if let Some((polygon_index, _)) = hovered_vertex {
+            let polygon = &mut level.polygons[polygon_index];
+
+            if polygon.hole_in.is_some() {
+                println!("Holes can't be containers");
+            } else {
+                polygon.is_container = !polygon.is_container;
+                orient_polygon(polygon);
+                editor.level_edited = true;
+
+                println!(
+                    "Polygon {} is {}",
+                    polygon_index,
+                    if polygon.is_container {
+                        "a container"
+                    } else {
+                        "solid"
+                    }
+                );
+            }
+        }
+    }
+
+    // O to make the polygon under the cursor a one-way platform, or a solid again
+    if keyboard_input.just_pressed(KeyCode::KeyO) && editor.dragging.is_none() {
+        if let Some((polygon_index, _)) = hovered_vertex {
+            let polygon = &mut level.polygons[polygon_index];
+
+            if polygon.is_hollow() {
+                println!("Containers and holes can't be one-way platforms");
+            } else {
+                polygon.is_one_way = !polygon.is_one_way;
+                editor.level_edited = true;
+
+                println!(
+                    "Polygon {} is {}",
+                    polygon_index,
+                    if polygon.is_one_way {
+                        "a one-way platform"
+                    } else {
+                        "solid"
+                    }
+                );
+            }
+        }
+    }
+
+    // P to place an agent spawn point at the cursor, H a home position, and Shift + P to
+    // remove the spawn point under it
+    if keyboard_input.just_pressed(KeyCode::KeyP) {
+        let shift_held = keyboard_input.pressed(KeyCode::ShiftLeft)
+            || keyboard_input.pressed(KeyCode::ShiftRight);
+
+        if shift_held {
+            let spawn_point_index = level.spawn_points.iter().position(|spawn_point| {
+                (spawn_point.position - mouse_pos_world).length_squared()
+                    < EDITOR_GRAB_RADIUS.powi(2)
+            });
+
+            if let Some(spawn_point_index) = spawn_point_index {
+                level.spawn_points.remove(spawn_point_index);
+            }
+        } else {
+            add_spawn_point(&mut level, SpawnPointKind::Agent, snapped_pos);
+        }
+    }
+
+    if keyboard_input.just_pressed(KeyCode::KeyH) {
+        add_spawn_point(&mut level, SpawnPointKind::Home, snapped_pos);
+    }
+}
+
+/// Adds a spawn point, named after its kind and how many of that kind there already are
+fn add_spawn_point(level: &mut Level, kind: SpawnPointKind, position: Vec2) {
+    let kind_name = match kind {
+        SpawnPointKind::Agent => "agent",
+        SpawnPointKind::GoalStart => "goal",
+        SpawnPointKind::Home => "home",
+    };
+    let kind_count = level
+        .spawn_points
+        .iter()
+        .filter(|spawn_point| spawn_point.kind == kind)
+        .count();
+
+    level.spawn_points.push(SpawnPoint {
+        name: format!("{} {}", kind_name, kind_count + 1),
+        kind,
+        position,
+    });
+}
+
+/// The (polygon index, point index) of the vertex closest to the position, if one is in reach
+///
+/// The closing point of a polygon is never returned, since it's the same vertex as the first one
+fn find_vertex(level: &Level, position: Vec2) -> Option<(usize, usize)> {
+    level
+        .polygons
+        .iter()
+        .enumerate()
+        .flat_map(|(polygon_index, polygon)| {
+            let closed =
+                polygon.points.len() > 1 && polygon.points.first() == polygon.points.last();
+            let vertex_count = polygon.points.len() - closed as usize;
+
+            polygon.points[..vertex_count]
+                .iter()
+                .enumerate()
+                .map(move |(point_index, point)| (polygon_index, point_index, *point))
+        })
+        .map(|(polygon_index, point_index, point)| {
+            (
+                polygon_index,
+                point_index,
+                (point - position).length_squared(),
+            )
+        })
+        .filter(|(_, _, dist_sq)| *dist_sq < EDITOR_GRAB_RADIUS.powi(2))
+        .min_by(|(_, _, a), (_, _, b)| a.total_cmp(b))
+        .map(|(polygon_index, point_index, _)| (polygon_index, point_index))
+}
+
+fn move_vertex(polygon: &mut Polygon, point_index: usize, position: Vec2) {
+    let closed = polygon.points.first() == polygon.points.last();
+
+    polygon.points[point_index] = position;
+
+    if closed && point_index == 0 {
+        *polygon.points.last_mut().unwrap() = position;
+    }
+}
+
+/// Removes the vertex, along with its polygon if there's no longer enough of it left
+fn remove_vertex(level: &mut Level, polygon_index: usize, point_index: usize) {
+    let polygon = &mut level.polygons[polygon_index];
+
+    let closed = polygon.points.first() == polygon.points.last();
+    if closed {
+        polygon.points.pop();
+    }
+
+    polygon.points.remove(point_index);
+
+    if polygon.points.len() < 3 {
+        level.remove_polygon(polygon_index);
+        return;
+    }
+
+    if closed {
+        polygon.points.push(polygon.points[0]);
+    }
+
+    // The lines around the vertex were merged, so the old line indices don't line up anymore
+    polygon.no_node_lines.clear();
+    polygon.destructible_lines.clear();
+    polygon.broken_lines.clear();
+    polygon.line_frictions.clear();
+    polygon.line_conveyor_speeds.clear();
+    polygon.line_restitutions.clear();
+
+    orient_polygon(polygon);
+}
+
+pub fn s_render_editor(mut gizmos: Gizmos, editor: Res<Editor>, level: Res<Level>) {
+    if !editor.active {
+        return;
+    }
+
+    // Draw the vertices, with containers in a different color
+    for (polygon_index, polygon) in level.polygons.iter().enumerate() {
+        for (point_index, point) in polygon.points.iter().enumerate() {
+            let color = if editor.dragging == Some((polygon_index, point_index)) {
+                Color::YELLOW
+            } else if polygon.is_hollow() {
+                Color::CYAN
+            } else {
+                Color::WHITE
+            };
+
+            gizmos.circle_2d(*point, 3.0, color);
+        }
+    }
+
+    // Draw the polygon being drawn
+    if !editor.drawing.is_empty() {
+        gizmos.linestrip_2d(editor.drawing.iter().cloned(), Color::ORANGE);
+
+        for point in editor.drawing.iter() {
+            gizmos.circle_2d(*point, 3.0, Color::ORANGE);
+        }
+    }
+
+    // Draw the spawn points, colored by what they're for
+    for spawn_point in level.spawn_points.iter() {
+        let color = match spawn_point.kind {
+            SpawnPointKind::Agent => Color::GREEN,
+            SpawnPointKind::GoalStart => Color::YELLOW,
+            SpawnPointKind::Home => Color::BLUE,
+        };
+
+        gizmos.rect_2d(spawn_point.position, 0.0, Vec2::splat(12.0), color);
+    }
+}
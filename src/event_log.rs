@@ -0,0 +1,353 @@
+use std::{collections::VecDeque, fmt::Write};
+
+use bevy::{
+    app::{App, FixedUpdate, Plugin, Startup, Update},
+    ecs::{
+        component::Component,
+        entity::Entity,
+        event::EventReader,
+        query::{With, Without},
+        reflect::ReflectComponent,
+        schedule::IntoSystemConfigs,
+        system::{Commands, Local, Query, Res, ResMut, Resource},
+    },
+    input::{keyboard::KeyCode, ButtonInput},
+    math::Vec3Swizzles,
+    reflect::Reflect,
+    render::color::Color,
+    text::{Text, TextStyle},
+    transform::components::Transform,
+    ui::{node_bundles::TextBundle, PositionType, Style, Val},
+    utils::{default, HashMap},
+};
+
+use crate::{
+    ai::platformer_ai::{PlatformerAI, PlatformerAIState},
+    collisions::{s_collision, CollisionStarted},
+    level::Level,
+    s_init,
+    simulation::{Simulation, SIMULATION_TICK_RATE_HZ},
+    GoalPoint, PursueAISet,
+};
+
+/// How many entries the log keeps before dropping the oldest
+const EVENT_LOG_CAPACITY: usize = 500;
+
+/// How many of the latest entries the console shows
+const EVENT_LOG_CONSOLE_LINES: usize = 20;
+
+pub struct EventLogPlugin;
+
+impl Plugin for EventLogPlugin {
+    fn build(&self, app: &mut App) {
+        app.register_type::<EventLogText>()
+            .insert_resource(EventLog::default())
+            .add_systems(Startup, s_init_event_log.after(s_init))
+            .add_systems(
+                FixedUpdate,
+                (
+                    s_tick_event_log.before(PursueAISet::Perception),
+                    (s_log_agent_events, s_log_collisions)
+                        .in_set(PursueAISet::Collision)
+                        .after(s_collision),
+                ),
+            )
+            .add_systems(
+                Update,
+                (
+                    s_event_log_input,
+                    s_render_event_log.in_set(PursueAISet::Render),
+                )
+                    .chain(),
+            );
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum LogCategory {
+    State,
+    Replan,
+    Jump,
+    Collision,
+    Detection,
+}
+
+impl LogCategory {
+    /// Every category, in the order the number keys toggle them
+    pub const ALL: [LogCategory; 5] = [
+        LogCategory::State,
+        LogCategory::Replan,
+        LogCategory::Jump,
+        LogCategory::Collision,
+        LogCategory::Detection,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            LogCategory::State => "state",
+            LogCategory::Replan => "replan",
+            LogCategory::Jump => "jump",
+            LogCategory::Collision => "collision",
+            LogCategory::Detection => "detection",
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct LogEntry {
+    pub tick: u32,
+    pub category: LogCategory,
+    pub message: String,
+}
+
+/// What the agents have been up to, shown in a console toggled with F1
+///
+/// Headless runs have no console, so everything logged is printed instead
+#[derive(Resource)]
+pub struct EventLog {
+    pub tick: u32,
+    pub entries: VecDeque<LogEntry>,
+    /// The categories the console shows, which the number keys toggle
+    pub shown: [bool; LogCategory::ALL.len()],
+    pub visible: bool,
+    /// Whether entries in the shown categories are printed as they're logged
+    pub echo: bool,
+}
+
+impl Default for EventLog {
+    fn default() -> Self {
+        EventLog {
+            tick: 0,
+            entries: VecDeque::new(),
+            // Collisions happen all the time, so they're only shown when asked for
+            shown: LogCategory::ALL.map(|category| category != LogCategory::Collision),
+            visible: false,
+            echo: false,
+        }
+    }
+}
+
+impl EventLog {
+    pub fn log(&mut self, category: LogCategory, message: impl Into<String>) {
+        let entry = LogEntry {
+            tick: self.tick,
+            category,
+            message: message.into(),
+        };
+
+        if self.echo && self.is_shown(category) {
+            println!("{}", format_entry(&entry));
+        }
+
+        if self.entries.len() >= EVENT_LOG_CAPACITY {
+            self.entries.pop_front();
+        }
+        self.entries.push_back(entry);
+    }
+
+    pub fn is_shown(&self, category: LogCategory) -> bool {
+        LogCategory::ALL
+            .iter()
+            .position(|other| *other == category)
+            .is_some_and(|index| self.shown[index])
+    }
+}
+
+#[derive(Component, Reflect)]
+#[reflect(Component)]
+pub struct EventLogText;
+
+fn format_entry(entry: &LogEntry) -> String {
+    format!(
+        "[{:>8.2}s] {:<9} {}",
+        entry.tick as f64 / SIMULATION_TICK_RATE_HZ,
+        entry.category.name(),
+        entry.message
+    )
+}
+
+pub fn s_init_event_log(simulation: Res<Simulation>, mut event_log: ResMut<EventLog>) {
+    event_log.echo = simulation.headless;
+}
+
+pub fn s_tick_event_log(mut event_log: ResMut<EventLog>) {
+    event_log.tick += 1;
+}
+
+/// Logs agents changing state, replanning and spotting or losing the goal, by comparing each
+/// agent with how it was last tick
+pub fn s_log_agent_events(
+    mut event_log: ResMut<EventLog>,
+    level: Res<Level>,
+    agent_query: Query<(Entity, &Transform, &PlatformerAI), Without<GoalPoint>>,
+    goal_query: Query<&Transform, With<GoalPoint>>,
+    mut last_seen: Local<HashMap<Entity, (PlatformerAIState, bool)>>,
+) {
+    let goal_position = goal_query
+        .get_single()
+        .ok()
+        .map(|transform| transform.translation.xy());
+
+    for (entity, transform, platformer_ai) in agent_query.iter() {
+        let position = transform.translation.xy();
+        let sees_goal = goal_position
+            .is_some_and(|goal_position| level.line_of_sight_check(position, goal_position));
+
+        let (last_state, saw_goal) = *last_seen
+            .entry(entity)
+            .or_insert((platformer_ai.state, sees_goal));
+
+        if platformer_ai.state != last_state {
+            event_log.log(
+                LogCategory::State,
+                format!(
+                    "Agent {} {:?} -> {:?}",
+                    entity.index(),
+                    last_state,
+                    platformer_ai.state
+                ),
+            );
+        }
+
+        if sees_goal != saw_goal {
+            event_log.log(
+                LogCategory::Detection,
+                format!(
+                    "Agent {} {} the goal",
+                    entity.index(),
+                    if sees_goal {
+                        "spotted"
+                    } else {
+                        "lost sight of"
+                    }
+                ),
+            );
+        }
+
+        // The counter's only back at zero on the tick a new path was searched
+        if platformer_ai.ticks_since_replan == 0 {
+            event_log.log(
+                LogCategory::Replan,
+                format!(
+                    "Agent {} replanned, {} nodes ({} expanded)",
+                    entity.index(),
+                    platformer_ai.path.len(),
+                    platformer_ai.last_search_stats.nodes_expanded
+                ),
+            );
+        }
+
+        last_seen.insert(entity, (platformer_ai.state, sees_goal));
+    }
+}
+
+pub fn s_log_collisions(
+    mut event_log: ResMut<EventLog>,
+    mut collision_started_events: EventReader<CollisionStarted>,
+) {
+    for collision in collision_started_events.read() {
+        event_log.log(
+            LogCategory::Collision,
+            format!(
+                "Entity {} hit line {} of polygon {} at {:.1}",
+                collision.entity.index(),
+                collision.line_index,
+                collision.polygon_index,
+                collision.impact_speed
+            ),
+        );
+    }
+}
+
+pub fn s_event_log_input(
+    keyboard_input: Res<ButtonInput<KeyCode>>,
+    mut event_log: ResMut<EventLog>,
+) {
+    // F1 to show or hide the event log
+    if keyboard_input.just_pressed(KeyCode::F1) {
+        event_log.visible = !event_log.visible;
+    }
+
+    if !event_log.visible {
+        return;
+    }
+
+    // 1 to 5 to show or hide each category while the log's open
+    for (index, key) in [
+        KeyCode::Digit1,
+        KeyCode::Digit2,
+        KeyCode::Digit3,
+        KeyCode::Digit4,
+        KeyCode::Digit5,
+    ]
+    .into_iter()
+    .enumerate()
+    {
+        if keyboard_input.just_pressed(key) {
+            event_log.shown[index] = !event_log.shown[index];
+        }
+    }
+}
+
+pub fn s_render_event_log(
+    mut commands: Commands,
+    event_log: Res<EventLog>,
+    mut text_query: Query<(Entity, &mut Text), With<EventLogText>>,
+) {
+    if !event_log.visible {
+        for (entity, _) in text_query.iter() {
+            commands.entity(entity).despawn();
+        }
+        return;
+    }
+
+    let mut console = String::new();
+    for (index, category) in LogCategory::ALL.iter().enumerate() {
+        let _ = write!(
+            console,
+            "{}:{}{} ",
+            index + 1,
+            if event_log.shown[index] { "" } else { "-" },
+            category.name()
+        );
+    }
+
+    let mut lines: Vec<String> = event_log
+        .entries
+        .iter()
+        .rev()
+        .filter(|entry| event_log.is_shown(entry.category))
+        .take(EVENT_LOG_CONSOLE_LINES)
+        .map(format_entry)
+        .collect();
+    lines.reverse();
+
+    for line in lines {
+        let _ = write!(console, "\n{}", line);
+    }
+
+    if let Ok((_, mut text)) = text_query.get_single_mut() {
+        if text.sections[0].value != console {
+            text.sections[0].value = console;
+        }
+        return;
+    }
+
+    commands.spawn((
+        TextBundle::from_section(
+            console,
+            TextStyle {
+                font_size: 12.0,
+                color: Color::WHITE,
+                ..default()
+            },
+        )
+        .with_style(Style {
+            position_type: PositionType::Absolute,
+            bottom: Val::Px(8.0),
+            left: Val::Px(8.0),
+            ..default()
+        }),
+        EventLogText,
+    ));
+}
@@ -0,0 +1,229 @@
+use bevy::{
+    app::{App, FixedUpdate, Plugin, Startup, Update},
+    ecs::{
+        event::EventWriter,
+        query::With,
+        schedule::IntoSystemConfigs,
+        system::{Query, Res, ResMut, Resource},
+    },
+    input::{keyboard::KeyCode, ButtonInput},
+    math::{Vec2, Vec3Swizzles},
+    transform::components::Transform,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    ai::pathfinding::{GoalTeleported, Pathfinding},
+    s_init,
+    simulation::Simulation,
+    utils::vec2_serde,
+    GoalPoint, PursueAISet,
+};
+
+pub struct GoalScriptPlugin;
+
+impl Plugin for GoalScriptPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(GoalScript::default())
+            .add_systems(Startup, s_load_goal_script.after(s_init))
+            .add_systems(Update, s_goal_script_input)
+            .add_systems(FixedUpdate, s_run_goal_script.in_set(PursueAISet::Decision));
+    }
+}
+
+/// One step of a goal script, each lasting a number of ticks except teleports
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum GoalCommand {
+    /// Head to the position in a straight line, getting there on the last tick
+    Waypoint {
+        #[serde(with = "vec2_serde")]
+        position: Vec2,
+        ticks: u32,
+    },
+    /// Move at the velocity (per tick)
+    Velocity {
+        #[serde(with = "vec2_serde")]
+        velocity: Vec2,
+        ticks: u32,
+    },
+    /// Stand still
+    Wait { ticks: u32 },
+    /// Jump straight to the position, taking no time
+    Teleport {
+        #[serde(with = "vec2_serde")]
+        position: Vec2,
+    },
+}
+
+/// A goal script as it's written in a RON file, like
+///
+/// `(looping: true, commands: [Waypoint(position: (64.0, 0.0), ticks: 120), Wait(ticks: 60)])`
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct GoalScriptFile {
+    #[serde(default)]
+    pub looping: bool,
+    pub commands: Vec<GoalCommand>,
+}
+
+/// Moves the goal point on its own, so the agents can be run against the same evasion over
+/// and over instead of whatever the arrow keys did that time
+///
+/// The goal still collides with the level, so a script can't take it through walls
+#[derive(Resource, Default)]
+pub struct GoalScript {
+    pub script: GoalScriptFile,
+    pub running: bool,
+    pub command_index: usize,
+    /// How many ticks the current command has been going for
+    pub command_tick: u32,
+    /// Where the goal was when the current waypoint started
+    pub leg_start: Vec2,
+    /// How far the script moves the goal this tick, if it's running
+    pub velocity: Option<Vec2>,
+}
+
+impl GoalScript {
+    /// Starts the script again from its first command
+    pub fn restart(&mut self) {
+        self.running = !self.script.commands.is_empty();
+        self.command_index = 0;
+        self.command_tick = 0;
+        self.velocity = None;
+    }
+
+    /// Moves on to the next command, going back to the start if the script loops
+    fn advance(&mut self) {
+        self.command_index += 1;
+        self.command_tick = 0;
+
+        if self.command_index >= self.script.commands.len() {
+            if self.script.looping {
+                self.command_index = 0;
+            } else {
+                self.running = false;
+            }
+        }
+    }
+}
+
+pub fn load_goal_script(path: &str) -> Result<GoalScriptFile, String> {
+    let goal_script_ron = std::fs::read_to_string(path).map_err(|error| error.to_string())?;
+    ron::from_str(&goal_script_ron).map_err(|error| error.to_string())
+}
+
+/// Loads the script given with `--goal-script`, or the scenario's, and sets it going
+pub fn s_load_goal_script(simulation: Res<Simulation>, mut goal_script: ResMut<GoalScript>) {
+    let Some(goal_script_path) = simulation.goal_script.as_deref().or_else(|| {
+        simulation
+            .scenario
+            .as_ref()
+            .and_then(|scenario| scenario.goal_script.as_deref())
+    }) else {
+        return;
+    };
+
+    match load_goal_script(goal_script_path) {
+        Ok(script) => {
+            println!(
+                "Running goal script {} ({} commands)",
+                goal_script_path,
+                script.commands.len()
+            );
+            goal_script.script = script;
+            goal_script.restart();
+        }
+        Err(error) => println!("Couldn't load goal script {} ({})", goal_script_path, error),
+    }
+}
+
+pub fn s_goal_script_input(
+    keyboard_input: Res<ButtonInput<KeyCode>>,
+    mut goal_script: ResMut<GoalScript>,
+) {
+    let shift = keyboard_input.any_pressed([KeyCode::ShiftLeft, KeyCode::ShiftRight]);
+
+    // Z to run the goal script from the start, or to stop it and take the goal back
+    if shift
+        || !keyboard_input.just_pressed(KeyCode::KeyZ)
+        || goal_script.script.commands.is_empty()
+    {
+        return;
+    }
+
+    if goal_script.running {
+        goal_script.running = false;
+        goal_script.velocity = None;
+    } else {
+        goal_script.restart();
+    }
+
+    println!("Goal script running: {}", goal_script.running);
+}
+
+pub fn s_run_goal_script(
+    mut goal_script: ResMut<GoalScript>,
+    mut pathfinding: ResMut<Pathfinding>,
+    mut goal_teleported_events: EventWriter<GoalTeleported>,
+    goal_query: Query<&Transform, With<GoalPoint>>,
+) {
+    if !goal_script.running {
+        goal_script.velocity = None;
+        return;
+    }
+
+    let Ok(goal_transform) = goal_query.get_single() else {
+        return;
+    };
+    let goal_position = goal_transform.translation.xy();
+
+    // Teleports take no time, so a run of them all happens on the same tick
+    while let Some(GoalCommand::Teleport { position }) = goal_script
+        .script
+        .commands
+        .get(goal_script.command_index)
+        .cloned()
+    {
+        goal_teleported_events.send(pathfinding.teleport_goal(position));
+        goal_script.advance();
+
+        if !goal_script.running || goal_script.command_index == 0 {
+            goal_script.velocity = None;
+            return;
+        }
+    }
+
+    let Some(command) = goal_script
+        .script
+        .commands
+        .get(goal_script.command_index)
+        .cloned()
+    else {
+        return;
+    };
+
+    if goal_script.command_tick == 0 {
+        goal_script.leg_start = goal_position;
+    }
+    goal_script.command_tick += 1;
+
+    let (velocity, ticks) = match command {
+        GoalCommand::Waypoint { position, ticks } => {
+            // Aimed at where the goal should be by now, which also makes up for anything
+            // that held it back on the way
+            let t = (goal_script.command_tick as f32 / ticks.max(1) as f32).min(1.0);
+            (
+                goal_script.leg_start.lerp(position, t) - goal_position,
+                ticks,
+            )
+        }
+        GoalCommand::Velocity { velocity, ticks } => (velocity, ticks),
+        GoalCommand::Wait { ticks } => (Vec2::ZERO, ticks),
+        GoalCommand::Teleport { .. } => (Vec2::ZERO, 0),
+    };
+
+    goal_script.velocity = Some(velocity);
+
+    if goal_script.command_tick >= ticks {
+        goal_script.advance();
+    }
+}
@@ -0,0 +1,214 @@
+use std::collections::HashMap;
+
+use bevy::{
+    app::{App, FixedUpdate, Plugin, Startup, Update},
+    ecs::{
+        change_detection::DetectChanges,
+        component::Component,
+        entity::Entity,
+        reflect::ReflectComponent,
+        schedule::IntoSystemConfigs,
+        system::{Query, Res, ResMut},
+    },
+    gizmos::gizmos::Gizmos,
+    math::{Vec2, Vec3Swizzles},
+    reflect::Reflect,
+    render::color::Color,
+    transform::components::Transform,
+};
+
+use crate::{
+    ai::{
+        pathfinding::{s_rebuild_pathfinding_graph, Pathfinding, PathfindingSettings},
+        platformer_ai::{PlatformerAI, PLATFORMER_AI_AGENT_RADIUS},
+    },
+    collisions::s_collision,
+    event_log::{EventLog, LogCategory},
+    level::{HazardKind, Level},
+    s_init, Physics, PursueAISet,
+};
+
+/// How much health agents start with
+const AGENT_MAX_HEALTH: f32 = 100.0;
+
+pub struct HazardsPlugin;
+
+impl Plugin for HazardsPlugin {
+    fn build(&self, app: &mut App) {
+        app.register_type::<Health>()
+            .add_systems(Startup, s_apply_hazard_costs.after(s_init))
+            .add_systems(
+                Update,
+                (
+                    s_reapply_hazard_costs.after(s_rebuild_pathfinding_graph),
+                    s_render_hazards.in_set(PursueAISet::Render),
+                ),
+            )
+            .add_systems(
+                FixedUpdate,
+                s_hazard_damage
+                    .in_set(PursueAISet::Collision)
+                    .after(s_collision),
+            );
+    }
+}
+
+#[derive(Component, Debug, Reflect)]
+#[reflect(Component)]
+pub struct Health {
+    pub current: f32,
+    pub max: f32,
+}
+
+impl Default for Health {
+    fn default() -> Self {
+        Health {
+            current: AGENT_MAX_HEALTH,
+            max: AGENT_MAX_HEALTH,
+        }
+    }
+}
+
+/// Makes every graph edge through a hazard cost its multiplier more, taking the worst
+/// hazard where they overlap
+///
+/// An edge goes through a hazard if its arc or line crosses the hazard's outline, or if an
+/// agent standing at either end of it would be touching the hazard
+pub fn apply_hazard_costs(level: &Level, pathfinding: &mut Pathfinding) {
+    let radius = PLATFORMER_AI_AGENT_RADIUS;
+    let mut connection_scales: HashMap<(usize, usize), f32> = HashMap::new();
+
+    for hazard in level.hazards.iter() {
+        let graph: &Pathfinding = pathfinding;
+        let standing_in_hazard = |node_id: usize| {
+            let node = &graph.nodes[node_id];
+            hazard.touches(node.position + node.normal * radius, radius)
+        };
+
+        let crossing_connections = graph.connections_cut_by(&hazard.outline(), false);
+        let touching_connections = graph.nodes.iter().flat_map(|node| {
+            node.connections
+                .iter()
+                .filter(|connection| {
+                    standing_in_hazard(node.id) || standing_in_hazard(connection.node_id)
+                })
+                .map(|connection| (node.id, connection.node_id))
+                .collect::<Vec<(usize, usize)>>()
+        });
+
+        for connection in crossing_connections.into_iter().chain(touching_connections) {
+            let scale = connection_scales.entry(connection).or_insert(1.0);
+            *scale = scale.max(hazard.kind.cost_multiplier());
+        }
+    }
+
+    for ((from_node_id, to_node_id), scale) in connection_scales {
+        pathfinding.set_connection_cost_scale(from_node_id, to_node_id, scale);
+    }
+}
+
+pub fn s_apply_hazard_costs(level: Res<Level>, mut pathfinding: ResMut<Pathfinding>) {
+    apply_hazard_costs(&level, &mut pathfinding);
+}
+
+/// Rebuilding the graph throws its modifiers away, hazard costs included
+pub fn s_reapply_hazard_costs(
+    settings: Res<PathfindingSettings>,
+    level: Res<Level>,
+    mut pathfinding: ResMut<Pathfinding>,
+) {
+    if !settings.is_changed() || settings.is_added() {
+        return;
+    }
+
+    apply_hazard_costs(&level, &mut pathfinding);
+}
+
+/// Hurts agents touching hazards, sending them back to the spawn point once they run out
+/// of health
+pub fn s_hazard_damage(
+    mut agent_query: Query<(
+        Entity,
+        &mut Transform,
+        &mut Physics,
+        &mut PlatformerAI,
+        &mut Health,
+    )>,
+    level: Res<Level>,
+    mut event_log: ResMut<EventLog>,
+) {
+    for (entity, mut transform, mut physics, mut platformer_ai, mut health) in
+        agent_query.iter_mut()
+    {
+        let agent_position = transform.translation.xy();
+
+        let damage: f32 = level
+            .hazards
+            .iter()
+            .filter(|hazard| hazard.touches(agent_position, physics.radius))
+            .map(|hazard| hazard.kind.damage_per_tick())
+            .sum();
+
+        if damage <= 0.0 {
+            continue;
+        }
+
+        health.current -= damage;
+
+        if health.current > 0.0 {
+            continue;
+        }
+
+        let spawn_position = level.agent_spawn_position();
+
+        event_log.log(
+            LogCategory::State,
+            format!(
+                "Agent {} died in a hazard, respawning at ({:.1}, {:.1})",
+                entity.index(),
+                spawn_position.x,
+                spawn_position.y
+            ),
+        );
+
+        transform.translation = spawn_position.extend(0.0);
+        physics.prev_position = spawn_position;
+        physics.velocity = Vec2::ZERO;
+        physics.acceleration = Vec2::ZERO;
+        health.current = health.max;
+
+        *platformer_ai = PlatformerAI {
+            state: platformer_ai.state,
+            ..Default::default()
+        };
+    }
+}
+
+pub fn s_render_hazards(mut gizmos: Gizmos, level: Res<Level>) {
+    for hazard in level.hazards.iter() {
+        let color = match hazard.kind {
+            HazardKind::Spikes => Color::SILVER,
+            HazardKind::Lava => Color::ORANGE_RED,
+        };
+
+        gizmos.linestrip_2d(hazard.outline(), color);
+
+        // Spikes get teeth along their top
+        if hazard.kind == HazardKind::Spikes {
+            let tooth_width = (hazard.max.y - hazard.min.y).max(4.0);
+            let mut x = hazard.min.x;
+
+            while x + tooth_width <= hazard.max.x + 0.01 {
+                gizmos.linestrip_2d(
+                    [
+                        Vec2::new(x, hazard.min.y),
+                        Vec2::new(x + tooth_width / 2.0, hazard.max.y),
+                        Vec2::new(x + tooth_width, hazard.min.y),
+                    ],
+                    color,
+                );
+                x += tooth_width;
+            }
+        }
+    }
+}
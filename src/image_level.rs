@@ -0,0 +1,404 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    fmt,
+};
+
+use bevy::{
+    math::Vec2,
+    render::{
+        color::Color,
+        render_asset::RenderAssetUsages,
+        render_resource::TextureFormat,
+        texture::{CompressedImageFormats, Image, ImageSampler, ImageType},
+    },
+};
+use rand::Rng;
+
+use crate::{
+    ai::platformer_ai::PLATFORMER_AI_AGENT_RADIUS,
+    level::{Level, Polygon, SpawnPoint},
+    polygon_ops::{contains_point, distance_to_segment},
+};
+
+/// How big one pixel of the image is in the level
+const IMAGE_LEVEL_PIXEL_SIZE: f32 = 4.0;
+
+/// Pixels at least this opaque are solid
+const SOLID_ALPHA: u8 = 128;
+
+/// How far (in pixels) simplified outlines are allowed to stray from the traced ones
+const SIMPLIFY_TOLERANCE: f32 = 0.75;
+
+/// A piece of a traced outline, between two points on the double resolution grid
+type GridSegment = ((i32, i32), (i32, i32));
+
+/// A level painted as an image, where opaque pixels are solid and transparent ones are open
+///
+/// Like text levels, the whole image is walled in so nothing can fall out of it
+pub struct ImageLevel {
+    /// Which pixels are solid (rows top to bottom)
+    pub solid: Vec<Vec<bool>>,
+}
+
+#[derive(Debug)]
+pub enum ImageLevelError {
+    Io(std::io::Error),
+    Decode(String),
+    Empty,
+}
+
+impl fmt::Display for ImageLevelError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ImageLevelError::Io(error) => write!(f, "couldn't read image level: {}", error),
+            ImageLevelError::Decode(error) => write!(f, "couldn't decode image level: {}", error),
+            ImageLevelError::Empty => write!(f, "image level has no pixels"),
+        }
+    }
+}
+
+impl std::error::Error for ImageLevelError {}
+
+impl From<std::io::Error> for ImageLevelError {
+    fn from(error: std::io::Error) -> Self {
+        ImageLevelError::Io(error)
+    }
+}
+
+pub fn load_image_level(path: &str) -> Result<ImageLevel, ImageLevelError> {
+    let bytes = std::fs::read(path)?;
+
+    let image = Image::from_buffer(
+        &bytes,
+        ImageType::Extension("png"),
+        CompressedImageFormats::NONE,
+        true,
+        ImageSampler::Default,
+        RenderAssetUsages::default(),
+    )
+    .map_err(|error| ImageLevelError::Decode(error.to_string()))?;
+
+    // Whatever the PNG's color type, the alpha ends up every fourth byte
+    let image = image
+        .convert(TextureFormat::Rgba8UnormSrgb)
+        .ok_or_else(|| ImageLevelError::Decode("unsupported pixel format".to_string()))?;
+
+    let (width, height) = (image.width() as usize, image.height() as usize);
+    if width == 0 || height == 0 {
+        return Err(ImageLevelError::Empty);
+    }
+
+    let solid = (0..height)
+        .map(|row| {
+            (0..width)
+                .map(|column| image.data[(row * width + column) * 4 + 3] >= SOLID_ALPHA)
+                .collect()
+        })
+        .collect();
+
+    Ok(ImageLevel { solid })
+}
+
+impl ImageLevel {
+    pub fn columns(&self) -> usize {
+        self.solid[0].len()
+    }
+
+    pub fn rows(&self) -> usize {
+        self.solid.len()
+    }
+
+    /// Traces the solid pixels into outlines, each becoming a polygon, with the open space
+    /// inside them becoming holes
+    pub fn build(&self, grid_size: f32, rng: &mut impl Rng) -> Level {
+        let outlines: Vec<Vec<Vec2>> = self
+            .trace_outlines()
+            .iter()
+            .map(|outline| simplify_outline(outline, SIMPLIFY_TOLERANCE * IMAGE_LEVEL_PIXEL_SIZE))
+            .filter(|outline| outline.len() >= 3)
+            .collect();
+
+        // An outline is around solid ground if it's inside an even number of others, and
+        // around open space (a hole in the one just outside it) if it's inside an odd number
+        let enclosing: Vec<Vec<usize>> = outlines
+            .iter()
+            .enumerate()
+            .map(|(outline_index, outline)| {
+                (0..outlines.len())
+                    .filter(|other_index| {
+                        *other_index != outline_index
+                            && contains_point(&outlines[*other_index], outline[0])
+                    })
+                    .collect()
+            })
+            .collect();
+
+        let mut polygons: Vec<Polygon> = Vec::new();
+        let mut polygon_indices: HashMap<usize, usize> = HashMap::new();
+
+        // Solids go in first so their holes have something to point at
+        for (outline_index, outline) in outlines.iter().enumerate() {
+            if enclosing[outline_index].len() % 2 == 1 {
+                continue;
+            }
+
+            // The wall around the image only ever gets walked on from the inside
+            let is_outer_wall = enclosing[outline_index].is_empty();
+
+            polygon_indices.insert(outline_index, polygons.len());
+            polygons.push(Polygon {
+                points: closed(outline),
+                color: Color::rgb(
+                    rng.gen_range(0.0..=1.0),
+                    rng.gen_range(0.0..=1.0),
+                    rng.gen_range(0.0..=1.0),
+                ),
+                is_container: false,
+                no_node_lines: if is_outer_wall {
+                    (0..outline.len()).collect()
+                } else {
+                    Vec::new()
+                },
+                node_spacing: None,
+                destructible_lines: Vec::new(),
+                broken_lines: Vec::new(),
+                is_one_way: false,
+                line_frictions: Vec::new(),
+                line_conveyor_speeds: Vec::new(),
+                line_restitutions: Vec::new(),
+                hole_in: None,
+            });
+        }
+
+        for (outline_index, outline) in outlines.iter().enumerate() {
+            if enclosing[outline_index].len().is_multiple_of(2) {
+                continue;
+            }
+
+            // The solid just outside the hole is the one enclosed by all the others
+            let solid_index = enclosing[outline_index]
+                .iter()
+                .copied()
+                .max_by_key(|other_index| enclosing[*other_index].len())
+                .and_then(|other_index| polygon_indices.get(&other_index).copied());
+
+            let Some(solid_index) = solid_index else {
+                continue;
+            };
+
+            polygons.push(Polygon {
+                points: closed(outline),
+                color: polygons[solid_index].color,
+                is_container: false,
+                no_node_lines: Vec::new(),
+                node_spacing: None,
+                destructible_lines: Vec::new(),
+                broken_lines: Vec::new(),
+                is_one_way: false,
+                line_frictions: Vec::new(),
+                line_conveyor_speeds: Vec::new(),
+                line_restitutions: Vec::new(),
+                hole_in: Some(solid_index),
+            });
+        }
+
+        // Walled in, the image is two pixels wider and taller
+        let size = Vec2::new(self.columns() as f32 + 2.0, self.rows() as f32 + 2.0)
+            * IMAGE_LEVEL_PIXEL_SIZE
+            / grid_size;
+
+        Level {
+            polygons,
+            grid_size,
+            size,
+            half_size: size / 2.0,
+            markers: Vec::new(),
+            spawn_points: vec![SpawnPoint::agent(self.spawn_position())],
+            ladders: Vec::new(),
+            teleporters: Vec::new(),
+            hazards: Vec::new(),
+            water: Vec::new(),
+            bounce_pads: Vec::new(),
+        }
+    }
+
+    /// Agents start on the lowest open pixel with solid ground under it, as far left as possible
+    pub fn spawn_position(&self) -> Vec2 {
+        let spawn_pixel = (0..self.rows() as i32).rev().find_map(|row| {
+            (0..self.columns() as i32)
+                .find(|&column| !self.is_solid((column, row)) && self.is_solid((column, row + 1)))
+                .map(|column| (column, row))
+        });
+
+        // Traced floors run halfway between the open pixel's middle and the solid one's
+        match spawn_pixel {
+            Some(pixel) => {
+                self.pixel_position(pixel)
+                    + Vec2::Y * (PLATFORMER_AI_AGENT_RADIUS - IMAGE_LEVEL_PIXEL_SIZE / 2.0)
+            }
+            None => Vec2::ZERO,
+        }
+    }
+
+    /// Whether the pixel is solid, counting the wall around the image and nothing past it
+    fn is_solid(&self, (column, row): (i32, i32)) -> bool {
+        let (columns, rows) = (self.columns() as i32, self.rows() as i32);
+
+        if column < -1 || row < -1 || column > columns || row > rows {
+            return false;
+        }
+
+        self.solid
+            .get(row as usize)
+            .and_then(|pixels| pixels.get(column as usize))
+            .copied()
+            .unwrap_or(true)
+    }
+
+    /// The world position of the middle of the pixel
+    fn pixel_position(&self, (column, row): (i32, i32)) -> Vec2 {
+        Vec2::new(
+            column as f32 + 0.5 - self.columns() as f32 / 2.0,
+            self.rows() as f32 / 2.0 - row as f32 - 0.5,
+        ) * IMAGE_LEVEL_PIXEL_SIZE
+    }
+
+    /// Marching squares over the pixel middles, giving every boundary between solid and open
+    /// as a loop of points
+    fn trace_outlines(&self) -> Vec<Vec<Vec2>> {
+        let (columns, rows) = (self.columns() as i32, self.rows() as i32);
+
+        // Points are kept at double resolution so the edge middles land on whole numbers
+        let mut neighbours: BTreeMap<(i32, i32), Vec<(i32, i32)>> = BTreeMap::new();
+
+        // The cells reach one past the wall, into the open space around everything
+        for row in -2..=rows {
+            for column in -2..=columns {
+                let corners = [
+                    self.is_solid((column, row)),
+                    self.is_solid((column + 1, row)),
+                    self.is_solid((column + 1, row + 1)),
+                    self.is_solid((column, row + 1)),
+                ];
+                let case = corners
+                    .iter()
+                    .fold(0, |case, solid| case << 1 | *solid as u8);
+
+                let top = (column * 2 + 1, row * 2);
+                let right = (column * 2 + 2, row * 2 + 1);
+                let bottom = (column * 2 + 1, row * 2 + 2);
+                let left = (column * 2, row * 2 + 1);
+
+                // Corners are top left, top right, bottom right and bottom left from the high
+                // bit down, and the saddles (5 and 10) are treated as joined through the middle
+                let segments: &[GridSegment] = match case {
+                    1 | 14 => &[(left, bottom)],
+                    2 | 13 => &[(bottom, right)],
+                    3 | 12 => &[(left, right)],
+                    4 | 11 => &[(top, right)],
+                    5 => &[(left, top), (bottom, right)],
+                    6 | 9 => &[(top, bottom)],
+                    7 | 8 => &[(left, top)],
+                    10 => &[(left, bottom), (top, right)],
+                    _ => &[],
+                };
+
+                for (start, end) in segments {
+                    neighbours.entry(*start).or_default().push(*end);
+                    neighbours.entry(*end).or_default().push(*start);
+                }
+            }
+        }
+
+        let mut outlines = Vec::new();
+
+        while let Some(first) = neighbours.keys().next().copied() {
+            let mut outline = Vec::new();
+            let mut previous = None;
+            let mut current = first;
+
+            loop {
+                outline.push(current);
+
+                let Some(next_points) = neighbours.remove(&current) else {
+                    break;
+                };
+                let next = next_points
+                    .into_iter()
+                    .find(|point| Some(*point) != previous && neighbours.contains_key(point));
+
+                match next {
+                    Some(next) => {
+                        previous = Some(current);
+                        current = next;
+                    }
+                    None => break,
+                }
+            }
+
+            outlines.push(
+                outline
+                    .iter()
+                    .map(|&(x, y)| {
+                        // Back from double resolution, where a whole number is a pixel middle
+                        Vec2::new(
+                            x as f32 / 2.0 + 0.5 - self.columns() as f32 / 2.0,
+                            self.rows() as f32 / 2.0 - y as f32 / 2.0 - 0.5,
+                        ) * IMAGE_LEVEL_PIXEL_SIZE
+                    })
+                    .collect(),
+            );
+        }
+
+        outlines
+    }
+}
+
+fn closed(outline: &[Vec2]) -> Vec<Vec2> {
+    let mut points = outline.to_vec();
+    points.push(outline[0]);
+    points
+}
+
+/// Douglas-Peucker on a loop, split in two at the point furthest from its first point
+fn simplify_outline(outline: &[Vec2], tolerance: f32) -> Vec<Vec2> {
+    if outline.len() < 4 {
+        return outline.to_vec();
+    }
+
+    let furthest = (1..outline.len())
+        .max_by(|a, b| {
+            (outline[*a] - outline[0])
+                .length_squared()
+                .total_cmp(&(outline[*b] - outline[0]).length_squared())
+        })
+        .unwrap_or(outline.len() / 2);
+
+    let mut second_half = outline[furthest..].to_vec();
+    second_half.push(outline[0]);
+
+    let mut simplified = douglas_peucker(&outline[..=furthest], tolerance);
+    simplified.pop();
+    simplified.extend(douglas_peucker(&second_half, tolerance));
+    simplified.pop();
+
+    simplified
+}
+
+fn douglas_peucker(points: &[Vec2], tolerance: f32) -> Vec<Vec2> {
+    let (start, end) = (points[0], points[points.len() - 1]);
+
+    let furthest = (1..points.len() - 1)
+        .map(|i| (i, distance_to_segment(points[i], start, end)))
+        .max_by(|(_, a), (_, b)| a.total_cmp(b));
+
+    match furthest {
+        Some((i, distance)) if distance > tolerance => {
+            let mut simplified = douglas_peucker(&points[..=i], tolerance);
+            simplified.pop();
+            simplified.extend(douglas_peucker(&points[i..], tolerance));
+            simplified
+        }
+        _ => vec![start, end],
+    }
+}
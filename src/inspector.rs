@@ -0,0 +1,121 @@
+use bevy::{
+    app::{App, Plugin, Update},
+    ecs::system::{Query, ResMut},
+};
+use bevy_egui::{egui, EguiContexts, EguiPlugin};
+
+use crate::ai::{
+    pathfinding::Pathfinding,
+    platformer_ai::{PlatformerAITuning, ReplanPolicy},
+};
+
+pub struct InspectorPlugin;
+
+impl Plugin for InspectorPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_plugins(EguiPlugin)
+            .add_systems(Update, s_inspector_panel);
+    }
+}
+
+/// A slider or field for the policy's setting, if it has one
+fn replan_policy_ui(ui: &mut egui::Ui, label: &str, replan_policy: &mut ReplanPolicy) {
+    ui.horizontal(|ui| {
+        ui.label(label);
+
+        match replan_policy {
+            ReplanPolicy::GoalMoved { threshold } => {
+                ui.add(egui::Slider::new(threshold, 0.0..=64.0).text("goal moved threshold"));
+            }
+            ReplanPolicy::GoalNodeChanged => {
+                ui.label("goal node changed");
+            }
+            ReplanPolicy::Timer { ticks } => {
+                ui.add(egui::Slider::new(ticks, 1..=300).text("ticks"));
+            }
+        }
+    });
+}
+
+/// Live tuning for the values the agents and the search run on
+///
+/// Every agent gets the first agent's tuning whenever something in the panel is changed
+pub fn s_inspector_panel(
+    mut contexts: EguiContexts,
+    mut tuning_query: Query<&mut PlatformerAITuning>,
+    mut pathfinding: ResMut<Pathfinding>,
+) {
+    let Some(first_tuning) = tuning_query.iter().next() else {
+        return;
+    };
+    let mut tuning = first_tuning.clone();
+
+    let mut heuristic_weight = pathfinding.heuristic_weight;
+    let mut skip_walkable_runs = pathfinding.skip_walkable_runs;
+    let mut decay_per_tick = pathfinding.influence.decay_per_tick;
+    let mut goal_danger_radius = pathfinding.influence.goal_danger_radius;
+    let mut agent_danger_radius = pathfinding.influence.agent_danger_radius;
+    let mut cost_weight = pathfinding.influence.cost_weight;
+
+    egui::Window::new("Tuning").show(contexts.ctx_mut(), |ui| {
+        ui.heading("Movement");
+        ui.add(egui::Slider::new(&mut tuning.max_speed, 0.5..=10.0).text("max speed"));
+        ui.add(egui::Slider::new(&mut tuning.acceleration, 0.01..=1.0).text("acceleration"));
+        ui.add(egui::Slider::new(&mut tuning.deceleration, 0.01..=1.0).text("deceleration"));
+        ui.add(
+            egui::Slider::new(&mut tuning.jump_horizontal_boost, 0.0..=10.0)
+                .text("jump horizontal boost"),
+        );
+        ui.add(egui::Slider::new(&mut tuning.air_control, 0.0..=1.0).text("air control"));
+        ui.add(egui::Slider::new(&mut tuning.climb_speed, 0.5..=10.0).text("climb speed"));
+        ui.add(egui::Slider::new(&mut tuning.swim_speed, 0.5..=10.0).text("swim speed"));
+
+        ui.heading("Replanning");
+        ui.add(
+            egui::Slider::new(&mut tuning.path_deviation_distance, 4.0..=128.0)
+                .text("path deviation distance"),
+        );
+        replan_policy_ui(ui, "Wander", &mut tuning.wander_replan);
+        replan_policy_ui(ui, "Pursue", &mut tuning.pursue_replan);
+        replan_policy_ui(ui, "Search", &mut tuning.search_replan);
+        replan_policy_ui(ui, "Attack", &mut tuning.attack_replan);
+
+        ui.heading("Search");
+        ui.add(egui::Slider::new(&mut heuristic_weight, 0.0..=5.0).text("heuristic weight"));
+        ui.checkbox(&mut skip_walkable_runs, "skip walkable runs");
+
+        ui.heading("Perception");
+        ui.add(egui::Slider::new(&mut goal_danger_radius, 0.0..=256.0).text("goal danger radius"));
+        ui.add(
+            egui::Slider::new(&mut agent_danger_radius, 0.0..=256.0).text("agent danger radius"),
+        );
+        ui.add(egui::Slider::new(&mut decay_per_tick, 0.5..=1.0).text("danger decay per tick"));
+        ui.add(egui::Slider::new(&mut cost_weight, 0.0..=1000.0).text("danger cost weight"));
+    });
+
+    // Only written back when changed, so nothing watching for changes sees them every frame
+    if tuning != *first_tuning {
+        for mut agent_tuning in tuning_query.iter_mut() {
+            *agent_tuning = tuning.clone();
+        }
+    }
+
+    if heuristic_weight != pathfinding.heuristic_weight {
+        pathfinding.heuristic_weight = heuristic_weight;
+    }
+
+    if skip_walkable_runs != pathfinding.skip_walkable_runs {
+        pathfinding.skip_walkable_runs = skip_walkable_runs;
+    }
+
+    if decay_per_tick != pathfinding.influence.decay_per_tick
+        || goal_danger_radius != pathfinding.influence.goal_danger_radius
+        || agent_danger_radius != pathfinding.influence.agent_danger_radius
+        || cost_weight != pathfinding.influence.cost_weight
+    {
+        pathfinding.influence.decay_per_tick = decay_per_tick;
+        pathfinding.influence.goal_danger_radius = goal_danger_radius;
+        pathfinding.influence.agent_danger_radius = agent_danger_radius;
+        pathfinding.influence.cost_weight = cost_weight;
+    }
+}
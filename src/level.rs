@@ -1,12 +1,321 @@
+use std::{
+    collections::hash_map::DefaultHasher,
+    hash::{Hash, Hasher},
+};
+
 use bevy::{ecs::system::Resource, math::Vec2, render::color::Color};
 use rand::Rng;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    markers::{load_markers, Marker},
+    utils::{cross_product, line_intersect, side_of_line_detection, vec2_serde},
+};
 
-use crate::utils::line_intersect;
+/// The friction of lines that don't set their own
+pub const DEFAULT_FRICTION: f32 = 1.0;
 
 pub struct Polygon {
     pub points: Vec<Vec2>,
     pub color: Color,
     pub is_container: bool,
+    /// Lines (by start point index) that should never get pathfinding nodes
+    pub no_node_lines: Vec<usize>,
+    /// Distance between pathfinding nodes on this polygon, instead of the global setting
+    pub node_spacing: Option<f32>,
+    /// Lines (by start point index) that can be broken at runtime
+    pub destructible_lines: Vec<usize>,
+    /// Destructible lines that have been broken, which nothing collides with anymore
+    pub broken_lines: Vec<usize>,
+    /// Only the floors of a one-way platform are solid, and only to things coming down onto them
+    pub is_one_way: bool,
+    /// (line index, friction) for the lines that aren't `DEFAULT_FRICTION`, like ice
+    pub line_frictions: Vec<(usize, f32)>,
+    /// (line index, speed) for the lines that are conveyors, positive when they carry things
+    /// from the line's start point toward its end point
+    pub line_conveyor_speeds: Vec<(usize, f32)>,
+    /// (line index, restitution) for the lines that bounce things off them instead of
+    /// stopping them dead
+    pub line_restitutions: Vec<(usize, f32)>,
+    /// The solid polygon this one is a hole cut out of, if it is one
+    pub hole_in: Option<usize>,
+}
+
+impl Polygon {
+    /// Whether the polygon's inside is open space with the solid around it, like containers
+    /// and holes, rather than solid itself
+    pub fn is_hollow(&self) -> bool {
+        self.is_container || self.hole_in.is_some()
+    }
+
+    pub fn is_line_excluded(&self, line_index: usize) -> bool {
+        self.no_node_lines.contains(&line_index)
+    }
+
+    pub fn is_line_destructible(&self, line_index: usize) -> bool {
+        self.destructible_lines.contains(&line_index)
+    }
+
+    pub fn is_line_broken(&self, line_index: usize) -> bool {
+        self.broken_lines.contains(&line_index)
+    }
+
+    /// Whether the line faces up (its outward normal is on the line's left)
+    pub fn is_line_floor(&self, line_index: usize) -> bool {
+        let line = self.points[line_index + 1] - self.points[line_index];
+
+        Vec2::new(-line.y, line.x).normalize_or_zero().y > 0.5
+    }
+
+    /// How much grip the line gives, from 0.0 (frictionless) up
+    pub fn line_friction(&self, line_index: usize) -> f32 {
+        self.line_frictions
+            .iter()
+            .find(|(friction_line_index, _)| *friction_line_index == line_index)
+            .map_or(DEFAULT_FRICTION, |(_, friction)| *friction)
+    }
+
+    /// How fast the line carries things standing on it from its start point toward its end
+    /// point (per tick), which is backward when negative
+    pub fn line_conveyor_speed(&self, line_index: usize) -> f32 {
+        self.line_conveyor_speeds
+            .iter()
+            .find(|(conveyor_line_index, _)| *conveyor_line_index == line_index)
+            .map_or(0.0, |(_, speed)| *speed)
+    }
+
+    /// How much of the speed things hit the line with they bounce back with, from 0.0 (none,
+    /// like most lines) to 1.0 (all of it)
+    pub fn line_restitution(&self, line_index: usize) -> f32 {
+        self.line_restitutions
+            .iter()
+            .find(|(restitution_line_index, _)| *restitution_line_index == line_index)
+            .map_or(0.0, |(_, restitution)| *restitution)
+    }
+
+    /// Whether things pass through the line from every side, like broken lines and the
+    /// sides and undersides of one-way platforms
+    pub fn is_line_passable(&self, line_index: usize) -> bool {
+        self.is_line_broken(line_index) || self.is_one_way && !self.is_line_floor(line_index)
+    }
+}
+
+/// A stretch of level geometry the author doesn't want agents walking along
+/// (decorative geometry, death floors, etc.)
+#[derive(Debug, Clone, Deserialize)]
+pub struct NoNodeEdge {
+    pub start: [f32; 2],
+    pub end: [f32; 2],
+}
+
+/// A stretch of level geometry that can be broken through at runtime (floors the agent
+/// smashes through, walls that give way, etc.)
+#[derive(Debug, Clone, Deserialize)]
+pub struct DestructibleEdge {
+    pub start: [f32; 2],
+    pub end: [f32; 2],
+}
+
+/// A stretch of level geometry with its own friction (ice, mud, etc.)
+#[derive(Debug, Clone, Deserialize)]
+pub struct FrictionEdge {
+    pub start: [f32; 2],
+    pub end: [f32; 2],
+    pub friction: f32,
+}
+
+/// A stretch of level geometry that carries things along it from its start toward its end
+#[derive(Debug, Clone, Deserialize)]
+pub struct ConveyorEdge {
+    pub start: [f32; 2],
+    pub end: [f32; 2],
+    /// How fast it carries things (per tick)
+    pub speed: f32,
+}
+
+/// A stretch of level geometry that things bounce off (trampolines, rubber walls, etc.)
+#[derive(Debug, Clone, Deserialize)]
+pub struct RestitutionEdge {
+    pub start: [f32; 2],
+    pub end: [f32; 2],
+    pub restitution: f32,
+}
+
+/// Makes the polygon whose outline passes through the point a one-way platform, which can be
+/// jumped up through from below and dropped through from above
+#[derive(Debug, Clone, Deserialize)]
+pub struct OneWayPlatform {
+    pub point: [f32; 2],
+}
+
+/// A climbable region, climbed straight up and down through its middle
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Ladder {
+    #[serde(with = "vec2_serde")]
+    pub min: Vec2,
+    #[serde(with = "vec2_serde")]
+    pub max: Vec2,
+}
+
+impl Ladder {
+    pub fn center_x(&self) -> f32 {
+        (self.min.x + self.max.x) / 2.0
+    }
+
+    /// Whether the position is on the ladder, give or take the margin
+    pub fn contains(&self, position: Vec2, margin: f32) -> bool {
+        position.cmpge(self.min - margin).all() && position.cmple(self.max + margin).all()
+    }
+}
+
+/// A pool of water, which agents swim through instead of walking and jumping
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WaterRegion {
+    #[serde(with = "vec2_serde")]
+    pub min: Vec2,
+    #[serde(with = "vec2_serde")]
+    pub max: Vec2,
+}
+
+impl WaterRegion {
+    /// Whether the position is in the water, give or take the margin
+    pub fn contains(&self, position: Vec2, margin: f32) -> bool {
+        position.cmpge(self.min - margin).all() && position.cmple(self.max + margin).all()
+    }
+}
+
+/// A pad on a floor that launches whatever steps on it, always with the same velocity
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BouncePad {
+    /// The middle of the pad, on the floor it sits on
+    #[serde(with = "vec2_serde")]
+    pub position: Vec2,
+    /// The velocity the pad launches things with (per tick)
+    #[serde(with = "vec2_serde")]
+    pub impulse: Vec2,
+}
+
+impl BouncePad {
+    /// How far either side of its middle the pad reaches
+    pub const HALF_WIDTH: f32 = 8.0;
+
+    /// Whether something standing with its middle at the position is on the pad
+    pub fn is_under(&self, position: Vec2, radius: f32) -> bool {
+        (position.x - self.position.x).abs() <= Self::HALF_WIDTH
+            && (position.y - radius - self.position.y).abs() <= 2.0
+    }
+}
+
+/// A one-way portal, moving whatever walks into the entrance to the exit
+///
+/// Teleporters going both ways are two of these, one for each direction
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Teleporter {
+    #[serde(with = "vec2_serde")]
+    pub entrance: Vec2,
+    #[serde(with = "vec2_serde")]
+    pub exit: Vec2,
+    /// What going through costs a path, since the trip itself covers no distance
+    #[serde(default)]
+    pub cost: f32,
+}
+
+/// What a hazard is, which decides how much it hurts and how hard paths avoid it
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum HazardKind {
+    Spikes,
+    Lava,
+}
+
+impl HazardKind {
+    /// How much health touching it takes away each tick
+    pub fn damage_per_tick(&self) -> f32 {
+        match self {
+            HazardKind::Spikes => 1.0,
+            HazardKind::Lava => 2.5,
+        }
+    }
+
+    /// How many times more the graph edges through it cost
+    pub fn cost_multiplier(&self) -> f32 {
+        match self {
+            HazardKind::Spikes => 8.0,
+            HazardKind::Lava => 20.0,
+        }
+    }
+}
+
+/// An area that hurts agents touching it, which paths only cross when there's no other way
+///
+/// Hazards aren't solid, so they're usually laid over a floor
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Hazard {
+    pub kind: HazardKind,
+    #[serde(with = "vec2_serde")]
+    pub min: Vec2,
+    #[serde(with = "vec2_serde")]
+    pub max: Vec2,
+}
+
+impl Hazard {
+    /// The hazard's outline, closed like a level polygon's
+    pub fn outline(&self) -> Vec<Vec2> {
+        vec![
+            self.min,
+            Vec2::new(self.max.x, self.min.y),
+            self.max,
+            Vec2::new(self.min.x, self.max.y),
+            self.min,
+        ]
+    }
+
+    /// Whether a circle at the position touches the hazard
+    pub fn touches(&self, position: Vec2, radius: f32) -> bool {
+        (position.clamp(self.min, self.max) - position).length_squared() <= radius.powi(2)
+    }
+}
+
+/// Gives the polygon whose outline passes through the point its own node spacing
+/// (dense nodes on small ledges, sparse ones on long floors)
+#[derive(Debug, Clone, Deserialize)]
+pub struct NodeSpacingOverride {
+    pub point: [f32; 2],
+    pub spacing: f32,
+}
+
+/// What a spawn point starts off
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SpawnPointKind {
+    /// Where an agent starts
+    #[default]
+    Agent,
+    /// Where the goal point starts
+    GoalStart,
+    /// Somewhere agents head back to when they've nothing better to do
+    Home,
+}
+
+/// A named place in the level where a scenario starts something off
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SpawnPoint {
+    #[serde(default)]
+    pub name: String,
+    #[serde(default)]
+    pub kind: SpawnPointKind,
+    #[serde(with = "vec2_serde")]
+    pub position: Vec2,
+}
+
+impl SpawnPoint {
+    pub fn agent(position: Vec2) -> Self {
+        SpawnPoint {
+            name: "agent".to_string(),
+            kind: SpawnPointKind::Agent,
+            position,
+        }
+    }
 }
 
 #[derive(Resource)]
@@ -15,9 +324,37 @@ pub struct Level {
     pub grid_size: f32,
     pub size: Vec2,
     pub half_size: Vec2,
+    pub markers: Vec<Marker>,
+    /// Where agents, the goal and anything else in the scenario start off
+    pub spawn_points: Vec<SpawnPoint>,
+    pub ladders: Vec<Ladder>,
+    pub teleporters: Vec<Teleporter>,
+    pub hazards: Vec<Hazard>,
+    pub water: Vec<WaterRegion>,
+    pub bounce_pads: Vec<BouncePad>,
 }
 
 impl Level {
+    /// The level that's built into the game, without the doors and moving platforms that get
+    /// added along with their entities
+    pub fn built_in(grid_size: f32, rng: &mut impl Rng) -> Level {
+        let (polygons, size, half_size) = generate_level_polygons(grid_size, rng);
+
+        Level {
+            polygons,
+            grid_size,
+            size,
+            half_size,
+            markers: load_markers(),
+            spawn_points: load_spawn_points(),
+            ladders: load_ladders(),
+            teleporters: load_teleporters(),
+            hazards: load_hazards(),
+            water: load_water(),
+            bounce_pads: load_bounce_pads(),
+        }
+    }
+
     pub fn get_polygon(&self, index: usize) -> Option<&Polygon> {
         self.polygons.get(index)
     }
@@ -31,32 +368,468 @@ impl Level {
         Some((start, end))
     }
 
-    pub fn line_of_sight_check(&self, start: Vec2, end: Vec2) -> bool {
+    /// Breaks the line if it's destructible and still standing, returning whether it broke
+    pub fn break_line(&mut self, polygon_index: usize, line_index: usize) -> bool {
+        let Some(polygon) = self.polygons.get_mut(polygon_index) else {
+            return false;
+        };
+
+        if !polygon.is_line_destructible(line_index) || polygon.is_line_broken(line_index) {
+            return false;
+        }
+
+        polygon.broken_lines.push(line_index);
+
+        true
+    }
+
+    /// The water region the position is in, if any
+    pub fn water_at(&self, position: Vec2) -> Option<&WaterRegion> {
+        self.water
+            .iter()
+            .find(|water| water.contains(position, 0.0))
+    }
+
+    pub fn spawn_point(&self, name: &str) -> Option<&SpawnPoint> {
+        self.spawn_points
+            .iter()
+            .find(|spawn_point| spawn_point.name == name)
+    }
+
+    /// Where the simulation spawns its agent, the first agent spawn point (or the origin)
+    pub fn agent_spawn_position(&self) -> Vec2 {
+        self.spawn_points
+            .iter()
+            .find(|spawn_point| spawn_point.kind == SpawnPointKind::Agent)
+            .map_or(Vec2::ZERO, |spawn_point| spawn_point.position)
+    }
+
+    /// Where the goal point starts, if the level says
+    pub fn goal_start_position(&self) -> Option<Vec2> {
+        self.spawn_points
+            .iter()
+            .find(|spawn_point| spawn_point.kind == SpawnPointKind::GoalStart)
+            .map(|spawn_point| spawn_point.position)
+    }
+
+    pub fn home_positions(&self) -> impl Iterator<Item = Vec2> + '_ {
+        self.spawn_points
+            .iter()
+            .filter(|spawn_point| spawn_point.kind == SpawnPointKind::Home)
+            .map(|spawn_point| spawn_point.position)
+    }
+
+    pub fn get_marker(&self, name: &str) -> Option<&Marker> {
+        self.markers.iter().find(|marker| marker.name == name)
+    }
+
+    /// The position of a named marker, for goal scripts and patrol routes
+    pub fn marker_position(&self, name: &str) -> Option<Vec2> {
+        self.get_marker(name).map(|marker| marker.position())
+    }
+
+    /// The holes cut out of the polygon
+    pub fn holes_of(&self, polygon_index: usize) -> impl Iterator<Item = &Polygon> {
+        self.polygons
+            .iter()
+            .filter(move |polygon| polygon.hole_in == Some(polygon_index))
+    }
+
+    /// Whether the point is in the solid part of the polygon, which for containers and holes is
+    /// outside them and for solids is inside them but not in any of their holes
+    pub fn is_point_in_solid(&self, polygon_index: usize, point: Vec2) -> bool {
+        let polygon = &self.polygons[polygon_index];
+        let inside_polygon = point_in_polygon(&polygon.points, point);
+
+        if polygon.is_hollow() {
+            return !inside_polygon;
+        }
+
+        inside_polygon
+            && !self
+                .holes_of(polygon_index)
+                .any(|hole| point_in_polygon(&hole.points, point))
+    }
+
+    /// Takes the polygon out of the level along with its holes, keeping every other hole
+    /// pointing at the right polygon
+    pub fn remove_polygon(&mut self, polygon_index: usize) {
+        let mut removed_indices: Vec<usize> = self
+            .polygons
+            .iter()
+            .enumerate()
+            .filter(|(index, polygon)| {
+                *index == polygon_index || polygon.hole_in == Some(polygon_index)
+            })
+            .map(|(index, _)| index)
+            .collect();
+        removed_indices.sort_unstable();
+
+        for removed_index in removed_indices.iter().rev() {
+            self.polygons.remove(*removed_index);
+        }
+
+        for polygon in self.polygons.iter_mut() {
+            if let Some(hole_in) = polygon.hole_in.as_mut() {
+                *hole_in -= removed_indices
+                    .iter()
+                    .filter(|removed_index| **removed_index < *hole_in)
+                    .count();
+            }
+        }
+    }
+
+    /// A hash of the level geometry, so anything baked from it can tell when it's out of date
+    pub fn fingerprint(&self) -> u64 {
+        let mut hasher = DefaultHasher::new();
+
         for polygon in &self.polygons {
-            for i in 1..polygon.points.len() {
-                let start = polygon.points[i - 1];
-                let end = polygon.points[i];
+            for point in &polygon.points {
+                point.x.to_bits().hash(&mut hasher);
+                point.y.to_bits().hash(&mut hasher);
+            }
+            polygon.is_container.hash(&mut hasher);
+            polygon.hole_in.hash(&mut hasher);
+            polygon.no_node_lines.hash(&mut hasher);
+            polygon.node_spacing.map(f32::to_bits).hash(&mut hasher);
+            polygon.broken_lines.hash(&mut hasher);
+            polygon.is_one_way.hash(&mut hasher);
+            for (line_index, friction) in &polygon.line_frictions {
+                line_index.hash(&mut hasher);
+                friction.to_bits().hash(&mut hasher);
+            }
+            for (line_index, speed) in &polygon.line_conveyor_speeds {
+                line_index.hash(&mut hasher);
+                speed.to_bits().hash(&mut hasher);
+            }
+        }
+
+        for ladder in &self.ladders {
+            for point in [ladder.min, ladder.max] {
+                point.x.to_bits().hash(&mut hasher);
+                point.y.to_bits().hash(&mut hasher);
+            }
+        }
 
-                let intersection = line_intersect(start, end, start, end);
+        for teleporter in &self.teleporters {
+            for point in [teleporter.entrance, teleporter.exit] {
+                point.x.to_bits().hash(&mut hasher);
+                point.y.to_bits().hash(&mut hasher);
+            }
+            teleporter.cost.to_bits().hash(&mut hasher);
+        }
+
+        for water in &self.water {
+            for point in [water.min, water.max] {
+                point.x.to_bits().hash(&mut hasher);
+                point.y.to_bits().hash(&mut hasher);
+            }
+        }
+
+        for bounce_pad in &self.bounce_pads {
+            for point in [bounce_pad.position, bounce_pad.impulse] {
+                point.x.to_bits().hash(&mut hasher);
+                point.y.to_bits().hash(&mut hasher);
+            }
+        }
+
+        hasher.finish()
+    }
+
+    pub fn line_of_sight_check(&self, start: Vec2, end: Vec2) -> bool {
+        self.raycast(start, end - start, (end - start).length())
+            .is_none()
+    }
+
+    /// The first solid line a ray from the origin hits within the max distance, from either
+    /// side
+    pub fn raycast(&self, origin: Vec2, dir: Vec2, max_dist: f32) -> Option<LevelHit> {
+        let dir = dir.normalize_or_zero();
+        let ray_end = origin + dir * max_dist;
+        let mut closest_hit: Option<LevelHit> = None;
 
-                if intersection.is_some() {
-                    return false;
+        for (polygon_index, polygon) in self.polygons.iter().enumerate() {
+            for line_index in 1..polygon.points.len() {
+                if polygon.is_line_passable(line_index - 1) {
+                    continue;
+                }
+
+                let start = polygon.points[line_index - 1];
+                let end = polygon.points[line_index];
+
+                let Some(point) = line_intersect(origin, ray_end, start, end) else {
+                    continue;
+                };
+
+                let distance = (point - origin).length();
+                if closest_hit.is_some_and(|hit| hit.distance <= distance) {
+                    continue;
+                }
+
+                // Facing back along the ray, whichever side it came from
+                let mut normal = (end - start).perp().normalize_or_zero();
+                if normal.dot(dir) > 0.0 {
+                    normal = -normal;
+                }
+
+                closest_hit = Some(LevelHit {
+                    point,
+                    normal,
+                    distance,
+                    polygon_index,
+                    line_index: line_index - 1,
+                });
+            }
+        }
+
+        closest_hit
+    }
+
+    /// The first solid line a circle moved from the origin hits within the max distance
+    ///
+    /// Only lines the circle starts outside of can be hit, and ones it's already overlapping
+    /// are ignored
+    pub fn circle_cast(
+        &self,
+        origin: Vec2,
+        dir: Vec2,
+        max_dist: f32,
+        radius: f32,
+    ) -> Option<LevelHit> {
+        self.circle_cast_filtered(origin, dir, max_dist, radius, |_| false)
+    }
+
+    /// A circle cast that passes through the polygons the filter says to ignore
+    pub fn circle_cast_filtered(
+        &self,
+        origin: Vec2,
+        dir: Vec2,
+        max_dist: f32,
+        radius: f32,
+        ignore_polygon: impl Fn(usize) -> bool,
+    ) -> Option<LevelHit> {
+        let motion = dir.normalize_or_zero() * max_dist;
+        if motion == Vec2::ZERO {
+            return None;
+        }
+
+        let mut earliest_hit: Option<(f32, LevelHit)> = None;
+
+        for (polygon_index, polygon) in self.polygons.iter().enumerate() {
+            if ignore_polygon(polygon_index) {
+                continue;
+            }
+
+            for line_index in 1..polygon.points.len() {
+                if polygon.is_line_passable(line_index - 1) {
+                    continue;
+                }
+
+                let Some((time, normal)) = circle_cast_against_line(
+                    origin,
+                    motion,
+                    radius,
+                    polygon.points[line_index - 1],
+                    polygon.points[line_index],
+                ) else {
+                    continue;
+                };
+
+                if earliest_hit.is_none_or(|(earliest_time, _)| time < earliest_time) {
+                    earliest_hit = Some((
+                        time,
+                        LevelHit {
+                            point: origin + motion * time - normal * radius,
+                            normal,
+                            distance: max_dist * time,
+                            polygon_index,
+                            line_index: line_index - 1,
+                        },
+                    ));
                 }
             }
         }
 
-        return true;
+        earliest_hit.map(|(_, hit)| hit)
+    }
+}
+
+/// Where a ray or a cast circle hits the level
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct LevelHit {
+    /// The point on the line that got hit
+    pub point: Vec2,
+    /// Pointing out of the line toward what hit it
+    pub normal: Vec2,
+    /// How far the ray or circle went before it hit, so a cast circle ends up at
+    /// `origin + dir * distance`
+    pub distance: f32,
+    pub polygon_index: usize,
+    pub line_index: usize,
+}
+
+/// When (as a fraction of the motion) a moving circle first touches the line from its solid
+/// side's outside, and the normal it touches it along
+///
+/// Circles already overlapping the line are left to the overlap test
+pub fn circle_cast_against_line(
+    from: Vec2,
+    motion: Vec2,
+    radius: f32,
+    start: Vec2,
+    end: Vec2,
+) -> Option<(f32, Vec2)> {
+    // Only lines the circle started outside of (or on) can be hit, like in the overlap test
+    if side_of_line_detection(start, end, from) < 0.0 {
+        return None;
+    }
+
+    let line = end - start;
+    let normal = Vec2::new(-line.y, line.x).normalize_or_zero();
+    let approach_speed = -motion.dot(normal);
+
+    if normal == Vec2::ZERO || approach_speed <= 0.0 {
+        return None;
+    }
+
+    let distance = (from - start).dot(normal);
+    if distance < radius {
+        return None;
+    }
+
+    // The flat of the line
+    let time = (distance - radius) / approach_speed;
+    if time <= 1.0 {
+        let contact = from + motion * time - normal * radius;
+        let along = (contact - start).dot(line) / line.length_squared();
+
+        if (0.0..=1.0).contains(&along) {
+            return Some((time, normal));
+        }
     }
+
+    // Otherwise one of its ends, whichever gets hit first
+    [start, end]
+        .into_iter()
+        .filter_map(|corner| {
+            let to_circle = from - corner;
+
+            // Solving |to_circle + motion * time| = radius for the earlier time
+            let a = motion.length_squared();
+            let b = 2.0 * to_circle.dot(motion);
+            let c = to_circle.length_squared() - radius * radius;
+            let discriminant = b * b - 4.0 * a * c;
+
+            if c < 0.0 || discriminant < 0.0 {
+                return None;
+            }
+
+            let time = (-b - discriminant.sqrt()) / (2.0 * a);
+            let normal = (to_circle + motion * time).normalize_or_zero();
+
+            (0.0..=1.0).contains(&time).then_some((time, normal))
+        })
+        .min_by(|(a, _), (b, _)| a.total_cmp(b))
 }
 
 const LEVEL_DATA: &'static [u8] = include_bytes!("../assets/level.json");
+const NO_NODE_EDGES_DATA: &[u8] = include_bytes!("../assets/no_node_edges.json");
+const NODE_SPACING_DATA: &[u8] = include_bytes!("../assets/node_spacing.json");
+const DESTRUCTIBLE_EDGES_DATA: &[u8] = include_bytes!("../assets/destructible_edges.json");
+const FRICTION_EDGES_DATA: &[u8] = include_bytes!("../assets/friction_edges.json");
+const CONVEYOR_EDGES_DATA: &[u8] = include_bytes!("../assets/conveyor_edges.json");
+const RESTITUTION_EDGES_DATA: &[u8] = include_bytes!("../assets/restitution_edges.json");
+const ONE_WAY_PLATFORMS_DATA: &[u8] = include_bytes!("../assets/one_way_platforms.json");
+const LADDERS_DATA: &[u8] = include_bytes!("../assets/ladders.json");
+const TELEPORTERS_DATA: &[u8] = include_bytes!("../assets/teleporters.json");
+const HAZARDS_DATA: &[u8] = include_bytes!("../assets/hazards.json");
+const WATER_DATA: &[u8] = include_bytes!("../assets/water.json");
+const BOUNCE_PADS_DATA: &[u8] = include_bytes!("../assets/bounce_pads.json");
+const SPAWN_POINTS_DATA: &[u8] = include_bytes!("../assets/spawn_points.json");
+
+/// The built-in level's ladders
+pub fn load_ladders() -> Vec<Ladder> {
+    let res = std::str::from_utf8(LADDERS_DATA);
+    serde_json::from_str(res.unwrap()).unwrap()
+}
+
+/// The built-in level's teleporters
+pub fn load_teleporters() -> Vec<Teleporter> {
+    let res = std::str::from_utf8(TELEPORTERS_DATA);
+    serde_json::from_str(res.unwrap()).unwrap()
+}
+
+/// The built-in level's hazards
+pub fn load_hazards() -> Vec<Hazard> {
+    let res = std::str::from_utf8(HAZARDS_DATA);
+    serde_json::from_str(res.unwrap()).unwrap()
+}
+
+/// The built-in level's water
+pub fn load_water() -> Vec<WaterRegion> {
+    let res = std::str::from_utf8(WATER_DATA);
+    serde_json::from_str(res.unwrap()).unwrap()
+}
+
+/// The built-in level's spawn points
+pub fn load_spawn_points() -> Vec<SpawnPoint> {
+    let res = std::str::from_utf8(SPAWN_POINTS_DATA);
+    serde_json::from_str(res.unwrap()).unwrap()
+}
 
-pub fn generate_level_polygons(grid_size: f32) -> (Vec<Polygon>, Vec2, Vec2) {
-    let mut rng = rand::thread_rng();
+/// The built-in level's bounce pads
+pub fn load_bounce_pads() -> Vec<BouncePad> {
+    let res = std::str::from_utf8(BOUNCE_PADS_DATA);
+    serde_json::from_str(res.unwrap()).unwrap()
+}
 
+pub fn generate_level_polygons(grid_size: f32, rng: &mut impl Rng) -> (Vec<Polygon>, Vec2, Vec2) {
     let res = std::str::from_utf8(LEVEL_DATA);
     let level_grid_data: Vec<Vec<usize>> = serde_json::from_str(&res.unwrap()).unwrap();
 
+    let res = std::str::from_utf8(NO_NODE_EDGES_DATA);
+    let no_node_edges: Vec<NoNodeEdge> = serde_json::from_str(res.unwrap()).unwrap();
+
+    let (mut polygons, size, half_size) =
+        generate_level_polygons_from_grid(level_grid_data, grid_size, &no_node_edges, rng);
+
+    let res = std::str::from_utf8(NODE_SPACING_DATA);
+    let node_spacing_overrides: Vec<NodeSpacingOverride> =
+        serde_json::from_str(res.unwrap()).unwrap();
+    apply_node_spacing_overrides(&mut polygons, &node_spacing_overrides);
+
+    let res = std::str::from_utf8(DESTRUCTIBLE_EDGES_DATA);
+    let destructible_edges: Vec<DestructibleEdge> = serde_json::from_str(res.unwrap()).unwrap();
+    apply_destructible_edges(&mut polygons, &destructible_edges);
+
+    let res = std::str::from_utf8(FRICTION_EDGES_DATA);
+    let friction_edges: Vec<FrictionEdge> = serde_json::from_str(res.unwrap()).unwrap();
+    apply_friction_edges(&mut polygons, &friction_edges);
+
+    let res = std::str::from_utf8(CONVEYOR_EDGES_DATA);
+    let conveyor_edges: Vec<ConveyorEdge> = serde_json::from_str(res.unwrap()).unwrap();
+    apply_conveyor_edges(&mut polygons, &conveyor_edges);
+
+    let res = std::str::from_utf8(RESTITUTION_EDGES_DATA);
+    let restitution_edges: Vec<RestitutionEdge> = serde_json::from_str(res.unwrap()).unwrap();
+    apply_restitution_edges(&mut polygons, &restitution_edges);
+
+    let res = std::str::from_utf8(ONE_WAY_PLATFORMS_DATA);
+    let one_way_platforms: Vec<OneWayPlatform> = serde_json::from_str(res.unwrap()).unwrap();
+    apply_one_way_platforms(&mut polygons, &one_way_platforms);
+
+    (polygons, size, half_size)
+}
+
+/// Turns a tile grid (rows top to bottom) into level polygons
+///
+/// The cell the origin falls in has to be open, since that's how the container polygon is found
+pub fn generate_level_polygons_from_grid(
+    level_grid_data: Vec<Vec<usize>>,
+    grid_size: f32,
+    no_node_edges: &[NoNodeEdge],
+    rng: &mut impl Rng,
+) -> (Vec<Polygon>, Vec2, Vec2) {
     let size = Vec2::new(
         level_grid_data[0].len() as f32,
         level_grid_data.len() as f32,
@@ -236,14 +1009,7 @@ pub fn generate_level_polygons(grid_size: f32) -> (Vec<Polygon>, Vec2, Vec2) {
         }
 
         let is_container = point_in_polygon(&polygon_lines, Vec2::new(0.0, 0.0));
-        let mut collision_side = calculate_winding_order(&polygon_lines).signum();
-        if is_container {
-            collision_side *= -1.0;
-        }
-
-        if collision_side == -1.0 {
-            polygon_lines.reverse();
-        }
+        orient_polygon_points(&mut polygon_lines, is_container);
 
         let color = if is_container {
             container_color
@@ -260,13 +1026,300 @@ pub fn generate_level_polygons(grid_size: f32) -> (Vec<Polygon>, Vec2, Vec2) {
             points: polygon_lines,
             color,
             is_container,
+            no_node_lines: Vec::new(),
+            node_spacing: None,
+            destructible_lines: Vec::new(),
+            broken_lines: Vec::new(),
+            is_one_way: false,
+            line_frictions: Vec::new(),
+            line_conveyor_speeds: Vec::new(),
+            line_restitutions: Vec::new(),
+            hole_in: None,
         });
     }
 
+    apply_no_node_edges(&mut polygons, no_node_edges);
+
     return (polygons, size, size / 2.0);
 }
 
-fn calculate_winding_order(vertices: &Vec<Vec2>) -> f32 {
+/// Flags every polygon line that lies along one of the no-node edges
+pub fn apply_no_node_edges(polygons: &mut [Polygon], no_node_edges: &[NoNodeEdge]) {
+    for polygon in polygons.iter_mut() {
+        for line_index in 1..polygon.points.len() {
+            let start = polygon.points[line_index - 1];
+            let end = polygon.points[line_index];
+
+            let excluded = no_node_edges.iter().any(|no_node_edge| {
+                let edge_start = Vec2::from(no_node_edge.start);
+                let edge_end = Vec2::from(no_node_edge.end);
+
+                point_on_segment(start, edge_start, edge_end)
+                    && point_on_segment(end, edge_start, edge_end)
+            });
+
+            if excluded && !polygon.is_line_excluded(line_index - 1) {
+                polygon.no_node_lines.push(line_index - 1);
+            }
+        }
+    }
+}
+
+/// Flags every polygon line that lies along one of the destructible edges
+pub fn apply_destructible_edges(polygons: &mut [Polygon], destructible_edges: &[DestructibleEdge]) {
+    for polygon in polygons.iter_mut() {
+        for line_index in 1..polygon.points.len() {
+            let start = polygon.points[line_index - 1];
+            let end = polygon.points[line_index];
+
+            let destructible = destructible_edges.iter().any(|destructible_edge| {
+                let edge_start = Vec2::from(destructible_edge.start);
+                let edge_end = Vec2::from(destructible_edge.end);
+
+                point_on_segment(start, edge_start, edge_end)
+                    && point_on_segment(end, edge_start, edge_end)
+            });
+
+            if destructible && !polygon.is_line_destructible(line_index - 1) {
+                polygon.destructible_lines.push(line_index - 1);
+            }
+        }
+    }
+}
+
+/// Gives every polygon line that lies along one of the friction edges its friction
+pub fn apply_friction_edges(polygons: &mut [Polygon], friction_edges: &[FrictionEdge]) {
+    for polygon in polygons.iter_mut() {
+        for line_index in 1..polygon.points.len() {
+            let start = polygon.points[line_index - 1];
+            let end = polygon.points[line_index];
+
+            let friction_edge = friction_edges.iter().find(|friction_edge| {
+                let edge_start = Vec2::from(friction_edge.start);
+                let edge_end = Vec2::from(friction_edge.end);
+
+                point_on_segment(start, edge_start, edge_end)
+                    && point_on_segment(end, edge_start, edge_end)
+            });
+
+            if let Some(friction_edge) = friction_edge {
+                polygon
+                    .line_frictions
+                    .retain(|(friction_line_index, _)| *friction_line_index != line_index - 1);
+                polygon
+                    .line_frictions
+                    .push((line_index - 1, friction_edge.friction));
+            }
+        }
+    }
+}
+
+/// Gives every polygon line that lies along one of the restitution edges its restitution
+pub fn apply_restitution_edges(polygons: &mut [Polygon], restitution_edges: &[RestitutionEdge]) {
+    for polygon in polygons.iter_mut() {
+        for line_index in 1..polygon.points.len() {
+            let start = polygon.points[line_index - 1];
+            let end = polygon.points[line_index];
+
+            let restitution_edge = restitution_edges.iter().find(|restitution_edge| {
+                let edge_start = Vec2::from(restitution_edge.start);
+                let edge_end = Vec2::from(restitution_edge.end);
+
+                point_on_segment(start, edge_start, edge_end)
+                    && point_on_segment(end, edge_start, edge_end)
+            });
+
+            if let Some(restitution_edge) = restitution_edge {
+                polygon
+                    .line_restitutions
+                    .retain(|(restitution_line_index, _)| {
+                        *restitution_line_index != line_index - 1
+                    });
+                polygon
+                    .line_restitutions
+                    .push((line_index - 1, restitution_edge.restitution));
+            }
+        }
+    }
+}
+
+/// Makes every polygon line that lies along one of the conveyor edges a conveyor, running the
+/// same way as the edge
+pub fn apply_conveyor_edges(polygons: &mut [Polygon], conveyor_edges: &[ConveyorEdge]) {
+    for polygon in polygons.iter_mut() {
+        for line_index in 1..polygon.points.len() {
+            let start = polygon.points[line_index - 1];
+            let end = polygon.points[line_index];
+
+            let conveyor_edge = conveyor_edges.iter().find(|conveyor_edge| {
+                let edge_start = Vec2::from(conveyor_edge.start);
+                let edge_end = Vec2::from(conveyor_edge.end);
+
+                point_on_segment(start, edge_start, edge_end)
+                    && point_on_segment(end, edge_start, edge_end)
+            });
+
+            if let Some(conveyor_edge) = conveyor_edge {
+                // The line might run the other way around the polygon to the edge
+                let edge_direction =
+                    Vec2::from(conveyor_edge.end) - Vec2::from(conveyor_edge.start);
+                let speed = if (end - start).dot(edge_direction) < 0.0 {
+                    -conveyor_edge.speed
+                } else {
+                    conveyor_edge.speed
+                };
+
+                polygon
+                    .line_conveyor_speeds
+                    .retain(|(conveyor_line_index, _)| *conveyor_line_index != line_index - 1);
+                polygon.line_conveyor_speeds.push((line_index - 1, speed));
+            }
+        }
+    }
+}
+
+/// Makes every polygon one of the one-way platforms points at one-way
+pub fn apply_one_way_platforms(polygons: &mut [Polygon], one_way_platforms: &[OneWayPlatform]) {
+    for one_way_platform in one_way_platforms {
+        let point = Vec2::from(one_way_platform.point);
+
+        let polygon = polygons.iter_mut().find(|polygon| {
+            !polygon.is_hollow()
+                && polygon
+                    .points
+                    .windows(2)
+                    .any(|line| point_on_segment(point, line[0], line[1]))
+        });
+
+        match polygon {
+            Some(polygon) => polygon.is_one_way = true,
+            None => println!("No polygon passes through one-way platform at {}", point),
+        }
+    }
+}
+
+/// Sets the node spacing of every polygon one of the overrides points at
+pub fn apply_node_spacing_overrides(
+    polygons: &mut [Polygon],
+    node_spacing_overrides: &[NodeSpacingOverride],
+) {
+    for node_spacing_override in node_spacing_overrides {
+        let point = Vec2::from(node_spacing_override.point);
+
+        let polygon = polygons.iter_mut().find(|polygon| {
+            polygon
+                .points
+                .windows(2)
+                .any(|line| point_on_segment(point, line[0], line[1]))
+        });
+
+        match polygon {
+            Some(polygon) => polygon.node_spacing = Some(node_spacing_override.spacing),
+            None => println!(
+                "No polygon passes through node spacing override at {}",
+                point
+            ),
+        }
+    }
+}
+
+fn point_on_segment(point: Vec2, segment_start: Vec2, segment_end: Vec2) -> bool {
+    let segment = segment_end - segment_start;
+    let to_point = point - segment_start;
+
+    // Not on the infinite line through the segment
+    if cross_product(segment, to_point).abs() > 0.5 * segment.length() {
+        return false;
+    }
+
+    let t = to_point.dot(segment) / segment.length_squared();
+
+    (-0.001..=1.001).contains(&t)
+}
+
+/// Winds the points so that the side collisions push out of is the right one
+pub fn orient_polygon_points(points: &mut [Vec2], is_container: bool) {
+    let mut collision_side = calculate_winding_order(points).signum();
+    if is_container {
+        collision_side *= -1.0;
+    }
+
+    if collision_side == -1.0 {
+        points.reverse();
+    }
+}
+
+/// Winds the polygon the right way for what it is, keeping its flagged lines on the same lines
+pub fn orient_polygon(polygon: &mut Polygon) {
+    let unoriented_points = polygon.points.clone();
+    let is_hollow = polygon.is_hollow();
+    orient_polygon_points(&mut polygon.points, is_hollow);
+
+    if polygon.points != unoriented_points {
+        let line_count = polygon.points.len() - 1;
+
+        for line_index in polygon
+            .no_node_lines
+            .iter_mut()
+            .chain(polygon.destructible_lines.iter_mut())
+            .chain(polygon.broken_lines.iter_mut())
+        {
+            *line_index = line_count - 1 - *line_index;
+        }
+
+        for (line_index, _) in polygon
+            .line_frictions
+            .iter_mut()
+            .chain(polygon.line_restitutions.iter_mut())
+        {
+            *line_index = line_count - 1 - *line_index;
+        }
+
+        // Reversed lines run the other way, so their conveyors have to as well
+        for (line_index, speed) in polygon.line_conveyor_speeds.iter_mut() {
+            *line_index = line_count - 1 - *line_index;
+            *speed = -*speed;
+        }
+    }
+}
+
+/// Gets polygons from anywhere into the shape the rest of the game expects: closed outlines,
+/// wound so their normals face out of the solid, with holes only in solid polygons
+pub fn normalize_polygons(polygons: &mut [Polygon]) {
+    let hollow: Vec<bool> = polygons.iter().map(Polygon::is_hollow).collect();
+
+    for (polygon_index, polygon) in polygons.iter_mut().enumerate() {
+        if let Some(hole_in) = polygon.hole_in {
+            let in_solid = hole_in != polygon_index && hollow.get(hole_in) == Some(&false);
+
+            if !in_solid || polygon.is_container || polygon.is_one_way {
+                println!(
+                    "Polygon {} can't be a hole in polygon {}, making it solid",
+                    polygon_index, hole_in
+                );
+                polygon.hole_in = None;
+            }
+        }
+
+        if polygon.points.len() > 1 && polygon.points.first() != polygon.points.last() {
+            polygon.points.push(polygon.points[0]);
+        }
+
+        orient_polygon(polygon);
+    }
+}
+
+/// Whether the points are already wound the way `orient_polygon_points` would wind them
+pub fn is_polygon_oriented(points: &[Vec2], is_container: bool) -> bool {
+    let mut collision_side = calculate_winding_order(points).signum();
+    if is_container {
+        collision_side *= -1.0;
+    }
+
+    collision_side != -1.0
+}
+
+fn calculate_winding_order(vertices: &[Vec2]) -> f32 {
     let mut sum = 0.0;
 
     for i in 0..vertices.len() {
@@ -278,7 +1331,7 @@ fn calculate_winding_order(vertices: &Vec<Vec2>) -> f32 {
     sum
 }
 
-fn point_in_polygon(polygon_lines: &Vec<Vec2>, point: Vec2) -> bool {
+pub fn point_in_polygon(polygon_lines: &Vec<Vec2>, point: Vec2) -> bool {
     let test_line_start = point;
     let test_line_end = point + Vec2::new(2.0, 1.0) * 1000.0;
 
@@ -0,0 +1,344 @@
+use std::fmt;
+
+use bevy::{
+    app::{App, Plugin, Startup, Update},
+    asset::{
+        io::Reader, Asset, AssetApp, AssetEvent, AssetLoader, AssetServer, Assets, AsyncReadExt,
+        Handle, LoadContext,
+    },
+    ecs::{
+        change_detection::DetectChangesMut,
+        event::EventReader,
+        schedule::IntoSystemConfigs,
+        system::{Res, ResMut, Resource},
+    },
+    math::Vec2,
+    reflect::TypePath,
+    render::color::Color,
+    utils::BoxedFuture,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    ai::pathfinding::{s_rebuild_pathfinding_graph, PathfindingSettings},
+    level::{
+        normalize_polygons, BouncePad, Hazard, Ladder, Level, Polygon, SpawnPoint, Teleporter,
+        WaterRegion,
+    },
+    markers::Marker,
+    polygon_ops::merge_overlapping_polygons,
+    simulation::Simulation,
+};
+
+pub struct LevelAssetPlugin;
+
+impl Plugin for LevelAssetPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_asset::<LevelAsset>()
+            .init_asset_loader::<LevelAssetLoader>()
+            .insert_resource(LevelAssetHandle(None))
+            .add_systems(Startup, s_load_level_asset)
+            .add_systems(
+                Update,
+                s_apply_level_asset.before(s_rebuild_pathfinding_graph),
+            );
+    }
+}
+
+/// A level stored as polygons in a `.level.json` file under `assets/`
+///
+/// Like the built-in level, containers come in pairs: the level's outer edge, which never gets
+/// pathfinding nodes, followed by the room agents move around in
+///
+/// Solids can have holes cut out of them (rooms inside walls, tunnels, etc.), which are
+/// separate polygons pointing back at the solid they're in
+#[derive(Asset, TypePath, Debug, Serialize, Deserialize)]
+pub struct LevelAsset {
+    pub grid_size: f32,
+    pub polygons: Vec<LevelAssetPolygon>,
+    #[serde(default)]
+    pub spawn_points: Vec<LevelAssetSpawnPoint>,
+    #[serde(default)]
+    pub ladders: Vec<Ladder>,
+    #[serde(default)]
+    pub teleporters: Vec<Teleporter>,
+    #[serde(default)]
+    pub hazards: Vec<Hazard>,
+    #[serde(default)]
+    pub water: Vec<WaterRegion>,
+    #[serde(default)]
+    pub bounce_pads: Vec<BouncePad>,
+    #[serde(default)]
+    pub markers: Vec<Marker>,
+}
+
+/// A spawn point, either named or (like older level files) just where an agent starts
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum LevelAssetSpawnPoint {
+    Named(SpawnPoint),
+    Agent([f32; 2]),
+}
+
+impl LevelAssetSpawnPoint {
+    pub fn to_spawn_point(&self) -> SpawnPoint {
+        match self {
+            LevelAssetSpawnPoint::Named(spawn_point) => spawn_point.clone(),
+            LevelAssetSpawnPoint::Agent(position) => SpawnPoint::agent(Vec2::from(*position)),
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct LevelAssetPolygon {
+    /// The outline, which gets closed if the last point isn't the first one
+    pub points: Vec<[f32; 2]>,
+    #[serde(default)]
+    pub is_container: bool,
+    /// The index (in this file) of the solid polygon this one is a hole cut out of
+    #[serde(default)]
+    pub hole_in: Option<usize>,
+    /// RGB from 0.0 to 1.0, white if left out
+    #[serde(default)]
+    pub color: Option<[f32; 3]>,
+    #[serde(default)]
+    pub no_node_lines: Vec<usize>,
+    #[serde(default)]
+    pub node_spacing: Option<f32>,
+    #[serde(default)]
+    pub destructible_lines: Vec<usize>,
+    #[serde(default)]
+    pub is_one_way: bool,
+    /// (line index, friction) for the lines that aren't the default friction
+    #[serde(default)]
+    pub line_frictions: Vec<(usize, f32)>,
+    /// (line index, speed) for the lines that are conveyors, positive running start to end
+    #[serde(default)]
+    pub line_conveyor_speeds: Vec<(usize, f32)>,
+    /// (line index, restitution) for the lines that bounce things off them
+    #[serde(default)]
+    pub line_restitutions: Vec<(usize, f32)>,
+}
+
+impl LevelAsset {
+    pub fn from_level(level: &Level) -> Self {
+        LevelAsset {
+            grid_size: level.grid_size,
+            polygons: level
+                .polygons
+                .iter()
+                .map(|polygon| LevelAssetPolygon {
+                    points: polygon
+                        .points
+                        .iter()
+                        .map(|point| point.to_array())
+                        .collect(),
+                    is_container: polygon.is_container,
+                    hole_in: polygon.hole_in,
+                    color: Some([polygon.color.r(), polygon.color.g(), polygon.color.b()]),
+                    no_node_lines: polygon.no_node_lines.clone(),
+                    node_spacing: polygon.node_spacing,
+                    destructible_lines: polygon.destructible_lines.clone(),
+                    is_one_way: polygon.is_one_way,
+                    line_frictions: polygon.line_frictions.clone(),
+                    line_conveyor_speeds: polygon.line_conveyor_speeds.clone(),
+                    line_restitutions: polygon.line_restitutions.clone(),
+                })
+                .collect(),
+            spawn_points: level
+                .spawn_points
+                .iter()
+                .map(|spawn_point| LevelAssetSpawnPoint::Named(spawn_point.clone()))
+                .collect(),
+            ladders: level.ladders.clone(),
+            teleporters: level.teleporters.clone(),
+            hazards: level.hazards.clone(),
+            water: level.water.clone(),
+            bounce_pads: level.bounce_pads.clone(),
+            markers: level.markers.clone(),
+        }
+    }
+
+    pub fn to_level(&self) -> Level {
+        let mut polygons: Vec<Polygon> = self
+            .polygons
+            .iter()
+            .map(|polygon| {
+                let points: Vec<Vec2> = polygon
+                    .points
+                    .iter()
+                    .map(|point| Vec2::from(*point))
+                    .collect();
+
+                Polygon {
+                    points,
+                    color: polygon
+                        .color
+                        .map(|[r, g, b]| Color::rgb(r, g, b))
+                        .unwrap_or(Color::WHITE),
+                    is_container: polygon.is_container,
+                    no_node_lines: polygon.no_node_lines.clone(),
+                    node_spacing: polygon.node_spacing,
+                    destructible_lines: polygon.destructible_lines.clone(),
+                    broken_lines: Vec::new(),
+                    is_one_way: polygon.is_one_way,
+                    line_frictions: polygon.line_frictions.clone(),
+                    line_conveyor_speeds: polygon.line_conveyor_speeds.clone(),
+                    line_restitutions: polygon.line_restitutions.clone(),
+                    hole_in: polygon.hole_in,
+                }
+            })
+            .collect();
+
+        // Hand-written and exported files can be wound either way
+        normalize_polygons(&mut polygons);
+
+        // The size is measured in grid cells, like the built-in level's
+        let (min, max) = polygons
+            .iter()
+            .flat_map(|polygon| polygon.points.iter())
+            .fold((Vec2::MAX, Vec2::MIN), |(min, max), point| {
+                (min.min(*point), max.max(*point))
+            });
+        let size = ((max - min) / self.grid_size).max(Vec2::ZERO);
+
+        let mut level = Level {
+            polygons,
+            grid_size: self.grid_size,
+            size,
+            half_size: size / 2.0,
+            markers: self.markers.clone(),
+            spawn_points: self
+                .spawn_points
+                .iter()
+                .map(LevelAssetSpawnPoint::to_spawn_point)
+                .collect(),
+            ladders: self.ladders.clone(),
+            teleporters: self.teleporters.clone(),
+            hazards: self.hazards.clone(),
+            water: self.water.clone(),
+            bounce_pads: self.bounce_pads.clone(),
+        };
+
+        // Authored shapes are allowed to overlap, but the graph and collisions want them apart
+        merge_overlapping_polygons(&mut level);
+
+        level
+    }
+}
+
+#[derive(Debug)]
+pub enum LevelAssetError {
+    Io(std::io::Error),
+    Json(serde_json::Error),
+}
+
+impl fmt::Display for LevelAssetError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LevelAssetError::Io(error) => write!(f, "couldn't read level file: {}", error),
+            LevelAssetError::Json(error) => write!(f, "couldn't parse level file: {}", error),
+        }
+    }
+}
+
+impl std::error::Error for LevelAssetError {}
+
+impl From<std::io::Error> for LevelAssetError {
+    fn from(error: std::io::Error) -> Self {
+        LevelAssetError::Io(error)
+    }
+}
+
+impl From<serde_json::Error> for LevelAssetError {
+    fn from(error: serde_json::Error) -> Self {
+        LevelAssetError::Json(error)
+    }
+}
+
+#[derive(Default)]
+pub struct LevelAssetLoader;
+
+impl AssetLoader for LevelAssetLoader {
+    type Asset = LevelAsset;
+    type Settings = ();
+    type Error = LevelAssetError;
+
+    fn load<'a>(
+        &'a self,
+        reader: &'a mut Reader,
+        _settings: &'a (),
+        _load_context: &'a mut LoadContext,
+    ) -> BoxedFuture<'a, Result<LevelAsset, LevelAssetError>> {
+        Box::pin(async move {
+            let mut bytes = Vec::new();
+            reader.read_to_end(&mut bytes).await?;
+
+            Ok(serde_json::from_slice(&bytes)?)
+        })
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["level.json"]
+    }
+}
+
+/// The level file being played, if one was asked for on the command line
+#[derive(Resource)]
+pub struct LevelAssetHandle(pub Option<Handle<LevelAsset>>);
+
+pub fn s_load_level_asset(
+    simulation: Res<Simulation>,
+    asset_server: Res<AssetServer>,
+    mut level_asset_handle: ResMut<LevelAssetHandle>,
+) {
+    if let Some(level_file) = &simulation.level_file {
+        level_asset_handle.0 = Some(asset_server.load(level_file.clone()));
+    }
+}
+
+/// Swaps the level for the level file whenever it finishes loading or changes on disk
+pub fn s_apply_level_asset(
+    mut asset_events: EventReader<AssetEvent<LevelAsset>>,
+    level_assets: Res<Assets<LevelAsset>>,
+    level_asset_handle: Res<LevelAssetHandle>,
+    mut level: ResMut<Level>,
+    mut pathfinding_settings: ResMut<PathfindingSettings>,
+) {
+    let Some(handle) = &level_asset_handle.0 else {
+        return;
+    };
+
+    for asset_event in asset_events.read() {
+        let changed = match asset_event {
+            AssetEvent::Added { id } | AssetEvent::Modified { id } => *id == handle.id(),
+            _ => false,
+        };
+
+        let Some(level_asset) = level_assets.get(handle).filter(|_| changed) else {
+            continue;
+        };
+
+        *level = level_asset.to_level();
+
+        // Rebuilding the graph (and everything that hangs off it) happens on a settings change
+        pathfinding_settings.set_changed();
+
+        println!("Loaded level with {} polygons", level.polygons.len());
+    }
+}
+
+/// Writes the level out as a level file, so it can be loaded again with `--level-file`
+pub fn save_level_asset(level: &Level, path: &str) {
+    #[cfg(not(target_arch = "wasm32"))]
+    match serde_json::to_string_pretty(&LevelAsset::from_level(level)) {
+        Ok(level_json) => match std::fs::write(path, level_json) {
+            Ok(()) => println!("Saved level to {}", path),
+            Err(error) => println!("Failed to save level: {}", error),
+        },
+        Err(error) => println!("Failed to serialize level: {}", error),
+    }
+
+    #[cfg(target_arch = "wasm32")]
+    let _ = (level, path);
+}
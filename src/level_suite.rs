@@ -0,0 +1,163 @@
+use bevy::math::Vec2;
+use rand::Rng;
+
+use crate::level::{generate_level_polygons_from_grid, Level, SpawnPoint};
+
+/// How many floors the tower level climbs
+const TOWER_FLOORS: usize = 40;
+
+/// How many cells long the corridor level is
+const CORRIDOR_LENGTH: usize = 201;
+
+/// How many cells wide and tall the dense grid level is
+const DENSE_GRID_SIZE: (usize, usize) = (61, 41);
+
+/// How many unreachable islands float above the floor of the islands level
+const ISLAND_COUNT: usize = 8;
+
+/// A generated stress level, so performance and correctness work isn't tied to the built-in level
+pub struct SuiteLevel {
+    pub name: &'static str,
+    /// Tiles in the same format as the built-in level (rows top to bottom)
+    pub grid: Vec<Vec<usize>>,
+    /// The (column, row) of the open cell agents start in
+    pub spawn_cell: (usize, usize),
+}
+
+impl SuiteLevel {
+    pub fn build(&self, grid_size: f32, rng: &mut impl Rng) -> Level {
+        let (polygons, size, half_size) =
+            generate_level_polygons_from_grid(self.grid.clone(), grid_size, &Vec::new(), rng);
+
+        Level {
+            polygons,
+            grid_size,
+            size,
+            half_size,
+            markers: Vec::new(),
+            spawn_points: vec![SpawnPoint::agent(self.spawn_position(grid_size))],
+            ladders: Vec::new(),
+            teleporters: Vec::new(),
+            hazards: Vec::new(),
+            water: Vec::new(),
+            bounce_pads: Vec::new(),
+        }
+    }
+
+    /// The world position of the middle of the spawn cell
+    pub fn spawn_position(&self, grid_size: f32) -> Vec2 {
+        let (columns, rows) = (self.grid[0].len() as f32, self.grid.len() as f32);
+        let (column, row) = (self.spawn_cell.0 as f32, self.spawn_cell.1 as f32);
+
+        Vec2::new(
+            (column + 0.5 - columns / 2.0) * grid_size,
+            (rows / 2.0 - row - 0.5) * grid_size,
+        )
+    }
+}
+
+/// Every level in the suite
+pub fn benchmark_level_suite() -> Vec<SuiteLevel> {
+    vec![
+        tower_level(TOWER_FLOORS),
+        corridor_level(CORRIDOR_LENGTH),
+        dense_grid_level(DENSE_GRID_SIZE.0, DENSE_GRID_SIZE.1),
+        islands_level(ISLAND_COUNT),
+    ]
+}
+
+pub fn find_suite_level(name: &str) -> Option<SuiteLevel> {
+    benchmark_level_suite()
+        .into_iter()
+        .find(|suite_level| suite_level.name == name)
+}
+
+/// A narrow shaft of ledges on alternating sides, each one a short hop above the last
+pub fn tower_level(floors: usize) -> SuiteLevel {
+    let (columns, rows) = (11, (floors + 3) | 1);
+    let mut grid = walled_room(columns, rows);
+
+    for floor in 0..floors {
+        let row = rows - 2 - floor;
+        let ledge_columns = if floor % 2 == 0 { 1..5 } else { 6..10 };
+
+        for column in ledge_columns {
+            grid[row][column] = 1;
+        }
+    }
+
+    // The middle column is never built on, so the spawn cell and the origin stay open
+    SuiteLevel {
+        name: "tower",
+        grid,
+        spawn_cell: (columns / 2, rows - 2),
+    }
+}
+
+/// One long, low, flat corridor, so paths are long chains of walkable connections
+pub fn corridor_level(length: usize) -> SuiteLevel {
+    let (columns, rows) = (length | 1, 7);
+
+    SuiteLevel {
+        name: "corridor",
+        grid: walled_room(columns, rows),
+        spawn_cell: (1, rows - 2),
+    }
+}
+
+/// A big room filled with single blocks, to blow up the node and connection count
+pub fn dense_grid_level(columns: usize, rows: usize) -> SuiteLevel {
+    let (columns, rows) = (columns | 1, rows | 1);
+    let mut grid = walled_room(columns, rows);
+
+    let center_cell = (columns / 2, rows / 2);
+
+    // Leave the floor row clear so there's somewhere to start from
+    for row in (3..rows - 2).step_by(3) {
+        for column in (3..columns - 1).step_by(3) {
+            if (column, row) != center_cell {
+                grid[row][column] = 1;
+            }
+        }
+    }
+
+    SuiteLevel {
+        name: "dense_grid",
+        grid,
+        spawn_cell: (center_cell.0, rows - 2),
+    }
+}
+
+/// A flat floor under platforms floating too high to ever be jumped to
+pub fn islands_level(island_count: usize) -> SuiteLevel {
+    let (columns, rows) = (island_count * 6 + 5, 13);
+    let mut grid = walled_room(columns, rows);
+
+    for island_index in 0..island_count {
+        let first_column = island_index * 6 + 4;
+
+        grid[3][first_column..first_column + 3].fill(1);
+    }
+
+    SuiteLevel {
+        name: "islands",
+        grid,
+        spawn_cell: (columns / 2, rows - 2),
+    }
+}
+
+/// An empty room with solid walls all the way around
+fn walled_room(columns: usize, rows: usize) -> Vec<Vec<usize>> {
+    (0..rows)
+        .map(|row| {
+            (0..columns)
+                .map(|column| {
+                    let on_border =
+                        row == 0 || row == rows - 1 || column == 0 || column == columns - 1;
+
+                    on_border as usize
+                })
+                .collect()
+        })
+        .collect()
+}
@@ -0,0 +1,254 @@
+use bevy::{
+    app::{App, Plugin, Startup, Update},
+    ecs::{
+        change_detection::DetectChanges,
+        schedule::IntoSystemConfigs,
+        system::{Res, ResMut, Resource},
+    },
+    gizmos::gizmos::Gizmos,
+    input::{keyboard::KeyCode, ButtonInput},
+    math::Vec2,
+    render::color::Color,
+};
+
+use crate::{
+    ai::pathfinding::{s_rebuild_pathfinding_graph, Pathfinding, PathfindingSettings},
+    level::{is_polygon_oriented, Level},
+    s_init,
+    utils::line_intersect,
+    PursueAISet,
+};
+
+/// Lines shorter than this are treated as having no length at all
+const DEGENERATE_EDGE_LENGTH: f32 = 0.01;
+
+/// How far off its surface a node is checked for being buried in something
+const NODE_PROBE_DISTANCE: f32 = 1.0;
+
+pub struct LevelValidationPlugin;
+
+impl Plugin for LevelValidationPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(LevelDiagnostics::default())
+            .add_systems(Startup, s_validate_level.after(s_init))
+            .add_systems(
+                Update,
+                (
+                    s_revalidate_level.after(s_rebuild_pathfinding_graph),
+                    s_level_diagnostics_input,
+                    s_render_level_diagnostics.in_set(PursueAISet::Render),
+                ),
+            );
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum LevelDiagnosticKind {
+    SelfIntersection,
+    InconsistentWinding,
+    DegenerateEdge,
+    NodeInsideSolid,
+}
+
+/// Something wrong with the level, where it is and what to do about it
+#[derive(Debug, Clone)]
+pub struct LevelDiagnostic {
+    pub kind: LevelDiagnosticKind,
+    pub position: Vec2,
+    pub message: String,
+}
+
+/// What the last validation of the level found
+#[derive(Resource, Default)]
+pub struct LevelDiagnostics {
+    pub diagnostics: Vec<LevelDiagnostic>,
+    /// Whether the diagnostics are marked on the level
+    pub overlay_visible: bool,
+}
+
+/// Checks the level (and the graph built for it) for geometry the rest of the simulation
+/// can't cope with
+pub fn validate_level(level: &Level, pathfinding: &Pathfinding) -> Vec<LevelDiagnostic> {
+    let mut diagnostics = Vec::new();
+
+    for (polygon_index, polygon) in level.polygons.iter().enumerate() {
+        let line_count = polygon.points.len().saturating_sub(1);
+
+        // Degenerate edges
+        for line_index in 0..line_count {
+            let (start, end) = (polygon.points[line_index], polygon.points[line_index + 1]);
+
+            if (end - start).length() < DEGENERATE_EDGE_LENGTH {
+                diagnostics.push(LevelDiagnostic {
+                    kind: LevelDiagnosticKind::DegenerateEdge,
+                    position: start,
+                    message: format!(
+                        "Polygon {} line {} has no length, remove one of its points",
+                        polygon_index, line_index
+                    ),
+                });
+            }
+        }
+
+        // Self-intersections, between lines that don't share a point
+        let closed = line_count > 1 && polygon.points.first() == polygon.points.last();
+        for line_index in 0..line_count {
+            for other_line_index in line_index + 2..line_count {
+                if closed && line_index == 0 && other_line_index == line_count - 1 {
+                    continue;
+                }
+
+                let intersection = line_intersect(
+                    polygon.points[line_index],
+                    polygon.points[line_index + 1],
+                    polygon.points[other_line_index],
+                    polygon.points[other_line_index + 1],
+                );
+
+                if let Some(intersection) = intersection {
+                    diagnostics.push(LevelDiagnostic {
+                        kind: LevelDiagnosticKind::SelfIntersection,
+                        position: intersection,
+                        message: format!(
+                            "Polygon {} lines {} and {} cross at {}, move a point so its \
+                             outline doesn't cross itself",
+                            polygon_index, line_index, other_line_index, intersection
+                        ),
+                    });
+                }
+            }
+        }
+
+        // Winding
+        if !is_polygon_oriented(&polygon.points, polygon.is_hollow()) {
+            diagnostics.push(LevelDiagnostic {
+                kind: LevelDiagnosticKind::InconsistentWinding,
+                position: polygon.points.first().copied().unwrap_or(Vec2::ZERO),
+                message: format!(
+                    "Polygon {} is wound the wrong way for {}, reverse its points",
+                    polygon_index,
+                    if polygon.is_container {
+                        "a container"
+                    } else if polygon.hole_in.is_some() {
+                        "a hole"
+                    } else {
+                        "a solid"
+                    }
+                ),
+            });
+        }
+    }
+
+    // Nodes buried in solid geometry, going by a point just off the surface they're on
+    for node in pathfinding.nodes.iter() {
+        let probe = node.position + node.normal * NODE_PROBE_DISTANCE;
+
+        let burying_polygon_index = level
+            .polygons
+            .iter()
+            .enumerate()
+            .filter(|(polygon_index, polygon)| {
+                *polygon_index != node.polygon_index
+                    && !polygon.is_hollow()
+                    && !polygon.is_one_way
+                    && polygon.broken_lines.is_empty()
+            })
+            .find(|(polygon_index, _)| level.is_point_in_solid(*polygon_index, probe))
+            .map(|(polygon_index, _)| polygon_index);
+
+        if let Some(burying_polygon_index) = burying_polygon_index {
+            diagnostics.push(LevelDiagnostic {
+                kind: LevelDiagnosticKind::NodeInsideSolid,
+                position: node.position,
+                message: format!(
+                    "Node {} on polygon {} is inside polygon {}, which overlaps it; \
+                     flag the buried line as a no-node line or pull the polygons apart",
+                    node.id, node.polygon_index, burying_polygon_index
+                ),
+            });
+        }
+    }
+
+    diagnostics
+}
+
+fn report_level_diagnostics(
+    level_diagnostics: &mut LevelDiagnostics,
+    level: &Level,
+    pathfinding: &Pathfinding,
+) {
+    level_diagnostics.diagnostics = validate_level(level, pathfinding);
+
+    if level_diagnostics.diagnostics.is_empty() {
+        println!("Level validated with no problems");
+        return;
+    }
+
+    println!(
+        "Level validated with {} problems (V to mark them):",
+        level_diagnostics.diagnostics.len()
+    );
+    for diagnostic in level_diagnostics.diagnostics.iter() {
+        println!("  {:?}: {}", diagnostic.kind, diagnostic.message);
+    }
+}
+
+pub fn s_validate_level(
+    mut level_diagnostics: ResMut<LevelDiagnostics>,
+    level: Res<Level>,
+    pathfinding: Res<Pathfinding>,
+) {
+    report_level_diagnostics(&mut level_diagnostics, &level, &pathfinding);
+}
+
+/// The level (or the graph's take on it) only changes along with a graph rebuild
+pub fn s_revalidate_level(
+    settings: Res<PathfindingSettings>,
+    mut level_diagnostics: ResMut<LevelDiagnostics>,
+    level: Res<Level>,
+    pathfinding: Res<Pathfinding>,
+) {
+    if !settings.is_changed() || settings.is_added() {
+        return;
+    }
+
+    report_level_diagnostics(&mut level_diagnostics, &level, &pathfinding);
+}
+
+pub fn s_level_diagnostics_input(
+    keyboard_input: Res<ButtonInput<KeyCode>>,
+    mut level_diagnostics: ResMut<LevelDiagnostics>,
+) {
+    // V to show and hide the level diagnostics
+    if keyboard_input.just_pressed(KeyCode::KeyV) {
+        level_diagnostics.overlay_visible = !level_diagnostics.overlay_visible;
+    }
+}
+
+pub fn s_render_level_diagnostics(mut gizmos: Gizmos, level_diagnostics: Res<LevelDiagnostics>) {
+    if !level_diagnostics.overlay_visible {
+        return;
+    }
+
+    for diagnostic in level_diagnostics.diagnostics.iter() {
+        let color = match diagnostic.kind {
+            LevelDiagnosticKind::SelfIntersection => Color::RED,
+            LevelDiagnosticKind::InconsistentWinding => Color::ORANGE,
+            LevelDiagnosticKind::DegenerateEdge => Color::YELLOW,
+            LevelDiagnosticKind::NodeInsideSolid => Color::PINK,
+        };
+
+        // An X over the problem, with a ring so it stands out from the level
+        gizmos.line_2d(
+            diagnostic.position - Vec2::splat(5.0),
+            diagnostic.position + Vec2::splat(5.0),
+            color,
+        );
+        gizmos.line_2d(
+            diagnostic.position + Vec2::new(-5.0, 5.0),
+            diagnostic.position + Vec2::new(5.0, -5.0),
+            color,
+        );
+        gizmos.circle_2d(diagnostic.position, 9.0, color);
+    }
+}
@@ -0,0 +1,813 @@
+#[cfg(feature = "debug-draw")]
+pub mod agent_labels;
+pub mod ai;
+#[cfg(feature = "audio")]
+pub mod audio_feedback;
+pub mod bounce_pads;
+pub mod collisions;
+pub mod crumbling_platforms;
+pub mod diagnostics;
+pub mod doors;
+pub mod edge_grid;
+#[cfg(feature = "editor")]
+pub mod editor;
+pub mod event_log;
+pub mod goal_script;
+pub mod hazards;
+pub mod image_level;
+#[cfg(feature = "inspector")]
+pub mod inspector;
+pub mod level;
+pub mod level_asset;
+pub mod level_suite;
+pub mod level_validation;
+pub mod markers;
+pub mod moving_platforms;
+#[cfg(feature = "debug-draw")]
+pub mod node_inspector;
+pub mod obstacles;
+pub mod player;
+pub mod polygon_ops;
+#[cfg(feature = "presentation")]
+pub mod presentation;
+pub mod recording;
+#[cfg(all(feature = "remote", not(target_arch = "wasm32")))]
+pub mod remote;
+pub mod scenario;
+pub mod scenario_scene;
+pub mod settings;
+pub mod simulation;
+pub mod split_view;
+pub mod teleporters;
+pub mod text_level;
+#[cfg(feature = "touch")]
+pub mod touch;
+#[cfg(feature = "debug-draw")]
+pub mod trails;
+pub mod triggers;
+pub mod utils;
+
+use ::bevy::prelude::*;
+#[cfg(feature = "debug-draw")]
+use agent_labels::AgentLabelsPlugin;
+#[cfg(feature = "debug-draw")]
+use ai::animation::{agent_color, AgentAnimation};
+#[cfg(feature = "scripting")]
+use ai::scripting::ScriptingPlugin;
+#[cfg(feature = "pursue")]
+use ai::squad::SquadPlugin;
+use ai::{
+    animation::AnimationPlugin,
+    evader::{Evader, EvaderPlugin},
+    graph_export::GraphExportPlugin,
+    influence_map::InfluenceMapPlugin,
+    jump_feedback::JumpFeedbackPlugin,
+    metrics::MetricsPlugin,
+    navmesh::{NavMesh, NavMeshPlugin},
+    pathfinding::{baked_graph_path, load_or_build_pathfinding_graph, GoalTeleported},
+    platformer_ai::PLATFORMER_AI_AGENT_RADIUS,
+    pursuit::PursuitPlugin,
+};
+#[cfg(feature = "debug-draw")]
+use ai::{
+    cost_heatmap::CostHeatmapPlugin, graph_overlay::GraphOverlayPlugin,
+    jump_preview::JumpPreviewPlugin, search_replay::SearchReplayPlugin,
+};
+#[cfg(feature = "audio")]
+use audio_feedback::AudioFeedbackPlugin;
+use bevy::{app::AppExit, window::PrimaryWindow};
+use bounce_pads::BouncePadsPlugin;
+use collisions::{s_collision, GOAL_POINT_RADIUS};
+use crumbling_platforms::{spawn_crumbling_platforms, CrumblingPlatformsPlugin};
+use diagnostics::AIDiagnosticsPlugin;
+use doors::{spawn_doors, DoorsPlugin};
+#[cfg(feature = "editor")]
+use editor::EditorPlugin;
+use event_log::EventLogPlugin;
+use goal_script::{GoalScript, GoalScriptPlugin};
+use hazards::{HazardsPlugin, Health};
+use image_level::load_image_level;
+use level::{normalize_polygons, DEFAULT_FRICTION};
+use level_asset::LevelAssetPlugin;
+use level_suite::find_suite_level;
+use level_validation::LevelValidationPlugin;
+use markers::MarkersPlugin;
+use moving_platforms::{spawn_moving_platforms, MovingPlatformsPlugin};
+#[cfg(feature = "debug-draw")]
+use node_inspector::NodeInspectorPlugin;
+use obstacles::ObstaclesPlugin;
+use player::{PlayerMode, PlayerPlugin};
+#[cfg(feature = "presentation")]
+use presentation::PresentationPlugin;
+use recording::RecordingPlugin;
+#[cfg(all(feature = "remote", not(target_arch = "wasm32")))]
+use remote::RemotePlugin;
+use scenario::ScenarioPlugin;
+use scenario_scene::ScenarioScenePlugin;
+use settings::SettingsPlugin;
+use simulation::SimulationRng;
+use split_view::SplitViewPlugin;
+use teleporters::TeleportersPlugin;
+use text_level::load_text_level;
+#[cfg(feature = "debug-draw")]
+use trails::TrailsPlugin;
+use triggers::{spawn_triggers, TriggersPlugin};
+use utils::cursor_world_position;
+
+// What other projects need to add the AI to their own app, the rest is reachable through the
+// modules
+pub use ai::{
+    pathfinding::{Pathfinding, PathfindingPlugin, PathfindingSettings},
+    platformer_ai::{PlatformerAI, PlatformerAIPlugin, PlatformerAITuning},
+    pursuit::{PursuitTarget, TargetSelection},
+};
+pub use collisions::{ColliderShape, CollisionPlugin};
+pub use level::Level;
+pub use settings::Settings;
+pub use simulation::{Simulation, SimulationPlugin};
+
+pub const GRAVITY_STRENGTH: f32 = 0.5;
+
+/// Everything the pursue AI needs, from the pathfinding graph and the agents to the level,
+/// the goal point and the debug views
+///
+/// Adds a default [`SimulationPlugin`] unless one was added first, which is how the demo
+/// passes on its command line arguments
+pub struct PursueAIPlugin;
+
+impl Plugin for PursueAIPlugin {
+    fn build(&self, app: &mut App) {
+        if !app.is_plugin_added::<SimulationPlugin>() {
+            app.add_plugins(SimulationPlugin::default());
+        }
+
+        app.register_type::<GoalPoint>()
+            .register_type::<MainCamera>()
+            .register_type::<InputDir>()
+            .register_type::<GoalDrag>()
+            .register_type::<GizmosVisible>()
+            .register_type::<Physics>()
+            .configure_sets(
+                FixedUpdate,
+                (
+                    PursueAISet::Perception,
+                    PursueAISet::Decision,
+                    PursueAISet::Planning,
+                    PursueAISet::Movement,
+                    PursueAISet::Collision,
+                )
+                    .chain(),
+            )
+            .insert_resource(InputDir { dir: Vec2::ZERO })
+            .insert_resource(GizmosVisible { visible: false })
+            .insert_resource(GoalDrag::default())
+            .add_plugins(SettingsPlugin)
+            .add_plugins(PathfindingPlugin)
+            .add_plugins(InfluenceMapPlugin)
+            .add_plugins(GraphExportPlugin)
+            .add_plugins(RecordingPlugin)
+            .add_plugins(MetricsPlugin)
+            .add_plugins(AIDiagnosticsPlugin)
+            .add_plugins(EventLogPlugin)
+            .add_plugins(ScenarioPlugin)
+            .add_plugins(ScenarioScenePlugin)
+            .add_plugins(GoalScriptPlugin)
+            .add_plugins(EvaderPlugin)
+            .add_plugins(NavMeshPlugin)
+            .add_plugins(PlatformerAIPlugin)
+            .add_plugins(PursuitPlugin)
+            .add_plugins(JumpFeedbackPlugin)
+            .add_plugins(AnimationPlugin)
+            .add_plugins(CollisionPlugin)
+            .add_plugins(MarkersPlugin)
+            .add_plugins(LevelAssetPlugin)
+            .add_plugins(ObstaclesPlugin)
+            .add_plugins(MovingPlatformsPlugin)
+            .add_plugins(DoorsPlugin)
+            .add_plugins(TeleportersPlugin)
+            .add_plugins(HazardsPlugin)
+            .add_plugins(BouncePadsPlugin)
+            .add_plugins(CrumblingPlatformsPlugin)
+            .add_plugins(LevelValidationPlugin)
+            .add_plugins(TriggersPlugin)
+            .add_plugins(PlayerPlugin)
+            .add_plugins(SplitViewPlugin)
+            // Startup systems
+            .add_systems(Startup, s_init)
+            // Update systems
+            .add_systems(Update, s_input)
+            // Fixed update systems
+            .add_systems(
+                FixedUpdate,
+                (
+                    s_move_goal_point.in_set(PursueAISet::Movement),
+                    s_sync_goal_point
+                        .in_set(PursueAISet::Collision)
+                        .after(s_collision),
+                ),
+            );
+
+        // The squad decides when each agent pursues, searches or attacks, without it they just
+        // chase the goal
+        #[cfg(feature = "pursue")]
+        app.add_plugins(SquadPlugin);
+
+        #[cfg(feature = "scripting")]
+        app.add_plugins(ScriptingPlugin);
+
+        #[cfg(all(feature = "remote", not(target_arch = "wasm32")))]
+        app.add_plugins(RemotePlugin);
+
+        #[cfg(feature = "editor")]
+        app.add_plugins(EditorPlugin);
+
+        #[cfg(feature = "presentation")]
+        app.add_plugins(PresentationPlugin);
+
+        #[cfg(feature = "audio")]
+        app.add_plugins(AudioFeedbackPlugin);
+
+        #[cfg(feature = "debug-draw")]
+        app.add_plugins(GraphOverlayPlugin)
+            .add_plugins(SearchReplayPlugin)
+            .add_plugins(NodeInspectorPlugin)
+            .add_plugins(CostHeatmapPlugin)
+            .add_plugins(JumpPreviewPlugin)
+            .add_plugins(AgentLabelsPlugin)
+            .add_plugins(TrailsPlugin)
+            .add_systems(Update, s_render.in_set(PursueAISet::Render));
+    }
+}
+
+/// Where the plugins' systems run, so other projects can slot their own in between
+///
+/// Every fixed tick runs `Perception`, `Decision`, `Planning`, `Movement` and `Collision` in
+/// that order, and `Render` runs in `Update`
+#[derive(SystemSet, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum PursueAISet {
+    /// Agents take in the world: what to pursue, where the danger is, what changed in the graph
+    Perception,
+    /// Agents (and the goal when it's not player controlled) decide what to do about it
+    Decision,
+    /// Paths are asked for and searched for
+    Planning,
+    /// Agents, the goal and the player's character move
+    Movement,
+    /// Bodies are pushed out of the level and each other, and everything that reacts to where
+    /// they ended up runs
+    Collision,
+    /// The gizmos and overlays are drawn
+    Render,
+}
+
+/// The goal point as a body, moved by the player and kept out of the level like the agents
+#[derive(Component, Reflect)]
+#[reflect(Component)]
+pub struct GoalPoint;
+
+/// The camera showing the whole level, when the view isn't split
+#[derive(Component, Reflect)]
+#[reflect(Component)]
+pub struct MainCamera;
+
+#[derive(Resource, Default, Reflect)]
+#[reflect(Resource)]
+pub struct InputDir {
+    pub dir: Vec2,
+}
+
+/// Where the goal point is being dragged to with the mouse
+#[derive(Resource, Default, Reflect)]
+#[reflect(Resource)]
+pub struct GoalDrag {
+    pub target: Option<Vec2>,
+}
+
+#[derive(Resource, Default, Reflect)]
+#[reflect(Resource)]
+pub struct GizmosVisible {
+    pub visible: bool,
+}
+
+#[derive(Component, Reflect)]
+#[reflect(Component)]
+pub struct Physics {
+    pub prev_position: Vec2,
+    pub velocity: Vec2,
+    pub acceleration: Vec2,
+    pub radius: f32,
+    /// What shape the body collides with the level as, sized by the radius unless it's a box
+    pub shape: ColliderShape,
+    pub normal: Vec2,
+    pub grounded: bool,
+    pub walled: i8,
+    pub has_wall_jumped: bool,
+    /// Whether the body's used up its jump in mid-air, until it lands or grabs a wall
+    pub has_double_jumped: bool,
+    /// The friction of the surface the body's on, which is the default while it's in the air
+    pub friction: f32,
+    /// How fast the surface the body's on carries it along, like a conveyor
+    pub surface_velocity: Vec2,
+    /// How much of its speed the body bounces back with when it hits something, on top of
+    /// whatever the line it hits gives
+    pub restitution: f32,
+}
+
+impl Physics {
+    /// A still, airborne circle of the radius, on the default friction
+    pub fn circle(radius: f32) -> Physics {
+        Physics {
+            prev_position: Vec2::ZERO,
+            velocity: Vec2::ZERO,
+            acceleration: Vec2::ZERO,
+            radius,
+            shape: ColliderShape::Circle,
+            normal: Vec2::ZERO,
+            grounded: false,
+            walled: 0,
+            has_wall_jumped: false,
+            has_double_jumped: false,
+            friction: DEFAULT_FRICTION,
+            surface_velocity: Vec2::ZERO,
+            restitution: 0.0,
+        }
+    }
+}
+
+pub fn s_init(
+    mut commands: Commands,
+    mut pathfinding: ResMut<Pathfinding>,
+    pathfinding_settings: Res<PathfindingSettings>,
+    simulation: Res<Simulation>,
+    mut simulation_rng: ResMut<SimulationRng>,
+) {
+    let grid_size = 32.0;
+    let rng = &mut simulation_rng.0;
+
+    let text_level = simulation
+        .text_level
+        .as_deref()
+        .and_then(|text_level_path| match load_text_level(text_level_path) {
+            Ok(text_level) => Some(text_level),
+            Err(error) => {
+                println!(
+                    "Couldn't load {} ({}), using the built-in level",
+                    text_level_path, error
+                );
+                None
+            }
+        });
+
+    let image_level = simulation
+        .image_level
+        .as_deref()
+        .and_then(
+            |image_level_path| match load_image_level(image_level_path) {
+                Ok(image_level) => Some(image_level),
+                Err(error) => {
+                    println!(
+                        "Couldn't load {} ({}), using the built-in level",
+                        image_level_path, error
+                    );
+                    None
+                }
+            },
+        );
+
+    let suite_level = simulation.level_name.as_deref().and_then(|level_name| {
+        let suite_level = find_suite_level(level_name);
+        if suite_level.is_none() {
+            println!(
+                "No suite level named {}, using the built-in level",
+                level_name
+            );
+        }
+        suite_level
+    });
+
+    let mut level = if let Some(text_level) = &text_level {
+        text_level.build(grid_size, rng)
+    } else if let Some(image_level) = &image_level {
+        image_level.build(grid_size, rng)
+    } else if let Some(suite_level) = &suite_level {
+        suite_level.build(grid_size, rng)
+    } else {
+        let mut level = Level::built_in(grid_size, rng);
+
+        spawn_moving_platforms(&mut commands, &mut level);
+        spawn_doors(&mut commands, &mut level);
+        spawn_crumbling_platforms(&mut commands, &mut level);
+        spawn_triggers(&mut commands);
+
+        level
+    };
+
+    normalize_polygons(&mut level.polygons);
+
+    // Scenarios can start the agent and the goal somewhere else
+    let scenario = simulation.scenario.as_ref();
+
+    let spawn_position = scenario
+        .and_then(|scenario| scenario.agent_spawn)
+        .unwrap_or_else(|| level.agent_spawn_position());
+
+    if let Some(goal_start_position) = scenario
+        .and_then(|scenario| scenario.goal_start)
+        .or_else(|| level.goal_start_position())
+    {
+        pathfinding.goal_position = goal_start_position;
+    }
+
+    // Text and image levels bake their graph under the file's name
+    let level_path = match (&text_level, &image_level) {
+        (Some(_), _) => simulation.text_level.as_deref(),
+        (None, Some(_)) => simulation.image_level.as_deref(),
+        (None, None) => None,
+    };
+    let baked_graph_name = match level_path {
+        Some(level_path) => std::path::Path::new(level_path)
+            .file_stem()
+            .and_then(|file_stem| file_stem.to_str()),
+        None => simulation.level_name.as_deref(),
+    };
+
+    load_or_build_pathfinding_graph(
+        &level,
+        &mut pathfinding,
+        &pathfinding_settings,
+        &baked_graph_path(baked_graph_name),
+        simulation.bake,
+    );
+
+    commands.insert_resource(NavMesh::build(&level, &pathfinding));
+
+    commands.insert_resource(level);
+
+    commands.spawn((Camera2dBundle::default(), MainCamera));
+
+    commands.spawn((
+        GoalPoint,
+        PursuitTarget,
+        Transform::from_translation(pathfinding.goal_position.extend(0.0)),
+        goal_point_physics(pathfinding.goal_position),
+    ));
+
+    commands.spawn((
+        Transform::from_translation(spawn_position.extend(0.0)),
+        agent_body(),
+        PlatformerAITuning::default(),
+    ));
+}
+
+/// The goal point's body, starting out still at the position
+pub fn goal_point_physics(position: Vec2) -> Physics {
+    Physics {
+        prev_position: position,
+        ..Physics::circle(GOAL_POINT_RADIUS)
+    }
+}
+
+/// Everything an agent needs on top of its transform and tuning
+pub fn agent_body() -> (Physics, PlatformerAI, Health) {
+    (
+        Physics::circle(PLATFORMER_AI_AGENT_RADIUS),
+        PlatformerAI::default(),
+        Health::default(),
+    )
+}
+
+/// Puts the agents back at their spawn and the goal back at its start
+pub fn reset_positions(
+    level: &Level,
+    pathfinding: &mut Pathfinding,
+    platformer_ai_query: &mut Query<(&mut Transform, &mut Physics, &mut PlatformerAI)>,
+) -> GoalTeleported {
+    for (mut transform, mut physics, mut platformer_ai) in platformer_ai_query.iter_mut() {
+        let spawn_position = level.agent_spawn_position();
+
+        transform.translation = spawn_position.extend(0.0);
+        physics.prev_position = spawn_position;
+        physics.velocity = Vec2::ZERO;
+        physics.acceleration = Vec2::ZERO;
+        platformer_ai.climbing = None;
+        platformer_ai.mantling = None;
+        platformer_ai.dashing = None;
+        platformer_ai.teleported_to = None;
+    }
+
+    pathfinding.teleport_goal(level.goal_start_position().unwrap_or(Vec2::ZERO))
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn s_input(
+    keyboard_input: Res<ButtonInput<KeyCode>>,
+    mut exit: EventWriter<AppExit>,
+    mut input_dir: ResMut<InputDir>,
+    mut gizmos_visible: ResMut<GizmosVisible>,
+    mut platformer_ai_query: Query<(&mut Transform, &mut Physics, &mut PlatformerAI)>,
+    mouse_buttons: Res<ButtonInput<MouseButton>>,
+    q_windows: Query<&Window, With<PrimaryWindow>>,
+    mut pathfinding: ResMut<Pathfinding>,
+    mut goal_teleported_events: EventWriter<GoalTeleported>,
+    mut goal_drag: ResMut<GoalDrag>,
+    camera_query: Query<(&Camera, &GlobalTransform), With<MainCamera>>,
+    level: Res<Level>,
+) {
+    let mouse_pos_world = match (q_windows.get_single(), camera_query.get_single()) {
+        (Ok(window), Ok((camera, camera_transform))) => {
+            cursor_world_position(window, camera, camera_transform)
+        }
+        _ => None,
+    };
+
+    // Escape to exit (if not WASM)
+    #[cfg(not(target_arch = "wasm32"))]
+    if keyboard_input.just_pressed(KeyCode::Escape) {
+        exit.send(AppExit);
+    }
+
+    // R to reset
+    if keyboard_input.just_pressed(KeyCode::KeyR) {
+        goal_teleported_events.send(reset_positions(
+            &level,
+            &mut pathfinding,
+            &mut platformer_ai_query,
+        ));
+    }
+
+    // Arrow keys to move goal point
+    {
+        let mut direction = Vec2::ZERO;
+
+        if keyboard_input.pressed(KeyCode::ArrowUp) {
+            direction.y += 1.0;
+        }
+        if keyboard_input.pressed(KeyCode::ArrowDown) {
+            direction.y -= 1.0;
+        }
+        if keyboard_input.pressed(KeyCode::ArrowLeft) {
+            direction.x -= 1.0;
+        }
+        if keyboard_input.pressed(KeyCode::ArrowRight) {
+            direction.x += 1.0;
+        }
+
+        // Normalize direction
+        direction = direction.normalize_or_zero();
+
+        // Set direction resource
+        input_dir.dir = direction;
+    }
+
+    // G to toggle gizmos
+    if keyboard_input.just_pressed(KeyCode::KeyG) {
+        gizmos_visible.visible = !gizmos_visible.visible;
+    }
+
+    // Space to toggle goal point
+    if keyboard_input.just_pressed(KeyCode::Space) {
+        toggle_goal(&mut pathfinding);
+    }
+
+    // Right click to teleport the goal point, and hold to drag it around
+    if mouse_buttons.just_pressed(MouseButton::Right) {
+        if let Some(mouse_pos_world) = mouse_pos_world {
+            goal_teleported_events.send(pathfinding.teleport_goal(mouse_pos_world));
+        }
+    } else if mouse_buttons.pressed(MouseButton::Right) {
+        // Keeps the last spot while the cursor's off the window
+        if mouse_pos_world.is_some() {
+            goal_drag.target = mouse_pos_world;
+        }
+    }
+    if mouse_buttons.just_released(MouseButton::Right) {
+        goal_drag.target = None;
+    }
+}
+pub fn toggle_goal(pathfinding: &mut Pathfinding) {
+    pathfinding.active = !pathfinding.active;
+    if pathfinding.active {
+        // Set the closest node to the node closest to the goal point
+        pathfinding.goal_graph_node = pathfinding
+            .nearest_node_id(pathfinding.goal_position)
+            .map(|node_id| pathfinding.nodes[node_id].clone());
+    } else {
+        pathfinding.goal_graph_node = None;
+    }
+}
+
+/// Moves the goal point's body, leaving the level collision to stop it at walls
+#[allow(clippy::too_many_arguments)]
+pub fn s_move_goal_point(
+    input_dir: Res<InputDir>,
+    goal_drag: Res<GoalDrag>,
+    player_mode: Res<PlayerMode>,
+    goal_script: Res<GoalScript>,
+    evader: Res<Evader>,
+    settings: Res<Settings>,
+    mut goal_teleported_events: EventReader<GoalTeleported>,
+    mut goal_query: Query<(&mut Transform, &mut Physics), With<GoalPoint>>,
+) {
+    let teleported_to = goal_teleported_events.read().last().map(|event| event.to);
+
+    for (mut transform, mut physics) in goal_query.iter_mut() {
+        // Teleports put the goal straight there, with nothing in the way
+        if let Some(teleported_to) = teleported_to {
+            transform.translation = teleported_to.extend(0.0);
+        }
+
+        // The player's character moves itself
+        if player_mode.active {
+            continue;
+        }
+
+        physics.prev_position = transform.translation.xy();
+
+        // Dragged goals follow the cursor, but still can't go through walls, and a running goal
+        // script or the evader takes over from both
+        physics.velocity = match (goal_script.velocity.or(evader.velocity), goal_drag.target) {
+            (Some(velocity), _) => velocity,
+            (None, Some(target)) => target - transform.translation.xy(),
+            (None, None) => input_dir.dir * settings.goal.speed,
+        };
+        transform.translation += physics.velocity.extend(0.0);
+    }
+}
+
+/// Puts the goal where its body ended up after collision
+pub fn s_sync_goal_point(
+    goal_query: Query<&Transform, With<GoalPoint>>,
+    mut pathfinding: ResMut<Pathfinding>,
+) {
+    let Ok(goal_transform) = goal_query.get_single() else {
+        return;
+    };
+
+    let goal_position = goal_transform.translation.xy();
+    if goal_position != pathfinding.goal_position {
+        pathfinding.goal_position = goal_position;
+    }
+
+    if pathfinding.active {
+        // Set the closest node to the node closest to the goal point
+        pathfinding.goal_graph_node = pathfinding
+            .nearest_node_id(pathfinding.goal_position)
+            .map(|node_id| pathfinding.nodes[node_id].clone());
+    }
+}
+
+#[cfg(feature = "debug-draw")]
+pub fn s_render(
+    mut gizmos: Gizmos,
+    level: Res<Level>,
+    platformer_ai_query: Query<(&Transform, &Physics, Option<&AgentAnimation>), With<PlatformerAI>>,
+    pathfinding: Res<Pathfinding>,
+    gizmos_visible: Res<GizmosVisible>,
+    #[cfg(feature = "presentation")] presentation: Res<presentation::Presentation>,
+) {
+    // The presentation layer fills in the level and draws the bodies as sprites instead
+    #[cfg(feature = "presentation")]
+    let presenting = presentation.active;
+    #[cfg(not(feature = "presentation"))]
+    let presenting = false;
+
+    if !presenting {
+        // Draw the level polygons
+        for polygon_index in 0..level.polygons.len() {
+            let polygon = &level.polygons[polygon_index];
+
+            if polygon.destructible_lines.is_empty()
+                && polygon.broken_lines.is_empty()
+                && !polygon.is_one_way
+            {
+                gizmos.linestrip_2d(
+                    polygon.points.iter().cloned().collect::<Vec<Vec2>>(),
+                    polygon.color,
+                );
+                continue;
+            }
+
+            // Destructible lines stand out, broken ones aren't there at all, and the sides of
+            // one-way platforms fade out since nothing collides with them
+            for line_index in 1..polygon.points.len() {
+                if polygon.is_line_broken(line_index - 1) {
+                    continue;
+                }
+
+                let color = if polygon.is_line_destructible(line_index - 1) {
+                    Color::ORANGE_RED
+                } else if polygon.is_line_passable(line_index - 1) {
+                    polygon.color.with_a(0.25)
+                } else {
+                    polygon.color
+                };
+
+                gizmos.line_2d(
+                    polygon.points[line_index - 1],
+                    polygon.points[line_index],
+                    color,
+                );
+            }
+        }
+    }
+
+    // Draw which way the conveyors run
+    for polygon in level.polygons.iter() {
+        for (line_index, speed) in polygon.line_conveyor_speeds.iter() {
+            let Some((&start, &end)) = polygon
+                .points
+                .get(*line_index)
+                .zip(polygon.points.get(*line_index + 1))
+            else {
+                continue;
+            };
+
+            let direction = (end - start).normalize_or_zero() * speed.signum();
+            let middle = (start + end) / 2.0;
+
+            gizmos.line_2d(
+                middle - direction * 6.0,
+                middle + direction * 6.0,
+                Color::TEAL,
+            );
+            gizmos.line_2d(
+                middle + direction * 6.0,
+                middle + direction * 2.0 + direction.perp() * 4.0,
+                Color::TEAL,
+            );
+            gizmos.line_2d(
+                middle + direction * 6.0,
+                middle + direction * 2.0 - direction.perp() * 4.0,
+                Color::TEAL,
+            );
+        }
+    }
+
+    // Draw the ladders, with a rung every half grid cell
+    for ladder in level.ladders.iter() {
+        gizmos.rect_2d(
+            (ladder.min + ladder.max) / 2.0,
+            0.0,
+            ladder.max - ladder.min,
+            Color::BEIGE,
+        );
+
+        let mut rung_y = ladder.min.y;
+        while rung_y <= ladder.max.y {
+            gizmos.line_2d(
+                Vec2::new(ladder.min.x, rung_y),
+                Vec2::new(ladder.max.x, rung_y),
+                Color::BEIGE,
+            );
+            rung_y += level.grid_size / 2.0;
+        }
+    }
+
+    // Draw the water
+    for water in level.water.iter() {
+        gizmos.rect_2d(
+            (water.min + water.max) / 2.0,
+            0.0,
+            water.max - water.min,
+            Color::rgba(0.2, 0.4, 1.0, 0.6),
+        );
+    }
+
+    if !presenting {
+        // Draw the goal point
+        gizmos.circle_2d(
+            pathfinding.goal_position,
+            GOAL_POINT_RADIUS,
+            if pathfinding.active {
+                Color::GREEN
+            } else {
+                Color::GRAY.with_a(0.2)
+            },
+        );
+
+        // Draw the AI
+        for (transform, physics, animation) in platformer_ai_query.iter() {
+            let position = transform.translation.xy();
+            let color = animation.map_or(Color::RED, agent_color);
+
+            match physics.shape {
+                ColliderShape::Circle => {
+                    gizmos.circle_2d(position, physics.radius, color);
+                }
+                ColliderShape::Capsule { half_height } => {
+                    let top = position + Vec2::Y * half_height;
+                    let bottom = position - Vec2::Y * half_height;
+                    let side = Vec2::X * physics.radius;
+
+                    gizmos.circle_2d(top, physics.radius, color);
+                    gizmos.circle_2d(bottom, physics.radius, color);
+                    gizmos.line_2d(top - side, bottom - side, color);
+                    gizmos.line_2d(top + side, bottom + side, color);
+                }
+                ColliderShape::Aabb { half_extents } => {
+                    gizmos.rect_2d(position, 0.0, half_extents * 2.0, color);
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,205 @@
+use bevy::{
+    app::{App, Plugin, Update},
+    ecs::{
+        change_detection::DetectChanges,
+        component::Component,
+        entity::Entity,
+        query::With,
+        reflect::ReflectComponent,
+        schedule::IntoSystemConfigs,
+        system::{Commands, Query, Res, ResMut},
+    },
+    gizmos::gizmos::Gizmos,
+    input::{keyboard::KeyCode, ButtonInput},
+    math::Vec2,
+    reflect::Reflect,
+    render::{camera::Camera, color::Color},
+    text::{Text, Text2dBundle, TextStyle},
+    transform::components::{GlobalTransform, Transform},
+    utils::default,
+    window::{PrimaryWindow, Window},
+};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    level::Level, level_asset::save_level_asset, simulation::Simulation,
+    utils::cursor_world_position, MainCamera, PursueAISet,
+};
+
+const MARKERS_DATA: &[u8] = include_bytes!("../assets/markers.json");
+
+#[cfg(not(target_arch = "wasm32"))]
+const MARKERS_FILE_PATH: &str = "assets/markers.json";
+
+/// How close the cursor has to be to a marker to remove it
+const MARKER_REMOVE_RADIUS: f32 = 16.0;
+
+pub struct MarkersPlugin;
+
+impl Plugin for MarkersPlugin {
+    fn build(&self, app: &mut App) {
+        app.register_type::<MarkerLabel>().add_systems(
+            Update,
+            (s_marker_input, s_render_markers.in_set(PursueAISet::Render)),
+        );
+    }
+}
+
+/// A named point in the level, used to script reproducible scenarios
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Marker {
+    pub name: String,
+    pub x: f32,
+    pub y: f32,
+}
+
+impl Marker {
+    pub fn position(&self) -> Vec2 {
+        Vec2::new(self.x, self.y)
+    }
+}
+
+#[derive(Component, Reflect)]
+#[reflect(Component)]
+pub struct MarkerLabel;
+
+/// Loads the markers saved alongside the built-in level, falling back to the bundled ones
+///
+/// Level files keep their markers in the file itself
+pub fn load_markers() -> Vec<Marker> {
+    #[cfg(not(target_arch = "wasm32"))]
+    if let Ok(markers_json) = std::fs::read_to_string(MARKERS_FILE_PATH) {
+        if let Ok(markers) = serde_json::from_str(&markers_json) {
+            return markers;
+        }
+    }
+
+    let res = std::str::from_utf8(MARKERS_DATA);
+    serde_json::from_str(res.unwrap()).unwrap_or_default()
+}
+
+pub fn save_markers(markers: &Vec<Marker>) {
+    #[cfg(not(target_arch = "wasm32"))]
+    match serde_json::to_string_pretty(markers) {
+        Ok(markers_json) => {
+            if let Err(error) = std::fs::write(MARKERS_FILE_PATH, markers_json) {
+                println!("Failed to save markers: {}", error);
+            }
+        }
+        Err(error) => println!("Failed to serialize markers: {}", error),
+    }
+
+    #[cfg(target_arch = "wasm32")]
+    let _ = markers;
+}
+
+pub fn s_marker_input(
+    keyboard_input: Res<ButtonInput<KeyCode>>,
+    q_windows: Query<&Window, With<PrimaryWindow>>,
+    camera_query: Query<(&Camera, &GlobalTransform), With<MainCamera>>,
+    mut level: ResMut<Level>,
+    simulation: Res<Simulation>,
+) {
+    if !keyboard_input.just_pressed(KeyCode::KeyM) {
+        return;
+    }
+
+    let (Ok(window), Ok((camera, camera_transform))) =
+        (q_windows.get_single(), camera_query.get_single())
+    else {
+        return;
+    };
+
+    if let Some(mouse_pos_world) = cursor_world_position(window, camera, camera_transform) {
+        let shift_held = keyboard_input.pressed(KeyCode::ShiftLeft)
+            || keyboard_input.pressed(KeyCode::ShiftRight);
+
+        // Shift + M to remove the marker under the cursor
+        if shift_held {
+            let marker_index = level.markers.iter().position(|marker| {
+                (marker.position() - mouse_pos_world).length_squared()
+                    < MARKER_REMOVE_RADIUS.powi(2)
+            });
+
+            if let Some(marker_index) = marker_index {
+                let marker = level.markers.remove(marker_index);
+                println!("Removed marker {}", marker.name);
+            }
+        }
+        // M to drop a marker at the cursor
+        else {
+            let mut marker_number = level.markers.len() + 1;
+            while level
+                .get_marker(&format!("marker_{}", marker_number))
+                .is_some()
+            {
+                marker_number += 1;
+            }
+
+            let marker = Marker {
+                name: format!("marker_{}", marker_number),
+                x: mouse_pos_world.x,
+                y: mouse_pos_world.y,
+            };
+
+            println!("Dropped marker {} at {}", marker.name, marker.position());
+
+            level.markers.push(marker);
+        }
+
+        match &simulation.level_file {
+            Some(level_file) => save_level_asset(&level, &format!("assets/{}", level_file)),
+            None => save_markers(&level.markers),
+        }
+    }
+}
+
+pub fn s_render_markers(
+    mut commands: Commands,
+    mut gizmos: Gizmos,
+    level: Res<Level>,
+    label_query: Query<Entity, With<MarkerLabel>>,
+) {
+    // Rebuild the labels whenever the level (and so possibly its markers) changes
+    if level.is_changed() {
+        for entity in label_query.iter() {
+            commands.entity(entity).despawn();
+        }
+
+        for marker in level.markers.iter() {
+            commands.spawn((
+                Text2dBundle {
+                    text: Text::from_section(
+                        marker.name.clone(),
+                        TextStyle {
+                            font_size: 14.0,
+                            color: Color::YELLOW,
+                            ..default()
+                        },
+                    ),
+                    transform: Transform::from_translation(
+                        (marker.position() + Vec2::new(0.0, 14.0)).extend(1.0),
+                    ),
+                    ..default()
+                },
+                MarkerLabel,
+            ));
+        }
+    }
+
+    // Draw a cross on each marker
+    for marker in level.markers.iter() {
+        let position = marker.position();
+
+        gizmos.line_2d(
+            position - Vec2::new(5.0, 5.0),
+            position + Vec2::new(5.0, 5.0),
+            Color::YELLOW,
+        );
+        gizmos.line_2d(
+            position - Vec2::new(5.0, -5.0),
+            position + Vec2::new(5.0, -5.0),
+            Color::YELLOW,
+        );
+    }
+}
@@ -0,0 +1,525 @@
+use std::{collections::HashMap, f32::consts::TAU, ops::Range};
+
+use bevy::{
+    app::{App, FixedUpdate, Plugin, Startup, Update},
+    ecs::{
+        change_detection::{DetectChanges, DetectChangesMut},
+        component::Component,
+        entity::Entity,
+        reflect::ReflectComponent,
+        schedule::IntoSystemConfigs,
+        system::{Commands, Query, Res, ResMut, Resource},
+    },
+    math::{Vec2, Vec3Swizzles},
+    reflect::Reflect,
+    render::color::Color,
+    transform::components::Transform,
+};
+use serde::Deserialize;
+
+use crate::{
+    ai::{
+        navmesh::{s_rebuild_navmesh, NavMesh},
+        pathfinding::{
+            build_spatial_index, find_jumps_touching, s_rebuild_pathfinding_graph, Pathfinding,
+            PathfindingGraphConnection, PathfindingGraphConnectionType, PathfindingSettings,
+        },
+        platformer_ai::PLATFORMER_AI_AGENT_RADIUS,
+    },
+    collisions::find_projection,
+    level::{orient_polygon_points, Level, Polygon},
+    s_init, Physics, PursueAISet,
+};
+
+const MOVING_PLATFORMS_DATA: &[u8] = include_bytes!("../assets/moving_platforms.json");
+
+/// How many poses along a sine platform's swing get their own jumps
+const SINE_PLATFORM_POSES: usize = 8;
+
+/// How close a platform has to be to a pose for the jumps worked out there to be taken
+const PLATFORM_POSE_TOLERANCE: f32 = 6.0;
+
+pub struct MovingPlatformsPlugin;
+
+impl Plugin for MovingPlatformsPlugin {
+    fn build(&self, app: &mut App) {
+        app.register_type::<PlatformMotion>()
+            .register_type::<MovingPlatform>()
+            .insert_resource(PlatformConnections::default())
+            .add_systems(Startup, s_setup_moving_platforms.after(s_init))
+            .add_systems(
+                Update,
+                s_rebuild_platform_connections
+                    .after(s_rebuild_pathfinding_graph)
+                    .before(s_rebuild_navmesh),
+            )
+            // Platforms move before the agents look at where they are
+            .add_systems(
+                FixedUpdate,
+                s_move_platforms.before(PursueAISet::Perception),
+            );
+    }
+}
+
+/// How a platform moves, relative to where it's placed in the level
+#[derive(Debug, Clone, Deserialize, Reflect)]
+pub enum PlatformMotion {
+    /// Travels between the offsets in order at a constant speed (per tick), looping back to
+    /// the first one and waiting at each of them
+    Waypoints {
+        offsets: Vec<[f32; 2]>,
+        speed: f32,
+        #[serde(default)]
+        wait_ticks: f32,
+    },
+    /// Swings back and forth through where it's placed, taking `period` ticks to come back
+    Sine { amplitude: [f32; 2], period: f32 },
+}
+
+impl PlatformMotion {
+    pub fn offset_at(&self, ticks: f32) -> Vec2 {
+        match self {
+            PlatformMotion::Waypoints {
+                offsets,
+                speed,
+                wait_ticks,
+            } => {
+                let offsets: Vec<Vec2> = offsets.iter().map(|offset| Vec2::from(*offset)).collect();
+                waypoint_offset(&offsets, *speed, *wait_ticks, ticks)
+            }
+            PlatformMotion::Sine { amplitude, period } => {
+                if *period <= 0.0 {
+                    return Vec2::ZERO;
+                }
+
+                Vec2::from(*amplitude) * (TAU * ticks / period).sin()
+            }
+        }
+    }
+
+    /// The offsets the platform's jumps are worked out at
+    pub fn poses(&self) -> Vec<Vec2> {
+        let poses: Vec<Vec2> = match self {
+            PlatformMotion::Waypoints { offsets, .. } => {
+                offsets.iter().map(|offset| Vec2::from(*offset)).collect()
+            }
+            PlatformMotion::Sine { period, .. } => (0..SINE_PLATFORM_POSES)
+                .map(|pose| self.offset_at(pose as f32 * period / SINE_PLATFORM_POSES as f32))
+                .collect(),
+        };
+
+        // A sine passes through most of its poses twice a period
+        let mut unique_poses: Vec<Vec2> = Vec::new();
+        for pose in poses {
+            if unique_poses
+                .iter()
+                .all(|unique_pose| unique_pose.distance(pose) > PLATFORM_POSE_TOLERANCE)
+            {
+                unique_poses.push(pose);
+            }
+        }
+
+        unique_poses
+    }
+}
+
+fn waypoint_offset(offsets: &[Vec2], speed: f32, wait_ticks: f32, ticks: f32) -> Vec2 {
+    if offsets.len() < 2 || speed <= 0.0 {
+        return offsets.first().copied().unwrap_or(Vec2::ZERO);
+    }
+
+    let legs = offsets.iter().zip(offsets.iter().cycle().skip(1));
+
+    let cycle_ticks: f32 = legs
+        .clone()
+        .map(|(start, end)| wait_ticks + start.distance(*end) / speed)
+        .sum();
+    if cycle_ticks <= 0.0 {
+        return offsets[0];
+    }
+
+    let mut ticks = ticks % cycle_ticks;
+
+    for (start, end) in legs {
+        if ticks < wait_ticks {
+            return *start;
+        }
+        ticks -= wait_ticks;
+
+        let travel_ticks = start.distance(*end) / speed;
+        if ticks < travel_ticks {
+            return start.lerp(*end, ticks / travel_ticks);
+        }
+        ticks -= travel_ticks;
+    }
+
+    offsets[0]
+}
+
+/// A platform in `assets/moving_platforms.json`, placed in the built-in level
+#[derive(Debug, Clone, Deserialize)]
+pub struct MovingPlatformData {
+    /// The outline where the platform is placed, which gets closed if it isn't already
+    pub points: Vec<[f32; 2]>,
+    pub motion: PlatformMotion,
+}
+
+/// A level polygon that moves, carrying whatever is standing on it
+#[derive(Component, Debug, Reflect)]
+#[reflect(Component)]
+pub struct MovingPlatform {
+    pub polygon_index: usize,
+    pub motion: PlatformMotion,
+    /// The outline where the platform is placed
+    pub rest_points: Vec<Vec2>,
+    /// Where the platform is, relative to where it's placed
+    pub offset: Vec2,
+    pub ticks: u32,
+    /// The platform's nodes in the pathfinding graph
+    pub node_ids: Range<usize>,
+    /// Where the platform's nodes are when it's where it's placed
+    pub node_rest_positions: Vec<Vec2>,
+}
+
+/// A jump onto or off a platform, only usable while the platform is at one of the poses it was
+/// worked out at
+#[derive(Debug, Clone)]
+pub struct TimedConnection {
+    pub platform: Entity,
+    pub from_node_id: usize,
+    pub to_node_id: usize,
+    pub poses: Vec<Vec2>,
+}
+
+#[derive(Resource, Default)]
+pub struct PlatformConnections {
+    pub connections: Vec<TimedConnection>,
+}
+
+/// Adds the platforms to the level, so the graph built for it gets nodes on them
+pub fn spawn_moving_platforms(commands: &mut Commands, level: &mut Level) {
+    let res = std::str::from_utf8(MOVING_PLATFORMS_DATA);
+    let platforms: Vec<MovingPlatformData> = serde_json::from_str(res.unwrap()).unwrap();
+
+    for platform in platforms {
+        let mut points: Vec<Vec2> = platform
+            .points
+            .iter()
+            .map(|point| Vec2::from(*point))
+            .collect();
+
+        if points.first() != points.last() {
+            points.push(points[0]);
+        }
+
+        orient_polygon_points(&mut points, false);
+
+        let polygon_index = level.polygons.len();
+        level.polygons.push(Polygon {
+            points: points.clone(),
+            color: Color::TURQUOISE,
+            is_container: false,
+            no_node_lines: Vec::new(),
+            node_spacing: None,
+            destructible_lines: Vec::new(),
+            broken_lines: Vec::new(),
+            is_one_way: false,
+            line_frictions: Vec::new(),
+            line_conveyor_speeds: Vec::new(),
+            line_restitutions: Vec::new(),
+            hole_in: None,
+        });
+
+        commands.spawn(MovingPlatform {
+            polygon_index,
+            motion: platform.motion,
+            rest_points: points,
+            offset: Vec2::ZERO,
+            ticks: 0,
+            node_ids: 0..0,
+            node_rest_positions: Vec::new(),
+        });
+    }
+}
+
+/// Moves the platform's polygon and nodes to the offset
+fn move_platform(
+    level: &mut Level,
+    pathfinding: &mut Pathfinding,
+    platform: &mut MovingPlatform,
+    offset: Vec2,
+) {
+    let polygon = &mut level.polygons[platform.polygon_index];
+    for (point, rest_point) in polygon.points.iter_mut().zip(platform.rest_points.iter()) {
+        *point = *rest_point + offset;
+    }
+
+    for (node_id, rest_position) in platform
+        .node_ids
+        .clone()
+        .zip(platform.node_rest_positions.iter())
+    {
+        pathfinding.nodes[node_id].position = *rest_position + offset;
+    }
+
+    platform.offset = offset;
+}
+
+/// Whether the platform's polygon is still the one it was spawned as
+fn platform_in_level(level: &Level, platform: &MovingPlatform) -> bool {
+    level
+        .polygons
+        .get(platform.polygon_index)
+        .is_some_and(|polygon| {
+            polygon.points.len() == platform.rest_points.len()
+                && polygon
+                    .points
+                    .iter()
+                    .zip(platform.rest_points.iter())
+                    .all(|(point, rest_point)| point.distance(*rest_point + platform.offset) < 0.01)
+        })
+}
+
+/// Swaps the jumps onto and off the platform's nodes for ones worked out at each of its poses,
+/// which start out disabled until the platform gets there
+fn setup_platform_connections(
+    level: &mut Level,
+    pathfinding: &mut Pathfinding,
+    entity: Entity,
+    platform: &mut MovingPlatform,
+    platform_connections: &mut PlatformConnections,
+) {
+    // Nodes are placed a polygon at a time, so the platform's are all together
+    let platform_node_ids: Vec<usize> = pathfinding
+        .nodes
+        .iter()
+        .filter(|node| node.polygon_index == platform.polygon_index)
+        .map(|node| node.id)
+        .collect();
+    platform.node_ids = match (platform_node_ids.first(), platform_node_ids.last()) {
+        (Some(first), Some(last)) => *first..*last + 1,
+        _ => 0..0,
+    };
+    platform.node_rest_positions = platform
+        .node_ids
+        .clone()
+        .map(|node_id| pathfinding.nodes[node_id].position - platform.offset)
+        .collect();
+
+    let node_ids = platform.node_ids.clone();
+
+    // The jumps the graph was built with only work for wherever the platform happened to be
+    for node in pathfinding.nodes.iter_mut() {
+        let on_platform = node_ids.contains(&node.id);
+
+        node.connections.retain(|connection| {
+            let is_jump = matches!(
+                connection.connection_type,
+                PathfindingGraphConnectionType::Jumpable
+                    | PathfindingGraphConnectionType::WallJumpable
+                    | PathfindingGraphConnectionType::Mantle
+                    | PathfindingGraphConnectionType::Dashable
+                    | PathfindingGraphConnectionType::DoubleJumpable
+            );
+
+            !is_jump || !on_platform && !node_ids.contains(&connection.node_id)
+        });
+    }
+
+    platform_connections
+        .connections
+        .retain(|connection| connection.platform != entity);
+
+    let current_offset = platform.offset;
+
+    let mut timed_connections: Vec<TimedConnection> = Vec::new();
+    let mut timed_connection_lookup: HashMap<(usize, usize), usize> = HashMap::new();
+    let mut connections: Vec<(usize, PathfindingGraphConnection)> = Vec::new();
+
+    for pose in platform.motion.poses() {
+        move_platform(level, pathfinding, platform, pose);
+        build_spatial_index(pathfinding);
+
+        let jumps = find_jumps_touching(
+            pathfinding,
+            level,
+            PLATFORMER_AI_AGENT_RADIUS,
+            node_ids.clone(),
+        );
+
+        // The same jump from more than one pose keeps the first pose's trajectory
+        for (from_node_id, connection) in jumps {
+            let key = (from_node_id, connection.node_id);
+
+            match timed_connection_lookup.get(&key) {
+                Some(&timed_connection_index) => {
+                    timed_connections[timed_connection_index].poses.push(pose)
+                }
+                None => {
+                    timed_connection_lookup.insert(key, timed_connections.len());
+                    timed_connections.push(TimedConnection {
+                        platform: entity,
+                        from_node_id,
+                        to_node_id: connection.node_id,
+                        poses: vec![pose],
+                    });
+                    connections.push((from_node_id, connection));
+                }
+            }
+        }
+    }
+
+    move_platform(level, pathfinding, platform, current_offset);
+    build_spatial_index(pathfinding);
+
+    for (from_node_id, connection) in connections {
+        pathfinding.nodes[from_node_id].connections.push(connection);
+    }
+
+    for timed_connection in timed_connections.iter() {
+        pathfinding.disable_connection(timed_connection.from_node_id, timed_connection.to_node_id);
+    }
+
+    println!(
+        "Platform on polygon {} has {} timed jumps",
+        platform.polygon_index,
+        timed_connections.len()
+    );
+
+    platform_connections.connections.extend(timed_connections);
+}
+
+pub fn s_setup_moving_platforms(
+    mut platform_query: Query<(Entity, &mut MovingPlatform)>,
+    mut level: ResMut<Level>,
+    mut pathfinding: ResMut<Pathfinding>,
+    mut platform_connections: ResMut<PlatformConnections>,
+    mut navmesh: ResMut<NavMesh>,
+) {
+    if platform_query.is_empty() {
+        return;
+    }
+
+    for (entity, mut platform) in platform_query.iter_mut() {
+        setup_platform_connections(
+            &mut level,
+            &mut pathfinding,
+            entity,
+            &mut platform,
+            &mut platform_connections,
+        );
+    }
+
+    // The navmesh was built with the jumps the platforms just lost
+    *navmesh = NavMesh::build(&level, &pathfinding);
+}
+
+/// Redoes the platforms' jumps after the graph is rebuilt, dropping platforms the level lost
+pub fn s_rebuild_platform_connections(
+    mut commands: Commands,
+    settings: Res<PathfindingSettings>,
+    mut platform_query: Query<(Entity, &mut MovingPlatform)>,
+    mut level: ResMut<Level>,
+    mut pathfinding: ResMut<Pathfinding>,
+    mut platform_connections: ResMut<PlatformConnections>,
+) {
+    if !settings.is_changed() || settings.is_added() {
+        return;
+    }
+
+    platform_connections.connections.clear();
+
+    for (entity, mut platform) in platform_query.iter_mut() {
+        if !platform_in_level(&level, &platform) {
+            println!(
+                "Polygon {} isn't the platform anymore, removing it",
+                platform.polygon_index
+            );
+            commands.entity(entity).despawn();
+            continue;
+        }
+
+        setup_platform_connections(
+            &mut level,
+            &mut pathfinding,
+            entity,
+            &mut platform,
+            &mut platform_connections,
+        );
+    }
+}
+
+/// Whether the agent is on top of one of the polygon's lines
+fn is_standing_on(level: &Level, polygon_index: usize, agent_position: Vec2, radius: f32) -> bool {
+    let touch_radius = radius + 0.5;
+
+    level.polygons[polygon_index].points.windows(2).any(|line| {
+        let (distance_sq, projection) = find_projection(line[0], line[1], agent_position, radius);
+
+        distance_sq <= touch_radius.powi(2)
+            && (agent_position - projection).normalize_or_zero().y > 0.5
+    })
+}
+
+pub fn s_move_platforms(
+    mut platform_query: Query<(Entity, &mut MovingPlatform)>,
+    mut agent_query: Query<(&mut Transform, &mut Physics)>,
+    mut level: ResMut<Level>,
+    mut pathfinding: ResMut<Pathfinding>,
+    platform_connections: Res<PlatformConnections>,
+) {
+    if platform_query.is_empty() {
+        return;
+    }
+
+    // Platforms moving isn't the kind of level change anything has to rebuild for
+    let level = level.bypass_change_detection();
+
+    for (entity, mut platform) in platform_query.iter_mut() {
+        platform.ticks += 1;
+
+        let offset = platform.motion.offset_at(platform.ticks as f32);
+        let delta = offset - platform.offset;
+
+        // Carry whoever is standing on the platform, before it moves out from under them
+        for (mut transform, mut physics) in agent_query.iter_mut() {
+            if physics.grounded
+                && is_standing_on(
+                    level,
+                    platform.polygon_index,
+                    transform.translation.xy(),
+                    physics.radius,
+                )
+            {
+                transform.translation += delta.extend(0.0);
+                physics.prev_position += delta;
+            }
+        }
+
+        move_platform(level, &mut pathfinding, &mut platform, offset);
+
+        // Only the jumps worked out for around where the platform is now can be taken
+        for timed_connection in platform_connections
+            .connections
+            .iter()
+            .filter(|timed_connection| timed_connection.platform == entity)
+        {
+            let usable = timed_connection
+                .poses
+                .iter()
+                .any(|pose| pose.distance(offset) <= PLATFORM_POSE_TOLERANCE);
+            let enabled = pathfinding
+                .is_connection_enabled(timed_connection.from_node_id, timed_connection.to_node_id);
+
+            if usable && !enabled {
+                pathfinding
+                    .enable_connection(timed_connection.from_node_id, timed_connection.to_node_id);
+            } else if !usable && enabled {
+                pathfinding
+                    .disable_connection(timed_connection.from_node_id, timed_connection.to_node_id);
+            }
+        }
+    }
+
+    build_spatial_index(&mut pathfinding);
+}
@@ -0,0 +1,239 @@
+use std::fmt::Write;
+
+use bevy::{
+    app::{App, Plugin, Update},
+    ecs::{
+        component::Component,
+        entity::Entity,
+        query::With,
+        reflect::{ReflectComponent, ReflectResource},
+        schedule::IntoSystemConfigs,
+        system::{Commands, Query, Res, ResMut, Resource},
+    },
+    gizmos::gizmos::Gizmos,
+    input::{keyboard::KeyCode, mouse::MouseButton, ButtonInput},
+    reflect::Reflect,
+    render::{camera::Camera, color::Color},
+    sprite::Anchor,
+    text::{Text, Text2dBundle, TextStyle},
+    transform::components::{GlobalTransform, Transform},
+    utils::default,
+    window::{PrimaryWindow, Window},
+};
+
+#[cfg(feature = "editor")]
+use crate::editor::Editor;
+use crate::{
+    ai::pathfinding::{Pathfinding, PathfindingGraphNode},
+    utils::cursor_world_position,
+    MainCamera, PursueAISet,
+};
+
+/// How close a click has to be to a node to pick it
+const NODE_PICK_RADIUS: f32 = 6.0;
+
+pub struct NodeInspectorPlugin;
+
+impl Plugin for NodeInspectorPlugin {
+    fn build(&self, app: &mut App) {
+        app.register_type::<SelectedNode>()
+            .register_type::<SelectedNodeLabel>()
+            .insert_resource(SelectedNode::default())
+            .add_systems(
+                Update,
+                (
+                    s_node_inspector_input,
+                    s_render_selected_node.in_set(PursueAISet::Render),
+                ),
+            );
+    }
+}
+
+/// The pathfinding node picked with a left click, shown with everything it connects to
+#[derive(Resource, Default, Reflect)]
+#[reflect(Resource)]
+pub struct SelectedNode {
+    pub node_id: Option<usize>,
+}
+
+#[derive(Component, Reflect)]
+#[reflect(Component)]
+pub struct SelectedNodeLabel;
+
+fn node_description(pathfinding: &Pathfinding, node: &PathfindingGraphNode) -> String {
+    let mut description = String::new();
+
+    let _ = writeln!(
+        description,
+        "Node {}{}",
+        node.id,
+        if pathfinding.is_node_enabled(node.id) {
+            ""
+        } else {
+            " (disabled)"
+        }
+    );
+    let _ = writeln!(
+        description,
+        "position: ({:.1}, {:.1})",
+        node.position.x, node.position.y
+    );
+    let _ = writeln!(
+        description,
+        "normal: ({:.2}, {:.2})",
+        node.normal.x, node.normal.y
+    );
+    let _ = writeln!(
+        description,
+        "polygon {}, lines {:?}",
+        node.polygon_index, node.line_indicies
+    );
+    let _ = writeln!(
+        description,
+        "corner: {}",
+        match node.is_external_corner {
+            Some(true) => "external",
+            Some(false) => "internal",
+            None => "no",
+        }
+    );
+
+    for connection in node.connections.iter() {
+        let _ = write!(
+            description,
+            "\n{:?} -> {}: dist {:.1}, effort {:.2}, cost {:.1}",
+            connection.connection_type,
+            connection.node_id,
+            connection.dist,
+            connection.effort,
+            pathfinding.connection_cost(node.id, connection)
+        );
+
+        if let Some(run_up) = connection.run_up.as_ref() {
+            let _ = write!(
+                description,
+                ", run-up {:.1} from {}",
+                run_up.distance, run_up.start_node_id
+            );
+        }
+
+        if !pathfinding.is_connection_enabled(node.id, connection.node_id) {
+            let _ = write!(description, " (disabled)");
+        }
+    }
+
+    description
+}
+
+pub fn s_node_inspector_input(
+    keyboard_input: Res<ButtonInput<KeyCode>>,
+    mouse_buttons: Res<ButtonInput<MouseButton>>,
+    q_windows: Query<&Window, With<PrimaryWindow>>,
+    camera_query: Query<(&Camera, &GlobalTransform), With<MainCamera>>,
+    #[cfg(feature = "editor")] editor: Res<Editor>,
+    pathfinding: Res<Pathfinding>,
+    mut selected_node: ResMut<SelectedNode>,
+) {
+    // The editor and shift-clicked obstacles have their own use for clicks
+    #[cfg(feature = "editor")]
+    if editor.active {
+        return;
+    }
+
+    let shift_held =
+        keyboard_input.pressed(KeyCode::ShiftLeft) || keyboard_input.pressed(KeyCode::ShiftRight);
+
+    if shift_held || !mouse_buttons.just_pressed(MouseButton::Left) {
+        return;
+    }
+
+    let (Ok(window), Ok((camera, camera_transform))) =
+        (q_windows.get_single(), camera_query.get_single())
+    else {
+        return;
+    };
+
+    let Some(mouse_pos_world) = cursor_world_position(window, camera, camera_transform) else {
+        return;
+    };
+
+    // Left click on a node to inspect it, or anywhere else to put it away
+    selected_node.node_id = pathfinding
+        .node_ids_within_radius(mouse_pos_world, NODE_PICK_RADIUS)
+        .into_iter()
+        .min_by(|a, b| {
+            let distance_a = pathfinding.nodes[*a]
+                .position
+                .distance_squared(mouse_pos_world);
+            let distance_b = pathfinding.nodes[*b]
+                .position
+                .distance_squared(mouse_pos_world);
+            distance_a.total_cmp(&distance_b)
+        });
+
+    if let Some(node_id) = selected_node.node_id {
+        println!(
+            "{}",
+            node_description(&pathfinding, &pathfinding.nodes[node_id])
+        );
+    }
+}
+
+pub fn s_render_selected_node(
+    mut commands: Commands,
+    mut gizmos: Gizmos,
+    pathfinding: Res<Pathfinding>,
+    mut selected_node: ResMut<SelectedNode>,
+    mut label_query: Query<(Entity, &mut Text, &mut Transform), With<SelectedNodeLabel>>,
+) {
+    // Rebuilding the graph can take the node away
+    let node = selected_node
+        .node_id
+        .and_then(|node_id| pathfinding.nodes.get(node_id));
+
+    let Some(node) = node else {
+        if selected_node.node_id.is_some() {
+            selected_node.node_id = None;
+        }
+        for (entity, _, _) in label_query.iter() {
+            commands.entity(entity).despawn();
+        }
+        return;
+    };
+
+    gizmos.circle_2d(node.position, NODE_PICK_RADIUS, Color::WHITE);
+    for connection in node.connections.iter() {
+        if let Some(other_node) = pathfinding.nodes.get(connection.node_id) {
+            gizmos.line_2d(node.position, other_node.position, Color::WHITE);
+            gizmos.circle_2d(other_node.position, NODE_PICK_RADIUS / 2.0, Color::WHITE);
+        }
+    }
+
+    let description = node_description(&pathfinding, node);
+    let label_position = (node.position + NODE_PICK_RADIUS).extend(1.0);
+
+    if let Ok((_, mut text, mut transform)) = label_query.get_single_mut() {
+        if text.sections[0].value != description {
+            text.sections[0].value = description;
+        }
+        transform.translation = label_position;
+        return;
+    }
+
+    commands.spawn((
+        Text2dBundle {
+            text: Text::from_section(
+                description,
+                TextStyle {
+                    font_size: 12.0,
+                    color: Color::WHITE,
+                    ..default()
+                },
+            ),
+            text_anchor: Anchor::BottomLeft,
+            transform: Transform::from_translation(label_position),
+            ..default()
+        },
+        SelectedNodeLabel,
+    ));
+}
@@ -0,0 +1,115 @@
+use bevy::{
+    app::{App, Plugin, Update},
+    ecs::{
+        query::With,
+        system::{Query, Res, ResMut},
+    },
+    input::{keyboard::KeyCode, mouse::MouseButton, ButtonInput},
+    math::Vec2,
+    render::{camera::Camera, color::Color},
+    transform::components::GlobalTransform,
+    utils::Instant,
+    window::{PrimaryWindow, Window},
+};
+
+#[cfg(feature = "editor")]
+use crate::editor::Editor;
+use crate::{
+    ai::{
+        navmesh::NavMesh,
+        pathfinding::{Pathfinding, PathfindingSettings},
+    },
+    level::{orient_polygon_points, Level, Polygon},
+    utils::cursor_world_position,
+    MainCamera,
+};
+
+/// How big dropped obstacles are, in grid cells
+const OBSTACLE_SIZE: Vec2 = Vec2::new(2.0, 1.0);
+
+pub struct ObstaclesPlugin;
+
+impl Plugin for ObstaclesPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Update, s_obstacle_input);
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn s_obstacle_input(
+    keyboard_input: Res<ButtonInput<KeyCode>>,
+    mouse_buttons: Res<ButtonInput<MouseButton>>,
+    q_windows: Query<&Window, With<PrimaryWindow>>,
+    camera_query: Query<(&Camera, &GlobalTransform), With<MainCamera>>,
+    #[cfg(feature = "editor")] editor: Res<Editor>,
+    settings: Res<PathfindingSettings>,
+    mut level: ResMut<Level>,
+    mut pathfinding: ResMut<Pathfinding>,
+    mut navmesh: ResMut<NavMesh>,
+) {
+    // The editor has its own use for clicks
+    #[cfg(feature = "editor")]
+    if editor.active {
+        return;
+    }
+
+    let shift_held =
+        keyboard_input.pressed(KeyCode::ShiftLeft) || keyboard_input.pressed(KeyCode::ShiftRight);
+
+    // Shift + left click to drop an obstacle at the cursor
+    if !(shift_held && mouse_buttons.just_pressed(MouseButton::Left)) {
+        return;
+    }
+
+    let (Ok(window), Ok((camera, camera_transform))) =
+        (q_windows.get_single(), camera_query.get_single())
+    else {
+        return;
+    };
+
+    let Some(mouse_pos_world) = cursor_world_position(window, camera, camera_transform) else {
+        return;
+    };
+
+    // Line the obstacle up with the grid so it sits flush on the level's floors
+    let size = OBSTACLE_SIZE * level.grid_size;
+    let min = ((mouse_pos_world - size / 2.0) / level.grid_size).round() * level.grid_size;
+    let max = min + size;
+
+    let mut points = vec![
+        min,
+        Vec2::new(max.x, min.y),
+        max,
+        Vec2::new(min.x, max.y),
+        min,
+    ];
+    orient_polygon_points(&mut points, false);
+
+    let polygon_index = level.polygons.len();
+    level.polygons.push(Polygon {
+        points,
+        color: Color::ORANGE,
+        is_container: false,
+        no_node_lines: Vec::new(),
+        node_spacing: None,
+        destructible_lines: Vec::new(),
+        broken_lines: Vec::new(),
+        is_one_way: false,
+        line_frictions: Vec::new(),
+        line_conveyor_speeds: Vec::new(),
+        line_restitutions: Vec::new(),
+        hole_in: None,
+    });
+
+    let start_time = Instant::now();
+
+    pathfinding.add_polygon(&level, polygon_index, &settings);
+
+    println!(
+        "Added obstacle {} to the graph in {:?}",
+        polygon_index,
+        start_time.elapsed()
+    );
+
+    *navmesh = NavMesh::build(&level, &pathfinding);
+}
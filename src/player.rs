@@ -0,0 +1,174 @@
+use bevy::{
+    app::{App, FixedUpdate, Plugin, Update},
+    ecs::{
+        query::With,
+        reflect::ReflectResource,
+        schedule::IntoSystemConfigs,
+        system::{Query, Res, ResMut, Resource},
+    },
+    gizmos::gizmos::Gizmos,
+    input::{keyboard::KeyCode, ButtonInput},
+    math::{Vec2, Vec3Swizzles},
+    reflect::Reflect,
+    render::color::Color,
+    transform::components::Transform,
+};
+
+use crate::{
+    ai::platformer_ai::{
+        apply_gravity_toward_normal, apply_movement_acceleration, update_physics_and_transform,
+        ACCELERATION_SCALERS, PLATFORMER_AI_AGENT_RADIUS, PLATFORMER_AI_AIR_CONTROL,
+        PLATFORMER_AI_JUMP_FORCE, WANDER_MAX_SPEED,
+    },
+    collisions::GOAL_POINT_RADIUS,
+    s_move_goal_point, GoalPoint, Physics, PursueAISet, GRAVITY_STRENGTH,
+};
+
+pub struct PlayerPlugin;
+
+impl Plugin for PlayerPlugin {
+    fn build(&self, app: &mut App) {
+        app.register_type::<PlayerMode>()
+            .insert_resource(PlayerMode::default())
+            .add_systems(
+                Update,
+                (s_player_input, s_render_player.in_set(PursueAISet::Render)),
+            )
+            .add_systems(
+                FixedUpdate,
+                s_player_movement
+                    .in_set(PursueAISet::Movement)
+                    .after(s_move_goal_point),
+            );
+    }
+}
+
+/// Whether the goal point is a platformer character the player runs and jumps around as,
+/// instead of a point flying wherever the arrow keys send it
+#[derive(Resource, Default, Reflect)]
+#[reflect(Resource)]
+pub struct PlayerMode {
+    pub active: bool,
+    /// -1, 0 or 1 for which way the player is holding
+    pub move_x: f32,
+    /// Set when jump is pressed, until the next tick gets to it
+    pub jump_queued: bool,
+}
+
+pub fn s_player_input(
+    keyboard_input: Res<ButtonInput<KeyCode>>,
+    mut player_mode: ResMut<PlayerMode>,
+    mut goal_query: Query<&mut Physics, With<GoalPoint>>,
+) {
+    // T to take control of the goal as a character, or to let it fly again
+    if keyboard_input.just_pressed(KeyCode::KeyT) {
+        player_mode.active = !player_mode.active;
+        player_mode.jump_queued = false;
+
+        for mut physics in goal_query.iter_mut() {
+            physics.radius = if player_mode.active {
+                PLATFORMER_AI_AGENT_RADIUS
+            } else {
+                GOAL_POINT_RADIUS
+            };
+            physics.velocity = Vec2::ZERO;
+            physics.acceleration = Vec2::ZERO;
+        }
+
+        println!("Player character: {}", player_mode.active);
+    }
+
+    if !player_mode.active {
+        return;
+    }
+
+    // A and D (or left and right) to run, W (or up) to jump and wall jump
+    let mut move_x = 0.0;
+    if keyboard_input.any_pressed([KeyCode::KeyA, KeyCode::ArrowLeft]) {
+        move_x -= 1.0;
+    }
+    if keyboard_input.any_pressed([KeyCode::KeyD, KeyCode::ArrowRight]) {
+        move_x += 1.0;
+    }
+    player_mode.move_x = move_x;
+
+    if keyboard_input.any_just_pressed([KeyCode::KeyW, KeyCode::ArrowUp]) {
+        player_mode.jump_queued = true;
+    }
+}
+
+/// Moves the player's character with the same acceleration, gravity and jumps as the agents
+pub fn s_player_movement(
+    mut player_mode: ResMut<PlayerMode>,
+    mut goal_query: Query<(&mut Transform, &mut Physics), With<GoalPoint>>,
+) {
+    if !player_mode.active {
+        return;
+    }
+
+    let jumping = player_mode.jump_queued;
+    player_mode.jump_queued = false;
+
+    for (mut transform, mut physics) in goal_query.iter_mut() {
+        let move_dir = Vec2::new(player_mode.move_x, 0.0);
+
+        let falling = physics.normal.length_squared() == 0.0;
+        let no_move_dir = move_dir.length_squared() == 0.0;
+
+        apply_movement_acceleration(
+            &mut physics,
+            &move_dir,
+            falling,
+            no_move_dir,
+            WANDER_MAX_SPEED,
+            ACCELERATION_SCALERS,
+            PLATFORMER_AI_AIR_CONTROL,
+        );
+
+        apply_gravity_toward_normal(&mut physics, falling);
+
+        if jumping && !falling {
+            if physics.grounded {
+                physics.velocity.y = PLATFORMER_AI_JUMP_FORCE;
+                physics.acceleration.x = 0.0;
+                physics.acceleration.y = -GRAVITY_STRENGTH;
+                physics.grounded = false;
+                physics.has_wall_jumped = false;
+                physics.walled = 0;
+            }
+            // Off a wall, away from it
+            else if physics.walled != 0 {
+                physics.velocity = Vec2::new(
+                    physics.walled as f32 * WANDER_MAX_SPEED,
+                    PLATFORMER_AI_JUMP_FORCE,
+                );
+                physics.acceleration.x = 0.0;
+                physics.acceleration.y = -GRAVITY_STRENGTH;
+                physics.walled = 0;
+                physics.grounded = false;
+                physics.has_wall_jumped = true;
+            }
+        }
+
+        // Conveyors carry the player too
+        if physics.grounded {
+            transform.translation += physics.surface_velocity.extend(0.0);
+        }
+
+        update_physics_and_transform(&mut physics, &mut transform);
+    }
+}
+
+pub fn s_render_player(
+    mut gizmos: Gizmos,
+    player_mode: Res<PlayerMode>,
+    goal_query: Query<(&Transform, &Physics), With<GoalPoint>>,
+) {
+    if !player_mode.active {
+        return;
+    }
+
+    for (transform, physics) in goal_query.iter() {
+        gizmos.circle_2d(transform.translation.xy(), physics.radius, Color::CYAN);
+    }
+}
@@ -0,0 +1,475 @@
+use bevy::math::Vec2;
+
+use crate::{
+    level::{orient_polygon, Level, Polygon},
+    utils::{cross_product, line_intersect},
+};
+
+/// Points closer together than this are the same point
+const EPSILON: f32 = 0.01;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BooleanOp {
+    /// Everything in either polygon
+    Union,
+    /// Everything in the first polygon but not the second
+    Difference,
+    /// Everything in both polygons
+    Intersection,
+}
+
+/// Where a piece of one polygon's outline is relative to the other polygon
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum FragmentSide {
+    Inside,
+    Outside,
+    /// Along the other polygon's outline, running the same way
+    SharedSame,
+    /// Along the other polygon's outline, running the opposite way
+    SharedOpposite,
+}
+
+/// Combines two outlines (closed or not, wound either way) into the outlines of the result
+///
+/// Outer outlines come back counterclockwise and holes clockwise, all closed
+pub fn boolean_op(a: &[Vec2], b: &[Vec2], op: BooleanOp) -> Vec<Vec<Vec2>> {
+    let a = counterclockwise_loop(a);
+    let b = counterclockwise_loop(b);
+
+    if a.len() < 3 || b.len() < 3 {
+        return Vec::new();
+    }
+
+    let mut fragments: Vec<(Vec2, Vec2)> = Vec::new();
+
+    for (start, end) in split_edges(&a, &b) {
+        let keep = matches!(
+            (op, classify_fragment(start, end, &b)),
+            (
+                BooleanOp::Union,
+                FragmentSide::Outside | FragmentSide::SharedSame
+            ) | (
+                BooleanOp::Intersection,
+                FragmentSide::Inside | FragmentSide::SharedSame
+            ) | (
+                BooleanOp::Difference,
+                FragmentSide::Outside | FragmentSide::SharedOpposite
+            )
+        );
+
+        if keep {
+            fragments.push((start, end));
+        }
+    }
+
+    // Shared edges were already taken from the first polygon
+    for (start, end) in split_edges(&b, &a) {
+        match (op, classify_fragment(start, end, &a)) {
+            (BooleanOp::Union, FragmentSide::Outside) => fragments.push((start, end)),
+            (BooleanOp::Intersection, FragmentSide::Inside) => fragments.push((start, end)),
+            // What's cut out of the first polygon is bounded by the second one, inside out
+            (BooleanOp::Difference, FragmentSide::Inside) => fragments.push((end, start)),
+            _ => {}
+        }
+    }
+
+    chain_fragments(fragments)
+        .into_iter()
+        .map(|outline| simplify_loop(&outline))
+        .filter(|outline| outline.len() >= 3 && signed_area(outline).abs() > EPSILON)
+        .map(|mut outline| {
+            outline.push(outline[0]);
+            outline
+        })
+        .collect()
+}
+
+/// Whether the outlines cross, touch, or one is inside the other
+pub fn outlines_overlap(a: &[Vec2], b: &[Vec2]) -> bool {
+    let edges_meet = a.windows(2).any(|a_line| {
+        b.windows(2)
+            .any(|b_line| line_intersect(a_line[0], a_line[1], b_line[0], b_line[1]).is_some())
+    });
+
+    edges_meet
+        || a.first()
+            .is_some_and(|point| contains_point(&counterclockwise_loop(b), *point))
+        || b.first()
+            .is_some_and(|point| contains_point(&counterclockwise_loop(a), *point))
+}
+
+/// Whether the polygon can be freely merged and cut, with nothing about it tied to its lines
+/// or to other polygons
+fn is_plain_solid(level: &Level, polygon_index: usize) -> bool {
+    let polygon = &level.polygons[polygon_index];
+
+    !polygon.is_hollow()
+        && !polygon.is_one_way
+        && polygon.no_node_lines.is_empty()
+        && polygon.destructible_lines.is_empty()
+        && polygon.broken_lines.is_empty()
+        && polygon.line_frictions.is_empty()
+        && polygon.line_conveyor_speeds.is_empty()
+        && polygon.line_restitutions.is_empty()
+        && level.holes_of(polygon_index).next().is_none()
+}
+
+/// Merges every set of overlapping plain solids into a single outline (with holes wherever
+/// they wall off open space), so nothing has doubled-up edges or gets collided with twice
+pub fn merge_overlapping_polygons(level: &mut Level) {
+    let mut polygon_index = 0;
+
+    while polygon_index < level.polygons.len() {
+        let merge = (polygon_index + 1..level.polygons.len()).find_map(|other_index| {
+            if !is_plain_solid(level, polygon_index) || !is_plain_solid(level, other_index) {
+                return None;
+            }
+
+            let (polygon, other) = (&level.polygons[polygon_index], &level.polygons[other_index]);
+            if polygon.node_spacing != other.node_spacing
+                || !outlines_overlap(&polygon.points, &other.points)
+            {
+                return None;
+            }
+
+            let outlines = boolean_op(&polygon.points, &other.points, BooleanOp::Union);
+
+            // Polygons that only touch at a corner stay apart
+            let outer_count = outlines
+                .iter()
+                .filter(|outline| signed_area(outline) > 0.0)
+                .count();
+
+            (outer_count == 1).then_some((other_index, outlines))
+        });
+
+        match merge {
+            Some((other_index, outlines)) => {
+                level.remove_polygon(other_index);
+                replace_polygon(level, polygon_index, outlines);
+            }
+            None => polygon_index += 1,
+        }
+    }
+}
+
+/// Cuts the shape out of every plain solid it overlaps, or cuts them down to it
+pub fn apply_shape_to_polygons(level: &mut Level, shape: &[Vec2], op: BooleanOp) {
+    let overlapping: Vec<usize> = (0..level.polygons.len())
+        .filter(|polygon_index| {
+            is_plain_solid(level, *polygon_index)
+                && outlines_overlap(&level.polygons[*polygon_index].points, shape)
+        })
+        .collect();
+
+    // Going backward keeps the earlier indices pointing at the same polygons
+    for polygon_index in overlapping.into_iter().rev() {
+        let outlines = boolean_op(&level.polygons[polygon_index].points, shape, op);
+        replace_polygon(level, polygon_index, outlines);
+    }
+}
+
+/// Swaps the polygon's outline for the outlines, adding any extra outlines and holes as
+/// polygons of their own
+fn replace_polygon(level: &mut Level, polygon_index: usize, outlines: Vec<Vec<Vec2>>) {
+    let (outers, holes): (Vec<Vec<Vec2>>, Vec<Vec<Vec2>>) = outlines
+        .into_iter()
+        .partition(|outline| signed_area(outline) > 0.0);
+
+    if outers.is_empty() {
+        level.remove_polygon(polygon_index);
+        return;
+    }
+
+    let template = &level.polygons[polygon_index];
+    let new_polygon = |points: Vec<Vec2>, hole_in: Option<usize>| Polygon {
+        points,
+        color: template.color,
+        is_container: false,
+        no_node_lines: Vec::new(),
+        node_spacing: template.node_spacing,
+        destructible_lines: Vec::new(),
+        broken_lines: Vec::new(),
+        is_one_way: false,
+        line_frictions: Vec::new(),
+        line_conveyor_speeds: Vec::new(),
+        line_restitutions: Vec::new(),
+        hole_in,
+    };
+
+    // The first outline keeps the polygon's place, the rest go on the end
+    let mut outer_indices = vec![polygon_index];
+    let mut new_polygons: Vec<Polygon> = Vec::new();
+    for (outer_number, outer) in outers.iter().enumerate().skip(1) {
+        outer_indices.push(level.polygons.len() + outer_number - 1);
+        new_polygons.push(new_polygon(outer.clone(), None));
+    }
+
+    for hole in holes {
+        let outer_number = outers
+            .iter()
+            .position(|outer| contains_point(&counterclockwise_loop(outer), hole[0]))
+            .unwrap_or(0);
+
+        new_polygons.push(new_polygon(hole, Some(outer_indices[outer_number])));
+    }
+
+    level.polygons[polygon_index].points = outers[0].clone();
+    level.polygons.extend(new_polygons);
+
+    for polygon in level.polygons.iter_mut().skip(polygon_index) {
+        orient_polygon(polygon);
+    }
+}
+
+/// The outline's points without the closing point or any repeats, counterclockwise
+fn counterclockwise_loop(points: &[Vec2]) -> Vec<Vec2> {
+    let mut outline: Vec<Vec2> = Vec::new();
+
+    for point in points {
+        if outline
+            .last()
+            .is_none_or(|last| (*last - *point).length() > EPSILON)
+        {
+            outline.push(*point);
+        }
+    }
+
+    while outline.len() > 1 && (outline[0] - outline[outline.len() - 1]).length() <= EPSILON {
+        outline.pop();
+    }
+
+    if signed_area(&outline) < 0.0 {
+        outline.reverse();
+    }
+
+    outline
+}
+
+/// Positive for counterclockwise outlines, negative for clockwise ones
+fn signed_area(outline: &[Vec2]) -> f32 {
+    let mut sum = 0.0;
+
+    for i in 0..outline.len() {
+        sum += cross_product(outline[i], outline[(i + 1) % outline.len()]);
+    }
+
+    sum / 2.0
+}
+
+/// Every edge of the outline, split wherever the other outline crosses or touches it
+fn split_edges(outline: &[Vec2], other: &[Vec2]) -> Vec<(Vec2, Vec2)> {
+    let mut fragments = Vec::new();
+
+    for i in 0..outline.len() {
+        let (start, end) = (outline[i], outline[(i + 1) % outline.len()]);
+        let edge = end - start;
+
+        let mut splits: Vec<f32> = Vec::new();
+
+        for j in 0..other.len() {
+            let (other_start, other_end) = (other[j], other[(j + 1) % other.len()]);
+
+            if let Some(hit) = line_intersect(start, end, other_start, other_end) {
+                splits.push((hit - start).dot(edge) / edge.length_squared());
+            }
+
+            // Parallel edges never "intersect", so overlaps are found by their endpoints
+            if distance_to_segment(other_start, start, end) <= EPSILON {
+                splits.push((other_start - start).dot(edge) / edge.length_squared());
+            }
+        }
+
+        splits.retain(|t| t.is_finite() && *t > 0.0 && *t < 1.0);
+        splits.sort_by(|a, b| a.total_cmp(b));
+
+        let mut previous = start;
+        for t in splits.into_iter().chain(std::iter::once(1.0)) {
+            let point = if t == 1.0 { end } else { start + edge * t };
+
+            if (point - previous).length() > EPSILON {
+                fragments.push((previous, point));
+                previous = point;
+            }
+        }
+    }
+
+    fragments
+}
+
+fn classify_fragment(start: Vec2, end: Vec2, other: &[Vec2]) -> FragmentSide {
+    let middle = (start + end) / 2.0;
+    let direction = (end - start).normalize_or_zero();
+
+    for i in 0..other.len() {
+        let (other_start, other_end) = (other[i], other[(i + 1) % other.len()]);
+
+        if distance_to_segment(middle, other_start, other_end) <= EPSILON {
+            return if direction.dot(other_end - other_start) > 0.0 {
+                FragmentSide::SharedSame
+            } else {
+                FragmentSide::SharedOpposite
+            };
+        }
+    }
+
+    if contains_point(other, middle) {
+        FragmentSide::Inside
+    } else {
+        FragmentSide::Outside
+    }
+}
+
+/// Even-odd test that counts each crossing once, even through vertices
+pub fn contains_point(outline: &[Vec2], point: Vec2) -> bool {
+    let mut inside = false;
+
+    for i in 0..outline.len() {
+        let (start, end) = (outline[i], outline[(i + 1) % outline.len()]);
+
+        if (start.y > point.y) != (end.y > point.y) {
+            let crossing_x = start.x + (point.y - start.y) / (end.y - start.y) * (end.x - start.x);
+
+            if point.x < crossing_x {
+                inside = !inside;
+            }
+        }
+    }
+
+    inside
+}
+
+/// Splits an outline (closed or not, wound either way) into triangles by clipping ears off it,
+/// for filling it in
+///
+/// Returns the outline the triangles index into, counterclockwise and without the repeated
+/// closing point. Outlines that touch themselves can run out of ears, leaving the rest unfilled
+pub fn triangulate(points: &[Vec2]) -> (Vec<Vec2>, Vec<[usize; 3]>) {
+    let outline = counterclockwise_loop(points);
+    let mut remaining: Vec<usize> = (0..outline.len()).collect();
+    let mut triangles = Vec::new();
+
+    while remaining.len() > 3 {
+        let count = remaining.len();
+        let corner = |i: usize| {
+            [
+                remaining[(i + count - 1) % count],
+                remaining[i],
+                remaining[(i + 1) % count],
+            ]
+        };
+
+        // A convex corner with none of the rest of the outline inside it
+        let ear = (0..count).map(corner).find(|&[prev, current, next]| {
+            let (a, b, c) = (outline[prev], outline[current], outline[next]);
+
+            cross_product(b - a, c - b) > EPSILON
+                && remaining.iter().all(|&other| {
+                    other == prev
+                        || other == current
+                        || other == next
+                        || !strictly_inside_triangle(outline[other], a, b, c)
+                })
+        });
+
+        let Some(ear) = ear else {
+            break;
+        };
+
+        triangles.push(ear);
+        remaining.retain(|&index| index != ear[1]);
+    }
+
+    if remaining.len() == 3 {
+        triangles.push([remaining[0], remaining[1], remaining[2]]);
+    }
+
+    (outline, triangles)
+}
+
+/// Whether the point is inside the counterclockwise triangle and not on its edges
+fn strictly_inside_triangle(point: Vec2, a: Vec2, b: Vec2, c: Vec2) -> bool {
+    cross_product(b - a, point - a) > 0.0
+        && cross_product(c - b, point - b) > 0.0
+        && cross_product(a - c, point - c) > 0.0
+}
+
+pub fn distance_to_segment(point: Vec2, start: Vec2, end: Vec2) -> f32 {
+    let segment = end - start;
+    let t = ((point - start).dot(segment) / segment.length_squared()).clamp(0.0, 1.0);
+
+    if !t.is_finite() {
+        return (point - start).length();
+    }
+
+    (point - (start + segment * t)).length()
+}
+
+/// Joins the fragments end to start into loops, taking the sharpest right turn wherever
+/// several carry on from the same point so touching loops come out separate
+fn chain_fragments(fragments: Vec<(Vec2, Vec2)>) -> Vec<Vec<Vec2>> {
+    let mut used = vec![false; fragments.len()];
+    let mut loops = Vec::new();
+
+    for first in 0..fragments.len() {
+        if used[first] {
+            continue;
+        }
+
+        used[first] = true;
+        let mut outline = vec![fragments[first].0];
+        let (mut current, mut direction) =
+            (fragments[first].1, fragments[first].1 - fragments[first].0);
+
+        loop {
+            if (current - outline[0]).length() <= EPSILON {
+                loops.push(outline);
+                break;
+            }
+
+            let next = (0..fragments.len())
+                .filter(|i| !used[*i] && (fragments[*i].0 - current).length() <= EPSILON)
+                .min_by(|a, b| {
+                    let turn = |i: &usize| {
+                        let outgoing = fragments[*i].1 - fragments[*i].0;
+                        cross_product(direction, outgoing).atan2(direction.dot(outgoing))
+                    };
+                    turn(a).total_cmp(&turn(b))
+                });
+
+            // A dangling piece (from rounding) that doesn't close up is dropped
+            let Some(next) = next else {
+                break;
+            };
+
+            used[next] = true;
+            outline.push(current);
+            direction = fragments[next].1 - fragments[next].0;
+            current = fragments[next].1;
+        }
+    }
+
+    loops
+}
+
+/// Drops points that sit in the middle of a straight run, left over from splitting
+fn simplify_loop(outline: &[Vec2]) -> Vec<Vec2> {
+    let mut simplified: Vec<Vec2> = Vec::new();
+
+    for i in 0..outline.len() {
+        let previous = outline[(i + outline.len() - 1) % outline.len()];
+        let next = outline[(i + 1) % outline.len()];
+        let (incoming, outgoing) = (outline[i] - previous, next - outline[i]);
+
+        let straight = cross_product(incoming.normalize_or_zero(), outgoing.normalize_or_zero())
+            .abs()
+            <= 0.001
+            && incoming.dot(outgoing) > 0.0;
+
+        if !straight {
+            simplified.push(outline[i]);
+        }
+    }
+
+    simplified
+}
@@ -0,0 +1,365 @@
+use bevy::{
+    app::{App, Plugin, Update},
+    asset::Assets,
+    ecs::{
+        change_detection::DetectChanges,
+        component::Component,
+        entity::Entity,
+        query::{Has, Or, With, Without},
+        reflect::{ReflectComponent, ReflectResource},
+        schedule::IntoSystemConfigs,
+        system::{Commands, Query, Res, ResMut, Resource},
+    },
+    hierarchy::{BuildChildren, DespawnRecursiveExt},
+    input::{keyboard::KeyCode, ButtonInput},
+    math::{Quat, Vec2, Vec3Swizzles},
+    reflect::Reflect,
+    render::{
+        camera::ClearColor,
+        color::Color,
+        mesh::{Indices, Mesh, PrimitiveTopology},
+        render_asset::RenderAssetUsages,
+        view::Visibility,
+    },
+    sprite::{ColorMaterial, ColorMesh2dBundle, Mesh2dHandle, Sprite, SpriteBundle},
+    transform::components::Transform,
+    utils::default,
+};
+
+use crate::{
+    ai::{
+        animation::{agent_color, AgentAnimation},
+        pathfinding::Pathfinding,
+        platformer_ai::PlatformerAI,
+    },
+    level::{Level, Polygon},
+    polygon_ops::triangulate,
+    GoalPoint, Physics, PursueAISet,
+};
+
+/// How far past the level's container its solid backdrop reaches, so the edges never show
+const LEVEL_BACKDROP_MARGIN: f32 = 2000.0;
+
+/// How far back each layer of the level is drawn, so holes cover the solids they're cut out of
+const LEVEL_BACKDROP_Z: f32 = -3.0;
+const LEVEL_CONTAINER_Z: f32 = -2.5;
+const LEVEL_SOLID_Z: f32 = -2.0;
+const LEVEL_HOLE_Z: f32 = -1.5;
+
+/// Sprites go in front of the level but behind the agent labels
+const BODY_SPRITE_Z: f32 = 0.5;
+
+/// How fast a body has to move before its sprite turns to face where it's going
+const FACING_MIN_SPEED: f32 = 0.5;
+
+pub struct PresentationPlugin;
+
+impl Plugin for PresentationPlugin {
+    fn build(&self, app: &mut App) {
+        app.register_type::<Presentation>()
+            .register_type::<LevelMesh>()
+            .register_type::<BodySprite>()
+            .insert_resource(Presentation { active: true })
+            .insert_resource(LevelMeshes::default())
+            .add_systems(
+                Update,
+                (
+                    s_presentation_input,
+                    (s_sync_level_meshes, s_sync_body_sprites).in_set(PursueAISet::Render),
+                )
+                    .chain(),
+            );
+    }
+}
+
+/// Whether the level is drawn filled in and the agents and the goal as sprites, in place of
+/// the gizmo outlines
+#[derive(Resource, Default, Reflect)]
+#[reflect(Resource)]
+pub struct Presentation {
+    pub active: bool,
+}
+
+/// A filled in level polygon, or the solid backdrop around the level's container
+#[derive(Component, Reflect)]
+#[reflect(Component)]
+pub struct LevelMesh {
+    pub polygon_index: usize,
+    pub backdrop: bool,
+}
+
+/// The outlines the level meshes were last built from, so only polygons that changed (like
+/// moving platforms) are built again
+#[derive(Resource, Default)]
+pub struct LevelMeshes {
+    pub outlines: Vec<Vec<Vec2>>,
+}
+
+/// The sprite drawn for an agent or the goal, turned to face the way it's moving
+#[derive(Component, Reflect)]
+#[reflect(Component)]
+pub struct BodySprite {
+    pub body: Entity,
+    /// Which way the body's facing, in radians from the x axis
+    pub facing: f32,
+}
+
+pub fn s_presentation_input(
+    keyboard_input: Res<ButtonInput<KeyCode>>,
+    mut presentation: ResMut<Presentation>,
+) {
+    // F6 to swap between the sprites and the gizmos
+    if keyboard_input.just_pressed(KeyCode::F6) {
+        presentation.active = !presentation.active;
+        println!("Presentation mode: {}", presentation.active);
+    }
+}
+
+fn fill_mesh(outline: &[Vec2], triangles: &[[usize; 3]]) -> Mesh {
+    Mesh::new(
+        PrimitiveTopology::TriangleList,
+        RenderAssetUsages::default(),
+    )
+    .with_inserted_attribute(
+        Mesh::ATTRIBUTE_POSITION,
+        outline
+            .iter()
+            .map(|point| [point.x, point.y, 0.0])
+            .collect::<Vec<[f32; 3]>>(),
+    )
+    .with_inserted_attribute(Mesh::ATTRIBUTE_NORMAL, vec![[0.0, 0.0, 1.0]; outline.len()])
+    .with_inserted_attribute(Mesh::ATTRIBUTE_UV_0, vec![[0.0, 0.0]; outline.len()])
+    .with_inserted_indices(Indices::U32(
+        triangles
+            .iter()
+            .flatten()
+            .map(|&index| index as u32)
+            .collect(),
+    ))
+}
+
+/// The polygon filled in, or for the backdrop a rectangle well past it on every side
+fn level_mesh(points: &[Vec2], backdrop: bool) -> Mesh {
+    if !backdrop {
+        let (outline, triangles) = triangulate(points);
+        return fill_mesh(&outline, &triangles);
+    }
+
+    let min = points.iter().copied().fold(Vec2::MAX, Vec2::min) - LEVEL_BACKDROP_MARGIN;
+    let max = points.iter().copied().fold(Vec2::MIN, Vec2::max) + LEVEL_BACKDROP_MARGIN;
+
+    fill_mesh(
+        &[min, Vec2::new(max.x, min.y), max, Vec2::new(min.x, max.y)],
+        &[[0, 1, 2], [0, 2, 3]],
+    )
+}
+
+/// What a layer of the level is filled with and how far back it's drawn
+///
+/// Containers and holes are open space, so they're filled with the background over the solid
+/// around them
+fn level_layer(polygon: &Polygon, backdrop: bool, background: Color) -> (Color, f32) {
+    if backdrop {
+        (polygon.color, LEVEL_BACKDROP_Z)
+    } else if polygon.is_container {
+        (background, LEVEL_CONTAINER_Z)
+    } else if polygon.hole_in.is_some() {
+        (background, LEVEL_HOLE_Z)
+    } else if polygon.is_one_way {
+        (polygon.color.with_a(0.5), LEVEL_SOLID_Z)
+    } else {
+        (polygon.color, LEVEL_SOLID_Z)
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn s_sync_level_meshes(
+    mut commands: Commands,
+    level: Res<Level>,
+    presentation: Res<Presentation>,
+    clear_color: Res<ClearColor>,
+    mut level_meshes: ResMut<LevelMeshes>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+    mut mesh_query: Query<(Entity, &LevelMesh, &Mesh2dHandle, &mut Visibility)>,
+) {
+    if !level.is_changed() && !presentation.is_changed() {
+        return;
+    }
+
+    let visibility = if presentation.active {
+        Visibility::Inherited
+    } else {
+        Visibility::Hidden
+    };
+
+    // Polygons were added or taken away (a level was loaded or edited), so the indices don't
+    // line up anymore and everything's built again
+    if level_meshes.outlines.len() != level.polygons.len() {
+        for (entity, ..) in mesh_query.iter() {
+            commands.entity(entity).despawn();
+        }
+
+        level_meshes.outlines = level
+            .polygons
+            .iter()
+            .map(|polygon| polygon.points.clone())
+            .collect();
+
+        for (polygon_index, polygon) in level.polygons.iter().enumerate() {
+            let layers: &[bool] = if polygon.is_container {
+                &[true, false]
+            } else {
+                &[false]
+            };
+
+            for &backdrop in layers {
+                let (color, z) = level_layer(polygon, backdrop, clear_color.0);
+
+                commands.spawn((
+                    ColorMesh2dBundle {
+                        mesh: Mesh2dHandle(meshes.add(level_mesh(&polygon.points, backdrop))),
+                        material: materials.add(ColorMaterial::from(color)),
+                        transform: Transform::from_xyz(0.0, 0.0, z),
+                        visibility,
+                        ..default()
+                    },
+                    LevelMesh {
+                        polygon_index,
+                        backdrop,
+                    },
+                ));
+            }
+        }
+
+        return;
+    }
+
+    for (_, level_mesh_info, mesh_handle, mut mesh_visibility) in mesh_query.iter_mut() {
+        *mesh_visibility = visibility;
+
+        let points = &level.polygons[level_mesh_info.polygon_index].points;
+        if level_meshes.outlines[level_mesh_info.polygon_index] == *points {
+            continue;
+        }
+
+        meshes.insert(
+            mesh_handle.0.id(),
+            level_mesh(points, level_mesh_info.backdrop),
+        );
+    }
+
+    for (outline, polygon) in level_meshes.outlines.iter_mut().zip(level.polygons.iter()) {
+        if *outline != polygon.points {
+            *outline = polygon.points.clone();
+        }
+    }
+}
+
+fn body_color(is_goal: bool, goal_active: bool, animation: Option<&AgentAnimation>) -> Color {
+    match (is_goal, goal_active) {
+        (true, true) => Color::GREEN,
+        (true, false) => Color::GRAY.with_a(0.2),
+        (false, _) => animation.map_or(Color::RED, agent_color),
+    }
+}
+
+#[allow(clippy::type_complexity)]
+pub fn s_sync_body_sprites(
+    mut commands: Commands,
+    presentation: Res<Presentation>,
+    pathfinding: Res<Pathfinding>,
+    body_query: Query<
+        (
+            Entity,
+            &Transform,
+            &Physics,
+            Option<&AgentAnimation>,
+            Has<GoalPoint>,
+        ),
+        Or<(With<PlatformerAI>, With<GoalPoint>)>,
+    >,
+    mut sprite_query: Query<
+        (
+            Entity,
+            &mut BodySprite,
+            &mut Sprite,
+            &mut Transform,
+            &mut Visibility,
+        ),
+        Without<Physics>,
+    >,
+) {
+    let visibility = if presentation.active {
+        Visibility::Inherited
+    } else {
+        Visibility::Hidden
+    };
+
+    let mut sprited_bodies = Vec::new();
+
+    for (sprite_entity, mut body_sprite, mut sprite, mut transform, mut sprite_visibility) in
+        sprite_query.iter_mut()
+    {
+        // The body's gone, so its sprite goes too
+        let Ok((_, body_transform, physics, animation, is_goal)) = body_query.get(body_sprite.body)
+        else {
+            commands.entity(sprite_entity).despawn_recursive();
+            continue;
+        };
+        sprited_bodies.push(body_sprite.body);
+
+        *sprite_visibility = visibility;
+        if !presentation.active {
+            continue;
+        }
+
+        // Standing still keeps the way it last faced
+        if physics.velocity.length() > FACING_MIN_SPEED {
+            body_sprite.facing = physics.velocity.y.atan2(physics.velocity.x);
+        }
+
+        sprite.color = body_color(is_goal, pathfinding.active, animation);
+        transform.translation = body_transform.translation.xy().extend(BODY_SPRITE_Z);
+        transform.rotation = Quat::from_rotation_z(body_sprite.facing);
+    }
+
+    // Bodies spawned since last frame
+    for (body_entity, body_transform, physics, animation, is_goal) in body_query.iter() {
+        if sprited_bodies.contains(&body_entity) {
+            continue;
+        }
+
+        commands
+            .spawn((
+                SpriteBundle {
+                    sprite: Sprite {
+                        color: body_color(is_goal, pathfinding.active, animation),
+                        custom_size: Some(physics.shape.extents(physics.radius) * 2.0),
+                        ..default()
+                    },
+                    transform: Transform::from_translation(
+                        body_transform.translation.xy().extend(BODY_SPRITE_Z),
+                    ),
+                    visibility,
+                    ..default()
+                },
+                BodySprite {
+                    body: body_entity,
+                    facing: 0.0,
+                },
+            ))
+            .with_children(|parent| {
+                // An eye toward the front, so which way it's facing shows
+                parent.spawn(SpriteBundle {
+                    sprite: Sprite {
+                        color: Color::WHITE,
+                        custom_size: Some(Vec2::splat(physics.radius * 0.6)),
+                        ..default()
+                    },
+                    transform: Transform::from_xyz(physics.radius * 0.5, 0.0, 0.1),
+                    ..default()
+                });
+            });
+    }
+}
@@ -0,0 +1,338 @@
+use bevy::{
+    app::{App, FixedUpdate, Plugin, Update},
+    ecs::{
+        entity::Entity,
+        query::{With, Without},
+        schedule::IntoSystemConfigs,
+        system::{Query, Res, ResMut, Resource},
+    },
+    gizmos::gizmos::Gizmos,
+    input::{keyboard::KeyCode, ButtonInput},
+    math::{Vec2, Vec3Swizzles},
+    render::color::Color,
+    transform::components::Transform,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    ai::{
+        pathfinding::Pathfinding,
+        platformer_ai::{PlatformerAI, PlatformerAIState},
+    },
+    simulation::Simulation,
+    toggle_goal,
+    utils::vec2_serde,
+    GoalPoint, Physics, PursueAISet,
+};
+
+#[cfg(not(target_arch = "wasm32"))]
+const RECORDING_FILE_PATH: &str = "recording.json";
+
+/// How many ticks Shift jumps the replay by
+const REPLAY_BIG_STEP: usize = 60;
+
+pub struct RecordingPlugin;
+
+impl Plugin for RecordingPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(Recorder::default())
+            .add_systems(FixedUpdate, s_record_tick.after(PursueAISet::Collision))
+            .add_systems(Update, (s_recording_input, s_replay).chain());
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RecordedBody {
+    #[serde(with = "vec2_serde")]
+    pub position: Vec2,
+    #[serde(with = "vec2_serde")]
+    pub velocity: Vec2,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RecordedAgent {
+    pub body: RecordedBody,
+    pub state: PlatformerAIState,
+    /// The node ids of the new path, on the ticks the agent replanned
+    pub replanned_path: Option<Vec<usize>>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RecordedTick {
+    /// The agents in the order they were spawned
+    pub agents: Vec<RecordedAgent>,
+    pub goal: Option<RecordedBody>,
+    pub goal_active: bool,
+}
+
+/// Everything that moved, tick by tick
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Recording {
+    pub ticks: Vec<RecordedTick>,
+}
+
+/// Captures the agents and the goal every tick while recording, and puts them back the way
+/// they were on any recorded tick while replaying
+///
+/// The simulation is paused for the whole replay, and carries on from wherever the replay
+/// was left when it's stopped
+#[derive(Resource, Default)]
+pub struct Recorder {
+    pub recording: bool,
+    pub replaying: bool,
+    /// Whether the replay moves forward on its own, one tick per frame
+    pub playing: bool,
+    pub replay_tick: usize,
+    pub data: Recording,
+}
+
+pub fn save_recording(recording: &Recording) {
+    #[cfg(not(target_arch = "wasm32"))]
+    match serde_json::to_string(recording) {
+        Ok(recording_json) => match std::fs::write(RECORDING_FILE_PATH, recording_json) {
+            Ok(()) => println!(
+                "Saved {} ticks to {}",
+                recording.ticks.len(),
+                RECORDING_FILE_PATH
+            ),
+            Err(error) => println!("Failed to save recording: {}", error),
+        },
+        Err(error) => println!("Failed to serialize recording: {}", error),
+    }
+
+    #[cfg(target_arch = "wasm32")]
+    let _ = recording;
+}
+
+/// Loads the last saved recording, or nothing if there isn't one
+pub fn load_recording() -> Option<Recording> {
+    #[cfg(not(target_arch = "wasm32"))]
+    {
+        let recording_json = std::fs::read_to_string(RECORDING_FILE_PATH).ok()?;
+
+        match serde_json::from_str(&recording_json) {
+            Ok(recording) => Some(recording),
+            Err(error) => {
+                println!("Failed to load recording: {}", error);
+                None
+            }
+        }
+    }
+
+    #[cfg(target_arch = "wasm32")]
+    None
+}
+
+/// The agents sorted by when they were spawned, so recordings line up with them again
+fn agents_in_spawn_order<T>(mut agents: Vec<(Entity, T)>) -> Vec<T> {
+    agents.sort_by_key(|(entity, _)| *entity);
+    agents.into_iter().map(|(_, agent)| agent).collect()
+}
+
+pub fn s_record_tick(
+    mut recorder: ResMut<Recorder>,
+    pathfinding: Res<Pathfinding>,
+    agent_query: Query<(Entity, &Transform, &Physics, &PlatformerAI)>,
+    goal_query: Query<(&Transform, &Physics), With<GoalPoint>>,
+) {
+    if !recorder.recording {
+        return;
+    }
+
+    let agents = agents_in_spawn_order(
+        agent_query
+            .iter()
+            .map(|(entity, transform, physics, platformer_ai)| {
+                (
+                    entity,
+                    RecordedAgent {
+                        body: RecordedBody {
+                            position: transform.translation.xy(),
+                            velocity: physics.velocity,
+                        },
+                        state: platformer_ai.state,
+                        // The counter's only back at zero on the tick a new path was searched
+                        replanned_path: (platformer_ai.ticks_since_replan == 0).then(|| {
+                            platformer_ai
+                                .path
+                                .iter()
+                                .map(|path_node| path_node.id)
+                                .collect()
+                        }),
+                    },
+                )
+            })
+            .collect(),
+    );
+
+    let goal = goal_query
+        .iter()
+        .next()
+        .map(|(transform, physics)| RecordedBody {
+            position: transform.translation.xy(),
+            velocity: physics.velocity,
+        });
+
+    recorder.data.ticks.push(RecordedTick {
+        agents,
+        goal,
+        goal_active: pathfinding.active,
+    });
+}
+
+pub fn s_recording_input(
+    keyboard_input: Res<ButtonInput<KeyCode>>,
+    mut recorder: ResMut<Recorder>,
+    mut simulation: ResMut<Simulation>,
+) {
+    let shift = keyboard_input.any_pressed([KeyCode::ShiftLeft, KeyCode::ShiftRight]);
+
+    if keyboard_input.just_pressed(KeyCode::KeyE) {
+        // Shift+E to replay the saved recording, or to go back to the live simulation
+        if shift {
+            if recorder.replaying {
+                recorder.replaying = false;
+                simulation.paused = false;
+                println!("Replay stopped at tick {}", recorder.replay_tick);
+            } else {
+                // A recording in progress is saved first, so it's the one replayed
+                if recorder.recording {
+                    recorder.recording = false;
+                    save_recording(&recorder.data);
+                }
+
+                let Some(recording) = load_recording() else {
+                    return;
+                };
+
+                recorder.data = recording;
+                recorder.replaying = true;
+                recorder.playing = true;
+                recorder.replay_tick = 0;
+                simulation.paused = true;
+                println!("Replaying {} ticks", recorder.data.ticks.len());
+            }
+        }
+        // E to start recording, and again to stop and save it
+        else if !recorder.replaying {
+            recorder.recording = !recorder.recording;
+
+            if recorder.recording {
+                recorder.data = Recording::default();
+                println!("Recording");
+            } else {
+                save_recording(&recorder.data);
+            }
+        }
+    }
+
+    if !recorder.replaying {
+        return;
+    }
+
+    let tick_count = recorder.data.ticks.len();
+    let step = if shift { REPLAY_BIG_STEP } else { 1 };
+
+    // Slash to pause or play, and comma and period to scrub back and forward
+    if keyboard_input.just_pressed(KeyCode::Slash) {
+        recorder.playing = !recorder.playing;
+    }
+    if keyboard_input.pressed(KeyCode::Comma) {
+        recorder.playing = false;
+        recorder.replay_tick = recorder.replay_tick.saturating_sub(step);
+    }
+    if keyboard_input.pressed(KeyCode::Period) {
+        recorder.playing = false;
+        recorder.replay_tick = (recorder.replay_tick + step).min(tick_count.saturating_sub(1));
+    }
+}
+
+/// Puts the agents and the goal where they were on the replay's tick and draws the paths the
+/// agents were following then
+pub fn s_replay(
+    mut recorder: ResMut<Recorder>,
+    mut gizmos: Gizmos,
+    mut pathfinding: ResMut<Pathfinding>,
+    mut agent_query: Query<
+        (Entity, &mut Transform, &mut Physics, &mut PlatformerAI),
+        Without<GoalPoint>,
+    >,
+    mut goal_query: Query<(&mut Transform, &mut Physics), With<GoalPoint>>,
+) {
+    if !recorder.replaying {
+        return;
+    }
+
+    let tick_count = recorder.data.ticks.len();
+    let Some(tick) = recorder.data.ticks.get(recorder.replay_tick) else {
+        return;
+    };
+
+    let agents = agents_in_spawn_order(
+        agent_query
+            .iter_mut()
+            .map(|(entity, transform, physics, platformer_ai)| {
+                (entity, (transform, physics, platformer_ai))
+            })
+            .collect(),
+    );
+
+    for (agent_index, (mut transform, mut physics, mut platformer_ai)) in
+        agents.into_iter().enumerate()
+    {
+        let Some(recorded_agent) = tick.agents.get(agent_index) else {
+            continue;
+        };
+
+        transform.translation = recorded_agent.body.position.extend(0.0);
+        physics.prev_position = recorded_agent.body.position;
+        physics.velocity = recorded_agent.body.velocity;
+        platformer_ai.state = recorded_agent.state;
+
+        // The path the agent was on is the last one it found up to this tick
+        let path = recorder.data.ticks[..=recorder.replay_tick]
+            .iter()
+            .rev()
+            .find_map(|tick| {
+                tick.agents
+                    .get(agent_index)
+                    .and_then(|agent| agent.replanned_path.as_ref())
+            });
+
+        if let Some(path) = path {
+            gizmos.linestrip_2d(
+                std::iter::once(recorded_agent.body.position).chain(
+                    path.iter()
+                        .filter_map(|node_id| pathfinding.nodes.get(*node_id))
+                        .map(|node| node.position),
+                ),
+                Color::FUCHSIA,
+            );
+        }
+    }
+
+    if let (Some(recorded_goal), Some((mut transform, mut physics))) =
+        (tick.goal.as_ref(), goal_query.iter_mut().next())
+    {
+        transform.translation = recorded_goal.position.extend(0.0);
+        physics.prev_position = recorded_goal.position;
+        physics.velocity = recorded_goal.velocity;
+
+        // Nothing syncs the goal up while the simulation's paused
+        if pathfinding.goal_position != recorded_goal.position {
+            pathfinding.goal_position = recorded_goal.position;
+        }
+    }
+
+    if pathfinding.active != tick.goal_active {
+        toggle_goal(&mut pathfinding);
+    }
+
+    if recorder.playing {
+        if recorder.replay_tick + 1 < tick_count {
+            recorder.replay_tick += 1;
+        } else {
+            recorder.playing = false;
+        }
+    }
+}
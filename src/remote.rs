@@ -0,0 +1,336 @@
+use std::{
+    io,
+    net::{SocketAddr, TcpListener, TcpStream, ToSocketAddrs},
+    sync::{
+        mpsc::{self, Receiver, Sender},
+        Arc, Mutex,
+    },
+    thread,
+    time::Duration,
+};
+
+use bevy::{
+    app::{App, FixedUpdate, Plugin, Startup, Update},
+    ecs::{
+        entity::Entity,
+        event::EventWriter,
+        schedule::IntoSystemConfigs,
+        system::{Commands, Query, Res, ResMut, Resource},
+    },
+    math::{Vec2, Vec3Swizzles},
+    transform::components::Transform,
+};
+use serde::{Deserialize, Serialize};
+use tungstenite::Message;
+
+use crate::{
+    ai::{
+        pathfinding::{GoalTeleported, Pathfinding, PathfindingSettings},
+        platformer_ai::{PlatformerAI, PlatformerAIState, PlatformerAITuning},
+    },
+    level::Level,
+    reset_positions,
+    settings::{apply_settings, Settings},
+    simulation::Simulation,
+    Physics, PursueAISet,
+};
+
+/// How often each client's connection checks for telemetry to send while waiting for commands
+const REMOTE_POLL_INTERVAL: Duration = Duration::from_millis(10);
+
+/// Telemetry goes out every this many ticks, so clients aren't flooded at the full tick rate
+const REMOTE_TELEMETRY_INTERVAL_TICKS: u32 = 6;
+
+pub struct RemotePlugin;
+
+impl Plugin for RemotePlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Startup, s_start_remote_server)
+            .add_systems(Update, s_remote_commands)
+            .add_systems(
+                FixedUpdate,
+                s_remote_telemetry.after(PursueAISet::Collision),
+            );
+    }
+}
+
+/// Something a client asks the simulation to do, sent as JSON like
+/// `{ "command": "move_goal", "x": 10.0, "y": -50.0 }`
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(tag = "command", rename_all = "snake_case")]
+pub enum RemoteCommand {
+    /// Teleports the goal point there
+    MoveGoal {
+        x: f32,
+        y: f32,
+    },
+    /// Puts the agents and the goal back where they started, like R
+    Reset,
+    /// Changes only the settings given, laid out like `settings.ron`
+    Settings {
+        settings: serde_json::Value,
+    },
+    Pause,
+    Resume,
+}
+
+/// What's sent to every client as JSON every few ticks
+#[derive(Debug, Clone, Serialize)]
+pub struct Telemetry {
+    pub tick: u32,
+    /// Where the goal is, or nothing while it's toggled off
+    pub goal: Option<[f32; 2]>,
+    pub agents: Vec<AgentTelemetry>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct AgentTelemetry {
+    pub id: u32,
+    pub position: [f32; 2],
+    pub velocity: [f32; 2],
+    pub state: PlatformerAIState,
+    /// The nodes left on its path
+    pub path: Vec<[f32; 2]>,
+}
+
+/// The WebSocket server started with `--remote <address>`
+///
+/// Clients are served on their own threads, with commands and telemetry passed over channels
+#[derive(Resource)]
+pub struct RemoteServer {
+    /// Where it's listening, with the port filled in if it was picked by the OS
+    pub local_address: SocketAddr,
+    commands: Mutex<Receiver<RemoteCommand>>,
+    clients: Arc<Mutex<Vec<Sender<String>>>>,
+    pub ticks: u32,
+}
+
+impl RemoteServer {
+    /// Listens for clients on the address, on a thread of its own
+    pub fn start(address: impl ToSocketAddrs) -> io::Result<RemoteServer> {
+        let listener = TcpListener::bind(address)?;
+        let local_address = listener.local_addr()?;
+        let (command_sender, command_receiver) = mpsc::channel();
+        let clients = Arc::new(Mutex::new(Vec::new()));
+
+        let listener_clients = clients.clone();
+        thread::spawn(move || accept_clients(listener, command_sender, listener_clients));
+
+        Ok(RemoteServer {
+            local_address,
+            commands: Mutex::new(command_receiver),
+            clients,
+            ticks: 0,
+        })
+    }
+
+    /// The commands clients have sent since last time
+    pub fn commands(&self) -> Vec<RemoteCommand> {
+        self.commands.lock().unwrap().try_iter().collect()
+    }
+
+    /// Sends the message to every client still connected
+    pub fn broadcast(&self, message: &str) {
+        self.clients
+            .lock()
+            .unwrap()
+            .retain(|client| client.send(message.to_string()).is_ok());
+    }
+}
+
+fn accept_clients(
+    listener: TcpListener,
+    command_sender: Sender<RemoteCommand>,
+    clients: Arc<Mutex<Vec<Sender<String>>>>,
+) {
+    for stream in listener.incoming().flatten() {
+        let (telemetry_sender, telemetry_receiver) = mpsc::channel();
+        clients.lock().unwrap().push(telemetry_sender);
+
+        let command_sender = command_sender.clone();
+        thread::spawn(move || serve_client(stream, command_sender, telemetry_receiver));
+    }
+}
+
+fn serve_client(
+    stream: TcpStream,
+    command_sender: Sender<RemoteCommand>,
+    telemetry_receiver: Receiver<String>,
+) {
+    let Ok(mut socket) = tungstenite::accept(stream) else {
+        return;
+    };
+    // Reads give up after a moment so telemetry still goes out while the client's quiet
+    if socket
+        .get_ref()
+        .set_read_timeout(Some(REMOTE_POLL_INTERVAL))
+        .is_err()
+    {
+        return;
+    }
+
+    loop {
+        match socket.read() {
+            Ok(Message::Text(text)) => match serde_json::from_str(&text) {
+                Ok(command) => {
+                    if command_sender.send(command).is_err() {
+                        return;
+                    }
+                }
+                Err(error) => {
+                    let reply = serde_json::json!({ "error": error.to_string() }).to_string();
+                    if socket.send(Message::Text(reply)).is_err() {
+                        return;
+                    }
+                }
+            },
+            Ok(Message::Close(_)) => return,
+            Ok(_) => {}
+            Err(tungstenite::Error::Io(error))
+                if matches!(
+                    error.kind(),
+                    io::ErrorKind::WouldBlock | io::ErrorKind::TimedOut
+                ) => {}
+            Err(_) => return,
+        }
+
+        for telemetry in telemetry_receiver.try_iter() {
+            if socket.send(Message::Text(telemetry)).is_err() {
+                return;
+            }
+        }
+    }
+}
+
+/// Lays the changes over the current settings, so a client only has to send what it changes
+pub fn merge_settings(
+    settings: &Settings,
+    changes: &serde_json::Value,
+) -> Result<Settings, serde_json::Error> {
+    fn merge(value: &mut serde_json::Value, changes: &serde_json::Value) {
+        match (value, changes) {
+            (serde_json::Value::Object(value), serde_json::Value::Object(changes)) => {
+                for (key, change) in changes {
+                    match value.get_mut(key) {
+                        Some(field) => merge(field, change),
+                        None => {
+                            value.insert(key.clone(), change.clone());
+                        }
+                    }
+                }
+            }
+            (value, changes) => *value = changes.clone(),
+        }
+    }
+
+    let mut value = serde_json::to_value(settings)?;
+    merge(&mut value, changes);
+
+    serde_json::from_value(value)
+}
+
+pub fn s_start_remote_server(mut commands: Commands, simulation: Res<Simulation>) {
+    let Some(address) = &simulation.remote else {
+        return;
+    };
+
+    match RemoteServer::start(address) {
+        Ok(remote_server) => {
+            println!(
+                "Remote control listening on ws://{}",
+                remote_server.local_address
+            );
+            commands.insert_resource(remote_server);
+        }
+        Err(error) => println!("Couldn't start remote control on {} ({})", address, error),
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn s_remote_commands(
+    remote_server: Option<Res<RemoteServer>>,
+    level: Res<Level>,
+    mut simulation: ResMut<Simulation>,
+    mut settings: ResMut<Settings>,
+    mut pathfinding_settings: ResMut<PathfindingSettings>,
+    mut pathfinding: ResMut<Pathfinding>,
+    mut goal_teleported_events: EventWriter<GoalTeleported>,
+    mut platformer_ai_query: Query<(&mut Transform, &mut Physics, &mut PlatformerAI)>,
+    mut tuning_query: Query<&mut PlatformerAITuning>,
+) {
+    let Some(remote_server) = remote_server else {
+        return;
+    };
+
+    for command in remote_server.commands() {
+        match command {
+            RemoteCommand::MoveGoal { x, y } => {
+                goal_teleported_events.send(pathfinding.teleport_goal(Vec2::new(x, y)));
+            }
+            RemoteCommand::Reset => {
+                goal_teleported_events.send(reset_positions(
+                    &level,
+                    &mut pathfinding,
+                    &mut platformer_ai_query,
+                ));
+            }
+            RemoteCommand::Settings { settings: changes } => {
+                match merge_settings(&settings, &changes) {
+                    Ok(new_settings) => apply_settings(
+                        &new_settings,
+                        &mut settings,
+                        &mut pathfinding_settings,
+                        &mut pathfinding,
+                        &mut tuning_query,
+                    ),
+                    Err(error) => println!("Remote control sent bad settings ({})", error),
+                }
+            }
+            RemoteCommand::Pause => simulation.paused = true,
+            RemoteCommand::Resume => simulation.paused = false,
+        }
+    }
+}
+
+pub fn s_remote_telemetry(
+    remote_server: Option<ResMut<RemoteServer>>,
+    pathfinding: Res<Pathfinding>,
+    agent_query: Query<(Entity, &Transform, &Physics, &PlatformerAI)>,
+) {
+    let Some(mut remote_server) = remote_server else {
+        return;
+    };
+
+    remote_server.ticks += 1;
+    if remote_server.ticks % REMOTE_TELEMETRY_INTERVAL_TICKS != 0 {
+        return;
+    }
+
+    let telemetry = Telemetry {
+        tick: remote_server.ticks,
+        goal: pathfinding
+            .active
+            .then(|| pathfinding.goal_position.to_array()),
+        agents: agent_query
+            .iter()
+            .map(
+                |(entity, transform, physics, platformer_ai)| AgentTelemetry {
+                    id: entity.index(),
+                    position: transform.translation.xy().to_array(),
+                    velocity: physics.velocity.to_array(),
+                    state: platformer_ai.state,
+                    path: platformer_ai
+                        .path
+                        .iter()
+                        .map(|path_node| path_node.position.to_array())
+                        .collect(),
+                },
+            )
+            .collect(),
+    };
+
+    match serde_json::to_string(&telemetry) {
+        Ok(telemetry) => remote_server.broadcast(&telemetry),
+        Err(error) => println!("Failed to serialize telemetry: {}", error),
+    }
+}
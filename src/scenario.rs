@@ -0,0 +1,221 @@
+use bevy::{
+    app::{App, AppExit, FixedUpdate, Plugin},
+    ecs::{
+        entity::Entity,
+        event::EventWriter,
+        query::With,
+        schedule::IntoSystemConfigs,
+        system::{Query, Res, ResMut, Resource},
+    },
+    math::{Vec2, Vec3Swizzles},
+    transform::components::Transform,
+    utils::HashMap,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    ai::{pathfinding::Pathfinding, platformer_ai::PlatformerAI},
+    simulation::{Simulation, SIMULATION_TICK_RATE_HZ},
+    toggle_goal, GoalPoint, Physics, PursueAISet,
+};
+
+/// How far an agent has to move to not be counted as stuck
+const SCENARIO_STUCK_DISTANCE: f32 = 8.0;
+
+/// A scripted run for checking the AI without anyone at the controls, loaded from a JSON file
+/// with `--scenario <path>`
+///
+/// The goal is switched on at the start, so the agents chase it straight away
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Scenario {
+    /// A level from the benchmark suite, unless `--level` picks one
+    #[serde(default)]
+    pub level: Option<String>,
+    /// A level file (relative to `assets/`), unless `--level-file` picks one
+    #[serde(default)]
+    pub level_file: Option<String>,
+    /// Where the agent starts instead of the level's spawn point
+    #[serde(default, with = "option_vec2_serde")]
+    pub agent_spawn: Option<Vec2>,
+    /// Where the goal starts instead of the level's goal start
+    #[serde(default, with = "option_vec2_serde")]
+    pub goal_start: Option<Vec2>,
+    /// A goal script (RON) that moves the goal during the run, unless `--goal-script` picks one
+    #[serde(default)]
+    pub goal_script: Option<String>,
+    /// A scene (relative to `assets/`) with the agents, the goal and the settings to start
+    /// from, unless `--scene` picks one
+    #[serde(default)]
+    pub scene: Option<String>,
+    /// How long the agents get to catch the goal
+    pub max_ticks: u32,
+    /// How close (on top of touching) an agent has to get to the goal to catch it
+    #[serde(default)]
+    pub catch_distance: f32,
+    /// How long an agent can go without getting anywhere before it counts as stuck
+    #[serde(default = "default_stuck_ticks")]
+    pub stuck_ticks: u32,
+}
+
+fn default_stuck_ticks() -> u32 {
+    (SIMULATION_TICK_RATE_HZ * 5.0) as u32
+}
+
+mod option_vec2_serde {
+    use bevy::math::Vec2;
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+    pub fn serialize<S: Serializer>(
+        vector: &Option<Vec2>,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        vector.map(|vector| vector.to_array()).serialize(serializer)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Option<Vec2>, D::Error> {
+        Option::<[f32; 2]>::deserialize(deserializer).map(|vector| vector.map(Vec2::from))
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ScenarioResult {
+    Caught,
+    Stuck,
+    TimedOut,
+}
+
+/// How a scenario went, printed as a line of JSON when it's over
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ScenarioOutcome {
+    pub result: ScenarioResult,
+    pub ticks: u32,
+    pub seconds: f64,
+    /// The closest any agent got to the goal
+    pub closest_distance: f32,
+}
+
+pub fn load_scenario(path: &str) -> Result<Scenario, String> {
+    let scenario_json = std::fs::read_to_string(path).map_err(|error| error.to_string())?;
+    serde_json::from_str(&scenario_json).map_err(|error| error.to_string())
+}
+
+pub struct ScenarioPlugin;
+
+impl Plugin for ScenarioPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(ScenarioRun::default()).add_systems(
+            FixedUpdate,
+            (
+                s_script_scenario_goal.in_set(PursueAISet::Decision),
+                s_check_scenario_outcome.after(PursueAISet::Collision),
+            ),
+        );
+    }
+}
+
+/// Where the running scenario is up to
+#[derive(Resource, Default)]
+pub struct ScenarioRun {
+    pub tick: u32,
+    /// Where each agent last got somewhere, and on which tick
+    pub last_progress: HashMap<Entity, (Vec2, u32)>,
+    pub closest_distance: Option<f32>,
+    pub outcome: Option<ScenarioOutcome>,
+}
+
+pub fn s_script_scenario_goal(
+    simulation: Res<Simulation>,
+    scenario_run: Res<ScenarioRun>,
+    mut pathfinding: ResMut<Pathfinding>,
+) {
+    if simulation.scenario.is_none() || scenario_run.outcome.is_some() {
+        return;
+    }
+
+    if scenario_run.tick == 0 && !pathfinding.active {
+        toggle_goal(&mut pathfinding);
+    }
+}
+
+pub fn s_check_scenario_outcome(
+    simulation: Res<Simulation>,
+    mut scenario_run: ResMut<ScenarioRun>,
+    agent_query: Query<(Entity, &Transform, &Physics), With<PlatformerAI>>,
+    goal_query: Query<(&Transform, &Physics), With<GoalPoint>>,
+    mut exit: EventWriter<AppExit>,
+) {
+    let Some(scenario) = simulation.scenario.as_ref() else {
+        return;
+    };
+
+    if scenario_run.outcome.is_some() {
+        return;
+    }
+
+    let tick = scenario_run.tick;
+    scenario_run.tick += 1;
+
+    let Ok((goal_transform, goal_physics)) = goal_query.get_single() else {
+        return;
+    };
+    let goal_position = goal_transform.translation.xy();
+
+    let mut result = None;
+
+    for (entity, transform, physics) in agent_query.iter() {
+        let position = transform.translation.xy();
+
+        // Measured between the edges, so it's zero when they touch
+        let distance =
+            (position.distance(goal_position) - physics.radius - goal_physics.radius).max(0.0);
+        scenario_run.closest_distance = Some(
+            scenario_run
+                .closest_distance
+                .map_or(distance, |closest_distance| closest_distance.min(distance)),
+        );
+
+        if distance <= scenario.catch_distance {
+            result = Some(ScenarioResult::Caught);
+        }
+
+        let (progress_position, progress_tick) = *scenario_run
+            .last_progress
+            .entry(entity)
+            .or_insert((position, tick));
+
+        if position.distance(progress_position) > SCENARIO_STUCK_DISTANCE {
+            scenario_run.last_progress.insert(entity, (position, tick));
+        } else if tick - progress_tick >= scenario.stuck_ticks && result.is_none() {
+            result = Some(ScenarioResult::Stuck);
+        }
+    }
+
+    if result.is_none() && tick + 1 >= scenario.max_ticks {
+        result = Some(ScenarioResult::TimedOut);
+    }
+
+    let Some(result) = result else {
+        return;
+    };
+
+    let outcome = ScenarioOutcome {
+        result,
+        ticks: tick + 1,
+        seconds: (tick + 1) as f64 / SIMULATION_TICK_RATE_HZ,
+        closest_distance: scenario_run.closest_distance.unwrap_or(f32::MAX),
+    };
+
+    match serde_json::to_string(&outcome) {
+        Ok(outcome_json) => println!("{}", outcome_json),
+        Err(error) => println!("Failed to serialize scenario outcome: {}", error),
+    }
+    scenario_run.outcome = Some(outcome);
+
+    // Headless runs are only there for the outcome
+    if simulation.headless {
+        exit.send(AppExit);
+    }
+}
@@ -0,0 +1,244 @@
+use bevy::{
+    app::{App, Plugin, Startup, Update},
+    asset::{AssetEvent, AssetServer, Assets, Handle, LoadState},
+    ecs::{
+        change_detection::DetectChangesMut,
+        entity::{Entity, EntityHashMap},
+        event::EventReader,
+        query::{Or, With, Without},
+        reflect::AppTypeRegistry,
+        schedule::IntoSystemConfigs,
+        system::{Commands, Query, Res, ResMut, Resource},
+        world::{Mut, World},
+    },
+    input::{keyboard::KeyCode, ButtonInput},
+    math::Vec3Swizzles,
+    scene::{DynamicScene, DynamicSceneBuilder},
+    transform::components::Transform,
+};
+
+use crate::{
+    agent_body,
+    ai::{
+        pathfinding::{Pathfinding, PathfindingSettings},
+        platformer_ai::{PlatformerAI, PlatformerAITuning},
+        pursuit::{PursuitTarget, TargetSelection},
+    },
+    goal_point_physics, s_init,
+    settings::Settings,
+    simulation::Simulation,
+    GoalPoint, Physics,
+};
+
+/// Where F5 saves the running scenario's scene
+#[cfg(not(target_arch = "wasm32"))]
+const SCENARIO_SCENE_EXPORT_PATH: &str = "assets/scenes/exported.scn.ron";
+
+pub struct ScenarioScenePlugin;
+
+impl Plugin for ScenarioScenePlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(ScenarioScene::default())
+            .add_systems(Startup, s_load_scenario_scene.after(s_init))
+            .add_systems(
+                Update,
+                (
+                    s_export_scenario_scene,
+                    (
+                        s_watch_scenario_scene,
+                        s_apply_scenario_scene,
+                        s_complete_scene_entities,
+                    )
+                        .chain(),
+                ),
+            );
+    }
+}
+
+/// The scene the agents, the goal and the settings were loaded from, if there is one
+#[derive(Resource, Default)]
+pub struct ScenarioScene {
+    pub path: String,
+    pub handle: Option<Handle<DynamicScene>>,
+    /// Set when the scene's finished loading or changed on disk, until it's put in the world
+    pub pending: bool,
+    /// Whether the simulation's paused until the scene's in, so scenarios start from it
+    pub holding: bool,
+}
+
+/// Captures the agents and the goal (where they are, their tuning and what they pursue) and
+/// the settings as a scene
+///
+/// Everything else about them is rebuilt when the scene's loaded, so a scene starts a fresh run
+/// rather than resuming one
+pub fn build_scenario_scene(world: &World) -> DynamicScene {
+    let entities: Vec<Entity> = world
+        .iter_entities()
+        .filter(|entity| entity.contains::<PlatformerAI>() || entity.contains::<GoalPoint>())
+        .map(|entity| entity.id())
+        .collect();
+
+    DynamicSceneBuilder::from_world(world)
+        .allow::<Transform>()
+        .allow::<GoalPoint>()
+        .allow::<PursuitTarget>()
+        .allow::<PlatformerAITuning>()
+        .allow::<TargetSelection>()
+        .allow_resource::<Settings>()
+        .extract_entities(entities.into_iter())
+        .extract_resources()
+        .build()
+}
+
+pub fn save_scenario_scene(scene_ron: &str) {
+    #[cfg(not(target_arch = "wasm32"))]
+    {
+        let path = std::path::Path::new(SCENARIO_SCENE_EXPORT_PATH);
+        let result = path
+            .parent()
+            .map_or(Ok(()), std::fs::create_dir_all)
+            .and_then(|_| std::fs::write(path, scene_ron));
+
+        match result {
+            Ok(_) => println!("Saved scene to {}", SCENARIO_SCENE_EXPORT_PATH),
+            Err(error) => println!("Failed to save scene: {}", error),
+        }
+    }
+
+    #[cfg(target_arch = "wasm32")]
+    let _ = scene_ron;
+}
+
+pub fn s_export_scenario_scene(world: &mut World) {
+    // F5 to save the agents, the goal and the settings as a scene
+    if !world
+        .resource::<ButtonInput<KeyCode>>()
+        .just_pressed(KeyCode::F5)
+    {
+        return;
+    }
+
+    let scene = build_scenario_scene(world);
+
+    match scene.serialize_ron(&world.resource::<AppTypeRegistry>().0) {
+        Ok(scene_ron) => save_scenario_scene(&scene_ron),
+        Err(error) => println!("Failed to serialize scene: {}", error),
+    }
+}
+
+/// Starts loading the scene given with `--scene`, or the scenario's, and holds the simulation
+/// until it's in
+pub fn s_load_scenario_scene(
+    asset_server: Res<AssetServer>,
+    mut simulation: ResMut<Simulation>,
+    mut scenario_scene: ResMut<ScenarioScene>,
+) {
+    let Some(scene_path) = simulation.scene.clone().or_else(|| {
+        simulation
+            .scenario
+            .as_ref()
+            .and_then(|scenario| scenario.scene.clone())
+    }) else {
+        return;
+    };
+
+    scenario_scene.handle = Some(asset_server.load(scene_path.clone()));
+    scenario_scene.path = scene_path;
+    scenario_scene.holding = true;
+    simulation.paused = true;
+}
+
+pub fn s_watch_scenario_scene(
+    mut asset_events: EventReader<AssetEvent<DynamicScene>>,
+    asset_server: Res<AssetServer>,
+    mut simulation: ResMut<Simulation>,
+    mut scenario_scene: ResMut<ScenarioScene>,
+) {
+    let Some(handle) = scenario_scene.handle.clone() else {
+        return;
+    };
+
+    for asset_event in asset_events.read() {
+        if let AssetEvent::LoadedWithDependencies { id } | AssetEvent::Modified { id } = asset_event
+        {
+            if *id == handle.id() {
+                scenario_scene.pending = true;
+            }
+        }
+    }
+
+    // Run from the usual start rather than not at all
+    if scenario_scene.holding && asset_server.load_state(&handle) == LoadState::Failed {
+        println!("Couldn't load scene {}", scenario_scene.path);
+        scenario_scene.holding = false;
+        simulation.paused = false;
+    }
+}
+
+/// Swaps the agents and the goal for the scene's whenever it finishes loading or changes on
+/// disk
+pub fn s_apply_scenario_scene(world: &mut World) {
+    let scenario_scene = world.resource::<ScenarioScene>();
+    let (Some(handle), true) = (scenario_scene.handle.clone(), scenario_scene.pending) else {
+        return;
+    };
+
+    let result = world.resource_scope(|world, scenes: Mut<Assets<DynamicScene>>| {
+        let scene = scenes.get(&handle)?;
+
+        let replaced: Vec<Entity> = world
+            .query_filtered::<Entity, Or<(With<PlatformerAI>, With<GoalPoint>)>>()
+            .iter(world)
+            .collect();
+        for entity in replaced {
+            world.despawn(entity);
+        }
+
+        Some(scene.write_to_world(world, &mut EntityHashMap::default()))
+    });
+
+    let Some(result) = result else {
+        return;
+    };
+
+    let scene_path = &world.resource::<ScenarioScene>().path;
+    match result {
+        Ok(_) => println!("Loaded scene {}", scene_path),
+        Err(error) => println!("Couldn't spawn scene {} ({})", scene_path, error),
+    }
+
+    // The scene's settings only reach the graph and the search through their own resources
+    let settings = world.resource::<Settings>().clone();
+    world
+        .resource_mut::<PathfindingSettings>()
+        .set_if_neq(settings.pathfinding);
+    let mut pathfinding = world.resource_mut::<Pathfinding>();
+    pathfinding.heuristic_weight = settings.search.heuristic_weight;
+    pathfinding.skip_walkable_runs = settings.search.skip_walkable_runs;
+
+    let mut scenario_scene = world.resource_mut::<ScenarioScene>();
+    scenario_scene.pending = false;
+    if std::mem::take(&mut scenario_scene.holding) {
+        world.resource_mut::<Simulation>().paused = false;
+    }
+}
+
+/// Gives the agents and the goal spawned from a scene the bodies and state scenes leave out
+#[allow(clippy::type_complexity)]
+pub fn s_complete_scene_entities(
+    mut commands: Commands,
+    mut pathfinding: ResMut<Pathfinding>,
+    goal_query: Query<(Entity, &Transform), (With<GoalPoint>, Without<Physics>)>,
+    agent_query: Query<Entity, (With<PlatformerAITuning>, Without<Physics>)>,
+) {
+    for (entity, transform) in goal_query.iter() {
+        let position = transform.translation.xy();
+
+        pathfinding.goal_position = position;
+        commands.entity(entity).insert(goal_point_physics(position));
+    }
+
+    for entity in agent_query.iter() {
+        commands.entity(entity).insert(agent_body());
+    }
+}
@@ -0,0 +1,308 @@
+use std::fmt;
+
+use bevy::{
+    app::{App, Plugin, Startup, Update},
+    asset::{
+        io::Reader, Asset, AssetApp, AssetEvent, AssetLoader, AssetServer, Assets, AsyncReadExt,
+        Handle, LoadContext,
+    },
+    ecs::{
+        event::EventReader,
+        reflect::ReflectResource,
+        schedule::IntoSystemConfigs,
+        system::{Query, Res, ResMut, Resource},
+    },
+    reflect::Reflect,
+    utils::BoxedFuture,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::ai::{
+    pathfinding::{s_rebuild_pathfinding_graph, Pathfinding, PathfindingSettings},
+    platformer_ai::{PlatformerAITuning, PLATFORMER_AI_AGENT_RADIUS},
+};
+
+/// The settings file loaded at startup (relative to `assets/`), which is reloaded when it
+/// changes on disk with the `hot_reload` feature
+pub const SETTINGS_FILE: &str = "settings.ron";
+
+pub struct SettingsPlugin;
+
+impl Plugin for SettingsPlugin {
+    fn build(&self, app: &mut App) {
+        app.register_type::<Settings>()
+            .register_type::<SearchSettings>()
+            .register_type::<GoalSettings>()
+            .register_type::<SquadSettings>()
+            .register_type::<JumpFeedbackSettings>()
+            .register_type::<EvaderSettings>()
+            .init_asset::<Settings>()
+            .init_asset_loader::<SettingsLoader>()
+            .insert_resource(Settings::default())
+            .insert_resource(SettingsHandle(None))
+            .add_systems(Startup, s_load_settings)
+            .add_systems(Update, s_apply_settings.before(s_rebuild_pathfinding_graph));
+    }
+}
+
+/// Everything worth tweaking about how the agents, the goal and the evader behave, loaded from
+/// `settings.ron`
+///
+/// Anything left out of the file keeps its default
+#[derive(Resource, Asset, Debug, Clone, PartialEq, Default, Serialize, Deserialize, Reflect)]
+#[reflect(Resource)]
+#[serde(default)]
+pub struct Settings {
+    /// How agents without tuning of their own move and replan
+    pub agent: PlatformerAITuning,
+    pub pathfinding: PathfindingSettings,
+    pub search: SearchSettings,
+    pub goal: GoalSettings,
+    pub squad: SquadSettings,
+    pub jump_feedback: JumpFeedbackSettings,
+    pub evader: EvaderSettings,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, Reflect)]
+#[serde(default)]
+pub struct SearchSettings {
+    /// How much the straight-line distance to the goal counts for in A*, where anything over
+    /// 1.0 trades the shortest paths for faster searches
+    pub heuristic_weight: f32,
+    /// Whether A* crosses plain stretches of floor and wall in one go instead of a node at a
+    /// time
+    pub skip_walkable_runs: bool,
+}
+
+impl Default for SearchSettings {
+    fn default() -> Self {
+        SearchSettings {
+            heuristic_weight: 1.0,
+            skip_walkable_runs: true,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, Reflect)]
+#[serde(default)]
+pub struct GoalSettings {
+    /// How fast the arrow keys move the goal (per tick)
+    pub speed: f32,
+}
+
+impl Default for GoalSettings {
+    fn default() -> Self {
+        GoalSettings { speed: 4.0 }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, Reflect)]
+#[serde(default)]
+pub struct SquadSettings {
+    /// How many ticks pass between squad role reassignments
+    pub coordination_interval: u32,
+    /// How much longer an agent's path has to be before it hands the goal off
+    pub handoff_cost_ratio: f32,
+    /// How many of the last path nodes two agents have to share to count as converging
+    pub convergence_nodes: usize,
+    /// How far from the goal to look for escape routes to cover
+    pub exit_search_distance: f32,
+    /// How close a chasing agent has to get to the goal before it attacks
+    pub attack_distance: f32,
+}
+
+impl Default for SquadSettings {
+    fn default() -> Self {
+        SquadSettings {
+            coordination_interval: 30,
+            handoff_cost_ratio: 1.5,
+            convergence_nodes: 3,
+            exit_search_distance: 250.0,
+            attack_distance: 64.0,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, Reflect)]
+#[serde(default)]
+pub struct JumpFeedbackSettings {
+    /// How close to the target node a jump has to come down to count as made
+    pub landing_tolerance: f32,
+    /// How much each failure (that hasn't been made up for) adds to a connection's cost scale
+    pub failure_penalty: f32,
+    /// The most a connection's cost can be scaled up by for failing
+    pub max_penalty: f32,
+    /// How long a connection stays penalized after its last failure
+    pub penalty_ticks: u32,
+}
+
+impl Default for JumpFeedbackSettings {
+    fn default() -> Self {
+        JumpFeedbackSettings {
+            landing_tolerance: PLATFORMER_AI_AGENT_RADIUS * 2.0,
+            failure_penalty: 1.5,
+            max_penalty: 10.0,
+            penalty_ticks: 600,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, Reflect)]
+#[serde(default)]
+pub struct EvaderSettings {
+    /// How many ticks pass between the evader picking somewhere new to run to
+    pub replan_interval: u32,
+    /// How fast the evader flies (per tick)
+    pub max_speed: f32,
+    /// How much every unit the evader has to travel takes off a node's score
+    pub travel_weight: f32,
+    /// What a node out of sight of every pursuer is worth, in units of distance
+    pub hidden_bonus: f32,
+    /// How close the evader has to get to a path node to move on to the next one
+    pub node_reached_distance: f32,
+}
+
+impl Default for EvaderSettings {
+    fn default() -> Self {
+        EvaderSettings {
+            replan_interval: 30,
+            max_speed: 4.0,
+            travel_weight: 0.5,
+            hidden_bonus: 150.0,
+            node_reached_distance: 6.0,
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum SettingsError {
+    Io(std::io::Error),
+    Ron(ron::error::SpannedError),
+}
+
+impl fmt::Display for SettingsError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SettingsError::Io(error) => write!(f, "couldn't read settings file: {}", error),
+            SettingsError::Ron(error) => write!(f, "couldn't parse settings file: {}", error),
+        }
+    }
+}
+
+impl std::error::Error for SettingsError {}
+
+impl From<std::io::Error> for SettingsError {
+    fn from(error: std::io::Error) -> Self {
+        SettingsError::Io(error)
+    }
+}
+
+impl From<ron::error::SpannedError> for SettingsError {
+    fn from(error: ron::error::SpannedError) -> Self {
+        SettingsError::Ron(error)
+    }
+}
+
+#[derive(Default)]
+pub struct SettingsLoader;
+
+impl AssetLoader for SettingsLoader {
+    type Asset = Settings;
+    type Settings = ();
+    type Error = SettingsError;
+
+    fn load<'a>(
+        &'a self,
+        reader: &'a mut Reader,
+        _settings: &'a (),
+        _load_context: &'a mut LoadContext,
+    ) -> BoxedFuture<'a, Result<Settings, SettingsError>> {
+        Box::pin(async move {
+            let mut bytes = Vec::new();
+            reader.read_to_end(&mut bytes).await?;
+
+            Ok(ron::de::from_bytes(&bytes)?)
+        })
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["settings.ron"]
+    }
+}
+
+#[derive(Resource)]
+pub struct SettingsHandle(pub Option<Handle<Settings>>);
+
+pub fn s_load_settings(
+    asset_server: Res<AssetServer>,
+    mut settings_handle: ResMut<SettingsHandle>,
+) {
+    settings_handle.0 = Some(asset_server.load(SETTINGS_FILE));
+}
+
+/// Swaps in the settings file whenever it finishes loading or changes on disk
+///
+/// Only what changed in the file is passed on, so the graph isn't rebuilt for nothing and
+/// agents tuned some other way (by a scenario or the inspector) keep their tuning
+pub fn s_apply_settings(
+    mut asset_events: EventReader<AssetEvent<Settings>>,
+    settings_assets: Res<Assets<Settings>>,
+    settings_handle: Res<SettingsHandle>,
+    mut settings: ResMut<Settings>,
+    mut pathfinding_settings: ResMut<PathfindingSettings>,
+    mut pathfinding: ResMut<Pathfinding>,
+    mut tuning_query: Query<&mut PlatformerAITuning>,
+) {
+    let Some(handle) = &settings_handle.0 else {
+        return;
+    };
+
+    for asset_event in asset_events.read() {
+        let changed = match asset_event {
+            AssetEvent::Added { id } | AssetEvent::Modified { id } => *id == handle.id(),
+            _ => false,
+        };
+
+        let Some(new_settings) = settings_assets.get(handle).filter(|_| changed) else {
+            continue;
+        };
+
+        apply_settings(
+            new_settings,
+            &mut settings,
+            &mut pathfinding_settings,
+            &mut pathfinding,
+            &mut tuning_query,
+        );
+
+        println!("Loaded settings from {}", SETTINGS_FILE);
+    }
+}
+
+/// Swaps in new settings, passing on only what changed
+pub fn apply_settings(
+    new_settings: &Settings,
+    settings: &mut Settings,
+    pathfinding_settings: &mut PathfindingSettings,
+    pathfinding: &mut Pathfinding,
+    tuning_query: &mut Query<&mut PlatformerAITuning>,
+) {
+    if new_settings.agent != settings.agent {
+        for mut tuning in tuning_query.iter_mut() {
+            if *tuning == settings.agent {
+                *tuning = new_settings.agent.clone();
+            }
+        }
+    }
+
+    if new_settings.pathfinding != settings.pathfinding {
+        *pathfinding_settings = new_settings.pathfinding.clone();
+    }
+
+    if new_settings.search != settings.search {
+        pathfinding.heuristic_weight = new_settings.search.heuristic_weight;
+        pathfinding.skip_walkable_runs = new_settings.search.skip_walkable_runs;
+    }
+
+    *settings = new_settings.clone();
+}
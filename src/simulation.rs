@@ -0,0 +1,326 @@
+use std::time::Duration;
+
+use bevy::{
+    app::{App, FixedMain, Plugin, Update},
+    ecs::{
+        change_detection::{DetectChanges, DetectChangesMut},
+        schedule::IntoSystemConfigs,
+        system::{Res, ResMut, Resource},
+        world::World,
+    },
+    input::{keyboard::KeyCode, ButtonInput},
+    time::{Fixed, Time, Virtual},
+};
+use rand::{rngs::StdRng, SeedableRng};
+
+#[cfg(feature = "editor")]
+use crate::editor::Editor;
+use crate::scenario::{load_scenario, Scenario};
+
+/// How many simulation ticks run per second of (virtual) time
+pub const SIMULATION_TICK_RATE_HZ: f64 = 60.0;
+
+/// How many fixed ticks fast-forward tries to fit into a single render frame
+pub const FAST_FORWARD_TICKS_PER_FRAME: f32 = 10.0;
+
+/// The longest real frame time that gets turned into simulated time, so a slow
+/// frame can't snowball into an ever growing backlog of fixed ticks
+pub const FAST_FORWARD_FRAME_BUDGET: Duration = Duration::from_millis(100);
+
+/// How much each press of + or - speeds up or slows down the simulation
+pub const TIME_SCALE_STEP: f32 = 2.0;
+
+/// The slowest and fastest the simulation can be set to run, fast-forward aside
+pub const TIME_SCALE_RANGE: (f32, f32) = (1.0 / 16.0, 4.0);
+
+#[derive(Default)]
+pub struct SimulationPlugin {
+    pub headless: bool,
+    pub fast_forward: bool,
+    pub level_name: Option<String>,
+    pub level_file: Option<String>,
+    pub text_level: Option<String>,
+    pub image_level: Option<String>,
+    pub bake: bool,
+    pub seed: u64,
+    pub scenario: Option<Scenario>,
+    pub goal_script: Option<String>,
+    pub scene: Option<String>,
+    pub behavior_script: Option<String>,
+    pub remote: Option<String>,
+    pub evader: bool,
+}
+
+impl Plugin for SimulationPlugin {
+    fn build(&self, app: &mut App) {
+        // Printed so any run can be repeated with --seed
+        println!("Seed: {}", self.seed);
+
+        app.insert_resource(Time::<Fixed>::from_hz(SIMULATION_TICK_RATE_HZ))
+            .insert_resource(SimulationRng(StdRng::seed_from_u64(self.seed)))
+            .insert_resource(Simulation {
+                headless: self.headless,
+                fast_forward: self.fast_forward,
+                level_name: self.level_name.clone(),
+                level_file: self.level_file.clone(),
+                text_level: self.text_level.clone(),
+                image_level: self.image_level.clone(),
+                bake: self.bake,
+                seed: self.seed,
+                scenario: self.scenario.clone(),
+                goal_script: self.goal_script.clone(),
+                scene: self.scene.clone(),
+                behavior_script: self.behavior_script.clone(),
+                remote: self.remote.clone(),
+                evader: self.evader,
+                paused: false,
+                step_queued: false,
+                time_scale: 1.0,
+                ticks_per_frame: FAST_FORWARD_TICKS_PER_FRAME,
+                frame_budget: FAST_FORWARD_FRAME_BUDGET,
+            })
+            .add_systems(
+                Update,
+                (
+                    s_simulation_input,
+                    s_step_simulation,
+                    s_apply_simulation_speed,
+                )
+                    .chain(),
+            );
+    }
+}
+
+/// The one source of random numbers for anything that affects a run, so the same seed, level
+/// and input play out the same way every time
+///
+/// The simulation already steps at a fixed rate, however fast it's rendered or fast-forwarded
+#[derive(Resource)]
+pub struct SimulationRng(pub StdRng);
+
+#[derive(Resource)]
+pub struct Simulation {
+    pub headless: bool,
+    pub fast_forward: bool,
+    /// Which level from the benchmark suite to run instead of the built-in one
+    pub level_name: Option<String>,
+    /// A level file (relative to `assets/`) to load in place of the starting level
+    pub level_file: Option<String>,
+    /// A text file sketching the level to run instead of the built-in one
+    pub text_level: Option<String>,
+    /// A PNG painting the level (opaque pixels solid) to run instead of the built-in one
+    pub image_level: Option<String>,
+    /// Whether to rebuild the pathfinding graph at startup and bake it to disk
+    pub bake: bool,
+    /// What the simulation's random numbers are seeded with
+    pub seed: u64,
+    /// The scripted run to play out and report on, instead of waiting for input
+    pub scenario: Option<Scenario>,
+    /// A RON file with commands that move the goal point on their own
+    pub goal_script: Option<String>,
+    /// A scene file (relative to `assets/`) the agents, the goal and the settings are loaded
+    /// from
+    pub scene: Option<String>,
+    /// A rhai script (relative to `assets/`) deciding what the agents do, with the `scripting`
+    /// feature
+    pub behavior_script: Option<String>,
+    /// The address to serve telemetry and take commands over WebSocket on, with the `remote`
+    /// feature
+    pub remote: Option<String>,
+    /// Whether the goal point starts out running from the agents on its own
+    pub evader: bool,
+    /// Whether the fixed ticks (physics and AI) are stopped, leaving the camera and UI running
+    pub paused: bool,
+    /// Set to run a single fixed tick while paused, until the tick's run
+    pub step_queued: bool,
+    /// How fast the simulation runs compared to real time, before fast-forward
+    pub time_scale: f32,
+    pub ticks_per_frame: f32,
+    pub frame_budget: Duration,
+}
+
+impl Simulation {
+    /// Reads the simulation options from the command line
+    ///
+    /// `--headless` runs without a window, `--fast-forward` starts in fast-forward mode
+    /// `--level <name>` loads a level from the benchmark suite, `--level-file <path>` loads
+    /// a level file from `assets/`, `--text-level <path>` loads a level sketched in a text file,
+    /// `--image-level <path>` loads a level painted in a PNG and `--bake` rebuilds and saves the level's pathfinding graph instead of loading the baked one
+    /// `--seed <number>` seeds the random numbers, which are seeded randomly otherwise
+    /// `--scenario <path>` plays out a scenario file and reports how it went, which also
+    /// picks the level unless `--level` or `--level-file` do
+    /// `--goal-script <path>` moves the goal point with the commands in a RON file, in place of
+    /// the scenario's, and `--evader` has the goal point run from the agents by itself
+    /// `--scene <path>` starts the agents and the goal as they were saved in a scene file in
+    /// `assets/`, in place of the scenario's
+    /// `--behavior-script <path>` has a rhai script in `assets/` decide what the agents do, with
+    /// the `scripting` feature
+    /// `--remote <address>` streams telemetry to and takes commands from WebSocket clients
+    /// connecting to the address, like `127.0.0.1:9001`, with the `remote` feature
+    pub fn plugin_from_args() -> SimulationPlugin {
+        let args: Vec<String> = std::env::args().collect();
+
+        let scenario = args
+            .iter()
+            .position(|arg| arg == "--scenario")
+            .and_then(|arg_index| args.get(arg_index + 1))
+            .and_then(|scenario_path| match load_scenario(scenario_path) {
+                Ok(scenario) => Some(scenario),
+                Err(error) => {
+                    println!("Couldn't load scenario {} ({})", scenario_path, error);
+                    None
+                }
+            });
+
+        SimulationPlugin {
+            headless: args.iter().any(|arg| arg == "--headless"),
+            fast_forward: args.iter().any(|arg| arg == "--fast-forward"),
+            level_name: args
+                .iter()
+                .position(|arg| arg == "--level")
+                .and_then(|arg_index| args.get(arg_index + 1))
+                .cloned()
+                .or_else(|| {
+                    scenario
+                        .as_ref()
+                        .and_then(|scenario| scenario.level.clone())
+                }),
+            level_file: args
+                .iter()
+                .position(|arg| arg == "--level-file")
+                .and_then(|arg_index| args.get(arg_index + 1))
+                .cloned()
+                .or_else(|| {
+                    scenario
+                        .as_ref()
+                        .and_then(|scenario| scenario.level_file.clone())
+                }),
+            text_level: args
+                .iter()
+                .position(|arg| arg == "--text-level")
+                .and_then(|arg_index| args.get(arg_index + 1))
+                .cloned(),
+            image_level: args
+                .iter()
+                .position(|arg| arg == "--image-level")
+                .and_then(|arg_index| args.get(arg_index + 1))
+                .cloned(),
+            bake: args.iter().any(|arg| arg == "--bake"),
+            seed: args
+                .iter()
+                .position(|arg| arg == "--seed")
+                .and_then(|arg_index| args.get(arg_index + 1))
+                .and_then(|seed| seed.parse().ok())
+                .unwrap_or_else(rand::random),
+            scenario,
+            goal_script: args
+                .iter()
+                .position(|arg| arg == "--goal-script")
+                .and_then(|arg_index| args.get(arg_index + 1))
+                .cloned(),
+            scene: args
+                .iter()
+                .position(|arg| arg == "--scene")
+                .and_then(|arg_index| args.get(arg_index + 1))
+                .cloned(),
+            behavior_script: args
+                .iter()
+                .position(|arg| arg == "--behavior-script")
+                .and_then(|arg_index| args.get(arg_index + 1))
+                .cloned(),
+            remote: args
+                .iter()
+                .position(|arg| arg == "--remote")
+                .and_then(|arg_index| args.get(arg_index + 1))
+                .cloned(),
+            evader: args.iter().any(|arg| arg == "--evader"),
+        }
+    }
+}
+
+pub fn s_simulation_input(
+    keyboard_input: Res<ButtonInput<KeyCode>>,
+    #[cfg(feature = "editor")] editor: Res<Editor>,
+    mut simulation: ResMut<Simulation>,
+) {
+    // F to toggle fast-forward
+    if keyboard_input.just_pressed(KeyCode::KeyF) {
+        simulation.fast_forward = !simulation.fast_forward;
+        println!("Fast-forward: {}", simulation.fast_forward);
+    }
+
+    // The editor has its own use for P, and shifted - and = space out the pathfinding nodes
+    #[cfg(feature = "editor")]
+    if editor.active {
+        return;
+    }
+
+    if keyboard_input.any_pressed([KeyCode::ShiftLeft, KeyCode::ShiftRight]) {
+        return;
+    }
+
+    // P to pause or resume the simulation
+    if keyboard_input.just_pressed(KeyCode::KeyP) {
+        simulation.paused = !simulation.paused;
+        println!("Paused: {}", simulation.paused);
+    }
+
+    // N to run a single tick while paused
+    if keyboard_input.just_pressed(KeyCode::KeyN) && simulation.paused {
+        simulation.step_queued = true;
+    }
+
+    // + and - to speed up or slow down the simulation
+    let time_scale = if keyboard_input.just_pressed(KeyCode::Equal) {
+        simulation.time_scale * TIME_SCALE_STEP
+    } else if keyboard_input.just_pressed(KeyCode::Minus) {
+        simulation.time_scale / TIME_SCALE_STEP
+    } else {
+        return;
+    };
+
+    simulation.time_scale = time_scale.clamp(TIME_SCALE_RANGE.0, TIME_SCALE_RANGE.1);
+    println!("Time scale: {}", simulation.time_scale);
+}
+
+/// Runs the fixed schedule once by hand while the simulation's paused, with the fixed clock
+/// moved on a tick as if it had run on its own
+pub fn s_step_simulation(world: &mut World) {
+    let mut simulation = world.resource_mut::<Simulation>();
+    if !simulation.step_queued {
+        return;
+    }
+    simulation.bypass_change_detection().step_queued = false;
+
+    let mut fixed_time = world.resource_mut::<Time<Fixed>>();
+    let timestep = fixed_time.timestep();
+    fixed_time.advance_by(timestep);
+
+    *world.resource_mut::<Time>() = world.resource::<Time<Fixed>>().as_generic();
+    world.run_schedule(FixedMain);
+    *world.resource_mut::<Time>() = world.resource::<Time<Virtual>>().as_generic();
+}
+
+pub fn s_apply_simulation_speed(simulation: Res<Simulation>, mut time: ResMut<Time<Virtual>>) {
+    if !simulation.is_changed() {
+        return;
+    }
+
+    // Speeding up virtual time makes the fixed step run several times per frame
+    time.set_relative_speed(
+        simulation.time_scale
+            * if simulation.fast_forward {
+                simulation.ticks_per_frame
+            } else {
+                1.0
+            },
+    );
+    time.set_max_delta(simulation.frame_budget);
+
+    // Pausing virtual time stops the fixed ticks, while everything on real time carries on
+    if simulation.paused {
+        time.pause();
+    } else {
+        time.unpause();
+    }
+}
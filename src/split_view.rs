@@ -0,0 +1,150 @@
+use bevy::{
+    app::{App, Plugin, PostUpdate, Startup, Update},
+    core_pipeline::core_2d::Camera2dBundle,
+    ecs::{
+        component::Component,
+        query::{With, Without},
+        reflect::{ReflectComponent, ReflectResource},
+        schedule::IntoSystemConfigs,
+        system::{Commands, Query, Res, ResMut, Resource},
+    },
+    input::{keyboard::KeyCode, ButtonInput},
+    math::{UVec2, Vec3Swizzles},
+    reflect::Reflect,
+    render::camera::{Camera, ClearColorConfig, Viewport},
+    transform::{components::Transform, TransformSystem},
+    window::{PrimaryWindow, Window},
+};
+
+use crate::{ai::platformer_ai::PlatformerAI, s_init, GoalPoint, MainCamera};
+
+pub struct SplitViewPlugin;
+
+impl Plugin for SplitViewPlugin {
+    fn build(&self, app: &mut App) {
+        app.register_type::<SplitView>()
+            .register_type::<FollowTarget>()
+            .register_type::<FollowCamera>()
+            .insert_resource(SplitView::default())
+            .add_systems(Startup, s_spawn_follow_cameras.after(s_init))
+            .add_systems(Update, s_split_view_input)
+            .add_systems(
+                PostUpdate,
+                s_update_follow_cameras.before(TransformSystem::TransformPropagate),
+            );
+    }
+}
+
+/// Whether the window is split between a view following the pursuer and one following the
+/// goal, instead of the one fixed view of the level
+#[derive(Resource, Default, Reflect)]
+#[reflect(Resource)]
+pub struct SplitView {
+    pub active: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Reflect)]
+pub enum FollowTarget {
+    /// The first agent, on the left
+    Pursuer,
+    /// The goal point, on the right
+    Goal,
+}
+
+/// A camera with half the window, which keeps what it follows in the middle of its half
+#[derive(Component, Reflect)]
+#[reflect(Component)]
+pub struct FollowCamera {
+    pub target: FollowTarget,
+}
+
+pub fn s_spawn_follow_cameras(mut commands: Commands) {
+    for (order, target) in [FollowTarget::Pursuer, FollowTarget::Goal]
+        .into_iter()
+        .enumerate()
+    {
+        commands.spawn((
+            Camera2dBundle {
+                camera: Camera {
+                    is_active: false,
+                    // Drawn after the main camera, and the second half mustn't clear the first
+                    order: order as isize + 1,
+                    clear_color: if order == 0 {
+                        ClearColorConfig::default()
+                    } else {
+                        ClearColorConfig::None
+                    },
+                    ..Default::default()
+                },
+                ..Default::default()
+            },
+            FollowCamera { target },
+        ));
+    }
+}
+
+pub fn s_split_view_input(
+    keyboard_input: Res<ButtonInput<KeyCode>>,
+    mut split_view: ResMut<SplitView>,
+    mut main_camera_query: Query<&mut Camera, (With<MainCamera>, Without<FollowCamera>)>,
+    mut follow_camera_query: Query<&mut Camera, With<FollowCamera>>,
+) {
+    // J to split the view between the pursuer and the goal, or join it back up
+    if !keyboard_input.just_pressed(KeyCode::KeyJ) {
+        return;
+    }
+
+    split_view.active = !split_view.active;
+
+    for mut camera in main_camera_query.iter_mut() {
+        camera.is_active = !split_view.active;
+    }
+    for mut camera in follow_camera_query.iter_mut() {
+        camera.is_active = split_view.active;
+    }
+
+    println!("Split view: {}", split_view.active);
+}
+
+/// Sizes the follow cameras to their halves of the window and moves them onto their targets
+pub fn s_update_follow_cameras(
+    split_view: Res<SplitView>,
+    q_windows: Query<&Window, With<PrimaryWindow>>,
+    mut follow_camera_query: Query<(&mut Camera, &mut Transform, &FollowCamera)>,
+    pursuer_query: Query<&Transform, (With<PlatformerAI>, Without<FollowCamera>)>,
+    goal_query: Query<&Transform, (With<GoalPoint>, Without<FollowCamera>)>,
+) {
+    if !split_view.active {
+        return;
+    }
+
+    let Ok(window) = q_windows.get_single() else {
+        return;
+    };
+    let half_size = UVec2::new(window.physical_width() / 2, window.physical_height());
+
+    // Minimized, with nothing to split
+    if half_size.x == 0 || half_size.y == 0 {
+        return;
+    }
+
+    for (mut camera, mut transform, follow_camera) in follow_camera_query.iter_mut() {
+        let (half_index, target_transform) = match follow_camera.target {
+            FollowTarget::Pursuer => (0, pursuer_query.iter().next()),
+            FollowTarget::Goal => (1, goal_query.iter().next()),
+        };
+
+        camera.viewport = Some(Viewport {
+            physical_position: UVec2::new(half_size.x * half_index, 0),
+            physical_size: half_size,
+            ..Default::default()
+        });
+
+        // Left where it was if there's nothing to follow
+        if let Some(target_transform) = target_transform {
+            let target_position = target_transform.translation.xy();
+            transform.translation.x = target_position.x;
+            transform.translation.y = target_position.y;
+        }
+    }
+}
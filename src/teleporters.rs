@@ -0,0 +1,87 @@
+use bevy::{
+    app::{App, FixedUpdate, Plugin, Update},
+    ecs::{
+        schedule::IntoSystemConfigs,
+        system::{Query, Res},
+    },
+    gizmos::gizmos::Gizmos,
+    math::{Vec2, Vec3Swizzles},
+    render::color::Color,
+    transform::components::Transform,
+};
+
+use crate::{
+    ai::platformer_ai::PlatformerAI, collisions::s_collision, level::Level, Physics, PursueAISet,
+};
+
+/// How close an agent has to get to a teleporter's entrance to go through it
+const TELEPORTER_RADIUS: f32 = 10.0;
+
+pub struct TeleportersPlugin;
+
+impl Plugin for TeleportersPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            FixedUpdate,
+            s_teleport_agents
+                .in_set(PursueAISet::Collision)
+                .after(s_collision),
+        )
+        .add_systems(Update, s_render_teleporters.in_set(PursueAISet::Render));
+    }
+}
+
+/// Moves agents that walked into a teleporter's entrance out of its exit
+pub fn s_teleport_agents(
+    mut platformer_ai_query: Query<(&mut Transform, &mut Physics, &mut PlatformerAI)>,
+    level: Res<Level>,
+) {
+    for (mut transform, mut physics, mut platformer_ai) in platformer_ai_query.iter_mut() {
+        let agent_position = transform.translation.xy();
+        let reach = physics.radius + TELEPORTER_RADIUS;
+
+        // Stepping off the exit lets the agent use the teleporter there (if any) again
+        if let Some(exit) = platformer_ai.teleported_to {
+            if (agent_position - exit).length() > reach {
+                platformer_ai.teleported_to = None;
+            }
+        }
+
+        let teleporter = level.teleporters.iter().find(|teleporter| {
+            (agent_position - teleporter.entrance).length() <= reach
+                && platformer_ai
+                    .teleported_to
+                    .is_none_or(|exit| (teleporter.entrance - exit).length() > reach)
+        });
+
+        let Some(teleporter) = teleporter else {
+            continue;
+        };
+
+        transform.translation.x = teleporter.exit.x;
+        transform.translation.y = teleporter.exit.y;
+        physics.prev_position = teleporter.exit;
+        physics.velocity = Vec2::ZERO;
+
+        // Whatever the agent was in the middle of stayed behind at the entrance
+        platformer_ai.teleported_to = Some(teleporter.exit);
+        platformer_ai.climbing = None;
+        platformer_ai.dropping_through = None;
+        platformer_ai.running_up = false;
+        platformer_ai.run_up_jump = None;
+        platformer_ai.jump_from_pos = None;
+        platformer_ai.jump_to_pos = None;
+    }
+}
+
+pub fn s_render_teleporters(mut gizmos: Gizmos, level: Res<Level>) {
+    for teleporter in level.teleporters.iter() {
+        gizmos.circle_2d(teleporter.entrance, TELEPORTER_RADIUS, Color::FUCHSIA);
+        gizmos.circle_2d(teleporter.exit, TELEPORTER_RADIUS / 2.0, Color::FUCHSIA);
+        gizmos.line_2d(
+            teleporter.entrance,
+            teleporter.exit,
+            Color::FUCHSIA.with_a(0.2),
+        );
+    }
+}
@@ -0,0 +1,351 @@
+use std::fmt;
+
+use bevy::{math::Vec2, render::color::Color};
+use rand::Rng;
+
+use crate::level::{orient_polygon_points, Level, Polygon, SpawnPoint, SpawnPointKind};
+
+const SOLID_CELL: char = '#';
+const EMPTY_CELL: char = '.';
+/// An empty cell that agents start in
+const SPAWN_CELL: char = 'S';
+/// An empty cell that the goal point starts in
+const GOAL_CELL: char = 'G';
+
+/// A level sketched in a text file, one character per grid cell
+///
+/// `#` is solid, `.` is empty, `S` is the empty cell agents start in and `G` is the empty cell
+/// the goal starts in. Short rows are
+/// padded with empty cells and the whole sketch is walled in, so nothing can fall out of it
+pub struct TextLevel {
+    /// Which cells are solid (rows top to bottom), including the wall around the sketch
+    pub solid: Vec<Vec<bool>>,
+    /// The (column, row) of the `S` cell, if there was one
+    pub spawn_cell: Option<(usize, usize)>,
+    /// The (column, row) of the `G` cell, if there was one
+    pub goal_cell: Option<(usize, usize)>,
+}
+
+#[derive(Debug)]
+pub enum TextLevelError {
+    Io(std::io::Error),
+    UnknownCell {
+        line: usize,
+        column: usize,
+        character: char,
+    },
+    Empty,
+}
+
+impl fmt::Display for TextLevelError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TextLevelError::Io(error) => write!(f, "couldn't read text level: {}", error),
+            TextLevelError::UnknownCell {
+                line,
+                column,
+                character,
+            } => write!(
+                f,
+                "unknown cell '{}' at line {}, column {}",
+                character, line, column
+            ),
+            TextLevelError::Empty => write!(f, "text level has no cells"),
+        }
+    }
+}
+
+impl std::error::Error for TextLevelError {}
+
+impl From<std::io::Error> for TextLevelError {
+    fn from(error: std::io::Error) -> Self {
+        TextLevelError::Io(error)
+    }
+}
+
+pub fn load_text_level(path: &str) -> Result<TextLevel, TextLevelError> {
+    parse_text_level(&std::fs::read_to_string(path)?)
+}
+
+pub fn parse_text_level(text: &str) -> Result<TextLevel, TextLevelError> {
+    let mut sketch: Vec<Vec<bool>> = Vec::new();
+    let mut spawn_cell = None;
+    let mut goal_cell = None;
+
+    for (line_index, line) in text.lines().enumerate() {
+        let line = line.trim_end();
+        if line.is_empty() {
+            continue;
+        }
+
+        let mut row = Vec::new();
+
+        for (column, character) in line.chars().enumerate() {
+            match character {
+                SOLID_CELL => row.push(true),
+                EMPTY_CELL => row.push(false),
+                SPAWN_CELL => {
+                    // Shifted by one for the wall around the sketch
+                    spawn_cell = Some((column + 1, sketch.len() + 1));
+                    row.push(false);
+                }
+                GOAL_CELL => {
+                    goal_cell = Some((column + 1, sketch.len() + 1));
+                    row.push(false);
+                }
+                _ => {
+                    return Err(TextLevelError::UnknownCell {
+                        line: line_index + 1,
+                        column: column + 1,
+                        character,
+                    })
+                }
+            }
+        }
+
+        sketch.push(row);
+    }
+
+    let sketch_columns = sketch.iter().map(Vec::len).max().unwrap_or(0);
+    if sketch_columns == 0 {
+        return Err(TextLevelError::Empty);
+    }
+
+    let (columns, rows) = (sketch_columns + 2, sketch.len() + 2);
+
+    let solid = (0..rows)
+        .map(|row| {
+            (0..columns)
+                .map(|column| {
+                    let on_border =
+                        row == 0 || row == rows - 1 || column == 0 || column == columns - 1;
+
+                    on_border || sketch[row - 1].get(column - 1).copied().unwrap_or(false)
+                })
+                .collect()
+        })
+        .collect();
+
+    Ok(TextLevel {
+        solid,
+        spawn_cell,
+        goal_cell,
+    })
+}
+
+impl TextLevel {
+    pub fn columns(&self) -> usize {
+        self.solid[0].len()
+    }
+
+    pub fn rows(&self) -> usize {
+        self.solid.len()
+    }
+
+    /// Solid cells are merged into as few rectangles as possible, each one becoming a polygon
+    pub fn build(&self, grid_size: f32, rng: &mut impl Rng) -> Level {
+        let polygons = merge_solid_cells(&self.solid)
+            .into_iter()
+            .map(|rectangle| {
+                let mut polygon = self.rectangle_polygon(rectangle, grid_size);
+                polygon.color = Color::rgb(
+                    rng.gen_range(0.0..=1.0),
+                    rng.gen_range(0.0..=1.0),
+                    rng.gen_range(0.0..=1.0),
+                );
+                polygon
+            })
+            .collect();
+
+        let size = Vec2::new(self.columns() as f32, self.rows() as f32);
+
+        let mut spawn_points = vec![SpawnPoint::agent(self.spawn_position(grid_size))];
+        if let Some(goal_cell) = self.goal_cell {
+            spawn_points.push(SpawnPoint {
+                name: "goal".to_string(),
+                kind: SpawnPointKind::GoalStart,
+                position: self.cell_center_position(goal_cell, grid_size),
+            });
+        }
+
+        Level {
+            polygons,
+            grid_size,
+            size,
+            half_size: size / 2.0,
+            markers: Vec::new(),
+            spawn_points,
+            ladders: Vec::new(),
+            teleporters: Vec::new(),
+            hazards: Vec::new(),
+            water: Vec::new(),
+            bounce_pads: Vec::new(),
+        }
+    }
+
+    /// The world position of the middle of the spawn cell
+    ///
+    /// Without an `S` cell, agents start on the lowest floor, as far left as possible
+    pub fn spawn_position(&self, grid_size: f32) -> Vec2 {
+        let spawn_cell = self.spawn_cell.or_else(|| {
+            (1..self.rows() - 1).rev().find_map(|row| {
+                (1..self.columns() - 1)
+                    .find(|&column| !self.solid[row][column] && self.solid[row + 1][column])
+                    .map(|column| (column, row))
+            })
+        });
+
+        let (column, row) = spawn_cell.unwrap_or((self.columns() / 2, self.rows() / 2));
+
+        self.cell_center_position((column, row), grid_size)
+    }
+
+    fn cell_center_position(&self, cell: (usize, usize), grid_size: f32) -> Vec2 {
+        self.cell_corner_position(cell, grid_size) + Vec2::new(0.5, -0.5) * grid_size
+    }
+
+    fn is_solid(&self, (column, row): (i32, i32)) -> bool {
+        if column < 0 || row < 0 {
+            return true;
+        }
+
+        self.solid
+            .get(row as usize)
+            .and_then(|cells| cells.get(column as usize))
+            .copied()
+            .unwrap_or(true)
+    }
+
+    /// The world position of a cell's top left corner
+    fn cell_corner_position(&self, (column, row): (usize, usize), grid_size: f32) -> Vec2 {
+        Vec2::new(
+            (column as f32 - self.columns() as f32 / 2.0) * grid_size,
+            (self.rows() as f32 / 2.0 - row as f32) * grid_size,
+        )
+    }
+
+    /// Outlines the rectangle, splitting its sides wherever they go from open to buried
+    /// against other solid cells, so the buried stretches never get pathfinding nodes
+    fn rectangle_polygon(&self, rectangle: CellRectangle, grid_size: f32) -> Polygon {
+        let (left, top) = (rectangle.column as i32, rectangle.row as i32);
+        let (right, bottom) = (left + rectangle.width as i32, top + rectangle.height as i32);
+
+        let corners = [(left, top), (right, top), (right, bottom), (left, bottom)];
+
+        let mut cell_points = vec![corners[0]];
+        let mut buried_lines: Vec<bool> = Vec::new();
+
+        for side in 0..corners.len() {
+            let (start, end) = (corners[side], corners[(side + 1) % corners.len()]);
+            let step = ((end.0 - start.0).signum(), (end.1 - start.1).signum());
+            let length = (end.0 - start.0).abs().max((end.1 - start.1).abs());
+
+            // Rows go down, so turning the step this way points out of the rectangle
+            let outward = (step.1, -step.0);
+
+            for i in 0..length {
+                let point = (start.0 + step.0 * i, start.1 + step.1 * i);
+
+                // The cell just outside the side, next to this stretch of it
+                let outside_cell = (
+                    (point.0 as f32 + (step.0 + outward.0) as f32 * 0.5).floor() as i32,
+                    (point.1 as f32 + (step.1 + outward.1) as f32 * 0.5).floor() as i32,
+                );
+                let buried = self.is_solid(outside_cell);
+
+                if i == 0 {
+                    buried_lines.push(buried);
+                } else if buried_lines.last() != Some(&buried) {
+                    cell_points.push(point);
+                    buried_lines.push(buried);
+                }
+            }
+
+            cell_points.push(end);
+        }
+
+        let mut points: Vec<Vec2> = cell_points
+            .iter()
+            .map(|&(column, row)| {
+                self.cell_corner_position((column as usize, row as usize), grid_size)
+            })
+            .collect();
+
+        let unoriented_points = points.clone();
+        orient_polygon_points(&mut points, false);
+        if points != unoriented_points {
+            buried_lines.reverse();
+        }
+
+        Polygon {
+            points,
+            color: Color::WHITE,
+            is_container: false,
+            no_node_lines: buried_lines
+                .iter()
+                .enumerate()
+                .filter(|(_, &buried)| buried)
+                .map(|(line_index, _)| line_index)
+                .collect(),
+            node_spacing: None,
+            destructible_lines: Vec::new(),
+            broken_lines: Vec::new(),
+            is_one_way: false,
+            line_frictions: Vec::new(),
+            line_conveyor_speeds: Vec::new(),
+            line_restitutions: Vec::new(),
+            hole_in: None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+struct CellRectangle {
+    column: usize,
+    row: usize,
+    width: usize,
+    height: usize,
+}
+
+/// Greedily covers the solid cells with rectangles, widest first, then as tall as they'll go
+fn merge_solid_cells(solid: &[Vec<bool>]) -> Vec<CellRectangle> {
+    let (columns, rows) = (solid[0].len(), solid.len());
+
+    let mut covered = vec![vec![false; columns]; rows];
+    let mut rectangles = Vec::new();
+
+    for row in 0..rows {
+        for column in 0..columns {
+            if !solid[row][column] || covered[row][column] {
+                continue;
+            }
+
+            let width = (column..columns)
+                .take_while(|&end_column| solid[row][end_column] && !covered[row][end_column])
+                .count();
+
+            let height = (row..rows)
+                .take_while(|&end_row| {
+                    (column..column + width).all(|cell_column| {
+                        solid[end_row][cell_column] && !covered[end_row][cell_column]
+                    })
+                })
+                .count();
+
+            for covered_row in &mut covered[row..row + height] {
+                for cell in &mut covered_row[column..column + width] {
+                    *cell = true;
+                }
+            }
+
+            rectangles.push(CellRectangle {
+                column,
+                row,
+                width,
+                height,
+            });
+        }
+    }
+
+    rectangles
+}
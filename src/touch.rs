@@ -0,0 +1,238 @@
+use bevy::{
+    app::{App, Plugin, Update},
+    ecs::{
+        event::EventWriter,
+        query::With,
+        reflect::ReflectResource,
+        schedule::IntoSystemConfigs,
+        system::{Query, Res, ResMut, Resource},
+    },
+    gizmos::gizmos::Gizmos,
+    input::touch::Touches,
+    math::Vec2,
+    reflect::Reflect,
+    render::{camera::Camera, color::Color},
+    transform::components::GlobalTransform,
+    window::{PrimaryWindow, Window},
+};
+
+use crate::{
+    ai::pathfinding::{GoalTeleported, Pathfinding},
+    player::{s_player_input, PlayerMode},
+    s_input, toggle_goal, GizmosVisible, InputDir, MainCamera, PursueAISet,
+};
+
+/// How far the joystick's knob can be pushed from its middle, in screen pixels
+const JOYSTICK_RADIUS: f32 = 50.0;
+
+/// How far the joystick sits in from the bottom left corner of the screen
+const JOYSTICK_MARGIN: f32 = 90.0;
+
+/// How big the buttons are, in screen pixels
+const BUTTON_RADIUS: f32 = 24.0;
+
+/// How far apart the buttons along the top right of the screen are
+const BUTTON_SPACING: f32 = 64.0;
+
+/// A touch that moves less than this before it's let go is a tap
+const TAP_MAX_DISTANCE: f32 = 12.0;
+
+/// How far the joystick has to be pushed sideways to run, in player mode
+const JOYSTICK_DEAD_ZONE: f32 = 0.3;
+
+pub struct TouchPlugin;
+
+impl Plugin for TouchPlugin {
+    fn build(&self, app: &mut App) {
+        app.register_type::<TouchControls>()
+            .insert_resource(TouchControls::default())
+            .add_systems(
+                Update,
+                (
+                    s_touch_input.after(s_input).after(s_player_input),
+                    s_render_touch_controls.in_set(PursueAISet::Render),
+                ),
+            );
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TouchButton {
+    /// Turns the goal point on and off, like Space
+    Goal,
+    /// Shows and hides the gizmos, like G
+    Gizmos,
+    /// Jumps, in player mode
+    Jump,
+}
+
+impl TouchButton {
+    fn color(&self) -> Color {
+        match self {
+            TouchButton::Goal => Color::GREEN,
+            TouchButton::Gizmos => Color::YELLOW,
+            TouchButton::Jump => Color::CYAN,
+        }
+    }
+}
+
+/// On-screen controls for phones and tablets: a joystick in the bottom left that moves the
+/// goal, buttons in the top right, and a tap anywhere else to put the goal there
+#[derive(Resource, Default, Reflect)]
+#[reflect(Resource)]
+pub struct TouchControls {
+    /// The touch holding the joystick
+    pub joystick_touch: Option<u64>,
+    /// Where the joystick's pushed, with each axis from -1 to 1 and up being positive
+    pub joystick_offset: Vec2,
+}
+
+fn joystick_center(window_size: Vec2) -> Vec2 {
+    Vec2::new(JOYSTICK_MARGIN, window_size.y - JOYSTICK_MARGIN)
+}
+
+/// The buttons showing and where they are on the screen, right to left
+fn buttons(window_size: Vec2, player_mode_active: bool) -> Vec<(TouchButton, Vec2)> {
+    let mut buttons = vec![TouchButton::Goal, TouchButton::Gizmos];
+    if player_mode_active {
+        buttons.push(TouchButton::Jump);
+    }
+
+    buttons
+        .into_iter()
+        .enumerate()
+        .map(|(index, button)| {
+            (
+                button,
+                Vec2::new(
+                    window_size.x - BUTTON_SPACING * (index as f32 + 0.5),
+                    BUTTON_SPACING / 2.0,
+                ),
+            )
+        })
+        .collect()
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn s_touch_input(
+    touches: Res<Touches>,
+    q_windows: Query<&Window, With<PrimaryWindow>>,
+    camera_query: Query<(&Camera, &GlobalTransform), With<MainCamera>>,
+    mut touch_controls: ResMut<TouchControls>,
+    mut input_dir: ResMut<InputDir>,
+    mut player_mode: ResMut<PlayerMode>,
+    mut gizmos_visible: ResMut<GizmosVisible>,
+    mut pathfinding: ResMut<Pathfinding>,
+    mut goal_teleported_events: EventWriter<GoalTeleported>,
+) {
+    let Ok(window) = q_windows.get_single() else {
+        return;
+    };
+    let window_size = Vec2::new(window.width(), window.height());
+    let joystick_center = joystick_center(window_size);
+    let buttons = buttons(window_size, player_mode.active);
+
+    let on_controls = |position: Vec2| {
+        position.distance(joystick_center) <= JOYSTICK_RADIUS * 1.5
+            || buttons
+                .iter()
+                .any(|(_, center)| position.distance(*center) <= BUTTON_RADIUS)
+    };
+
+    for touch in touches.iter_just_pressed() {
+        if touch.position().distance(joystick_center) <= JOYSTICK_RADIUS * 1.5 {
+            touch_controls.joystick_touch = Some(touch.id());
+            continue;
+        }
+
+        let pressed_button = buttons
+            .iter()
+            .find(|(_, center)| touch.position().distance(*center) <= BUTTON_RADIUS)
+            .map(|(button, _)| *button);
+
+        match pressed_button {
+            Some(TouchButton::Goal) => toggle_goal(&mut pathfinding),
+            Some(TouchButton::Gizmos) => gizmos_visible.visible = !gizmos_visible.visible,
+            Some(TouchButton::Jump) => player_mode.jump_queued = true,
+            None => {}
+        }
+    }
+
+    // Taps away from the controls put the goal where they are
+    for touch in touches.iter_just_released() {
+        if touch.distance().length() > TAP_MAX_DISTANCE || on_controls(touch.start_position()) {
+            continue;
+        }
+
+        let tap_position = camera_query
+            .get_single()
+            .ok()
+            .and_then(|(camera, camera_transform)| {
+                camera.viewport_to_world_2d(camera_transform, touch.position())
+            });
+
+        if let Some(tap_position) = tap_position {
+            goal_teleported_events.send(pathfinding.teleport_goal(tap_position));
+        }
+    }
+
+    let Some(joystick_touch) = touch_controls.joystick_touch else {
+        return;
+    };
+
+    // The joystick lets go with the finger
+    let Some(touch) = touches.get_pressed(joystick_touch) else {
+        touch_controls.joystick_touch = None;
+        touch_controls.joystick_offset = Vec2::ZERO;
+        return;
+    };
+
+    // Screen space goes down, the world goes up
+    let offset =
+        (touch.position() - joystick_center).clamp_length_max(JOYSTICK_RADIUS) / JOYSTICK_RADIUS;
+    touch_controls.joystick_offset = Vec2::new(offset.x, -offset.y);
+
+    input_dir.dir = touch_controls.joystick_offset;
+
+    if player_mode.active {
+        player_mode.move_x = if touch_controls.joystick_offset.x.abs() > JOYSTICK_DEAD_ZONE {
+            touch_controls.joystick_offset.x.signum()
+        } else {
+            0.0
+        };
+    }
+}
+
+pub fn s_render_touch_controls(
+    mut gizmos: Gizmos,
+    q_windows: Query<&Window, With<PrimaryWindow>>,
+    camera_query: Query<(&Camera, &GlobalTransform), With<MainCamera>>,
+    touch_controls: Res<TouchControls>,
+    player_mode: Res<PlayerMode>,
+) {
+    let (Ok(window), Ok((camera, camera_transform))) =
+        (q_windows.get_single(), camera_query.get_single())
+    else {
+        return;
+    };
+    let window_size = Vec2::new(window.width(), window.height());
+
+    // The controls are laid out on the screen, so they're drawn through the camera
+    let to_world = |position: Vec2| camera.viewport_to_world_2d(camera_transform, position);
+
+    let joystick_center = joystick_center(window_size);
+    if let Some(center) = to_world(joystick_center) {
+        gizmos.circle_2d(center, JOYSTICK_RADIUS, Color::GRAY.with_a(0.5));
+        gizmos.circle_2d(
+            center + touch_controls.joystick_offset * JOYSTICK_RADIUS,
+            JOYSTICK_RADIUS / 3.0,
+            Color::WHITE.with_a(0.6),
+        );
+    }
+
+    for (button, position) in buttons(window_size, player_mode.active) {
+        if let Some(center) = to_world(position) {
+            gizmos.circle_2d(center, BUTTON_RADIUS, button.color().with_a(0.6));
+        }
+    }
+}
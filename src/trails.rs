@@ -0,0 +1,146 @@
+use std::collections::VecDeque;
+
+use bevy::{
+    app::{App, FixedUpdate, Plugin, Update},
+    ecs::{
+        component::Component,
+        entity::Entity,
+        query::{Or, With, Without},
+        reflect::ReflectComponent,
+        schedule::IntoSystemConfigs,
+        system::{Commands, Query, Res, ResMut, Resource},
+    },
+    gizmos::gizmos::Gizmos,
+    input::{keyboard::KeyCode, ButtonInput},
+    math::{Vec2, Vec3Swizzles},
+    reflect::Reflect,
+    render::color::Color,
+    transform::components::Transform,
+};
+
+use crate::{ai::platformer_ai::PlatformerAI, GoalPoint, PursueAISet};
+
+/// How many ticks of history a trail keeps, unless it's given its own length
+pub const TRAIL_DEFAULT_LENGTH: usize = 180;
+
+/// Anything further than this between two ticks was a teleport or a reset, not movement
+const TRAIL_MAX_STEP: f32 = 32.0;
+
+pub struct TrailsPlugin;
+
+impl Plugin for TrailsPlugin {
+    fn build(&self, app: &mut App) {
+        app.register_type::<Trail>()
+            .insert_resource(TrailsVisible { visible: true })
+            .add_systems(FixedUpdate, s_record_trails.after(PursueAISet::Collision))
+            .add_systems(
+                Update,
+                (
+                    s_add_trails,
+                    s_trails_input,
+                    s_render_trails.in_set(PursueAISet::Render),
+                ),
+            );
+    }
+}
+
+#[derive(Resource)]
+pub struct TrailsVisible {
+    pub visible: bool,
+}
+
+/// Where the entity has been over its last few ticks, drawn behind it fading out
+#[derive(Component, Reflect)]
+#[reflect(Component)]
+pub struct Trail {
+    /// Oldest first
+    pub points: VecDeque<Vec2>,
+    /// How many points are kept before the oldest are dropped
+    pub length: usize,
+    pub color: Color,
+}
+
+impl Trail {
+    pub fn new(length: usize, color: Color) -> Trail {
+        Trail {
+            points: VecDeque::with_capacity(length),
+            length,
+            color,
+        }
+    }
+
+    pub fn push(&mut self, point: Vec2) {
+        while self.points.len() >= self.length.max(1) {
+            self.points.pop_front();
+        }
+        self.points.push_back(point);
+    }
+}
+
+/// Gives the agents and the goal a trail in their own color, however they were spawned
+#[allow(clippy::type_complexity)]
+pub fn s_add_trails(
+    mut commands: Commands,
+    trailless_query: Query<
+        (Entity, Option<&GoalPoint>),
+        (Or<(With<PlatformerAI>, With<GoalPoint>)>, Without<Trail>),
+    >,
+) {
+    for (entity, goal_point) in trailless_query.iter() {
+        let color = if goal_point.is_some() {
+            Color::GREEN
+        } else {
+            Color::RED
+        };
+
+        commands
+            .entity(entity)
+            .insert(Trail::new(TRAIL_DEFAULT_LENGTH, color));
+    }
+}
+
+pub fn s_record_trails(mut trail_query: Query<(&Transform, &mut Trail)>) {
+    for (transform, mut trail) in trail_query.iter_mut() {
+        trail.push(transform.translation.xy());
+    }
+}
+
+pub fn s_trails_input(
+    keyboard_input: Res<ButtonInput<KeyCode>>,
+    mut trails_visible: ResMut<TrailsVisible>,
+) {
+    // F2 to show or hide the trails
+    if keyboard_input.just_pressed(KeyCode::F2) {
+        trails_visible.visible = !trails_visible.visible;
+    }
+}
+
+/// Draws each trail as a polyline that fades from nothing at its oldest point to its full color
+/// at the newest
+pub fn s_render_trails(
+    mut gizmos: Gizmos,
+    trails_visible: Res<TrailsVisible>,
+    trail_query: Query<&Trail>,
+) {
+    if !trails_visible.visible {
+        return;
+    }
+
+    for trail in trail_query.iter() {
+        let segment_count = trail.points.len().saturating_sub(1);
+
+        for (index, (start, end)) in trail
+            .points
+            .iter()
+            .zip(trail.points.iter().skip(1))
+            .enumerate()
+        {
+            if start.distance(*end) > TRAIL_MAX_STEP {
+                continue;
+            }
+
+            let age = (index + 1) as f32 / segment_count as f32;
+            gizmos.line_2d(*start, *end, trail.color.with_a(age * trail.color.a()));
+        }
+    }
+}
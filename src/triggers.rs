@@ -0,0 +1,210 @@
+use bevy::{
+    app::{App, FixedUpdate, Plugin, Update},
+    ecs::{
+        component::Component,
+        entity::Entity,
+        event::{Event, EventWriter},
+        reflect::ReflectComponent,
+        schedule::IntoSystemConfigs,
+        system::{Commands, Query},
+    },
+    gizmos::gizmos::Gizmos,
+    math::{Vec2, Vec3Swizzles},
+    reflect::Reflect,
+    render::color::Color,
+    transform::components::Transform,
+};
+use serde::Deserialize;
+
+use crate::{
+    collisions::{find_projection, s_collision},
+    level::point_in_polygon,
+    Physics, PursueAISet,
+};
+
+const TRIGGERS_DATA: &[u8] = include_bytes!("../assets/triggers.json");
+
+pub struct TriggersPlugin;
+
+impl Plugin for TriggersPlugin {
+    fn build(&self, app: &mut App) {
+        app.register_type::<TriggerArea>()
+            .register_type::<Trigger>()
+            .add_event::<TriggerEntered>()
+            .add_event::<TriggerExited>()
+            .add_systems(
+                FixedUpdate,
+                s_update_triggers
+                    .in_set(PursueAISet::Collision)
+                    .after(s_collision),
+            )
+            .add_systems(Update, s_render_triggers.in_set(PursueAISet::Render));
+    }
+}
+
+/// The area a trigger covers
+#[derive(Debug, Clone, Deserialize)]
+#[serde(tag = "shape", rename_all = "snake_case")]
+pub enum TriggerShape {
+    Circle {
+        center: [f32; 2],
+        radius: f32,
+    },
+    /// The outline, which gets closed if the last point isn't the first one
+    Polygon {
+        points: Vec<[f32; 2]>,
+    },
+}
+
+/// A trigger in `assets/triggers.json`, placed in the built-in level
+#[derive(Debug, Clone, Deserialize)]
+pub struct TriggerData {
+    pub name: String,
+    #[serde(flatten)]
+    pub shape: TriggerShape,
+}
+
+#[derive(Debug, Clone, Reflect)]
+pub enum TriggerArea {
+    Circle { center: Vec2, radius: f32 },
+    Polygon { points: Vec<Vec2> },
+}
+
+impl TriggerArea {
+    /// Whether a circle at the position overlaps the area at all
+    pub fn overlaps(&self, position: Vec2, radius: f32) -> bool {
+        match self {
+            TriggerArea::Circle {
+                center,
+                radius: area_radius,
+            } => (position - *center).length() <= radius + area_radius,
+            TriggerArea::Polygon { points } => {
+                point_in_polygon(points, position)
+                    || points.windows(2).any(|line| {
+                        find_projection(line[0], line[1], position, radius).0 <= radius.powi(2)
+                    })
+            }
+        }
+    }
+}
+
+/// A region that nothing collides with, which reports the bodies going in and out of it
+/// (detection zones, level exits and so on)
+#[derive(Component, Debug, Reflect)]
+#[reflect(Component)]
+pub struct Trigger {
+    pub name: String,
+    pub area: TriggerArea,
+    /// The bodies inside it as of the last tick
+    pub occupants: Vec<Entity>,
+}
+
+impl Trigger {
+    pub fn new(name: String, area: TriggerArea) -> Self {
+        Trigger {
+            name,
+            area,
+            occupants: Vec::new(),
+        }
+    }
+}
+
+/// Sent when a body starts overlapping a trigger
+#[derive(Event, Debug, Clone)]
+pub struct TriggerEntered {
+    pub trigger: Entity,
+    pub entity: Entity,
+}
+
+/// Sent when a body stops overlapping a trigger
+#[derive(Event, Debug, Clone)]
+pub struct TriggerExited {
+    pub trigger: Entity,
+    pub entity: Entity,
+}
+
+pub fn spawn_triggers(commands: &mut Commands) {
+    let res = std::str::from_utf8(TRIGGERS_DATA);
+    let triggers: Vec<TriggerData> = serde_json::from_str(res.unwrap()).unwrap();
+
+    for trigger in triggers {
+        let area = match trigger.shape {
+            TriggerShape::Circle { center, radius } => TriggerArea::Circle {
+                center: Vec2::from(center),
+                radius,
+            },
+            TriggerShape::Polygon { points } => {
+                let mut points: Vec<Vec2> = points.into_iter().map(Vec2::from).collect();
+
+                if points.first() != points.last() {
+                    points.push(points[0]);
+                }
+
+                TriggerArea::Polygon { points }
+            }
+        };
+
+        commands.spawn(Trigger::new(trigger.name, area));
+    }
+}
+
+/// Works out which bodies are in each trigger, and reports the ones that came and went
+pub fn s_update_triggers(
+    mut trigger_query: Query<(Entity, &mut Trigger)>,
+    body_query: Query<(Entity, &Transform, &Physics)>,
+    mut trigger_entered_events: EventWriter<TriggerEntered>,
+    mut trigger_exited_events: EventWriter<TriggerExited>,
+) {
+    for (trigger_entity, mut trigger) in trigger_query.iter_mut() {
+        let occupants: Vec<Entity> = body_query
+            .iter()
+            .filter(|(_, transform, physics)| {
+                trigger
+                    .area
+                    .overlaps(transform.translation.xy(), physics.radius)
+            })
+            .map(|(entity, _, _)| entity)
+            .collect();
+
+        for entity in occupants.iter() {
+            if !trigger.occupants.contains(entity) {
+                trigger_entered_events.send(TriggerEntered {
+                    trigger: trigger_entity,
+                    entity: *entity,
+                });
+            }
+        }
+
+        // Bodies that were despawned leave too
+        for entity in trigger.occupants.iter() {
+            if !occupants.contains(entity) {
+                trigger_exited_events.send(TriggerExited {
+                    trigger: trigger_entity,
+                    entity: *entity,
+                });
+            }
+        }
+
+        trigger.occupants = occupants;
+    }
+}
+
+pub fn s_render_triggers(mut gizmos: Gizmos, trigger_query: Query<&Trigger>) {
+    // Triggers light up while something's in them
+    for trigger in trigger_query.iter() {
+        let color = if trigger.occupants.is_empty() {
+            Color::LIME_GREEN.with_a(0.2)
+        } else {
+            Color::LIME_GREEN.with_a(0.6)
+        };
+
+        match &trigger.area {
+            TriggerArea::Circle { center, radius } => {
+                gizmos.circle_2d(*center, *radius, color);
+            }
+            TriggerArea::Polygon { points } => {
+                gizmos.linestrip_2d(points.iter().cloned(), color);
+            }
+        }
+    }
+}
@@ -1,4 +1,6 @@
-use bevy::math::Vec2;
+use bevy::{
+    math::Vec2, render::camera::Camera, transform::components::GlobalTransform, window::Window,
+};
 
 // pub fn lerp(a: f32, b: f32, t: f32) -> f32 {
 //     a + (b - a) * t
@@ -27,13 +29,46 @@ pub fn line_intersect(
     }
 }
 
+/// Where the cursor is in the world, going through the camera so it's right wherever the
+/// camera is
+pub fn cursor_world_position(
+    window: &Window,
+    camera: &Camera,
+    camera_transform: &GlobalTransform,
+) -> Option<Vec2> {
+    let cursor_position = window.cursor_position()?;
+
+    camera.viewport_to_world_2d(camera_transform, cursor_position)
+}
+
 pub fn cross_product(a: Vec2, b: Vec2) -> f32 {
     a.x * b.y - a.y * b.x
 }
 
+/// Which side of the line the point is on, 1 to the left looking from the start to the end,
+/// -1 to the right and 0 right on it
 pub fn side_of_line_detection(line_start: Vec2, line_end: Vec2, point: Vec2) -> f32 {
     let determinant = (line_end.x - line_start.x) * (point.y - line_start.y)
         - (line_end.y - line_start.y) * (point.x - line_start.x);
 
+    // signum() keeps the sign of a zero, which would put points on the line on either side
+    if determinant == 0.0 {
+        return 0.0;
+    }
+
     return determinant.signum();
 }
+
+/// Serializes a `Vec2` as an `[x, y]` pair, for use with `#[serde(with = "vec2_serde")]`
+pub mod vec2_serde {
+    use bevy::math::Vec2;
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+    pub fn serialize<S: Serializer>(vector: &Vec2, serializer: S) -> Result<S::Ok, S::Error> {
+        vector.to_array().serialize(serializer)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec2, D::Error> {
+        <[f32; 2]>::deserialize(deserializer).map(Vec2::from)
+    }
+}
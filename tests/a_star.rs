@@ -0,0 +1,735 @@
+use bevy::{
+    ecs::{entity::Entity, system::RunSystemOnce, world::World},
+    math::Vec2,
+};
+use pursue_ai_test::{
+    ai::{
+        a_star::{find_path_from, path_length, SearchStats},
+        influence_map::InfluenceMap,
+        pathfinding::{
+            arc_clears_level, jump_launch_velocity, jump_trajectory_points,
+            jump_trajectory_points_at_speed, launch_velocity, s_compute_paths, ComputedPath,
+            MovementCapabilities, PathfindingGraphConnectionType, RequestPath, SurfaceKind,
+            JUMP_HOP_APEX_HEIGHT,
+        },
+        platformer_ai::PLATFORMER_AI_AGENT_RADIUS,
+    },
+    image_level::ImageLevel,
+    level::{normalize_polygons, BouncePad, Ladder, Teleporter, WaterRegion},
+    text_level::parse_text_level,
+    Level, Pathfinding, PathfindingSettings,
+};
+use rand::{rngs::StdRng, SeedableRng};
+
+/// How far a path's length can drift from the recorded one before it counts as a different route
+const PATH_LENGTH_TOLERANCE: f32 = 0.5;
+
+/// A route through the built-in level as it was when these tests were written, from the start
+/// node to the goal node and what the search came back with (which stops short of the goal
+/// node itself)
+struct GoldenPath {
+    start_node_id: usize,
+    goal_node_id: usize,
+    node_ids: &'static [usize],
+    length: f32,
+}
+
+const GOLDEN_PATHS: &[GoldenPath] = &[
+    // From the agent's spawn, across the bottom and wall jumping up the gap on the left
+    // (140 to 162) to the top left
+    GoldenPath {
+        start_node_id: 182,
+        goal_node_id: 107,
+        node_ids: &[
+            182, 183, 177, 172, 171, 140, 162, 156, 157, 132, 131, 130, 129, 128, 27, 26, 25, 24,
+            23, 22, 113, 112, 111, 110, 109, 108,
+        ],
+        length: 731.1,
+    },
+    // The way back down, which drops instead of climbing
+    GoldenPath {
+        start_node_id: 107,
+        goal_node_id: 182,
+        node_ids: &[107, 127, 128, 129, 130, 131, 177, 183],
+        length: 526.0,
+    },
+    GoldenPath {
+        start_node_id: 182,
+        goal_node_id: 121,
+        node_ids: &[
+            182, 183, 177, 178, 179, 74, 75, 76, 77, 78, 79, 80, 81, 82, 83, 84, 85, 86, 87, 88,
+            89, 90, 91, 92, 93, 94, 95, 96, 97, 98, 99, 100, 119, 118, 117, 116, 115, 114, 125,
+            124, 123, 122,
+        ],
+        length: 1079.5,
+    },
+    GoldenPath {
+        start_node_id: 42,
+        goal_node_id: 100,
+        node_ids: &[
+            42, 43, 44, 175, 176, 170, 171, 172, 177, 178, 179, 74, 75, 76, 77, 78, 79, 80, 81, 82,
+            83, 84, 85, 86, 87, 88, 89, 90, 91, 92, 93, 94, 95, 96, 97, 98, 99,
+        ],
+        length: 912.0,
+    },
+    // Mantling up onto the ledge at 158 it can't quite jump onto
+    GoldenPath {
+        start_node_id: 121,
+        goal_node_id: 25,
+        node_ids: &[121, 158, 132, 131, 130, 129, 128, 27, 26],
+        length: 373.3,
+    },
+    GoldenPath {
+        start_node_id: 25,
+        goal_node_id: 42,
+        node_ids: &[25, 151, 150],
+        length: 158.6,
+    },
+    GoldenPath {
+        start_node_id: 100,
+        goal_node_id: 121,
+        node_ids: &[100, 119, 118, 117, 116, 115, 114, 125, 124, 123, 122],
+        length: 403.5,
+    },
+];
+
+/// The graph for the built-in level, built the same way as at startup but without the doors
+/// and moving platforms, which need entities
+fn built_in_graph() -> Pathfinding {
+    let mut level = Level::built_in(32.0, &mut StdRng::seed_from_u64(0));
+    normalize_polygons(&mut level.polygons);
+
+    let mut pathfinding = Pathfinding::default();
+    pathfinding.rebuild(&level, &PathfindingSettings::default());
+
+    pathfinding
+}
+
+/// The (from, to) node ids of every connection of the type in the graph
+fn connected_node_ids(
+    pathfinding: &Pathfinding,
+    connection_type: PathfindingGraphConnectionType,
+) -> Vec<(usize, usize)> {
+    pathfinding
+        .nodes
+        .iter()
+        .flat_map(|node| {
+            node.connections_of_type(connection_type)
+                .map(move |connection| (node.id, connection.node_id))
+        })
+        .collect()
+}
+
+#[test]
+fn built_in_graph_is_unchanged() {
+    let pathfinding = built_in_graph();
+
+    assert_eq!(pathfinding.nodes.len(), 184);
+}
+
+#[test]
+fn golden_paths_are_unchanged() {
+    let pathfinding = built_in_graph();
+
+    for golden_path in GOLDEN_PATHS {
+        let start_position = pathfinding.nodes[golden_path.start_node_id].position;
+        let goal_position = pathfinding.nodes[golden_path.goal_node_id].position;
+
+        let path = find_path_from(
+            &pathfinding,
+            golden_path.start_node_id,
+            golden_path.goal_node_id,
+            goal_position,
+            None,
+            None,
+        )
+        .unwrap_or_else(|| {
+            panic!(
+                "No path from {} to {}",
+                golden_path.start_node_id, golden_path.goal_node_id
+            )
+        });
+
+        let node_ids: Vec<usize> = path.iter().map(|path_node| path_node.id).collect();
+        assert_eq!(
+            node_ids, golden_path.node_ids,
+            "Path from {} to {} changed",
+            golden_path.start_node_id, golden_path.goal_node_id
+        );
+
+        let length = path_length(start_position, &path);
+        assert!(
+            (length - golden_path.length).abs() <= PATH_LENGTH_TOLERANCE,
+            "Path from {} to {} is {} long, not {}",
+            golden_path.start_node_id,
+            golden_path.goal_node_id,
+            length,
+            golden_path.length
+        );
+    }
+}
+
+#[test]
+fn golden_paths_follow_connections() {
+    let pathfinding = built_in_graph();
+
+    for golden_path in GOLDEN_PATHS {
+        for pair in golden_path.node_ids.windows(2) {
+            assert!(
+                pathfinding.nodes[pair[0]]
+                    .connections
+                    .iter()
+                    .any(|connection| connection.node_id == pair[1]),
+                "No connection from {} to {}",
+                pair[0],
+                pair[1]
+            );
+        }
+    }
+}
+
+#[test]
+fn wall_jumps_go_up_between_facing_walls() {
+    let pathfinding = built_in_graph();
+
+    let wall_jumps = connected_node_ids(&pathfinding, PathfindingGraphConnectionType::WallJumpable);
+    assert!(!wall_jumps.is_empty());
+
+    for (from_node_id, to_node_id) in wall_jumps {
+        let from_node = &pathfinding.nodes[from_node_id];
+        let to_node = &pathfinding.nodes[to_node_id];
+
+        assert_eq!(from_node.surface_kind(), SurfaceKind::Wall);
+        assert_eq!(to_node.surface_kind(), SurfaceKind::Wall);
+        assert!(from_node.normal.dot(to_node.normal) < 0.0);
+        assert!(to_node.position.y > from_node.position.y);
+        assert!((to_node.position - from_node.position).dot(from_node.normal) > 0.0);
+    }
+}
+
+#[test]
+fn short_hops_are_launched_on_lower_arcs_that_still_clear_the_level() {
+    let mut level = Level::built_in(32.0, &mut StdRng::seed_from_u64(0));
+    normalize_polygons(&mut level.polygons);
+
+    let mut pathfinding = Pathfinding::default();
+    pathfinding.rebuild(&level, &PathfindingSettings::default());
+
+    let mut hops = 0;
+    for from_node in pathfinding.nodes.iter() {
+        for connection in from_node.connections_of_type(PathfindingGraphConnectionType::Jumpable) {
+            let to_node = &pathfinding.nodes[connection.node_id];
+            let delta_p = to_node.position - from_node.position;
+
+            // Long jumps go at the speed they need
+            let min_energy_velocity = launch_velocity(delta_p);
+            if connection.effort <= min_energy_velocity.length() + 0.01 {
+                continue;
+            }
+            hops += 1;
+
+            // Hops go faster and peak lower, but still land on the node
+            let velocity = jump_launch_velocity(delta_p, connection.effort);
+            assert!((velocity.length() - connection.effort).abs() < 0.01);
+            assert!(velocity.y < min_energy_velocity.y);
+
+            let points = jump_trajectory_points_at_speed(
+                from_node.position,
+                to_node.position,
+                connection.effort,
+            );
+            assert_ne!(
+                points,
+                jump_trajectory_points(from_node.position, to_node.position)
+            );
+            assert_eq!(points.last(), Some(&to_node.position));
+            assert!(arc_clears_level(
+                &points,
+                from_node,
+                to_node,
+                &level,
+                PLATFORMER_AI_AGENT_RADIUS
+            ));
+
+            let highest_point = points.iter().map(|point| point.y).fold(f32::MIN, f32::max);
+            assert!(
+                highest_point
+                    <= from_node.position.y.max(to_node.position.y) + JUMP_HOP_APEX_HEIGHT + 0.01
+            );
+        }
+    }
+
+    assert!(hops > 0);
+}
+
+#[test]
+fn mantles_go_from_floors_to_ledges() {
+    let pathfinding = built_in_graph();
+
+    let mantles = connected_node_ids(&pathfinding, PathfindingGraphConnectionType::Mantle);
+    assert!(!mantles.is_empty());
+
+    let jumps = connected_node_ids(&pathfinding, PathfindingGraphConnectionType::Jumpable);
+    for (from_node_id, to_node_id) in mantles {
+        assert_eq!(
+            pathfinding.nodes[from_node_id].surface_kind(),
+            SurfaceKind::Floor
+        );
+        assert!(pathfinding.nodes[to_node_id].is_ledge());
+        assert!(!jumps.contains(&(from_node_id, to_node_id)));
+    }
+}
+
+#[test]
+fn paths_mantle_onto_ledges_they_cant_jump_onto() {
+    let pathfinding = built_in_graph();
+
+    // The golden path from 121 to 25 starts by mantling up onto the ledge at 158
+    let connection_types = connection_types_along_path(&pathfinding, 121, 25).unwrap();
+    assert_eq!(connection_types[0], PathfindingGraphConnectionType::Mantle);
+    assert!(pathfinding.nodes[158].is_ledge());
+}
+
+#[test]
+fn double_jumps_are_only_made_for_agents_that_can_double_jump() {
+    let mut level = Level::built_in(32.0, &mut StdRng::seed_from_u64(0));
+    normalize_polygons(&mut level.polygons);
+
+    let mut pathfinding = Pathfinding::default();
+    pathfinding.rebuild(
+        &level,
+        &PathfindingSettings {
+            capabilities: MovementCapabilities { double_jump: true },
+            ..Default::default()
+        },
+    );
+
+    let double_jumps =
+        connected_node_ids(&pathfinding, PathfindingGraphConnectionType::DoubleJumpable);
+    assert!(!double_jumps.is_empty());
+
+    let jumps = connected_node_ids(&pathfinding, PathfindingGraphConnectionType::Jumpable);
+    for (from_node_id, to_node_id) in double_jumps {
+        assert_eq!(
+            pathfinding.nodes[from_node_id].surface_kind(),
+            SurfaceKind::Floor
+        );
+        assert_eq!(
+            pathfinding.nodes[to_node_id].surface_kind(),
+            SurfaceKind::Floor
+        );
+        assert!(!jumps.contains(&(from_node_id, to_node_id)));
+    }
+
+    assert!(connected_node_ids(
+        &built_in_graph(),
+        PathfindingGraphConnectionType::DoubleJumpable
+    )
+    .is_empty());
+}
+
+#[test]
+fn dashes_cross_gaps_under_ceilings_too_low_to_jump() {
+    // A tunnel just tall enough to stand in, with a pit too wide to jump without hitting the
+    // ceiling
+    let solid = (0..16)
+        .map(|row| {
+            (0..48)
+                .map(|column| row >= 5 && !(row < 15 && (14..34).contains(&column)))
+                .collect()
+        })
+        .collect();
+    let mut level = ImageLevel { solid }.build(32.0, &mut StdRng::seed_from_u64(0));
+    normalize_polygons(&mut level.polygons);
+
+    let mut pathfinding = Pathfinding::default();
+    pathfinding.rebuild(&level, &PathfindingSettings::default());
+
+    let ledges: Vec<usize> = pathfinding
+        .nodes
+        .iter()
+        .filter(|node| node.is_ledge())
+        .map(|node| node.id)
+        .collect();
+    assert_eq!(ledges.len(), 2);
+
+    let dashes = connected_node_ids(&pathfinding, PathfindingGraphConnectionType::Dashable);
+    let jumps = connected_node_ids(&pathfinding, PathfindingGraphConnectionType::Jumpable);
+    let mantles = connected_node_ids(&pathfinding, PathfindingGraphConnectionType::Mantle);
+
+    let (left, right) = (ledges[0], ledges[1]);
+    for pair in [(left, right), (right, left)] {
+        assert!(dashes.contains(&pair));
+        assert!(!jumps.contains(&pair) && !mantles.contains(&pair));
+    }
+
+    let start_node_id = left - 1;
+    let goal_node_id = right + 1;
+    let path = find_path_from(
+        &pathfinding,
+        start_node_id,
+        goal_node_id,
+        pathfinding.nodes[goal_node_id].position,
+        None,
+        None,
+    )
+    .unwrap();
+    let node_ids: Vec<usize> = path.iter().map(|path_node| path_node.id).collect();
+
+    assert!(node_ids.windows(2).any(|pair| pair == [left, right]));
+}
+
+#[test]
+fn path_requests_are_answered_with_computed_paths() {
+    let golden_path = &GOLDEN_PATHS[0];
+    let mut world = World::new();
+    world.insert_resource(built_in_graph());
+
+    let start_position = world.resource::<Pathfinding>().nodes[golden_path.start_node_id].position;
+    let goal_position = world.resource::<Pathfinding>().nodes[golden_path.goal_node_id].position;
+    let entity = world
+        .spawn(RequestPath {
+            start_position,
+            start_node_id: None,
+            goal_node_id: golden_path.goal_node_id,
+            goal_position,
+        })
+        .id();
+
+    world.run_system_once(s_compute_paths);
+
+    let entity = world.entity(entity);
+    assert!(!entity.contains::<RequestPath>());
+
+    let computed_path = entity.get::<ComputedPath>().unwrap();
+    let node_ids: Vec<usize> = computed_path
+        .path
+        .as_ref()
+        .unwrap()
+        .iter()
+        .map(|path_node| path_node.id)
+        .collect();
+    assert_eq!(node_ids, golden_path.node_ids);
+    assert_eq!(
+        computed_path.goal,
+        (golden_path.goal_node_id, goal_position)
+    );
+}
+
+#[test]
+fn agents_only_ignore_their_own_danger() {
+    let agent = Entity::from_raw(0);
+    let other_agent = Entity::from_raw(1);
+
+    let mut influence = InfluenceMap {
+        enabled: true,
+        danger: vec![0.0; 2],
+        sources: vec![None; 2],
+        ..Default::default()
+    };
+    let nodes = [(0, Vec2::ZERO), (1, Vec2::new(64.0, 0.0))];
+    influence.stamp(&nodes, Vec2::ZERO, 48.0, Some(agent));
+    influence.stamp(&nodes, Vec2::new(64.0, 0.0), 48.0, Some(other_agent));
+
+    assert_eq!(influence.cost(0, Some(agent)), 0.0);
+    assert!(influence.cost(0, Some(other_agent)) > 0.0);
+    assert!(influence.cost(0, None) > 0.0);
+    assert!(influence.cost(1, Some(agent)) > 0.0);
+}
+
+#[test]
+fn path_costs_count_danger_as_well_as_distance() {
+    let mut pathfinding = built_in_graph();
+    let golden_path = &GOLDEN_PATHS[0];
+
+    let path_cost = |pathfinding: &Pathfinding| {
+        let mut stats = SearchStats::default();
+        find_path_from(
+            pathfinding,
+            golden_path.start_node_id,
+            golden_path.goal_node_id,
+            pathfinding.nodes[golden_path.goal_node_id].position,
+            Some(&mut stats),
+            None,
+        )
+        .unwrap();
+
+        stats.path_cost
+    };
+
+    let safe_cost = path_cost(&pathfinding);
+    assert!(safe_cost > 0.0);
+
+    let node_count = pathfinding.nodes.len();
+    pathfinding.influence.enabled = true;
+    pathfinding.influence.danger = vec![0.5; node_count];
+    pathfinding.influence.sources = vec![None; node_count];
+
+    assert!(path_cost(&pathfinding) > safe_cost);
+}
+
+/// The node ids of the path between two nodes and how much work finding it took, with walkable
+/// runs skipped along or not
+fn search_with_stats(
+    pathfinding: &mut Pathfinding,
+    start_node_id: usize,
+    goal_node_id: usize,
+    skip_walkable_runs: bool,
+) -> (Vec<usize>, SearchStats) {
+    pathfinding.skip_walkable_runs = skip_walkable_runs;
+
+    let mut stats = SearchStats::default();
+    let path = find_path_from(
+        pathfinding,
+        start_node_id,
+        goal_node_id,
+        pathfinding.nodes[goal_node_id].position,
+        Some(&mut stats),
+        None,
+    )
+    .unwrap();
+
+    (path.iter().map(|path_node| path_node.id).collect(), stats)
+}
+
+#[test]
+fn skipping_walkable_runs_finds_the_same_paths_with_fewer_expansions() {
+    let mut pathfinding = built_in_graph();
+    let mut full_nodes_expanded = 0;
+    let mut skipped_nodes_expanded = 0;
+
+    for golden_path in GOLDEN_PATHS {
+        let (start_node_id, goal_node_id) = (golden_path.start_node_id, golden_path.goal_node_id);
+
+        let (full_node_ids, full_stats) =
+            search_with_stats(&mut pathfinding, start_node_id, goal_node_id, false);
+        let (skipped_node_ids, skipped_stats) =
+            search_with_stats(&mut pathfinding, start_node_id, goal_node_id, true);
+
+        assert_eq!(skipped_node_ids, full_node_ids);
+        assert!((skipped_stats.path_cost - full_stats.path_cost).abs() < 0.01);
+        assert!(
+            skipped_stats.nodes_expanded <= full_stats.nodes_expanded,
+            "Path from {} to {} expanded {} nodes skipping runs and {} without",
+            start_node_id,
+            goal_node_id,
+            skipped_stats.nodes_expanded,
+            full_stats.nodes_expanded
+        );
+
+        full_nodes_expanded += full_stats.nodes_expanded;
+        skipped_nodes_expanded += skipped_stats.nodes_expanded;
+    }
+
+    // Short paths have no runs worth skipping, but the long ones together have plenty
+    assert!(skipped_nodes_expanded < full_nodes_expanded);
+}
+
+#[test]
+fn walkable_runs_stop_at_disabled_connections_and_danger_still_counts() {
+    let mut pathfinding = built_in_graph();
+    let golden_path = &GOLDEN_PATHS[0];
+    let (start_node_id, goal_node_id) = (golden_path.start_node_id, golden_path.goal_node_id);
+
+    // Cut the golden path off, so the way round is a long walk, and put danger along the walk
+    pathfinding.disable_connection(172, 171);
+    let node_count = pathfinding.nodes.len();
+    pathfinding.influence.enabled = true;
+    pathfinding.influence.danger = vec![0.0; node_count];
+    pathfinding.influence.sources = vec![None; node_count];
+    for node_id in 48..54 {
+        pathfinding.influence.danger[node_id] = 0.5;
+    }
+
+    let (full_node_ids, full_stats) =
+        search_with_stats(&mut pathfinding, start_node_id, goal_node_id, false);
+    let (skipped_node_ids, skipped_stats) =
+        search_with_stats(&mut pathfinding, start_node_id, goal_node_id, true);
+
+    assert!(!full_node_ids.windows(2).any(|pair| pair == [172, 171]));
+    assert_eq!(skipped_node_ids, full_node_ids);
+    assert!((skipped_stats.path_cost - full_stats.path_cost).abs() < 0.01);
+}
+
+/// A room with a block in the middle, too high to jump onto, with `G` on top of it
+const BLOCK_LEVEL: &str = "\
+..................
+..................
+..................
+..................
+..................
+..........G.......
+.......####.......
+.......####.......
+S.................
+";
+
+/// How far the block reaches either side of the middle of the room
+const BLOCK_HALF_WIDTH: f32 = 64.0;
+
+/// The graph for the block level, with whatever the test adds to it, and the nodes nearest
+/// the spawn point and the top of the block
+///
+/// The block's sides get no nodes, so agents can't climb them either
+fn block_level_graph(add: impl FnOnce(&mut Level)) -> (Pathfinding, usize, usize) {
+    let mut level = parse_text_level(BLOCK_LEVEL)
+        .unwrap()
+        .build(32.0, &mut StdRng::seed_from_u64(0));
+
+    for polygon in level.polygons.iter_mut().filter(|polygon| {
+        polygon
+            .points
+            .iter()
+            .all(|point| point.x.abs() <= BLOCK_HALF_WIDTH)
+    }) {
+        polygon.no_node_lines = (1..polygon.points.len())
+            .filter(|line_index| polygon.points[line_index - 1].x == polygon.points[*line_index].x)
+            .map(|line_index| line_index - 1)
+            .collect();
+    }
+
+    add(&mut level);
+    normalize_polygons(&mut level.polygons);
+
+    let mut pathfinding = Pathfinding::default();
+    pathfinding.rebuild(&level, &PathfindingSettings::default());
+
+    let start_node_id = pathfinding
+        .nearest_node_id(level.agent_spawn_position())
+        .unwrap();
+    let goal_node_id = pathfinding
+        .nearest_node_id(level.goal_start_position().unwrap())
+        .unwrap();
+
+    (pathfinding, start_node_id, goal_node_id)
+}
+
+/// The kinds of connection a path from one node to the other takes, if there is one
+fn connection_types_along_path(
+    pathfinding: &Pathfinding,
+    start_node_id: usize,
+    goal_node_id: usize,
+) -> Option<Vec<PathfindingGraphConnectionType>> {
+    let path = find_path_from(
+        pathfinding,
+        start_node_id,
+        goal_node_id,
+        pathfinding.nodes[goal_node_id].position,
+        None,
+        None,
+    )?;
+
+    let mut node_ids: Vec<usize> = path.iter().map(|path_node| path_node.id).collect();
+    node_ids.push(goal_node_id);
+
+    let connection_types = node_ids
+        .windows(2)
+        .map(|pair| {
+            pathfinding.nodes[pair[0]]
+                .connections
+                .iter()
+                .find(|connection| connection.node_id == pair[1])
+                .unwrap()
+                .connection_type
+        })
+        .collect();
+
+    Some(connection_types)
+}
+
+#[test]
+fn the_block_cant_be_reached_on_foot() {
+    let (pathfinding, start_node_id, goal_node_id) = block_level_graph(|_| {});
+
+    assert!(connection_types_along_path(&pathfinding, start_node_id, goal_node_id).is_none());
+}
+
+#[test]
+fn paths_go_through_teleporters() {
+    let (pathfinding, start_node_id, goal_node_id) = block_level_graph(|level| {
+        level.teleporters.push(Teleporter {
+            entrance: level.agent_spawn_position(),
+            exit: level.goal_start_position().unwrap(),
+            cost: 0.0,
+        })
+    });
+
+    let connection_types =
+        connection_types_along_path(&pathfinding, start_node_id, goal_node_id).unwrap();
+    assert!(connection_types.contains(&PathfindingGraphConnectionType::Teleport));
+}
+
+#[test]
+fn paths_go_up_ladders() {
+    let (pathfinding, start_node_id, goal_node_id) = block_level_graph(|level| {
+        level.ladders.push(Ladder {
+            min: Vec2::new(-80.0, -160.0),
+            max: Vec2::new(-40.0, -40.0),
+        })
+    });
+
+    let connection_types =
+        connection_types_along_path(&pathfinding, start_node_id, goal_node_id).unwrap();
+    assert!(connection_types.contains(&PathfindingGraphConnectionType::Climbable));
+}
+
+#[test]
+fn paths_go_over_bounce_pads() {
+    let (pathfinding, start_node_id, goal_node_id) = block_level_graph(|level| {
+        level.bounce_pads.push(BouncePad {
+            position: Vec2::new(-128.0, -144.0),
+            impulse: Vec2::new(3.0, 12.0),
+        })
+    });
+
+    let connection_types =
+        connection_types_along_path(&pathfinding, start_node_id, goal_node_id).unwrap();
+    assert!(connection_types.contains(&PathfindingGraphConnectionType::Bounce));
+}
+
+#[test]
+fn paths_swim_through_water() {
+    let (pathfinding, start_node_id, goal_node_id) = block_level_graph(|level| {
+        level.water.push(WaterRegion {
+            min: Vec2::new(-160.0, -160.0),
+            max: Vec2::new(160.0, -16.0),
+        })
+    });
+
+    let connection_types =
+        connection_types_along_path(&pathfinding, start_node_id, goal_node_id).unwrap();
+    assert!(connection_types.contains(&PathfindingGraphConnectionType::Swimmable));
+}
+
+#[test]
+fn paths_drop_through_one_way_platforms() {
+    let (mut pathfinding, _, top_node_id) = block_level_graph(|level| {
+        for polygon in level.polygons.iter_mut().filter(|polygon| {
+            polygon
+                .points
+                .iter()
+                .all(|point| point.x.abs() <= BLOCK_HALF_WIDTH)
+        }) {
+            polygon.is_one_way = true;
+        }
+    });
+
+    // Jumps can go down through the platform too, so take them away to leave only the drops
+    let block_polygon_index = pathfinding.nodes[top_node_id].polygon_index;
+    for node in pathfinding
+        .nodes
+        .iter_mut()
+        .filter(|node| node.polygon_index == block_polygon_index)
+    {
+        node.remove_connections_of_type(PathfindingGraphConnectionType::Jumpable);
+    }
+
+    let top_position = pathfinding.nodes[top_node_id].position;
+    let below_node_id = pathfinding
+        .nearest_node_id(Vec2::new(top_position.x, -144.0))
+        .unwrap();
+
+    let connection_types =
+        connection_types_along_path(&pathfinding, top_node_id, below_node_id).unwrap();
+    assert!(connection_types.contains(&PathfindingGraphConnectionType::Droppable));
+}
@@ -0,0 +1,51 @@
+use bevy::math::Vec2;
+use pursue_ai_test::{
+    ai::{
+        animation::{AgentAnimation, AnimationEventKind},
+        platformer_ai::PlatformerAIState,
+    },
+    Physics,
+};
+
+#[test]
+fn jumps_landings_and_attacks_are_picked_up_between_ticks() {
+    let mut animation = AgentAnimation::default();
+    let mut physics = Physics::circle(10.0);
+
+    physics.grounded = true;
+    assert_eq!(
+        animation.advance(&physics, PlatformerAIState::Pursue, false),
+        vec![AnimationEventKind::Landed]
+    );
+
+    physics.grounded = false;
+    physics.velocity = Vec2::new(2.0, 8.0);
+    assert_eq!(
+        animation.advance(&physics, PlatformerAIState::Pursue, false),
+        vec![AnimationEventKind::JumpStarted]
+    );
+    assert!(animation
+        .advance(&physics, PlatformerAIState::Pursue, false)
+        .is_empty());
+
+    physics.grounded = true;
+    assert_eq!(
+        animation.advance(&physics, PlatformerAIState::Attack, false),
+        vec![
+            AnimationEventKind::Landed,
+            AnimationEventKind::StateChanged {
+                from: PlatformerAIState::Pursue,
+                to: PlatformerAIState::Attack,
+            },
+            AnimationEventKind::AttackStarted,
+        ]
+    );
+    assert_eq!(animation.ticks_in_state, 0);
+
+    physics.grounded = false;
+    physics.velocity = Vec2::new(0.0, -2.0);
+    assert_eq!(
+        animation.advance(&physics, PlatformerAIState::Attack, true),
+        vec![AnimationEventKind::LedgeGrabbed]
+    );
+}
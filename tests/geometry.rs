@@ -0,0 +1,201 @@
+use bevy::math::Vec2;
+use proptest::prelude::*;
+use pursue_ai_test::{
+    collisions::find_projection,
+    polygon_ops::triangulate,
+    utils::{line_intersect, side_of_line_detection},
+};
+
+/// Points on a whole number grid, so the cross products inside the helpers are exact and the
+/// properties don't hinge on rounding
+fn grid_point() -> impl Strategy<Value = Vec2> {
+    (-100i32..=100, -100i32..=100).prop_map(|(x, y)| Vec2::new(x as f32, y as f32))
+}
+
+/// Any point in the range, for properties that hold without exact arithmetic
+fn point() -> impl Strategy<Value = Vec2> {
+    (-1000.0f32..1000.0, -1000.0f32..1000.0).prop_map(|(x, y)| Vec2::new(x, y))
+}
+
+/// Squared distance from the point to the closest point on the segment
+fn distance_squared_to_segment(start: Vec2, end: Vec2, point: Vec2) -> f32 {
+    let line = end - start;
+    if line.length_squared() == 0.0 {
+        return point.distance_squared(start);
+    }
+
+    let t = ((point - start).dot(line) / line.length_squared()).clamp(0.0, 1.0);
+    point.distance_squared(start + line * t)
+}
+
+fn is_on_segment(start: Vec2, end: Vec2, point: Vec2) -> bool {
+    distance_squared_to_segment(start, end, point).sqrt() <= 1e-3 * (1.0 + start.distance(end))
+}
+
+proptest! {
+    #[test]
+    fn line_intersect_is_symmetric(a in grid_point(), b in grid_point(), c in grid_point(), d in grid_point()) {
+        let first = line_intersect(a, b, c, d);
+        let second = line_intersect(c, d, a, b);
+
+        prop_assert_eq!(first.is_some(), second.is_some());
+        if let (Some(first), Some(second)) = (first, second) {
+            prop_assert!(first.distance(second) <= 1e-2, "{} != {}", first, second);
+        }
+    }
+
+    #[test]
+    fn line_intersect_ignores_segment_direction(a in grid_point(), b in grid_point(), c in grid_point(), d in grid_point()) {
+        let forward = line_intersect(a, b, c, d);
+
+        for reversed in [line_intersect(b, a, c, d), line_intersect(a, b, d, c)] {
+            prop_assert_eq!(forward.is_some(), reversed.is_some());
+            if let (Some(forward), Some(reversed)) = (forward, reversed) {
+                prop_assert!(forward.distance(reversed) <= 1e-2, "{} != {}", forward, reversed);
+            }
+        }
+    }
+
+    #[test]
+    fn line_intersect_is_on_both_segments(a in grid_point(), b in grid_point(), c in grid_point(), d in grid_point()) {
+        if let Some(intersection) = line_intersect(a, b, c, d) {
+            prop_assert!(is_on_segment(a, b, intersection));
+            prop_assert!(is_on_segment(c, d, intersection));
+        }
+    }
+
+    #[test]
+    fn line_intersect_finds_crossings(
+        crossing in grid_point(),
+        direction_1 in grid_point(),
+        direction_2 in grid_point(),
+    ) {
+        // Two segments straddling the same point, in directions that aren't parallel
+        prop_assume!(direction_1.perp_dot(direction_2) != 0.0);
+
+        let intersection = line_intersect(
+            crossing - direction_1,
+            crossing + direction_1,
+            crossing - direction_2,
+            crossing + direction_2,
+        );
+
+        prop_assert!(intersection.is_some());
+        prop_assert!(intersection.unwrap().distance(crossing) <= 1e-2);
+    }
+
+    #[test]
+    fn line_intersect_misses_degenerate_segments(a in grid_point(), c in grid_point(), d in grid_point()) {
+        prop_assert_eq!(line_intersect(a, a, c, d), None);
+        prop_assert_eq!(line_intersect(c, d, a, a), None);
+    }
+
+    #[test]
+    fn line_intersect_misses_collinear_segments(
+        start in grid_point(),
+        direction in grid_point(),
+        t in -3i32..=3,
+        s in -3i32..=3,
+    ) {
+        // Parallel segments never report an intersection, even when they overlap
+        let line_1_end = start + direction;
+        let line_2_start = start + direction * t as f32;
+        let line_2_end = start + direction * s as f32;
+
+        prop_assert_eq!(line_intersect(start, line_1_end, line_2_start, line_2_end), None);
+    }
+
+    #[test]
+    fn side_of_line_flips_with_line_direction(start in grid_point(), end in grid_point(), point in grid_point()) {
+        prop_assert_eq!(
+            side_of_line_detection(start, end, point),
+            -side_of_line_detection(end, start, point)
+        );
+    }
+
+    #[test]
+    fn side_of_line_is_positive_on_the_left(start in grid_point(), end in grid_point(), along in -2i32..=2, offset in 1i32..=50) {
+        prop_assume!(start != end);
+
+        // Counterclockwise of the line's direction, whichever way it points
+        let line = end - start;
+        let point = start + line * along as f32 + line.perp() * offset as f32;
+
+        prop_assert_eq!(side_of_line_detection(start, end, point), 1.0);
+        prop_assert_eq!(side_of_line_detection(end, start, point), -1.0);
+    }
+
+    #[test]
+    fn side_of_line_is_zero_on_the_line(start in grid_point(), end in grid_point(), along in -3i32..=3) {
+        let point = start + (end - start) * along as f32;
+
+        prop_assert_eq!(side_of_line_detection(start, end, point), 0.0);
+    }
+
+    #[test]
+    fn find_projection_lands_on_the_segment(start in point(), end in point(), point in point(), radius in 0.0f32..32.0) {
+        let (_, projection) = find_projection(start, end, point, radius);
+
+        prop_assert!(projection.is_finite());
+        prop_assert!(is_on_segment(start, end, projection), "{} off {} -> {}", projection, start, end);
+    }
+
+    #[test]
+    fn find_projection_never_underestimates(start in point(), end in point(), point in point(), radius in 0.0f32..32.0) {
+        // Past the ends the distance is padded, but it's never shorter than the real one
+        let (distance_squared, _) = find_projection(start, end, point, radius);
+        let expected = distance_squared_to_segment(start, end, point);
+
+        prop_assert!(distance_squared >= expected - 1e-2 * (1.0 + expected));
+    }
+
+    #[test]
+    fn find_projection_is_symmetric(start in point(), end in point(), point in point()) {
+        let (_, forward) = find_projection(start, end, point, 0.0);
+        let (_, backward) = find_projection(end, start, point, 0.0);
+
+        prop_assert!(forward.distance(backward) <= 1e-2 * (1.0 + start.distance(end)));
+    }
+
+    #[test]
+    fn find_projection_handles_degenerate_segments(start in point(), point in point(), radius in 0.0f32..32.0) {
+        let (distance_squared, projection) = find_projection(start, start, point, radius);
+
+        prop_assert_eq!(projection, start);
+        prop_assert!(distance_squared.is_finite());
+        prop_assert!(distance_squared >= point.distance_squared(start) - 1e-2);
+    }
+}
+
+/// The total area of the triangles, which matches the outline's when they cover it exactly
+fn triangulated_area(outline: &[Vec2], triangles: &[[usize; 3]]) -> f32 {
+    triangles
+        .iter()
+        .map(|&[a, b, c]| (outline[b] - outline[a]).perp_dot(outline[c] - outline[a]) / 2.0)
+        .sum()
+}
+
+#[test]
+fn triangulation_covers_concave_outlines() {
+    // An L shape, closed and clockwise like a level container, with a point partway along the
+    // bottom as grid levels have
+    let points = [
+        Vec2::new(0.0, 0.0),
+        Vec2::new(0.0, 20.0),
+        Vec2::new(10.0, 20.0),
+        Vec2::new(10.0, 10.0),
+        Vec2::new(20.0, 10.0),
+        Vec2::new(20.0, 0.0),
+        Vec2::new(10.0, 0.0),
+        Vec2::new(0.0, 0.0),
+    ];
+
+    let (outline, triangles) = triangulate(&points);
+
+    assert_eq!(outline.len(), 7);
+    assert_eq!(triangles.len(), 5);
+    assert!(triangles
+        .iter()
+        .all(|&[a, b, c]| { (outline[b] - outline[a]).perp_dot(outline[c] - outline[a]) > 0.0 }));
+    assert!((triangulated_area(&outline, &triangles) - 300.0).abs() < 0.01);
+}
@@ -0,0 +1,33 @@
+use pursue_ai_test::{level_asset::LevelAsset, markers::Marker};
+
+#[test]
+fn markers_are_saved_with_level_files() {
+    let contents = std::fs::read_to_string("assets/levels/arena.level.json").unwrap();
+    let mut level = serde_json::from_str::<LevelAsset>(&contents)
+        .unwrap()
+        .to_level();
+
+    level.markers.push(Marker {
+        name: "ambush".to_string(),
+        x: 64.0,
+        y: -32.0,
+    });
+
+    let saved = serde_json::to_string(&LevelAsset::from_level(&level)).unwrap();
+    let loaded = serde_json::from_str::<LevelAsset>(&saved)
+        .unwrap()
+        .to_level();
+
+    let marker = loaded.get_marker("ambush").unwrap();
+    assert_eq!(marker.position(), level.markers[0].position());
+}
+
+#[test]
+fn level_files_without_markers_still_load() {
+    let contents = std::fs::read_to_string("assets/levels/arena.level.json").unwrap();
+    let level = serde_json::from_str::<LevelAsset>(&contents)
+        .unwrap()
+        .to_level();
+
+    assert!(level.markers.is_empty());
+}
@@ -0,0 +1,109 @@
+use pursue_ai_test::{
+    ai::a_star::find_path_from,
+    level::normalize_polygons,
+    level_suite::{benchmark_level_suite, dense_grid_level, find_suite_level, SuiteLevel},
+    Pathfinding, PathfindingSettings,
+};
+use rand::{rngs::StdRng, SeedableRng};
+
+const GRID_SIZE: f32 = 32.0;
+
+/// The graph for a suite level and the node nearest its spawn point
+fn suite_graph(suite_level: &SuiteLevel) -> (Pathfinding, usize) {
+    let mut level = suite_level.build(GRID_SIZE, &mut StdRng::seed_from_u64(0));
+    normalize_polygons(&mut level.polygons);
+
+    let mut pathfinding = Pathfinding::default();
+    pathfinding.rebuild(&level, &PathfindingSettings::default());
+
+    let spawn_node_id = pathfinding
+        .nearest_node_id(suite_level.spawn_position(GRID_SIZE))
+        .unwrap();
+
+    (pathfinding, spawn_node_id)
+}
+
+fn has_path(pathfinding: &Pathfinding, start_node_id: usize, goal_node_id: usize) -> bool {
+    find_path_from(
+        pathfinding,
+        start_node_id,
+        goal_node_id,
+        pathfinding.nodes[goal_node_id].position,
+        None,
+        None,
+    )
+    .is_some()
+}
+
+#[test]
+fn every_suite_level_spawns_in_an_open_cell() {
+    for suite_level in benchmark_level_suite() {
+        let (column, row) = suite_level.spawn_cell;
+
+        assert_eq!(suite_level.grid[row][column], 0, "{}", suite_level.name);
+        assert!(find_suite_level(suite_level.name).is_some());
+    }
+}
+
+#[test]
+fn the_dense_grid_can_be_crossed() {
+    // The full sized grid takes too long to build outside of release builds
+    let (pathfinding, spawn_node_id) = suite_graph(&dense_grid_level(13, 11));
+
+    let far_node = pathfinding
+        .nodes
+        .iter()
+        .filter(|node| node.normal.y > 0.5)
+        .max_by(|a, b| a.position.x.total_cmp(&b.position.x))
+        .unwrap();
+
+    assert!(has_path(&pathfinding, spawn_node_id, far_node.id));
+}
+
+#[test]
+fn the_tower_can_be_climbed_to_the_top() {
+    let (pathfinding, spawn_node_id) = suite_graph(&find_suite_level("tower").unwrap());
+
+    let top_node = pathfinding
+        .nodes
+        .iter()
+        .filter(|node| node.normal.y > 0.5)
+        .max_by(|a, b| a.position.y.total_cmp(&b.position.y))
+        .unwrap();
+
+    assert!(has_path(&pathfinding, spawn_node_id, top_node.id));
+}
+
+#[test]
+fn the_corridor_can_be_walked_end_to_end() {
+    let (pathfinding, spawn_node_id) = suite_graph(&find_suite_level("corridor").unwrap());
+
+    let far_node = pathfinding
+        .nodes
+        .iter()
+        .filter(|node| node.normal.y > 0.5)
+        .max_by(|a, b| a.position.x.total_cmp(&b.position.x))
+        .unwrap();
+
+    assert!(has_path(&pathfinding, spawn_node_id, far_node.id));
+}
+
+#[test]
+fn the_islands_cant_be_reached_from_the_floor() {
+    let suite_level = find_suite_level("islands").unwrap();
+    let (pathfinding, spawn_node_id) = suite_graph(&suite_level);
+
+    // Anything standing well above the spawn point is on an island
+    let spawn_height = suite_level.spawn_position(GRID_SIZE).y;
+    let island_node_ids: Vec<usize> = pathfinding
+        .nodes
+        .iter()
+        .filter(|node| node.normal.y > 0.5 && node.position.y > spawn_height + GRID_SIZE * 4.0)
+        .map(|node| node.id)
+        .collect();
+    assert!(!island_node_ids.is_empty());
+
+    for island_node_id in island_node_ids {
+        assert!(!has_path(&pathfinding, spawn_node_id, island_node_id));
+    }
+}
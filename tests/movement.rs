@@ -0,0 +1,158 @@
+use bevy::{
+    app::{App, Update},
+    ecs::{entity::Entity, schedule::IntoSystemConfigs},
+    gizmos::{config::DefaultGizmoConfigGroup, AppGizmoBuilder},
+    math::Vec2,
+    transform::components::Transform,
+};
+use pursue_ai_test::{
+    agent_body,
+    ai::{
+        pathfinding::{s_compute_paths, PathfindingGraphConnectionType},
+        platformer_ai::{
+            s_handle_head_bumps, s_platformer_ai_movement, s_receive_agent_paths,
+            s_request_agent_paths, ReplanPolicy,
+        },
+    },
+    collisions::{
+        s_collision, s_rebuild_level_edge_index, CollisionEnded, CollisionStarted, HeadBump,
+        LevelEdgeIndex,
+    },
+    event_log::EventLog,
+    level::normalize_polygons,
+    GizmosVisible, Level, Pathfinding, PathfindingSettings, PlatformerAI, PlatformerAITuning,
+    Settings,
+};
+use rand::{rngs::StdRng, SeedableRng};
+
+/// The built-in level with just the systems that plan and move the agents, which a run of the
+/// `Update` schedule steps by one tick with nothing drawn
+fn headless_app() -> App {
+    let mut level = Level::built_in(32.0, &mut StdRng::seed_from_u64(0));
+    normalize_polygons(&mut level.polygons);
+
+    let mut pathfinding = Pathfinding::default();
+    pathfinding.rebuild(&level, &PathfindingSettings::default());
+    pathfinding.active = true;
+
+    let mut app = App::new();
+    app.init_gizmo_group::<DefaultGizmoConfigGroup>()
+        .add_event::<HeadBump>()
+        .add_event::<CollisionStarted>()
+        .add_event::<CollisionEnded>()
+        .insert_resource(level)
+        .insert_resource(pathfinding)
+        .insert_resource(LevelEdgeIndex::default())
+        .insert_resource(Settings::default())
+        .insert_resource(EventLog::default())
+        .insert_resource(GizmosVisible::default())
+        .add_systems(
+            Update,
+            (
+                s_rebuild_level_edge_index,
+                s_request_agent_paths,
+                s_compute_paths,
+                s_receive_agent_paths,
+                s_platformer_ai_movement,
+                s_collision,
+                s_handle_head_bumps,
+            )
+                .chain(),
+        );
+
+    app
+}
+
+/// An agent standing still on flat floor, with its feet at the point
+fn spawn_agent(app: &mut App, floor_point: Vec2, tuning: PlatformerAITuning) -> Entity {
+    let (mut physics, platformer_ai, health) = agent_body();
+    let position = floor_point + Vec2::Y * physics.radius;
+
+    physics.prev_position = position;
+    physics.normal = Vec2::Y;
+    physics.grounded = true;
+
+    app.world
+        .spawn((
+            Transform::from_translation(position.extend(0.0)),
+            physics,
+            platformer_ai,
+            health,
+            tuning,
+        ))
+        .id()
+}
+
+#[test]
+fn agents_back_up_for_a_run_up_and_make_the_jump() {
+    let mut app = headless_app();
+
+    // Across the gap from the ledge at 172 to the wall at 183, which needs a longer run-up than
+    // the ledge is from the node behind it
+    let (from_node_id, to_node_id) = (172, 183);
+    let pathfinding = app.world.resource::<Pathfinding>();
+    let run_up = pathfinding.nodes[from_node_id]
+        .connections_of_type(PathfindingGraphConnectionType::Jumpable)
+        .find(|connection| connection.node_id == to_node_id)
+        .and_then(|connection| connection.run_up.clone())
+        .unwrap();
+    // Further down the wall, so the path goes by way of the jump
+    let goal_position = pathfinding.nodes[182].position - Vec2::X * 8.0;
+    let landing_position = pathfinding.nodes[to_node_id].position - Vec2::X * 8.0;
+    // On the floor just short of the ledge, closer than the run-up needs
+    let floor_point = pathfinding.nodes[from_node_id].position - Vec2::X * 2.0;
+
+    app.world
+        .resource_mut::<Pathfinding>()
+        .teleport_goal(goal_position);
+
+    // Replanning every tick searches from wherever the agent's backed up to, which starts the
+    // path from the nodes behind the ledge and mustn't lose the run-up
+    let agent = spawn_agent(
+        &mut app,
+        floor_point,
+        PlatformerAITuning {
+            pursue_replan: ReplanPolicy::Timer { ticks: 1 },
+            ..Default::default()
+        },
+    );
+
+    let start_position = app
+        .world
+        .get::<Transform>(agent)
+        .unwrap()
+        .translation
+        .truncate();
+    let mut furthest_back: f32 = 0.0;
+    let mut jumped = false;
+    for _ in 0..600 {
+        app.world.run_schedule(Update);
+
+        let position = app
+            .world
+            .get::<Transform>(agent)
+            .unwrap()
+            .translation
+            .truncate();
+        furthest_back = furthest_back.max((start_position.x - position.x) * run_up.direction);
+
+        let platformer_ai = app.world.get::<PlatformerAI>(agent).unwrap();
+        jumped |= platformer_ai.jump_connection == Some((from_node_id, to_node_id));
+
+        if jumped && position.distance(landing_position) < 8.0 {
+            break;
+        }
+    }
+
+    assert!(furthest_back >= run_up.distance);
+    assert!(jumped);
+
+    // And stuck to the wall across the gap
+    let position = app
+        .world
+        .get::<Transform>(agent)
+        .unwrap()
+        .translation
+        .truncate();
+    assert!(position.distance(landing_position) < 8.0);
+}
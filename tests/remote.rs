@@ -0,0 +1,59 @@
+#![cfg(feature = "remote")]
+
+use std::{thread, time::Duration};
+
+use pursue_ai_test::{
+    remote::{merge_settings, RemoteCommand, RemoteServer},
+    settings::Settings,
+};
+use tungstenite::Message;
+
+#[test]
+fn clients_send_commands_and_receive_telemetry() {
+    let remote_server = RemoteServer::start("127.0.0.1:0").unwrap();
+    let (mut socket, _) =
+        tungstenite::connect(format!("ws://{}", remote_server.local_address)).unwrap();
+
+    socket
+        .send(Message::Text(
+            r#"{ "command": "move_goal", "x": 10.0, "y": -20.0 }"#.to_string(),
+        ))
+        .unwrap();
+    socket
+        .send(Message::Text(r#"{ "command": "reset" }"#.to_string()))
+        .unwrap();
+
+    let mut commands = Vec::new();
+    for _ in 0..100 {
+        commands.extend(remote_server.commands());
+        if commands.len() == 2 {
+            break;
+        }
+        thread::sleep(Duration::from_millis(10));
+    }
+    assert_eq!(
+        commands,
+        vec![
+            RemoteCommand::MoveGoal { x: 10.0, y: -20.0 },
+            RemoteCommand::Reset
+        ]
+    );
+
+    remote_server.broadcast(r#"{"tick":6}"#);
+    assert_eq!(
+        socket.read().unwrap(),
+        Message::Text(r#"{"tick":6}"#.to_string())
+    );
+}
+
+#[test]
+fn settings_changes_only_touch_what_they_give() {
+    let settings = Settings::default();
+    let changes = serde_json::json!({ "goal": { "speed": 12.5 } });
+
+    let merged = merge_settings(&settings, &changes).unwrap();
+
+    assert_eq!(merged.goal.speed, 12.5);
+    assert_eq!(merged.squad, settings.squad);
+    assert_eq!(merged.agent, settings.agent);
+}
@@ -0,0 +1,183 @@
+use bevy::{
+    ecs::{
+        entity::EntityHashMap,
+        query::With,
+        reflect::AppTypeRegistry,
+        world::{EntityRef, World},
+    },
+    math::{Quat, Vec2, Vec3},
+    scene::{serde::SceneDeserializer, DynamicScene},
+    transform::components::Transform,
+};
+use pursue_ai_test::{
+    agent_body,
+    ai::{pathfinding::MovementCapabilities, platformer_ai::ReplanPolicy},
+    goal_point_physics,
+    scenario_scene::build_scenario_scene,
+    settings::{EvaderSettings, GoalSettings, JumpFeedbackSettings, SearchSettings, SquadSettings},
+    GoalPoint, PathfindingSettings, Physics, PlatformerAI, PlatformerAITuning, PursuitTarget,
+    Settings, TargetSelection,
+};
+use serde::de::DeserializeSeed;
+
+fn type_registry() -> AppTypeRegistry {
+    let type_registry = AppTypeRegistry::default();
+
+    {
+        let mut type_registry = type_registry.write();
+        type_registry.register::<Vec3>();
+        type_registry.register::<Quat>();
+        type_registry.register::<Transform>();
+        type_registry.register::<GoalPoint>();
+        type_registry.register::<PursuitTarget>();
+        type_registry.register::<TargetSelection>();
+        type_registry.register::<PlatformerAITuning>();
+        type_registry.register::<ReplanPolicy>();
+        type_registry.register::<Settings>();
+        type_registry.register::<PathfindingSettings>();
+        type_registry.register::<MovementCapabilities>();
+        type_registry.register::<SearchSettings>();
+        type_registry.register::<GoalSettings>();
+        type_registry.register::<SquadSettings>();
+        type_registry.register::<JumpFeedbackSettings>();
+        type_registry.register::<EvaderSettings>();
+    }
+
+    type_registry
+}
+
+/// Saves the world as a scenario scene and loads it into a fresh one
+fn round_trip(world: &World) -> World {
+    let type_registry = world.resource::<AppTypeRegistry>().clone();
+
+    let scene_ron = build_scenario_scene(world)
+        .serialize_ron(&type_registry.0)
+        .unwrap();
+
+    let scene: DynamicScene = SceneDeserializer {
+        type_registry: &type_registry.read(),
+    }
+    .deserialize(&mut ron::Deserializer::from_str(&scene_ron).unwrap())
+    .unwrap();
+
+    let mut loaded_world = World::new();
+    loaded_world.insert_resource(type_registry);
+    scene
+        .write_to_world(&mut loaded_world, &mut EntityHashMap::default())
+        .unwrap();
+
+    loaded_world
+}
+
+fn position(entity: &EntityRef) -> Vec2 {
+    entity.get::<Transform>().unwrap().translation.truncate()
+}
+
+#[test]
+fn scenario_scenes_keep_the_agents_goal_and_settings() {
+    let mut world = World::new();
+    world.insert_resource(type_registry());
+    world.insert_resource(Settings {
+        goal: GoalSettings { speed: 6.0 },
+        ..Default::default()
+    });
+
+    let goal_position = Vec2::new(320.0, 64.0);
+    world.spawn((
+        GoalPoint,
+        PursuitTarget,
+        Transform::from_translation(goal_position.extend(0.0)),
+        goal_point_physics(goal_position),
+    ));
+
+    let agent_positions = [Vec2::new(-200.0, 16.0), Vec2::new(100.0, -48.0)];
+    let tuning = PlatformerAITuning {
+        max_speed: 5.0,
+        pursue_replan: ReplanPolicy::Timer { ticks: 20 },
+        ..Default::default()
+    };
+    world.spawn((
+        Transform::from_translation(agent_positions[0].extend(0.0)),
+        agent_body(),
+        tuning.clone(),
+        TargetSelection::Keep,
+    ));
+    world.spawn((
+        Transform::from_translation(agent_positions[1].extend(0.0)),
+        agent_body(),
+        PlatformerAITuning::default(),
+    ));
+
+    // Anything that isn't an agent or the goal stays out of the scene
+    world.spawn(Transform::default());
+
+    let mut loaded_world = round_trip(&world);
+
+    assert_eq!(
+        *loaded_world.resource::<Settings>(),
+        *world.resource::<Settings>()
+    );
+    assert_eq!(loaded_world.iter_entities().count(), 3);
+
+    let goals: Vec<Vec2> = loaded_world
+        .query_filtered::<&Transform, With<GoalPoint>>()
+        .iter(&loaded_world)
+        .map(|transform| transform.translation.truncate())
+        .collect();
+    assert_eq!(goals, vec![goal_position]);
+
+    let mut agents: Vec<(Vec2, PlatformerAITuning, Option<TargetSelection>)> = loaded_world
+        .iter_entities()
+        .filter(|entity| entity.contains::<PlatformerAITuning>())
+        .map(|entity| {
+            (
+                position(&entity),
+                entity.get::<PlatformerAITuning>().unwrap().clone(),
+                entity.get::<TargetSelection>().copied(),
+            )
+        })
+        .collect();
+    agents.sort_by(|a, b| a.0.x.total_cmp(&b.0.x));
+
+    assert_eq!(
+        agents,
+        vec![
+            (agent_positions[0], tuning, Some(TargetSelection::Keep)),
+            (agent_positions[1], PlatformerAITuning::default(), None),
+        ]
+    );
+
+    // Bodies and AI state are rebuilt on load rather than saved
+    assert!(loaded_world
+        .iter_entities()
+        .all(|entity| !entity.contains::<Physics>() && !entity.contains::<PlatformerAI>()));
+}
+
+#[test]
+fn shipped_scene_loads() {
+    let type_registry = type_registry();
+    let scene_ron = std::fs::read_to_string("assets/scenes/two_agents.scn.ron").unwrap();
+
+    let scene: DynamicScene = SceneDeserializer {
+        type_registry: &type_registry.read(),
+    }
+    .deserialize(&mut ron::Deserializer::from_str(&scene_ron).unwrap())
+    .unwrap();
+
+    let mut world = World::new();
+    world.insert_resource(type_registry);
+    scene
+        .write_to_world(&mut world, &mut EntityHashMap::default())
+        .unwrap();
+
+    let goals = world
+        .query_filtered::<&Transform, With<GoalPoint>>()
+        .iter(&world)
+        .count();
+    let agents = world
+        .query_filtered::<&Transform, With<PlatformerAITuning>>()
+        .iter(&world)
+        .count();
+
+    assert_eq!((goals, agents), (1, 2));
+}
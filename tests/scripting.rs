@@ -0,0 +1,54 @@
+#![cfg(feature = "scripting")]
+
+use pursue_ai_test::ai::scripting::{behavior_script_engine, think};
+use rhai::{Dynamic, Map, FLOAT};
+
+fn point(x: FLOAT, y: FLOAT) -> Dynamic {
+    let mut point = Map::new();
+    point.insert("x".into(), x.into());
+    point.insert("y".into(), y.into());
+    point.into()
+}
+
+fn agent(target_visible: bool, target_distance: FLOAT, ticks: rhai::INT) -> Map {
+    let mut agent = Map::new();
+    agent.insert("position".into(), point(0.0, 0.0));
+    agent.insert("target".into(), point(target_distance, 0.0));
+    agent.insert("target_visible".into(), target_visible.into());
+    agent.insert("target_distance".into(), target_distance.into());
+    agent.insert("requested_path_length".into(), Dynamic::UNIT);
+    agent.insert("ticks".into(), ticks.into());
+    agent
+}
+
+fn decided_state(decision: &Dynamic) -> String {
+    decision.read_lock::<Map>().unwrap()["state"]
+        .clone()
+        .into_string()
+        .unwrap()
+}
+
+#[test]
+fn ambush_script_remembers_where_the_target_was_seen() {
+    let engine = behavior_script_engine();
+    let ast = engine
+        .compile(include_str!("../assets/behaviors/ambush.rhai"))
+        .unwrap();
+    let mut memory = Map::new();
+
+    let decision = think(&engine, &ast, &mut memory, agent(true, 50.0, 0)).unwrap();
+    assert_eq!(decided_state(&decision), "attack");
+    assert!(memory.contains_key("last_seen"));
+
+    let decision = think(&engine, &ast, &mut memory, agent(false, 500.0, 10)).unwrap();
+    assert_eq!(decided_state(&decision), "search");
+    assert!(decision.read_lock::<Map>().unwrap().contains_key("move_to"));
+}
+
+#[test]
+fn runaway_scripts_are_stopped() {
+    let engine = behavior_script_engine();
+    let ast = engine.compile("fn think(agent) { loop {} }").unwrap();
+
+    assert!(think(&engine, &ast, &mut Map::new(), Map::new()).is_err());
+}
@@ -0,0 +1,18 @@
+use pursue_ai_test::Settings;
+
+#[test]
+fn shipped_settings_match_the_defaults() {
+    let contents = std::fs::read_to_string("assets/settings.ron").unwrap();
+    let settings: Settings = ron::from_str(&contents).unwrap();
+
+    assert_eq!(settings, Settings::default());
+}
+
+#[test]
+fn missing_settings_keep_their_defaults() {
+    let settings: Settings = ron::from_str("(goal: (speed: 6.0))").unwrap();
+
+    assert_eq!(settings.goal.speed, 6.0);
+    assert_eq!(settings.squad, Settings::default().squad);
+    assert_eq!(settings.agent, Settings::default().agent);
+}
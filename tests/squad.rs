@@ -0,0 +1,112 @@
+use bevy::{
+    ecs::{entity::Entity, event::Events, system::RunSystemOnce, world::World},
+    math::Vec2,
+    transform::components::Transform,
+};
+use pursue_ai_test::{
+    ai::{
+        pathfinding::GoalTeleported,
+        pursuit::s_select_pursuit_targets,
+        squad::{s_coordinate_squad, SquadCoordinator, SquadRole},
+    },
+    event_log::EventLog,
+    level::normalize_polygons,
+    Level, Pathfinding, PathfindingSettings, Physics, PlatformerAI, PursuitTarget, Settings,
+};
+use rand::{rngs::StdRng, SeedableRng};
+
+/// Somewhere standing on the node, the way an agent or target would be
+fn standing_on(world: &World, node_id: usize) -> Transform {
+    let node = &world.resource::<Pathfinding>().nodes[node_id];
+    let position = node.position + node.normal * 8.0;
+
+    Transform::from_translation(position.extend(0.0))
+}
+
+fn spawn_agent(world: &mut World, node_id: usize) -> Entity {
+    let transform = standing_on(world, node_id);
+    let mut physics = Physics::circle(8.0);
+    physics.normal = world.resource::<Pathfinding>().nodes[node_id].normal;
+
+    world
+        .spawn((transform, physics, PlatformerAI::default()))
+        .id()
+}
+
+fn position(world: &World, entity: Entity) -> Vec2 {
+    world
+        .get::<Transform>(entity)
+        .unwrap()
+        .translation
+        .truncate()
+}
+
+#[test]
+fn agents_split_between_targets_and_coordinate_around_their_own() {
+    let mut level = Level::built_in(32.0, &mut StdRng::seed_from_u64(0));
+    normalize_polygons(&mut level.polygons);
+
+    let mut pathfinding = Pathfinding::default();
+    pathfinding.rebuild(&level, &PathfindingSettings::default());
+    pathfinding.active = true;
+
+    let mut world = World::new();
+    world.insert_resource(pathfinding);
+    world.insert_resource(level);
+    world.insert_resource(Settings::default());
+    world.insert_resource(EventLog::default());
+    world.insert_resource(SquadCoordinator::default());
+    world.init_resource::<Events<GoalTeleported>>();
+
+    // One target on a ledge on the right and one on the floor below, each with an agent close
+    // by and one further back along the same way in
+    let ledge = standing_on(&world, 74);
+    let ledge = world.spawn((ledge, PursuitTarget)).id();
+    let bottom = standing_on(&world, 60);
+    let bottom = world.spawn((bottom, PursuitTarget)).id();
+
+    let ledge_agents = [spawn_agent(&mut world, 77), spawn_agent(&mut world, 79)];
+    let bottom_agents = [spawn_agent(&mut world, 63), spawn_agent(&mut world, 65)];
+
+    world.run_system_once(s_select_pursuit_targets);
+    world.run_system_once(s_coordinate_squad);
+
+    for (target, agents) in [(ledge, ledge_agents), (bottom, bottom_agents)] {
+        let other_target = if target == ledge { bottom } else { ledge };
+
+        for agent in agents {
+            // Each agent goes for whichever target's nearest
+            let platformer_ai = world.get::<PlatformerAI>(agent).unwrap();
+            assert_eq!(platformer_ai.target, Some(target));
+            assert!(
+                position(&world, agent).distance(position(&world, target))
+                    < position(&world, agent).distance(position(&world, other_target))
+            );
+        }
+
+        // The closer agent chases its target, and the one behind hands off to it and covers one
+        // of that target's exits
+        let squad_coordinator = world.resource::<SquadCoordinator>();
+        assert_eq!(
+            squad_coordinator.roles.get(&agents[0]),
+            Some(&SquadRole::Chase)
+        );
+
+        let Some(SquadRole::Support { cover_node_id }) = squad_coordinator.roles.get(&agents[1])
+        else {
+            panic!("the agent behind should be covering an exit");
+        };
+        let (goal_node_id, _) = world
+            .get::<PlatformerAI>(agents[1])
+            .unwrap()
+            .target_goal
+            .unwrap();
+        let pathfinding = world.resource::<Pathfinding>();
+        assert!(
+            pathfinding.nodes[*cover_node_id]
+                .position
+                .distance(pathfinding.nodes[goal_node_id].position)
+                <= world.resource::<Settings>().squad.exit_search_distance
+        );
+    }
+}